@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use macroquad::math::{vec2, Vec2};
+use ray_cast::core::{trace, trace_with_media, SimConfig};
+use ray_cast::labyrinth::{Algorithm, Labyrinth};
+use ray_cast::{EdgeState, Laser, Segment};
+
+fn mirror_box() -> Vec<Segment> {
+    let corners = [vec2(0.0, 0.0), vec2(400.0, 0.0), vec2(400.0, 300.0), vec2(0.0, 300.0)];
+    (0..4).map(|i| Segment(corners[i], corners[(i + 1) % 4], EdgeState::Reflective)).collect()
+}
+
+fn transparent_circle(sides: usize) -> Vec<Segment> {
+    let corner = |i: usize| vec2(200.0, 150.0) + Vec2::from_angle(i as f32 * std::f32::consts::TAU / sides as f32) * 100.0;
+    (0..sides).map(|i| Segment(corner(i), corner(i + 1), EdgeState::Transparent)).collect()
+}
+
+fn maze(size: usize) -> Vec<Segment> {
+    let mut labyrinth = Labyrinth::new(20.0, (size, size));
+    labyrinth.generate(Algorithm::DepthFirst, 1);
+    labyrinth.get_as_lines().iter()
+        .map(|&((x1, y1), (x2, y2))| Segment(vec2(x1, y1), vec2(x2, y2), EdgeState::Reflective))
+        .collect()
+}
+
+fn bench_trace(c: &mut Criterion) {
+    let config = SimConfig { max_rays: 1000, ..Default::default() };
+    let laser = Laser::headless(vec2(10.0, 10.0), Vec2::from_angle(0.3));
+
+    let segments = mirror_box();
+    c.bench_function("mirror box", |b| b.iter(|| laser.solve_collisions(black_box(&segments))));
+
+    let segments = transparent_circle(20);
+    let ray = ray_cast::Ray::new(vec2(0.0, 160.0), Vec2::X, macroquad::color::RED);
+    c.bench_function("20-gon transparent circle", |b| b.iter(|| trace(ray, black_box(&segments), &config)));
+    let media = vec![Some(0); segments.len()];
+    c.bench_function("20-gon lens", |b| b.iter(|| trace_with_media(ray, black_box(&segments), &media, &config)));
+
+    let segments = maze(30);
+    let laser = Laser::headless(vec2(10.0, 10.0), Vec2::from_angle(0.7));
+    c.bench_function("30x30 maze", |b| b.iter(|| laser.solve_collisions(black_box(&segments))));
+}
+
+criterion_group!(benches, bench_trace);
+criterion_main!(benches);
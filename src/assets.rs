@@ -0,0 +1,91 @@
+// Sprites: the art embedded in the binary, art loaded at runtime, and placeholders for when
+// neither can be decoded.
+
+use std::path::Path;
+
+use log::error;
+use macroquad::color::{Color, WHITE};
+use macroquad::file::load_file;
+use macroquad::math::vec2;
+use macroquad::texture::{Image, Texture2D};
+
+/// File names of the sprites, in `assets/` and in any folder of replacement art.
+pub const LASER_FILE: &str = "laser.png";
+pub const NODE_FILE: &str = "node2.png";
+
+const EMBEDDED_LASER: &[u8] = include_bytes!("../assets/laser.png");
+const EMBEDDED_NODE: &[u8] = include_bytes!("../assets/node2.png");
+
+/// Side of the generated placeholders in pixels.
+const PLACEHOLDER_SIZE: u16 = 64;
+
+/// Decodes a PNG or any other format the `image` crate knows.
+pub fn decode_image(bytes: &[u8]) -> Result<Image, String> {
+    Image::from_file_with_format(bytes, None).map_err(|error| error.to_string())
+}
+
+/// A `size` pixels square image, transparent but for a solid circle of `color` filling it.
+pub fn placeholder_image(size: u16, color: Color) -> Image {
+    let mut image = Image::gen_image_color(size, size, Color::new(0.0, 0.0, 0.0, 0.0));
+    let (center, radius) = (vec2(size as f32, size as f32) / 2.0, size as f32 / 2.0);
+    for y in 0..size as u32 {
+        for x in 0..size as u32 {
+            if vec2(x as f32 + 0.5, y as f32 + 0.5).distance(center) <= radius {
+                image.set_pixel(x, y, color);
+            }
+        }
+    }
+    image
+}
+
+/// The embedded laser sprite, or a placeholder if it doesn't decode.
+pub fn embedded_laser() -> Texture2D {
+    texture_or_placeholder(decode_image(EMBEDDED_LASER), LASER_FILE, &mut Vec::new())
+}
+
+/// The embedded node sprite, or a placeholder if it doesn't decode.
+pub fn embedded_node() -> Texture2D {
+    texture_or_placeholder(decode_image(EMBEDDED_NODE), NODE_FILE, &mut Vec::new())
+}
+
+/// Textures the sandbox draws with, and what went wrong getting them. Nothing here is fatal: a
+/// sprite that can't be had is replaced by a placeholder.
+pub struct Textures {
+    pub laser: Texture2D,
+    pub node: Texture2D,
+    pub warnings: Vec<String>,
+}
+
+impl Textures {
+    /// The art built into the binary.
+    pub fn embedded() -> Self {
+        let mut warnings = Vec::new();
+        let laser = texture_or_placeholder(decode_image(EMBEDDED_LASER), LASER_FILE, &mut warnings);
+        let node = texture_or_placeholder(decode_image(EMBEDDED_NODE), NODE_FILE, &mut warnings);
+        Self { laser, node, warnings }
+    }
+
+    /// Art from `folder`, fetched over HTTP on the web. `None` keeps the embedded art.
+    pub async fn load(folder: Option<&Path>) -> Self {
+        let Some(folder) = folder else { return Self::embedded(); };
+        let mut warnings = Vec::new();
+        let laser = texture_or_placeholder(load_image(&folder.join(LASER_FILE)).await, LASER_FILE, &mut warnings);
+        let node = texture_or_placeholder(load_image(&folder.join(NODE_FILE)).await, NODE_FILE, &mut warnings);
+        Self { laser, node, warnings }
+    }
+}
+
+async fn load_image(path: &Path) -> Result<Image, String> {
+    let bytes = load_file(&path.to_string_lossy()).await.map_err(|error| format!("{}: {}", path.display(), error))?;
+    decode_image(&bytes).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn texture_or_placeholder(image: Result<Image, String>, name: &str, warnings: &mut Vec<String>) -> Texture2D {
+    let image = image.unwrap_or_else(|error| {
+        let warning = format!("Could not load {}, using a placeholder ({})", name, error);
+        error!("{}", warning);
+        warnings.push(warning);
+        placeholder_image(PLACEHOLDER_SIZE, WHITE)
+    });
+    Texture2D::from_image(&image)
+}
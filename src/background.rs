@@ -0,0 +1,114 @@
+// A reference image under the scene, for tracing existing optics diagrams.
+
+use std::path::{Path, PathBuf};
+
+use macroquad::color::Color;
+use macroquad::input::{is_mouse_button_down, is_mouse_button_pressed, MouseButton};
+use macroquad::math::Vec2;
+use macroquad::texture::{draw_texture_ex, DrawTextureParams, Image, Texture2D};
+use serde::{Deserialize, Serialize};
+
+use crate::rotate;
+
+/// Where the background image sits in the world.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundTransform {
+    /// World position of the image centre.
+    pub position: Vec2,
+    /// World units per image pixel.
+    pub scale: f32,
+    /// Clockwise on screen, in radians.
+    pub rotation: f32,
+}
+
+impl Default for BackgroundTransform {
+    fn default() -> Self {
+        Self { position: Vec2::ZERO, scale: 1.0, rotation: 0.0 }
+    }
+}
+
+impl BackgroundTransform {
+    /// `point` in image pixels from the image centre, along the image's own axes.
+    pub fn to_image(&self, point: Vec2) -> Vec2 {
+        rotate(point - self.position, -self.rotation) / self.scale
+    }
+
+    /// The world position of `pixel`, given from the image centre.
+    pub fn to_world(&self, pixel: Vec2) -> Vec2 {
+        self.position + rotate(pixel * self.scale, self.rotation)
+    }
+
+    /// Whether `point` falls on an image `size` pixels big.
+    pub fn contains(&self, size: Vec2, point: Vec2) -> bool {
+        let pixel = self.to_image(point);
+        pixel.x.abs() <= size.x / 2.0 && pixel.y.abs() <= size.y / 2.0
+    }
+}
+
+/// An image drawn under the network in world space. Nodes never snap to it; while unlocked it
+/// can be dragged around with the left mouse button.
+pub struct BackgroundLayer {
+    pub texture: Texture2D,
+    pub transform: BackgroundTransform,
+    pub opacity: f32,
+    pub locked: bool,
+    /// File it was loaded from, so the settings can load it again; `None` for images given as
+    /// bytes.
+    pub source: Option<PathBuf>,
+    /// Image centre minus the mouse position while dragged.
+    grab: Option<Vec2>,
+}
+
+impl BackgroundLayer {
+    /// Decodes a PNG, JPEG or any other format the `image` crate knows, e.g. on the web where
+    /// there is no file system.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let image = Image::from_file_with_format(bytes, None).map_err(|error| error.to_string())?;
+        Ok(Self {
+            texture: Texture2D::from_image(&image),
+            transform: BackgroundTransform::default(),
+            opacity: 0.5,
+            locked: false,
+            source: None,
+            grab: None,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+        Ok(Self { source: Some(path.to_path_buf()), ..Self::from_bytes(&bytes)? })
+    }
+
+    /// Call inside the world camera, before the network is drawn.
+    pub fn draw(&self) {
+        let size = self.texture.size() * self.transform.scale;
+        let corner = self.transform.position - size / 2.0;
+        draw_texture_ex(&self.texture, corner.x, corner.y, Color::new(1.0, 1.0, 1.0, self.opacity),
+                        DrawTextureParams {
+                            dest_size: Some(size),
+                            rotation: self.transform.rotation,
+                            ..Default::default()
+                        });
+    }
+
+    /// Left drag moves the image unless it is locked. `can_grab` is false while something on top
+    /// of it has the mouse; `mouse` is in world space.
+    pub fn handle_drag(&mut self, mouse: Vec2, can_grab: bool) {
+        if self.locked || !is_mouse_button_down(MouseButton::Left) {
+            self.grab = None;
+            return;
+        }
+        if can_grab && self.grab.is_none() && is_mouse_button_pressed(MouseButton::Left)
+            && self.transform.contains(self.texture.size(), mouse) {
+            self.grab = Some(self.transform.position - mouse);
+        }
+        if let Some(offset) = self.grab {
+            self.transform.position = mouse + offset;
+        }
+    }
+
+    pub const fn is_dragged(&self) -> bool {
+        self.grab.is_some()
+    }
+}
@@ -0,0 +1,192 @@
+// Batch mode: the native binary run with `--scene` replays a saved session without opening a
+// window, traces it and writes the results, for regression checks in CI and for parameter sweeps
+// driven by a script. There is no window to render a PNG in, the SVG stands in for it.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use macroquad::color::Color;
+use macroquad::math::Vec2;
+
+use crate::core::{Interaction, Segment, Surfaces, TraceResult, TraceStats};
+use crate::replay::{Replayer, Session};
+use crate::settings::SimulationSettings;
+use crate::{theme, Laser, NodeNetwork};
+
+pub const HELP: &str = "\
+Usage: ray_cast [--scene FILE [OPTIONS]]
+
+Without arguments the sandbox opens in a window. With --scene the saved session FILE is
+replayed without a window, traced, and the results written as asked.
+
+Options:
+  --scene FILE          session to load, as saved from the sandbox (session.json)
+  --trace-out FILE      write every traced line as CSV
+  --svg-out FILE        draw the scene and the beam as SVG
+  --max-rays N          trace with a budget of N lines instead of the session's
+  --time-limit-ms MS    give up on traces running longer, instead of the session's limit
+  -h, --help            print this help
+
+Exit codes: 0 done, 2 bad arguments, 3 scene not loaded, 4 trace aborted, 5 output not written.
+";
+
+/// What the command line asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// The sandbox in a window, as without arguments.
+    Window,
+    Help,
+    Batch(BatchArgs),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchArgs {
+    pub scene: PathBuf,
+    pub trace_out: Option<PathBuf>,
+    pub svg_out: Option<PathBuf>,
+    pub max_rays: Option<usize>,
+    pub time_limit_ms: Option<f32>,
+}
+
+/// Why a batch run failed, each with its own exit code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchError {
+    Usage(String),
+    Load(String),
+    Trace(String),
+    Write(String),
+}
+
+impl BatchError {
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            BatchError::Usage(_) => 2,
+            BatchError::Load(_) => 3,
+            BatchError::Trace(_) => 4,
+            BatchError::Write(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchError::Usage(message) => write!(f, "{}\n\n{}", message, HELP),
+            BatchError::Load(message) => write!(f, "could not load the scene: {}", message),
+            BatchError::Trace(message) => write!(f, "trace failed: {}", message),
+            BatchError::Write(message) => write!(f, "could not write the output: {}", message),
+        }
+    }
+}
+
+impl Command {
+    /// Reads the command line, without the program name.
+    pub fn parse(args: impl IntoIterator<Item=String>) -> Result<Self, BatchError> {
+        let mut args = args.into_iter();
+        let mut batch = BatchArgs::default();
+        let mut scene = None;
+        let mut any = false;
+        while let Some(flag) = args.next() {
+            any = true;
+            let mut value = || args.next().ok_or_else(|| BatchError::Usage(format!("{} needs a value", flag)));
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "--scene" => scene = Some(PathBuf::from(value()?)),
+                "--trace-out" => batch.trace_out = Some(PathBuf::from(value()?)),
+                "--svg-out" => batch.svg_out = Some(PathBuf::from(value()?)),
+                "--max-rays" => batch.max_rays = Some(parse_number(&flag, &value()?)?),
+                "--time-limit-ms" => batch.time_limit_ms = Some(parse_number(&flag, &value()?)?),
+                "--png-out" => {
+                    return Err(BatchError::Usage("--png-out needs a window to render in, use --svg-out".to_string()));
+                }
+                _ => return Err(BatchError::Usage(format!("unknown argument {}", flag))),
+            }
+        }
+        if !any { return Ok(Command::Window); }
+        batch.scene = scene.ok_or_else(|| BatchError::Usage("--scene is required".to_string()))?;
+        Ok(Command::Batch(batch))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, BatchError> {
+    value.trim().parse().map_err(|_| BatchError::Usage(format!("{} takes a number, not {}", flag, value)))
+}
+
+/// Replays the scene, traces it with the session's settings as overridden by `args` and writes
+/// the outputs asked for. The settings stay applied to the global sliders.
+pub fn run(args: &BatchArgs) -> Result<TraceStats, BatchError> {
+    let session = Session::load(&args.scene)
+        .map_err(|error| BatchError::Load(format!("{}: {}", args.scene.display(), error)))?;
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    Replayer::new(session).run_to_end(&mut network, &mut laser);
+    let mut settings = SimulationSettings::current();
+    // the automatic budget follows the frame time, which would make runs differ
+    settings.auto_ray_budget = false;
+    if let Some(max_rays) = args.max_rays { settings.max_rays = max_rays as f32; }
+    if let Some(limit) = args.time_limit_ms { settings.trace_time_limit_ms = limit; }
+    settings.apply();
+
+    let segments = network.traced_segments();
+    let surfaces = network.surfaces();
+    let trace = if laser.sweep.enabled {
+        laser.solve_collisions_sweep(&segments, &surfaces)
+    } else {
+        laser.solve_collisions_with_surfaces(&segments, &surfaces)
+    };
+    if trace.stats.aborted {
+        return Err(BatchError::Trace(format!("ran past the time limit of {} ms", settings.trace_time_limit_ms)));
+    }
+    if let Some(path) = &args.trace_out {
+        write_file(path, |w| trace.write_csv(w))?;
+    }
+    if let Some(path) = &args.svg_out {
+        write_file(path, |w| write_svg(w, &segments, &surfaces, &trace))?;
+    }
+    Ok(trace.stats)
+}
+
+fn write_file(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>) -> Result<(), BatchError> {
+    let written = File::create(path).map(BufWriter::new).and_then(|mut w| {
+        write(&mut w)?;
+        w.flush()
+    });
+    written.map_err(|error| BatchError::Write(format!("{}: {}", path.display(), error)))
+}
+
+/// Draws `segments` in their theme colors and widths from `surfaces`, and the lines of `trace`
+/// over them, framed around the segments and wherever the beam starts or bounces; escaping lines
+/// are cut off at the frame.
+pub fn write_svg<W: Write>(mut w: W, segments: &[Segment], surfaces: &Surfaces, trace: &TraceResult)
+                           -> std::io::Result<()> {
+    let points = segments.iter().flat_map(|segment| [segment.0, segment.1])
+        .chain(trace.hits.iter().map(|hit| hit.origin))
+        .chain(trace.hits.iter().filter(|hit| hit.interaction != Interaction::Escaped).map(|hit| hit.position));
+    let (min, max) = points.fold((Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                                 |(min, max), point| (min.min(point), max.max(point)));
+    let (min, max) = if min.x <= max.x { (min, max) } else { (Vec2::ZERO, Vec2::ONE) };
+    let margin = (max - min).max_element().max(1.0) * 0.05;
+    let (min, size) = (min - Vec2::splat(margin), max - min + Vec2::splat(2.0 * margin));
+    let theme = theme::current();
+    writeln!(w, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#, min.x, min.y, size.x, size.y)?;
+    writeln!(w, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#, min.x, min.y, size.x, size.y,
+             hex(theme.background))?;
+    for (i, segment) in segments.iter().enumerate() {
+        writeln!(w, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}"/>"#, segment.0.x,
+                 segment.0.y, segment.1.x, segment.1.y, hex(theme.state_color(segment.2)), surfaces.thickness(i))?;
+    }
+    for hit in &trace.hits {
+        let opacity = hit.color.a.clamp(0.0, 1.0);
+        writeln!(w, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-opacity="{}" stroke-width="1"/>"#,
+                 hit.origin.x, hit.origin.y, hit.position.x, hit.position.y, hex(hit.color), opacity)?;
+    }
+    writeln!(w, "</svg>")
+}
+
+/// `#rrggbb` of `color`, without its alpha.
+fn hex(color: Color) -> String {
+    let [r, g, b, _]: [u8; 4] = color.into();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
@@ -0,0 +1,105 @@
+// Reusing traces when neither the beam, the scene nor the settings changed.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use crate::core::{Ray, SimConfig, TraceResult, Watchdog};
+use crate::RayColorMode;
+
+/// Fingerprint of everything a trace depends on: the emitted ray, how it is colored, the
+/// network generation and the simulation settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceKey(u64);
+
+impl TraceKey {
+    pub fn new(ray: Ray, color_mode: RayColorMode, generation: u64, config: &SimConfig) -> Self {
+        let mut hasher = DefaultHasher::new();
+        let floats = [ray.origin.x, ray.origin.y, ray.direction.x, ray.direction.y,
+            ray.color.r, ray.color.g, ray.color.b, ray.color.a,
+            config.max_distance, config.min_intensity, config.edge_radius.unwrap_or(-1.0), config.refractive_index,
+            config.max_gloss_angle, config.attenuation.coefficient(), config.min_hit_distance];
+        for value in floats { value.to_bits().hash(&mut hasher); }
+        color_mode.hash(&mut hasher);
+        generation.hash(&mut hasher);
+        config.max_rays.hash(&mut hasher);
+        config.max_bounces_per_ray.hash(&mut hasher);
+        config.precision.hash(&mut hasher);
+        config.seed.hash(&mut hasher);
+        config.gloss_samples.hash(&mut hasher);
+        config.collision_mask.hash(&mut hasher);
+        config.attenuation.kind().hash(&mut hasher);
+        match config.watchdog {
+            Watchdog::Off => 0u8.hash(&mut hasher),
+            Watchdog::Micros(limit) => (1u8, limit.to_bits()).hash(&mut hasher),
+            Watchdog::Tests(limit) => (2u8, limit).hash(&mut hasher),
+        }
+        Self(hasher.finish())
+    }
+
+    /// This key with something else the trace depends on folded in.
+    pub fn with(self, extra: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        extra.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// The last few traces, most recently used first, so moving the laser back and forth between
+/// poses it already visited does not retrace.
+#[derive(Clone, Debug)]
+pub struct TraceCache {
+    entries: VecDeque<(TraceKey, TraceResult)>,
+    capacity: usize,
+    hits: usize,
+    misses: usize,
+}
+
+impl TraceCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: VecDeque::with_capacity(capacity), capacity: capacity.max(1), hits: 0, misses: 0 }
+    }
+
+    /// The trace stored for `key`, counting a hit or a miss.
+    pub fn get(&mut self, key: TraceKey) -> Option<&TraceResult> {
+        let Some(index) = self.entries.iter().position(|(stored, _)| *stored == key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let entry = self.entries.remove(index)?;
+        self.entries.push_front(entry);
+        self.entries.front().map(|(_, result)| result)
+    }
+
+    /// Stores a trace, evicting the least recently used one when full. A trace the watchdog
+    /// cut short depends on timing rather than on the key, so it isn't stored.
+    pub fn insert(&mut self, key: TraceKey, result: TraceResult) {
+        if result.stats.aborted { return; }
+        self.entries.retain(|(stored, _)| *stored != key);
+        self.entries.truncate(self.capacity - 1);
+        self.entries.push_front((key, result));
+    }
+
+    /// Forgets every stored trace, for changes the key cannot see.
+    pub fn invalidate(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub const fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub const fn misses(&self) -> usize {
+        self.misses
+    }
+}
@@ -0,0 +1,1648 @@
+// Headless tracing core: geometry, optics and the bounce loop.
+// Nothing in here touches textures or draw calls, so it can run in tests and benchmarks.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use macroquad::color::Color;
+use macroquad::math::{DVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum EdgeState {
+    #[default]
+    Reflective,
+    Absorptive,
+    Transparent,
+}
+
+impl EdgeState {
+    /// Every state, in the order clicking an edge cycles through them.
+    pub const ALL: [EdgeState; 3] = [EdgeState::Reflective, EdgeState::Absorptive, EdgeState::Transparent];
+
+    /// The state after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|state| *state == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Name shown in the UI.
+    pub const fn label(self) -> &'static str {
+        match self {
+            EdgeState::Reflective => "Reflective",
+            EdgeState::Absorptive => "Absorptive",
+            EdgeState::Transparent => "Transparent",
+        }
+    }
+
+    /// Stable lower-case name, what `Display` writes and `FromStr` reads.
+    pub const fn name(self) -> &'static str {
+        match self {
+            EdgeState::Reflective => "reflective",
+            EdgeState::Absorptive => "absorptive",
+            EdgeState::Transparent => "transparent",
+        }
+    }
+}
+
+impl fmt::Display for EdgeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EdgeState {
+    type Err = String;
+
+    /// One of the `name`s, in any case.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter()
+            .find(|state| state.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("unknown edge state {:?}", name))
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Segment(pub Vec2, pub Vec2, pub EdgeState);
+
+impl Segment {
+    /// The endpoints widened to double precision.
+    pub fn as_dvec2(&self) -> (DVec2, DVec2) {
+        (self.0.as_dvec2(), self.1.as_dvec2())
+    }
+}
+
+/// Layer segments are on unless `Surfaces::layers` says otherwise.
+pub const DEFAULT_LAYER: u8 = 1;
+/// Thickness segments are drawn at unless `Surfaces::thickness` says otherwise.
+pub const DEFAULT_THICKNESS: f32 = 5.0;
+
+/// Per-segment properties beyond the geometry, each parallel to the traced segments. Missing
+/// entries count as no medium, perfectly smooth, `DEFAULT_LAYER`, the same from both sides,
+/// `DEFAULT_THICKNESS` wide and lossless.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Surfaces {
+    /// See `trace_with_media`.
+    pub media: Vec<Option<usize>>,
+    /// See `trace_with_surfaces`.
+    pub roughness: Vec<f32>,
+    /// Bitmask of the layers each segment is on, rays only hit segments on a layer in
+    /// `SimConfig::collision_mask`.
+    pub layers: Vec<u8>,
+    /// State of the back face, the side `(end - start).perp()` points away from, where it
+    /// differs from the front's `Segment::2`.
+    pub back_states: Vec<Option<EdgeState>>,
+    /// Light the segment gives off itself, see `trace_lights`.
+    pub emitters: Vec<Option<EmitterConfig>>,
+    /// Width the segment is drawn at, and collides at with `SimConfig::edge_radius`.
+    pub thickness: Vec<f32>,
+    /// Light a reflective segment swallows depending on the angle it is hit at.
+    pub absorption: Vec<Option<AbsorptionCurve>>,
+}
+
+impl Surfaces {
+    /// Fraction of the light segment `segment` absorbs when reflecting a ray that meets it at
+    /// `cos_incidence`, the cosine of the angle from the normal.
+    pub fn absorbed(&self, segment: usize, cos_incidence: f32) -> f32 {
+        self.absorption.get(segment).copied().flatten().map_or(0.0, |curve| curve.at(cos_incidence))
+    }
+
+    /// Width of segment `segment`, `DEFAULT_THICKNESS` if not given.
+    pub fn thickness(&self, segment: usize) -> f32 {
+        self.thickness.get(segment).copied().unwrap_or(DEFAULT_THICKNESS)
+    }
+
+    /// Capsule radius of segment `segment` with `SimConfig::edge_radius` of `fallback`.
+    fn capsule_radius(&self, segment: usize, fallback: f32) -> f32 {
+        self.thickness.get(segment).map_or(fallback, |thickness| thickness / 2.0)
+    }
+
+    /// Whether rays tracing with `mask` hit segment `segment`.
+    pub fn collides(&self, segment: usize, mask: u8) -> bool {
+        on_layers(&self.layers, segment, mask)
+    }
+
+    /// How segment `index` behaves for a ray going `direction`, which decides the face it hits.
+    pub fn state(&self, index: usize, segment: &Segment, direction: DVec2) -> EdgeState {
+        let (start, end) = segment.as_dvec2();
+        match self.back_states.get(index).copied().flatten() {
+            Some(back) if direction.dot((end - start).perp()) > 0.0 => back,
+            _ => segment.2,
+        }
+    }
+}
+
+/// `Surfaces::collides` with the segments on `layers`.
+fn on_layers(layers: &[u8], segment: usize, mask: u8) -> bool {
+    layers.get(segment).copied().unwrap_or(DEFAULT_LAYER) & mask != 0
+}
+
+/// How much light a mirror absorbs by the angle θ from the normal it is hit at:
+/// `base + rise * (1 - cos θ)^power`, kept within 0..=1, so with a positive `rise` glancing hits
+/// lose the most. The default absorbs nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AbsorptionCurve {
+    pub base: f32,
+    pub rise: f32,
+    pub power: f32,
+}
+
+impl Default for AbsorptionCurve {
+    fn default() -> Self {
+        Self { base: 0.0, rise: 0.0, power: 1.0 }
+    }
+}
+
+impl AbsorptionCurve {
+    /// Fraction absorbed at `cos_incidence`, the cosine of the angle from the normal.
+    pub fn at(&self, cos_incidence: f32) -> f32 {
+        let steepness = 1.0 - cos_incidence.clamp(0.0, 1.0);
+        (self.base + self.rise * steepness.powf(self.power)).clamp(0.0, 1.0)
+    }
+}
+
+/// A glowing segment: `rays` rays leave from points spread evenly along it, off its front face
+/// (the side `(end - start).perp()` points to), each turned up to `spread` radians either way.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EmitterConfig {
+    pub rays: usize,
+    pub spread: f32,
+    /// RGB of the emitted light.
+    pub color: [f32; 3],
+    /// Alpha the rays start with, as the laser's does.
+    pub intensity: f32,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self { rays: 16, spread: 0.6, color: [1.0, 0.9, 0.6], intensity: 1.0 }
+    }
+}
+
+impl EmitterConfig {
+    /// The rays leaving `segment`, the same every call. A segment too short to have a face
+    /// emits nothing.
+    pub fn rays(&self, segment: &Segment) -> Vec<Ray> {
+        let Some(normal) = (segment.1 - segment.0).perp().try_normalize() else { return Vec::new(); };
+        let [r, g, b] = self.color;
+        let color = Color::new(r, g, b, self.intensity.clamp(0.0, 1.0));
+        (0..self.rays).map(|i| {
+            let along = (i as f32 + 0.5) / self.rays as f32;
+            // golden ratio steps, so neighbouring points don't all lean the same way
+            let turn = ((i as f32 * 0.618_034 + 0.5).fract() * 2.0 - 1.0) * self.spread;
+            Ray::new(segment.0.lerp(segment.1, along), Vec2::from_angle(turn).rotate(normal), color)
+        }).collect()
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CollisionInfo {
+    pub position: Vec2,
+    pub normal: Vec2,
+}
+
+/// The segment a ray meets first, see `find_closest_segment_new`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ClosestHit {
+    pub info: CollisionInfo,
+    /// Index into the traced segments, which `NodeNetwork::traced_edges` maps back to edges.
+    pub segment_index: usize,
+    /// 0 at the segment's start, 1 at its end.
+    pub t_along_segment: f32,
+}
+
+/// Where a ray crosses a segment, see `Ray::intersect_segment`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SegmentHit {
+    pub point: Vec2,
+    /// Unit normal of the segment facing the ray (`normal.dot(direction) <= 0`).
+    pub normal: Vec2,
+    /// Distance from the origin along the ray.
+    pub t_ray: f32,
+    /// 0 at the segment's start, 1 at its end.
+    pub t_seg: f32,
+}
+
+/// Where a ray first meets a circle, see `Ray::intersect_circle`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CircleHit {
+    pub point: Vec2,
+    /// Unit normal pointing out of the circle.
+    pub normal: Vec2,
+    /// Distance from the origin along the ray.
+    pub t: f32,
+    /// The ray starts inside the circle, so `point` is where it leaves.
+    pub from_inside: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Vec2,
+    pub direction: Vec2,
+    pub color: Color,
+}
+
+/// `Ray` with its geometry in double precision, see `Precision::Double`.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray64 {
+    pub origin: DVec2,
+    pub direction: DVec2,
+    pub color: Color,
+}
+
+/// Floating point width the bounce loop does its geometry in. Over thousands of bounces in a
+/// small box the error of `Single` adds up until the beam drifts off or tunnels through a
+/// wall; `Double` keeps it on track. Hits come out as `Vec2` either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Precision {
+    #[default]
+    Single,
+    Double,
+}
+
+/// What happened to a ray at the end of one traced line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    Reflected,
+    Absorbed,
+    /// Hit a transparent edge and split into a reflected and a transmitted ray.
+    Split,
+    /// Did not hit anything within `SimConfig::max_distance`.
+    Escaped,
+    /// Faded below `SimConfig::min_intensity` on the way, see `Attenuation`.
+    Faded,
+}
+
+/// One traced line: from `origin` to `position`, ending on `segment` (index into the slice
+/// passed to `trace`) unless the ray escaped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceHit {
+    pub origin: Vec2,
+    pub position: Vec2,
+    pub segment: Option<usize>,
+    pub interaction: Interaction,
+    pub color: Color,
+    /// Number of interactions before this line; the line leaving the laser is bounce 0.
+    pub bounce: usize,
+    /// Distance travelled from the laser up to `position`, along this branch.
+    pub path_length: f32,
+    /// Index in `hits` of the line this one leaves from, `None` for lines leaving a source.
+    pub parent: Option<usize>,
+    /// Normal of `segment` where it was hit, facing the ray; zero without a segment.
+    pub normal: Vec2,
+    /// Index in `hits` of the first line of this branch, the same for every line descending
+    /// from one ray leaving its source.
+    pub ray: usize,
+    /// State of `segment` as the ray met it, after two-sided segments picked their face.
+    pub state: Option<EdgeState>,
+    /// Radians between the ray and the normal of `segment`, 0 head-on.
+    pub incidence: f32,
+    /// Intensity of the rays continuing from `position`, 0 where the branch ends.
+    pub outgoing: f32,
+}
+
+/// Why a branch of the beam stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Termination {
+    Absorbed,
+    /// Too dim to continue (`SimConfig::min_intensity`).
+    IntensityCutoff,
+    /// Nothing hit within `SimConfig::max_distance`.
+    MaxDistance,
+    /// Still going when `SimConfig::max_rays` ran out.
+    Budget,
+    /// Bounced `SimConfig::max_bounces_per_ray` times already.
+    MaxBounces,
+}
+
+impl Termination {
+    pub const ALL: [Termination; 5] = [Termination::Absorbed, Termination::IntensityCutoff, Termination::MaxDistance,
+        Termination::Budget, Termination::MaxBounces];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathEnd {
+    pub position: Vec2,
+    pub reason: Termination,
+}
+
+/// Size and cost of one trace, for the performance HUD and the scene panel.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TraceStats {
+    pub segments: usize,
+    pub lines: usize,
+    /// Rays were still queued when `SimConfig::max_rays` ran out.
+    pub budget_exhausted: bool,
+    /// Branches ended by each `Termination`, see `TraceStats::terminations`.
+    pub absorbed: usize,
+    pub max_distance: usize,
+    pub intensity_cutoff: usize,
+    pub budget: usize,
+    pub max_bounces: usize,
+    /// Rough size of the hit, end and line buffers the result holds.
+    pub buffer_bytes: usize,
+    /// Wall time spent tracing, filled in by whoever timed the call; the core doesn't keep time.
+    pub trace_micros: f32,
+    /// `SimConfig::watchdog` stopped the trace before it was done, so the lines are only the
+    /// ones traced by then.
+    pub aborted: bool,
+    /// Rays tested against segments, counted once per segment, as a measure of the work done.
+    pub segment_tests: u64,
+}
+
+impl TraceStats {
+    /// Branches that ended for `reason`.
+    pub const fn terminations(&self, reason: Termination) -> usize {
+        match reason {
+            Termination::Absorbed => self.absorbed,
+            Termination::IntensityCutoff => self.intensity_cutoff,
+            Termination::MaxDistance => self.max_distance,
+            Termination::Budget => self.budget,
+            Termination::MaxBounces => self.max_bounces,
+        }
+    }
+}
+
+/// Everything `trace` produced: the lines in emission order and where each branch ended.
+#[derive(Debug, Clone, Default)]
+pub struct TraceResult {
+    pub hits: Vec<TraceHit>,
+    pub ends: Vec<PathEnd>,
+    pub stats: TraceStats,
+    lines: Vec<(Vec2, Vec2, Color)>,
+    segment_hits: Vec<f32>,
+}
+
+impl TraceResult {
+    pub fn new(hits: Vec<TraceHit>, ends: Vec<PathEnd>, segment_hits: Vec<f32>) -> Self {
+        let mut result = Self { hits, ends, stats: TraceStats::default(), lines: Vec::new(), segment_hits };
+        result.finish();
+        result
+    }
+    /// Empties the result for `segments` segments, keeping every buffer's capacity for the
+    /// next trace to fill.
+    fn reset(&mut self, segments: usize) {
+        self.hits.clear();
+        self.ends.clear();
+        self.lines.clear();
+        self.segment_hits.clear();
+        self.segment_hits.resize(segments, 0.0);
+    }
+    /// Builds the lines and the stats from the hits and ends.
+    fn finish(&mut self) {
+        self.lines.clear();
+        self.lines.extend(self.hits.iter().map(|hit| (hit.origin, hit.position, hit.color)));
+        let buffer_bytes = self.hits.capacity() * size_of::<TraceHit>()
+            + self.ends.capacity() * size_of::<PathEnd>()
+            + self.lines.capacity() * size_of::<(Vec2, Vec2, Color)>()
+            + self.segment_hits.capacity() * size_of::<f32>();
+        let count = |reason| self.ends.iter().filter(|end: &&PathEnd| end.reason == reason).count();
+        let budget = count(Termination::Budget);
+        self.stats = TraceStats {
+            segments: self.segment_hits.len(),
+            lines: self.lines.len(),
+            budget_exhausted: budget > 0,
+            absorbed: count(Termination::Absorbed),
+            max_distance: count(Termination::MaxDistance),
+            intensity_cutoff: count(Termination::IntensityCutoff),
+            budget,
+            max_bounces: count(Termination::MaxBounces),
+            buffer_bytes,
+            trace_micros: 0.0,
+            aborted: self.stats.aborted,
+            segment_tests: self.stats.segment_tests,
+        };
+    }
+    /// One result holding the lines and ends of all of `results` in order, with the segment
+    /// intensities summed, e.g. traces of the same scene from several beams.
+    pub fn combine(results: impl IntoIterator<Item=TraceResult>) -> Self {
+        let (mut hits, mut ends, mut segment_hits) = (Vec::<TraceHit>::new(), Vec::new(), Vec::<f32>::new());
+        let (mut aborted, mut segment_tests) = (false, 0);
+        for result in results {
+            aborted |= result.stats.aborted;
+            segment_tests += result.stats.segment_tests;
+            let offset = hits.len();
+            hits.extend(result.hits.into_iter().map(|hit| TraceHit {
+                ray: hit.ray + offset,
+                parent: hit.parent.map(|parent| parent + offset),
+                ..hit
+            }));
+            ends.extend(result.ends);
+            if segment_hits.len() < result.segment_hits.len() { segment_hits.resize(result.segment_hits.len(), 0.0); }
+            for (total, hits) in segment_hits.iter_mut().zip(result.segment_hits) { *total += hits; }
+        }
+        let mut combined = Self::new(hits, ends, segment_hits);
+        combined.stats.aborted = aborted;
+        combined.stats.segment_tests = segment_tests;
+        combined
+    }
+    /// Intensity received by each segment, parallel to the slice passed to `trace`. Transparent
+    /// segments only count the part they reflect away, not what passes through.
+    pub fn segment_hits(&self) -> &[f32] {
+        &self.segment_hits
+    }
+    /// Drops the counts of the segments from `len` on, for segments a caller appended to the
+    /// scene's for the trace only.
+    pub fn forget_segments_from(&mut self, len: usize) {
+        self.segment_hits.truncate(len);
+        self.stats.segments = self.segment_hits.len();
+    }
+    /// The hits as plain `(start, end, color)` lines, for drawing.
+    pub fn lines(&self) -> &[(Vec2, Vec2, Color)] {
+        &self.lines
+    }
+    /// Writes one CSV row per line, in emission order, for analysis elsewhere: the ray it
+    /// descends from, its bounce, where it starts and ends, the segment it ends on with the
+    /// segment's state and the angle of incidence in degrees, and its intensity arriving and
+    /// carried on. Cells that don't apply, like the segment of an escaped line, are empty.
+    pub fn write_csv<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        writeln!(w, "ray,bounce,origin_x,origin_y,hit_x,hit_y,segment,state,interaction,incidence,\
+                     intensity_before,intensity_after")?;
+        for hit in &self.hits {
+            let segment = hit.segment.map_or(String::new(), |segment| segment.to_string());
+            let state = hit.state.map_or(String::new(), |state| state.to_string());
+            let incidence = hit.state.map_or(String::new(), |_| hit.incidence.to_degrees().to_string());
+            writeln!(w, "{},{},{},{},{},{},{},{},{:?},{},{},{}", hit.ray, hit.bounce, hit.origin.x, hit.origin.y,
+                     hit.position.x, hit.position.y, segment, state, hit.interaction, incidence, hit.color.a,
+                     hit.outgoing)?;
+        }
+        Ok(())
+    }
+    /// The line ending on a segment closest to `point`, i.e. the bounce there, if one is within
+    /// `radius`.
+    pub fn nearest_bounce(&self, point: Vec2, radius: f32) -> Option<usize> {
+        self.hits.iter().enumerate()
+            .filter(|(_, hit)| hit.segment.is_some())
+            .map(|(i, hit)| (i, hit.position.distance(point)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+    /// The lines leaving from the end of the line at `index`.
+    pub fn children(&self, index: usize) -> impl Iterator<Item=&TraceHit> {
+        self.hits.iter().filter(move |hit| hit.parent == Some(index))
+    }
+    /// Recomputes the color of every line from its hit, leaving the hits themselves alone.
+    pub fn recolor(&mut self, color: impl Fn(&TraceHit) -> Color) {
+        for (line, hit) in self.lines.iter_mut().zip(&self.hits) {
+            line.2 = color(hit);
+        }
+    }
+    /// Hits that ended on an absorptive segment with the color of their line, i.e. where the
+    /// light ends up.
+    pub fn absorbed(&self) -> impl Iterator<Item=(&TraceHit, Color)> {
+        self.hits.iter().zip(&self.lines)
+            .filter(|(hit, _)| hit.interaction == Interaction::Absorbed)
+            .map(|(hit, line)| (hit, line.2))
+    }
+    /// Summed length of every traced line, escaped ones included.
+    pub fn total_path_length(&self) -> f32 {
+        self.hits.iter().map(|hit| hit.origin.distance(hit.position)).sum()
+    }
+    pub fn deepest_bounce(&self) -> Option<usize> {
+        self.hits.iter().map(|hit| hit.bounce).max()
+    }
+    pub fn terminations(&self, reason: Termination) -> usize {
+        self.stats.terminations(reason)
+    }
+    /// Where branches were cut off by `SimConfig::max_rays`.
+    pub fn truncated_ends(&self) -> impl Iterator<Item=Vec2> + '_ {
+        self.ends.iter().filter(|end| end.reason == Termination::Budget).map(|end| end.position)
+    }
+}
+
+/// Hits closer than this (in world units) to a ray's origin are ignored, so a ray leaving a
+/// wall does not immediately hit the wall, or a neighbour sharing the node, it starts on. The
+/// default of `SimConfig::min_hit_distance`.
+pub const HIT_EPSILON: f32 = 1e-3;
+/// World units a bounced ray starts off the wall it left, on the side it leaves to, so rounding
+/// can't put it back behind the wall, per `Precision`. Well below `HIT_EPSILON`; the drawn line
+/// still starts on the wall.
+const ORIGIN_NUDGE: f64 = 1e-4;
+const ORIGIN_NUDGE_64: f64 = 1e-7;
+/// Slack on the segment parameter so hits exactly on an endpoint count for the segment.
+const ENDPOINT_EPSILON: f32 = 1e-5;
+/// The same slack for single precision ray hits, in world units: rounding misses a corner by
+/// about as much along a short wall as along a long one.
+const ENDPOINT_SLACK: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimConfig {
+    /// Total number of lines a single trace may produce.
+    pub max_rays: usize,
+    /// Lines one ray from a source may be followed for, its own and those of every bounce and
+    /// split after it, so one ray trapped between mirrors can't use up `max_rays` on its own.
+    pub max_bounces_per_ray: usize,
+    pub max_distance: f32,
+    /// Rays whose alpha drops to or below this are discarded.
+    pub min_intensity: f32,
+    /// `Some(radius)` collides segments as capsules (thick walls with round, corner-sealing caps)
+    /// of half their `Surfaces::thickness`, or of `radius` where that isn't given; `None` keeps
+    /// them infinitely thin.
+    pub edge_radius: Option<f32>,
+    /// Index of refraction inside closed transparent shapes, see `trace_with_media`.
+    pub refractive_index: f32,
+    pub precision: Precision,
+    /// Seeds the scatter of rough reflections, so the same scene traces the same every frame.
+    pub seed: u64,
+    /// Widest turn, in radians either way, a reflection off an edge of roughness 1 may take.
+    pub max_gloss_angle: f32,
+    /// Rays a rough reflection splits into, each carrying an equal share of the intensity.
+    pub gloss_samples: usize,
+    /// Layers the rays collide with, see `Surfaces::layers`.
+    pub collision_mask: u8,
+    /// How the beam fades with the distance travelled.
+    pub attenuation: Attenuation,
+    /// Hits closer than this to a ray's origin are ignored, so a bounced ray doesn't hit the
+    /// wall it left, or one overlapping it, again at no distance. See `HIT_EPSILON`.
+    pub min_hit_distance: f32,
+    /// Gives up on scenes that take too long to trace, see `TraceStats::aborted`.
+    pub watchdog: Watchdog,
+}
+
+/// Ceiling on how long one trace may run, checked every `Watchdog::CHECK_EVERY` rays so it
+/// costs next to nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Watchdog {
+    #[default]
+    Off,
+    /// Wall time in microseconds. Native only: the web has no clock that moves within a frame,
+    /// so there it never fires.
+    Micros(f32),
+    /// Ray-segment tests, standing in for time where there is no clock. One test per segment
+    /// per ray, see `TraceStats::segment_tests`.
+    Tests(u64),
+}
+
+impl Watchdog {
+    pub const CHECK_EVERY: usize = 256;
+}
+
+/// A `Watchdog` counting from the start of a trace.
+struct WatchdogTimer {
+    watchdog: Watchdog,
+    #[cfg(not(target_family = "wasm"))]
+    started: std::time::Instant,
+}
+
+impl WatchdogTimer {
+    fn start(watchdog: Watchdog) -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        return Self { watchdog, started: std::time::Instant::now() };
+        #[cfg(target_family = "wasm")]
+        return Self { watchdog };
+    }
+
+    /// Whether the trace has run out of time `tests` ray-segment tests in.
+    fn expired(&self, tests: u64) -> bool {
+        match self.watchdog {
+            Watchdog::Off => false,
+            #[cfg(not(target_family = "wasm"))]
+            Watchdog::Micros(limit) => self.started.elapsed().as_secs_f32() * 1e6 > limit,
+            #[cfg(target_family = "wasm")]
+            Watchdog::Micros(_) => false,
+            Watchdog::Tests(limit) => tests > limit,
+        }
+    }
+}
+
+/// Loss of intensity with distance, as in hazy air. Distances are along the path from the laser.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Attenuation {
+    #[default]
+    None,
+    /// A fraction `per_unit` lost every unit travelled, i.e. `exp(-per_unit * distance)`.
+    Exponential { per_unit: f32 },
+    /// `1 / (1 + (distance / scale)^2)`: full strength near the laser, falling off as the
+    /// inverse square further out.
+    InverseSquareApprox { scale: f32 },
+}
+
+impl Attenuation {
+    pub const LABELS: [&'static str; 3] = ["None", "Exponential", "Inverse square"];
+
+    /// What the intensity is multiplied by going `length` further from `from` along the path.
+    pub fn factor(self, from: f32, length: f32) -> f32 {
+        match self {
+            Attenuation::None => 1.0,
+            Attenuation::Exponential { per_unit } => (-per_unit.max(0.0) * length).exp(),
+            Attenuation::InverseSquareApprox { scale } if scale > 0.0 => {
+                let falloff = |distance: f32| 1.0 / (1.0 + (distance / scale).powi(2));
+                falloff(from + length) / falloff(from)
+            }
+            Attenuation::InverseSquareApprox { .. } => 1.0,
+        }
+    }
+
+    /// How much further than `from` a ray of `intensity` gets before fading to `min`, `None`
+    /// if it never does.
+    pub fn reach(self, from: f32, intensity: f32, min: f32) -> Option<f32> {
+        if min <= 0.0 { return None; }
+        let ratio = intensity / min;
+        match self {
+            Attenuation::Exponential { per_unit } if per_unit > 0.0 => Some(ratio.ln().max(0.0) / per_unit),
+            Attenuation::InverseSquareApprox { scale } if scale > 0.0 => {
+                let end = scale * (ratio * (1.0 + (from / scale).powi(2)) - 1.0).max(0.0).sqrt();
+                Some((end - from).max(0.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Index into `LABELS`.
+    pub const fn kind(self) -> usize {
+        match self {
+            Attenuation::None => 0,
+            Attenuation::Exponential { .. } => 1,
+            Attenuation::InverseSquareApprox { .. } => 2,
+        }
+    }
+
+    /// The coefficient of the variant, 0 for `None`.
+    pub const fn coefficient(self) -> f32 {
+        match self {
+            Attenuation::None => 0.0,
+            Attenuation::Exponential { per_unit } => per_unit,
+            Attenuation::InverseSquareApprox { scale } => scale,
+        }
+    }
+
+    pub const fn from_parts(kind: usize, coefficient: f32) -> Self {
+        match kind {
+            1 => Attenuation::Exponential { per_unit: coefficient },
+            2 => Attenuation::InverseSquareApprox { scale: coefficient },
+            _ => Attenuation::None,
+        }
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self { max_rays: 1000, max_bounces_per_ray: usize::MAX, max_distance: 20_000.0, min_intensity: 0.1,
+            edge_radius: None, refractive_index: 1.5, precision: Precision::Single, seed: 0, max_gloss_angle: 0.5,
+            gloss_samples: 1, collision_mask: u8::MAX, attenuation: Attenuation::None, min_hit_distance: HIT_EPSILON,
+            watchdog: Watchdog::Off }
+    }
+}
+
+impl Ray {
+    pub const fn new(origin: Vec2, direction: Vec2, color: Color) -> Self {
+        Self { origin, direction, color }
+    }
+
+    /// Returns the hit position, the unit normal of the segment facing the ray
+    /// (`normal.dot(direction) <= 0`) and the hit parameter along the segment (0 at `start`, 1 at `end`).
+    pub fn collides_with(&self, other: (Vec2, Vec2)) -> Option<(Vec2, Vec2, f32)> {
+        self.intersect_segment(other.0, other.1).map(|hit| (hit.point, hit.normal, hit.t_seg))
+    }
+
+    /// Where the ray crosses `start..end`, parallel rays never do. Hits just past either end
+    /// count, clamped onto the end.
+    pub fn intersect_segment(&self, start: Vec2, end: Vec2) -> Option<SegmentHit> {
+        let ray_dir = self.direction.normalize_or_zero();
+        let ray_dir_perp = ray_dir.perp();
+
+        let start_to_origin = self.origin - start;
+        let line_segment = end - start;
+        let denominator = line_segment.dot(ray_dir_perp);
+
+        if denominator.abs() < f32::EPSILON {
+            return None; // Lines are parallel, no collision
+        }
+
+        let t1 = line_segment.perp_dot(start_to_origin) / denominator;
+        let t2 = start_to_origin.dot(ray_dir_perp) / denominator;
+
+        // endpoints are inclusive with a little slack, so a ray aimed exactly at a shared node
+        // hits both edges instead of slipping between them
+        let slack = ENDPOINT_EPSILON.max(ENDPOINT_SLACK / line_segment.length());
+        if t1 >= 0.0 && (-slack..=1.0 + slack).contains(&t2) {
+            let point = self.origin + ray_dir * t1;
+            let mut normal = line_segment.normalize().perp();
+            if normal.dot(ray_dir) > 0.0 { normal = -normal; }
+            Some(SegmentHit { point, normal, t_ray: t1, t_seg: t2.clamp(0.0, 1.0) })
+        } else {
+            None
+        }
+    }
+
+    /// Distances along the ray where it enters and leaves the box `min..max`. The entry is
+    /// negative when the ray starts inside; `None` when it misses or the box is behind it.
+    pub fn intersect_aabb(&self, min: Vec2, max: Vec2) -> Option<(f32, f32)> {
+        let ray_dir = self.direction.normalize_or_zero();
+        let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+        for (origin, direction, low, high) in [(self.origin.x, ray_dir.x, min.x, max.x),
+                                               (self.origin.y, ray_dir.y, min.y, max.y)] {
+            if direction == 0.0 {
+                // parallel to this slab, never crossing it
+                if origin < low || origin > high { return None; }
+                continue;
+            }
+            let (a, b) = ((low - origin) / direction, (high - origin) / direction);
+            enter = enter.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+        (enter <= exit && exit >= 0.0).then_some((enter, exit))
+    }
+
+    /// First point ahead of the origin where the ray meets the circle: where it enters, or
+    /// where it leaves when it starts inside. A tangent ray touches at one point.
+    pub fn intersect_circle(&self, center: Vec2, radius: f32) -> Option<CircleHit> {
+        let ray_dir = self.direction.normalize_or_zero();
+        let to_origin = self.origin - center;
+        let b = to_origin.dot(ray_dir);
+        let c = to_origin.length_squared() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 { return None; }
+        let from_inside = c < 0.0;
+        let t = if from_inside { -b + discriminant.sqrt() } else { -b - discriminant.sqrt() };
+        if t < 0.0 { return None; }
+        let point = self.origin + ray_dir * t;
+        Some(CircleHit { point, normal: (point - center).normalize_or_zero(), t, from_inside })
+    }
+
+    /// Entry point and outward normal of the ray into the capsule of `radius` around
+    /// `start..end`. Rays starting inside the capsule don't collide with it.
+    pub fn collides_with_capsule(&self, start: Vec2, end: Vec2, radius: f32) -> Option<(Vec2, Vec2)> {
+        if distance_to_segment(self.origin, start, end) < radius { return None; }
+        let mut closest: Option<(Vec2, Vec2)> = None;
+        let mut consider = |hit: (Vec2, Vec2)| {
+            if closest.is_none_or(|(position, _)|
+                self.origin.distance_squared(hit.0) < self.origin.distance_squared(position)) {
+                closest = Some(hit);
+            }
+        };
+
+        if let Some(side) = (end - start).try_normalize().map(Vec2::perp) {
+            for offset in [side * radius, -side * radius] {
+                if let Some((position, normal, _)) = self.collides_with((start + offset, end + offset)) {
+                    // only the face looking away from the axis is an entry point
+                    if normal.dot(offset) > 0.0 { consider((position, normal)); }
+                }
+            }
+        }
+        for center in [start, end] {
+            if let Some(position) = self.collides_with_circle(center, radius) {
+                consider((position, (position - center).normalize()));
+            }
+        }
+        closest
+    }
+
+    /// First point where the ray enters the circle, `None` if it starts inside or misses.
+    pub fn collides_with_circle(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        self.intersect_circle(center, radius).filter(|hit| !hit.from_inside).map(|hit| hit.point)
+    }
+}
+
+impl Ray64 {
+    pub const fn new(origin: DVec2, direction: DVec2, color: Color) -> Self {
+        Self { origin, direction, color }
+    }
+
+    pub fn as_ray(&self) -> Ray {
+        Ray::new(self.origin.as_vec2(), self.direction.as_vec2(), self.color)
+    }
+
+    /// `Ray::collides_with` in double precision.
+    pub fn collides_with(&self, other: (DVec2, DVec2)) -> Option<(DVec2, DVec2, f64)> {
+        let (start, end) = other;
+        let ray_dir = self.direction.normalize_or_zero();
+        let ray_dir_perp = ray_dir.perp();
+
+        let start_to_origin = self.origin - start;
+        let line_segment = end - start;
+        let denominator = line_segment.dot(ray_dir_perp);
+
+        if denominator.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let t1 = line_segment.perp_dot(start_to_origin) / denominator;
+        let t2 = start_to_origin.dot(ray_dir_perp) / denominator;
+
+        let slack = ENDPOINT_EPSILON as f64;
+        if t1 >= 0.0 && (-slack..=1.0 + slack).contains(&t2) {
+            let collision = self.origin + ray_dir * t1;
+            let mut normal = line_segment.normalize().perp();
+            if normal.dot(ray_dir) > 0.0 { normal = -normal; }
+            Some((collision, normal, t2.clamp(0.0, 1.0)))
+        } else {
+            None
+        }
+    }
+
+    /// `Ray::collides_with_capsule` in double precision.
+    pub fn collides_with_capsule(&self, start: DVec2, end: DVec2, radius: f64) -> Option<(DVec2, DVec2)> {
+        if distance_to_segment64(self.origin, start, end) < radius { return None; }
+        let mut closest: Option<(DVec2, DVec2)> = None;
+        let mut consider = |hit: (DVec2, DVec2)| {
+            if closest.is_none_or(|(position, _)|
+                self.origin.distance_squared(hit.0) < self.origin.distance_squared(position)) {
+                closest = Some(hit);
+            }
+        };
+
+        if let Some(side) = (end - start).try_normalize().map(DVec2::perp) {
+            for offset in [side * radius, -side * radius] {
+                if let Some((position, normal, _)) = self.collides_with((start + offset, end + offset)) {
+                    if normal.dot(offset) > 0.0 { consider((position, normal)); }
+                }
+            }
+        }
+        for center in [start, end] {
+            if let Some(position) = self.collides_with_circle(center, radius) {
+                consider((position, (position - center).normalize()));
+            }
+        }
+        closest
+    }
+
+    /// `Ray::collides_with_circle` in double precision.
+    pub fn collides_with_circle(&self, center: DVec2, radius: f64) -> Option<DVec2> {
+        let ray_dir = self.direction.normalize_or_zero();
+        let to_origin = self.origin - center;
+        let b = to_origin.dot(ray_dir);
+        let c = to_origin.length_squared() - radius * radius;
+        if c < 0.0 { return None; }
+        let discriminant = b * b - c;
+        if discriminant < 0.0 { return None; }
+        let t = -b - discriminant.sqrt();
+        if t < 0.0 { return None; }
+        Some(self.origin + ray_dir * t)
+    }
+}
+
+impl From<Ray> for Ray64 {
+    fn from(ray: Ray) -> Self {
+        Self::new(ray.origin.as_dvec2(), ray.direction.as_dvec2(), ray.color)
+    }
+}
+
+impl Precision {
+    /// Closest hit of `ray` as position, normal and segment index, worked out at this precision,
+    /// with the segments on `layers`.
+    fn closest_hit(self, ray: &Ray64, segments: &[Segment], surfaces: &Surfaces, layers: &[u8], config: &SimConfig)
+                   -> Option<(DVec2, DVec2, usize)> {
+        match self {
+            Precision::Single => closest_segment_on_layers(ray.as_ray(), segments, surfaces, layers, config)
+                .map(|hit| (hit.info.position.as_dvec2(), hit.info.normal.as_dvec2(), hit.segment_index)),
+            Precision::Double => find_closest_segment64(ray, segments, surfaces, layers, config),
+        }
+    }
+
+    /// `position` on a wall moved off it towards `side`, the wall's unit normal on the side a
+    /// child ray leaves to, by as little as rounding at this precision allows.
+    fn nudge(self, position: DVec2, side: DVec2) -> DVec2 {
+        position + side * match self {
+            Precision::Single => ORIGIN_NUDGE,
+            Precision::Double => ORIGIN_NUDGE_64,
+        }
+    }
+
+    fn reflect(self, direction: DVec2, normal: DVec2) -> DVec2 {
+        match self {
+            Precision::Single => reflect(direction.as_vec2(), normal.as_vec2()).as_dvec2(),
+            Precision::Double => reflect64(direction, normal),
+        }
+    }
+
+    /// Normalized refracted direction, `None` on total internal reflection.
+    fn refract(self, direction: DVec2, normal: DVec2, eta: f32) -> Option<DVec2> {
+        match self {
+            Precision::Single => refract(direction.as_vec2(), normal.as_vec2(), eta)
+                .and_then(Vec2::try_normalize).map(|direction| direction.as_dvec2()),
+            Precision::Double => refract64(direction, normal, eta as f64).and_then(DVec2::try_normalize),
+        }
+    }
+}
+
+/// Traces `ray` through `segments`. Hits come out in the order they were produced (breadth
+/// first over reflected/transmitted children).
+pub fn trace(ray: Ray, segments: &[Segment], config: &SimConfig) -> TraceResult {
+    trace_with_media(ray, segments, &[], config)
+}
+
+/// Like `trace`, with `media` tagging transparent segments (parallel to `segments`, missing
+/// entries count as `None`) as the boundary of a closed medium of `SimConfig::refractive_index`.
+/// Rays refract through tagged segments, tracking which media they are inside to tell entry
+/// from exit. Untagged transparent segments stay thin panes that split without bending.
+pub fn trace_with_media(ray: Ray, segments: &[Segment], media: &[Option<usize>], config: &SimConfig) -> TraceResult {
+    trace_with_surfaces(ray, segments, &Surfaces { media: media.to_vec(), ..Default::default() }, config)
+}
+
+/// Like `trace_with_media`, with the media taken from `surfaces` and rough segments scattering
+/// reflections: off a segment of roughness `r` a reflection turns by a random angle within
+/// `r * SimConfig::max_gloss_angle` either way, drawn from `SimConfig::seed`. Segments on no
+/// layer of `SimConfig::collision_mask` are passed through as if absent, and two-sided segments
+/// act by the face the ray meets. Every line fades by
+/// `SimConfig::attenuation` over its length, and ends early where it fades out. Emitting
+/// segments light the scene too, see `trace_lights`.
+pub fn trace_with_surfaces(ray: Ray, segments: &[Segment], surfaces: &Surfaces, config: &SimConfig) -> TraceResult {
+    trace_lights(Some(ray), segments, surfaces, config)
+}
+
+/// `trace_with_surfaces` lit by the `laser` ray, if any, and by every segment with an emitter in
+/// `Surfaces::emitters`. `SimConfig::max_rays` is shared between the sources by how many rays
+/// each starts with, at least one line each.
+pub fn trace_lights(laser: Option<Ray>, segments: &[Segment], surfaces: &Surfaces, config: &SimConfig) -> TraceResult {
+    let mut result = TraceResult::default();
+    trace_lights_into(laser, segments, surfaces, config, &mut TraceScratch::default(), &mut result);
+    result
+}
+
+/// Rays waiting to be traced, kept between traces so their queue isn't allocated every time.
+#[derive(Debug, Default)]
+pub struct TraceScratch {
+    /// Each queued ray with the line it leaves from, the media it is inside and its source, see
+    /// `trace_lights_into`.
+    rays: VecDeque<(Ray64, Option<usize>, Vec<usize>, usize)>,
+    /// The scene's segments with whatever a caller adds for one trace only, like the laser
+    /// housing.
+    pub(crate) segments: Vec<Segment>,
+    /// Layers of `segments`, see `trace_lights_on_layers`.
+    pub(crate) layers: Vec<u8>,
+}
+
+impl TraceScratch {
+    /// Rays the queue holds room for.
+    pub fn capacity(&self) -> usize {
+        self.rays.capacity()
+    }
+}
+
+/// `trace_lights` into `out`, replacing whatever it held, with the ray queue in `scratch`. Both
+/// keep their buffers, so tracing the same scene again allocates little.
+pub fn trace_lights_into(laser: Option<Ray>, segments: &[Segment], surfaces: &Surfaces, config: &SimConfig,
+                         scratch: &mut TraceScratch, out: &mut TraceResult) {
+    trace_lights_on_layers(laser, segments, surfaces, &surfaces.layers, config, scratch, out);
+}
+
+/// `trace_lights_into` with the segments on `layers` instead of `Surfaces::layers`, for a caller
+/// adding segments of its own without copying the rest of `surfaces`.
+pub(crate) fn trace_lights_on_layers(laser: Option<Ray>, segments: &[Segment], surfaces: &Surfaces, layers: &[u8],
+                                     config: &SimConfig, scratch: &mut TraceScratch, out: &mut TraceResult) {
+    let mut sources: Vec<Vec<Ray>> = laser.map(|ray| vec![ray]).into_iter().collect();
+    sources.extend(surfaces.emitters.iter().zip(segments)
+        .filter_map(|(emitter, segment)| emitter.map(|emitter| emitter.rays(segment)))
+        .filter(|rays| !rays.is_empty()));
+    let total = sources.iter().map(Vec::len).sum::<usize>().max(1);
+    let mut budgets: Vec<usize> = sources.iter()
+        .map(|rays| (config.max_rays * rays.len() / total).max(1))
+        .collect();
+    // each queued ray remembers the line it leaves from, so bounces and lengths can be chained,
+    // the media it is inside, innermost last, and the source whose budget it spends. Rays are
+    // kept in double precision and only narrowed for the output, so `Precision::Single` loses
+    // nothing by the round trip.
+    let precision = config.precision;
+    let ray_stack = &mut scratch.rays;
+    ray_stack.clear();
+    ray_stack.extend(sources.iter().enumerate()
+        .flat_map(|(source, rays)| rays.iter().map(move |ray| (Ray64::from(*ray), None, Vec::new(), source))));
+    out.reset(segments.len());
+    let TraceResult { hits, ends, segment_hits, .. } = out;
+    let mut rng = SeededRng::new(config.seed);
+    let watchdog = WatchdogTimer::start(config.watchdog);
+    let (mut traced, mut tests) = (0, 0);
+    out.stats.aborted = false;
+    while let Some((ray, parent, inside, source)) = ray_stack.pop_front() {
+        traced += 1;
+        if traced % Watchdog::CHECK_EVERY == 0 && watchdog.expired(tests) {
+            // whatever is still queued is dropped without an end, the trace as a whole is cut
+            out.stats.aborted = true;
+            break;
+        }
+        // bounced rays start nudged off the wall, their lines where the parent's ended
+        let origin = parent.map_or(ray.origin.as_vec2(), |i| hits[i].position);
+        if budgets[source] == 0 {
+            ends.push(PathEnd { position: origin, reason: Termination::Budget });
+            continue;
+        }
+        if ray.color.a <= config.min_intensity {
+            ends.push(PathEnd { position: origin, reason: Termination::IntensityCutoff });
+            continue;
+        }
+        // children are one bounce deeper than the line they leave from, whatever made them
+        let (bounce, path_length) = parent.map_or((0, 0.0), |i| (hits[i].bounce + 1, hits[i].path_length));
+        if bounce >= config.max_bounces_per_ray {
+            ends.push(PathEnd { position: origin, reason: Termination::MaxBounces });
+            continue;
+        }
+        debug_assert!(ray.direction.is_normalized(),
+                      "ray not normal: {}, normal is {:?}, len is {:}",
+                      ray.direction, ray.direction.normalize(), ray.direction.length());
+        let index = hits.len();
+        let root = parent.map_or(index, |i| hits[i].ray);
+        let closest = precision.closest_hit(&ray, segments, surfaces, layers, config);
+        tests += segments.len() as u64;
+        let travel = closest.map_or(config.max_distance, |(position, ..)| origin.distance(position.as_vec2()));
+        let attenuation = config.attenuation;
+        if let Some(reach) = attenuation.reach(path_length, ray.color.a, config.min_intensity)
+            .filter(|reach| *reach < travel) {
+            // dies on the way, so the line stops where it does
+            let position = origin + ray.direction.as_vec2() * reach;
+            ends.push(PathEnd { position, reason: Termination::IntensityCutoff });
+            hits.push(TraceHit {
+                origin,
+                position,
+                segment: None,
+                interaction: Interaction::Faded,
+                color: attenuate(ray.color, attenuation.factor(path_length, reach)),
+                bounce,
+                path_length: path_length + reach,
+                parent,
+                normal: Vec2::ZERO,
+                ray: root,
+                state: None,
+                incidence: 0.0,
+                outgoing: 0.0,
+            });
+        } else if let Some((position, normal, segment)) = closest {
+            let ray = Ray64 { color: attenuate(ray.color, attenuation.factor(path_length, travel)), ..ray };
+            debug_assert!(normal.is_normalized(),
+                          "not normal: {}, normal is {:?} at {:?}", normal, normal.normalize(), position);
+            let state = surfaces.state(segment, &segments[segment], ray.direction);
+            let queued = ray_stack.len();
+            let (interaction, received) = match state {
+                EdgeState::Reflective => {
+                    let direction = precision.reflect(ray.direction, normal);
+                    let absorbed = surfaces.absorbed(segment, (-ray.direction).dot(normal) as f32);
+                    let color = if absorbed > 0.0 { attenuate(ray.color, 1.0 - absorbed) } else { ray.color };
+                    let roughness = surfaces.roughness.get(segment).map_or(0.0, |r| r.clamp(0.0, 1.0));
+                    if roughness > 0.0 {
+                        let samples = config.gloss_samples.max(1);
+                        let color = attenuate(color, 1.0 / samples as f32);
+                        for _ in 0..samples {
+                            let angle = (rng.next_f32() * 2.0 - 1.0) * roughness * config.max_gloss_angle;
+                            ray_stack.push_back((Ray64 {
+                                origin: precision.nudge(position, normal),
+                                direction: scatter(direction, normal, angle as f64),
+                                color,
+                            }, Some(index), inside.clone(), source));
+                        }
+                    } else {
+                        ray_stack.push_back((Ray64 {
+                            origin: precision.nudge(position, normal),
+                            direction,
+                            color, // TODO: use segment color
+                        }, Some(index), inside, source));
+                    }
+                    (Interaction::Reflected, ray.color.a)
+                }
+                EdgeState::Transparent => match surfaces.media.get(segment).copied().flatten() {
+                    Some(medium) => {
+                        let (children, reflected) = cross_medium_boundary(ray, position, normal, medium, inside, config);
+                        ray_stack.extend(children.into_iter().map(|(child, stack)| (child, Some(index), stack, source)));
+                        (Interaction::Split, reflected)
+                    }
+                    None => {
+                        let (direction, normal_f32) = (ray.direction.as_vec2(), normal.as_vec2());
+                        let is_critical = normal_f32.dot(direction).abs().acos() == 0.8509;
+                        let fresnel = direction.dot(normal_f32).powi(6) * 0.97;
+                        ray_stack.push_back((Ray64 {
+                            origin: precision.nudge(position, normal),
+                            direction: precision.reflect(ray.direction, normal),
+                            color: if is_critical { ray.color } else { attenuate(ray.color, 1.0 - fresnel) },
+                        }, Some(index), inside.clone(), source));
+                        if !is_critical {
+                            ray_stack.push_back((Ray64 {
+                                origin: precision.nudge(position, -normal),
+                                direction: ray.direction,
+                                color: attenuate(ray.color, fresnel),
+                            }, Some(index), inside, source));
+                        }
+                        (Interaction::Split, if is_critical { ray.color.a } else { ray.color.a * (1.0 - fresnel) })
+                    }
+                },
+                EdgeState::Absorptive => {
+                    ends.push(PathEnd { position: position.as_vec2(), reason: Termination::Absorbed });
+                    (Interaction::Absorbed, ray.color.a)
+                }
+            };
+            segment_hits[segment] += received;
+            hits.push(TraceHit {
+                origin,
+                position: position.as_vec2(),
+                segment: Some(segment),
+                interaction,
+                color: ray.color,
+                bounce,
+                path_length: path_length + origin.distance(position.as_vec2()),
+                parent,
+                normal: normal.as_vec2(),
+                ray: root,
+                state: Some(state),
+                incidence: (-ray.direction).dot(normal).clamp(-1.0, 1.0).acos() as f32,
+                outgoing: ray_stack.range(queued..).map(|(child, ..)| child.color.a).sum(),
+            });
+        } else {
+            let position = origin + ray.direction.as_vec2() * config.max_distance;
+            ends.push(PathEnd { position, reason: Termination::MaxDistance });
+            hits.push(TraceHit {
+                origin,
+                position,
+                segment: None,
+                interaction: Interaction::Escaped,
+                color: attenuate(ray.color, attenuation.factor(path_length, config.max_distance)),
+                bounce,
+                path_length: path_length + config.max_distance,
+                parent,
+                normal: Vec2::ZERO,
+                ray: root,
+                state: None,
+                incidence: 0.0,
+                outgoing: 0.0,
+            });
+        }
+        budgets[source] -= 1;
+    }
+    out.stats.segment_tests = tests;
+    out.finish();
+}
+
+/// Whether the straight path from `a` to `b` is clear. Transparent segments let it through.
+pub fn line_of_sight(a: Vec2, b: Vec2, segments: &[Segment]) -> bool {
+    first_obstruction(a, b, segments).is_none()
+}
+
+/// Closest segment blocking the path from `a` to `b`, with where it is hit. Transparent
+/// segments let it through.
+pub fn first_obstruction(a: Vec2, b: Vec2, segments: &[Segment]) -> Option<(CollisionInfo, usize)> {
+    first_obstruction_where(a, b, segments, |segment| segment.2 != EdgeState::Transparent)
+}
+
+/// `first_obstruction` with `blocks` picking the segments that count. Hits within
+/// `HIT_EPSILON` of `a` are ignored, as in `trace`, so a point on a wall can see away from it.
+pub fn first_obstruction_where(a: Vec2, b: Vec2, segments: &[Segment], blocks: impl Fn(&Segment) -> bool)
+                               -> Option<(CollisionInfo, usize)> {
+    let length = a.distance(b);
+    let direction = (b - a).try_normalize()?;
+    let ray = Ray::new(a, direction, Color::new(0.0, 0.0, 0.0, 0.0));
+    let mut closest: Option<(CollisionInfo, usize, f32)> = None;
+    for (i, segment) in segments.iter().enumerate().filter(|(_, segment)| blocks(segment)) {
+        let Some((position, normal, _)) = ray.collides_with((segment.0, segment.1)) else { continue; };
+        let distance = a.distance(position);
+        if distance < HIT_EPSILON || distance > length { continue; }
+        if closest.is_none_or(|(.., best)| distance < best) {
+            closest = Some((CollisionInfo { position, normal }, i, distance));
+        }
+    }
+    closest.map(|(collision, i, _)| (collision, i))
+}
+
+/// How far `visibility_polygon` reaches where nothing blocks the light.
+pub const VISIBILITY_REACH: f32 = 20_000.0;
+/// Radians either side of every endpoint `visibility_polygon` also looks, to see past it.
+const VISIBILITY_SIDE_ANGLE: f64 = 1e-5;
+/// Directions `visibility_polygon` always looks in, so open scenes still close into a polygon.
+const VISIBILITY_OPEN_DIRECTIONS: usize = 32;
+
+/// The region lit by a point light at `origin`: its corners in order of angle around `origin`,
+/// to be drawn as a fan from it. Transparent segments let the light through, and it stops at
+/// `VISIBILITY_REACH` where nothing else does. Corners on a straight line are merged, so a
+/// square room gives its four corners.
+pub fn visibility_polygon(origin: Vec2, segments: &[Segment]) -> Vec<Vec2> {
+    let origin64 = origin.as_dvec2();
+    let walls: Vec<(DVec2, DVec2)> = segments.iter()
+        .filter(|segment| segment.2 != EdgeState::Transparent && segment.0 != segment.1)
+        .map(Segment::as_dvec2)
+        .collect();
+    // light only turns a corner at an endpoint, so looking at, just before and just after each
+    // one finds every corner of the region
+    let mut angles: Vec<f64> = walls.iter()
+        .flat_map(|&(start, end)| [start, end])
+        .filter(|&point| point.distance(origin64) > HIT_EPSILON as f64)
+        .map(|point| (point.y - origin64.y).atan2(point.x - origin64.x))
+        .chain((0..VISIBILITY_OPEN_DIRECTIONS)
+            .map(|i| i as f64 * std::f64::consts::TAU / VISIBILITY_OPEN_DIRECTIONS as f64 - std::f64::consts::PI))
+        .collect();
+    angles.sort_by(f64::total_cmp);
+    // endpoints shared by walls, or in line with the origin, only need looking at once
+    angles.dedup_by(|next, angle| *next - *angle < 2.0 * VISIBILITY_SIDE_ANGLE);
+    let mut corners: Vec<DVec2> = Vec::with_capacity(angles.len() * 3);
+    for angle in angles {
+        for side in [-VISIBILITY_SIDE_ANGLE, 0.0, VISIBILITY_SIDE_ANGLE] {
+            let direction = DVec2::from_angle(angle + side);
+            let reach = walls.iter()
+                .filter_map(|&(start, end)| ray_hits_at(origin64, direction, start, end))
+                .fold(VISIBILITY_REACH as f64, f64::min);
+            corners.push(origin64 + direction * reach);
+        }
+    }
+    merge_straight_corners(&mut corners);
+    corners.into_iter().map(|corner| corner.as_vec2()).collect()
+}
+
+/// Distance along the unit `direction` from `origin` at which it crosses `start..end`, if it
+/// does beyond `HIT_EPSILON`. A wall edge-on to the ray is never hit.
+fn ray_hits_at(origin: DVec2, direction: DVec2, start: DVec2, end: DVec2) -> Option<f64> {
+    let along = end - start;
+    let denominator = direction.perp_dot(along);
+    if denominator.abs() < 1e-12 * along.length() { return None; }
+    let to_start = start - origin;
+    let t = to_start.perp_dot(along) / denominator;
+    let u = to_start.perp_dot(direction) / denominator;
+    let slack = ENDPOINT_EPSILON as f64;
+    (t > HIT_EPSILON as f64 && (-slack..=1.0 + slack).contains(&u)).then_some(t)
+}
+
+/// Drops corners of the closed polygon `corners` that coincide with the previous one or lie on
+/// the straight line between their neighbours.
+fn merge_straight_corners(corners: &mut Vec<DVec2>) {
+    corners.dedup_by(|next, corner| next.distance(*corner) < 1e-9);
+    while corners.len() > 2 && corners[0].distance(corners[corners.len() - 1]) < 1e-9 { corners.pop(); }
+    let mut i = 0;
+    while corners.len() > 2 && i < corners.len() {
+        let n = corners.len();
+        let (previous, corner, next) = (corners[(i + n - 1) % n], corners[i], corners[(i + 1) % n]);
+        let (incoming, outgoing) = (corner - previous, next - corner);
+        let turn = incoming.perp_dot(outgoing) / (incoming.length() * outgoing.length());
+        if turn.abs() < 1e-9 && incoming.dot(outgoing) > 0.0 {
+            corners.remove(i);
+            // the previous corner may have become straight now
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Splits `ray` where it meets the boundary of `medium`: a reflected child staying on this side
+/// and, unless totally internally reflected, a refracted child on the other. `inside` are the
+/// media the ray is in, innermost last; meeting one of them means leaving it. Returns the
+/// children with their media and the intensity reflected away.
+fn cross_medium_boundary(ray: Ray64, position: DVec2, normal: DVec2, medium: usize, inside: Vec<usize>,
+                         config: &SimConfig) -> (Vec<(Ray64, Vec<usize>)>, f32) {
+    let index_of = |stack: &[usize]| if stack.is_empty() { 1.0 } else { config.refractive_index };
+    let mut beyond = inside.clone();
+    if let Some(position) = beyond.iter().rposition(|m| *m == medium) {
+        beyond.remove(position);
+    } else {
+        beyond.push(medium);
+    }
+    let (n1, n2) = (index_of(&inside), index_of(&beyond));
+    let precision = config.precision;
+    let reflect_ray = |color| Ray64 {
+        origin: precision.nudge(position, normal),
+        direction: precision.reflect(ray.direction, normal),
+        color,
+    };
+    let Some(direction) = precision.refract(ray.direction, normal, n1 / n2) else {
+        // total internal reflection, everything stays on this side
+        return (vec![(reflect_ray(ray.color), inside)], ray.color.a);
+    };
+    // the collision normal faces the ray, fresnel wants it along the ray
+    let reflectance = fresnel_reflect_amount(n1, n2, -normal.as_vec2(), ray.direction.as_vec2(), 0.0).clamp(0.0, 1.0);
+    let children = vec![
+        (reflect_ray(attenuate(ray.color, reflectance)), inside),
+        (Ray64 { origin: precision.nudge(position, -normal), direction,
+                 color: attenuate(ray.color, 1.0 - reflectance) }, beyond),
+    ];
+    (children, ray.color.a * reflectance)
+}
+
+/// Closest segment hit by `ray` within `config.max_distance`, ignoring anything within
+/// `config.min_hit_distance` of the origin. When several segments are hit at the same point (a
+/// shared node), their normals are averaged so corners reflect as corners rather than as one
+/// arbitrary wall; see `blends_at_corner`.
+pub fn find_closest_segment_new(
+    ray: Ray,
+    segments: &[Segment],
+    surfaces: &Surfaces,
+    config: &SimConfig,
+) -> Option<ClosestHit> {
+    closest_segment_on_layers(ray, segments, surfaces, &surfaces.layers, config)
+}
+
+/// `find_closest_segment_new` with the segments on `layers`.
+fn closest_segment_on_layers(ray: Ray, segments: &[Segment], surfaces: &Surfaces, layers: &[u8], config: &SimConfig)
+                             -> Option<ClosestHit> {
+    let hit_on = |i: usize, segment: &Segment| {
+        match config.edge_radius.map(|radius| surfaces.capsule_radius(i, radius)) {
+            // a capsule is met off the segment, the nearest point on it stands in
+            Some(radius) => ray.collides_with_capsule(segment.0, segment.1, radius)
+                .map(|(position, normal)| (position, normal, position_along(position, segment.0, segment.1))),
+            None => ray.collides_with((segment.0, segment.1)),
+        }
+    };
+    let mut closest_distance = config.max_distance;
+    let mut closest: Option<(usize, (Vec2, Vec2, f32))> = None;
+    let mut tied = false;
+
+    for (i, segment) in segments.iter().enumerate() {
+        if !on_layers(layers, i, config.collision_mask) { continue; }
+        let Some(hit) = hit_on(i, segment) else { continue; };
+        let distance = ray.origin.distance(hit.0);
+        if distance < config.min_hit_distance { continue; }
+        if distance < closest_distance - HIT_EPSILON {
+            closest_distance = distance;
+            closest = Some((i, hit));
+        } else if closest.is_some() && distance <= closest_distance + HIT_EPSILON {
+            tied = true;
+        }
+    }
+
+    let (mut segment_index, (mut position, mut normal, mut t_along_segment)) = closest?;
+    if tied && corner_end(&segments[segment_index], position).is_some() {
+        let ties = || segments.iter().enumerate()
+            .filter(|&(j, _)| j != segment_index && on_layers(layers, j, config.collision_mask))
+            .filter_map(|(j, segment)| hit_on(j, segment).map(|hit| (j, hit)))
+            .filter(|(_, hit)| (ray.origin.distance(hit.0) - closest_distance).abs() <= HIT_EPSILON);
+        if let Some((j, hit)) = ties().find(|(j, hit)| corner_end(&segments[*j], hit.0).is_none()) {
+            // a wall hit away from its ends hides whatever only touches it there
+            (segment_index, (position, normal, t_along_segment)) = (j, hit);
+        } else {
+            let nearest = &segments[segment_index];
+            normal = ties().filter(|(j, _)| blends_at_corner(ray.origin, position, nearest, &segments[*j]))
+                .fold(normal, |sum, (_, hit)| sum + hit.1)
+                .try_normalize().unwrap_or(normal);
+        }
+    }
+    Some(ClosestHit { info: CollisionInfo { position, normal }, segment_index, t_along_segment })
+}
+
+/// The end of `segment` that `point` is at, and its other end, within the slack rays get.
+fn corner_end(segment: &Segment, point: Vec2) -> Option<(Vec2, Vec2)> {
+    let t = position_along(point, segment.0, segment.1);
+    let slack = ENDPOINT_EPSILON.max(ENDPOINT_SLACK / segment.0.distance(segment.1));
+    if t <= slack {
+        Some((segment.0, segment.1))
+    } else if t >= 1.0 - slack {
+        Some((segment.1, segment.0))
+    } else {
+        None
+    }
+}
+
+/// Whether `other`, hit as far from `origin` as `nearest` at `position`, adds its normal to
+/// it: both must end there, and `other` mustn't reach behind the face `nearest` shows the ray.
+fn blends_at_corner(origin: Vec2, position: Vec2, nearest: &Segment, other: &Segment) -> bool {
+    let (Some((corner, _)), Some((other_corner, far))) = (corner_end(nearest, position), corner_end(other, position))
+        else { return false; };
+    let side = |point: Vec2| (nearest.1 - nearest.0).perp_dot(point - nearest.0);
+    corner.distance(other_corner) <= ENDPOINT_SLACK && side(far) * side(origin) >= 0.0
+}
+
+/// How far along `start..end` the point on it closest to `point` is, 0 at `start` and 1 at `end`.
+fn position_along(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared == 0.0 { return 0.0; }
+    ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0)
+}
+
+/// `closest_segment_on_layers` in double precision, returning the position, normal and segment.
+fn find_closest_segment64(ray: &Ray64, segments: &[Segment], surfaces: &Surfaces, layers: &[u8], config: &SimConfig)
+                          -> Option<(DVec2, DVec2, usize)> {
+    let hit_on = |i: usize, segment: &Segment| {
+        let (start, end) = segment.as_dvec2();
+        match config.edge_radius.map(|radius| surfaces.capsule_radius(i, radius) as f64) {
+            Some(radius) => ray.collides_with_capsule(start, end, radius),
+            None => ray.collides_with((start, end)).map(|(position, normal, _)| (position, normal)),
+        }
+    };
+    let epsilon = HIT_EPSILON as f64;
+    let min_distance = config.min_hit_distance as f64;
+    let mut closest: Option<(DVec2, DVec2, usize)> = None;
+    let mut closest_distance = config.max_distance as f64;
+    let mut tied = false;
+
+    for (i, segment) in segments.iter().enumerate() {
+        if !on_layers(layers, i, config.collision_mask) { continue; }
+        let Some((position, normal)) = hit_on(i, segment) else { continue; };
+        let distance = ray.origin.distance(position);
+        if distance < min_distance { continue; }
+        if distance < closest_distance - epsilon {
+            closest_distance = distance;
+            closest = Some((position, normal, i));
+        } else if closest.is_some() && distance <= closest_distance + epsilon {
+            tied = true;
+        }
+    }
+
+    let (position, normal, i) = closest?;
+    if !tied || corner_end(&segments[i], position.as_vec2()).is_none() { return closest; }
+    let ties = || segments.iter().enumerate()
+        .filter(|&(j, _)| j != i && on_layers(layers, j, config.collision_mask))
+        .filter_map(|(j, segment)| hit_on(j, segment).map(|(position, normal)| (position, normal, j)))
+        .filter(|(position, _, _)| (ray.origin.distance(*position) - closest_distance).abs() <= epsilon);
+    // a wall hit away from its ends hides whatever only touches it there
+    if let Some(hit) = ties().find(|(position, _, j)| corner_end(&segments[*j], position.as_vec2()).is_none()) {
+        return Some(hit);
+    }
+    let (origin, corner) = (ray.origin.as_vec2(), position.as_vec2());
+    let normal_sum = ties().filter(|(_, _, j)| blends_at_corner(origin, corner, &segments[i], &segments[*j]))
+        .fold(normal, |sum, (_, normal, _)| sum + normal);
+    Some((position, normal_sum.try_normalize().unwrap_or(normal), i))
+}
+
+/// `color` with its intensity (alpha) scaled by `factor`. The hue and brightness are the
+/// beam's and stay as they are, whatever the laser's color.
+pub fn attenuate(color: Color, factor: f32) -> Color {
+    Color { a: color.a * factor, ..color }
+}
+
+/// How two segments meet, see `segment_intersection`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentIntersection {
+    /// At a single point, `t` of the way along the first segment and `u` along the second.
+    Point { point: Vec2, t: f32, u: f32 },
+    /// Along a stretch of the line both lie on.
+    Overlap,
+}
+
+/// Points further than this from a line, in world units, aren't on it for `segment_intersection`.
+const COLLINEAR_TOLERANCE: f32 = 1e-3;
+
+/// Where segments `a` and `b` meet, their ends included with the same slack rays get. Segments
+/// on one line that share any stretch, even a single end, `Overlap`.
+pub fn segment_intersection(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> Option<SegmentIntersection> {
+    let (r, s) = (a.1 - a.0, b.1 - b.0);
+    let offset = b.0 - a.0;
+    let denominator = r.perp_dot(s);
+    if denominator.abs() <= f32::EPSILON * r.length() * s.length() {
+        let length_squared = r.length_squared();
+        if length_squared == 0.0 || offset.perp_dot(r).abs() > COLLINEAR_TOLERANCE * length_squared.sqrt() {
+            return None;
+        }
+        let (from, to) = (offset.dot(r) / length_squared, (b.1 - a.0).dot(r) / length_squared);
+        return (from.max(to) >= 0.0 && from.min(to) <= 1.0).then_some(SegmentIntersection::Overlap);
+    }
+    let (t, u) = (offset.perp_dot(s) / denominator, offset.perp_dot(r) / denominator);
+    let range = -ENDPOINT_EPSILON..=1.0 + ENDPOINT_EPSILON;
+    (range.contains(&t) && range.contains(&u)).then(|| {
+        let (t, u) = (t.clamp(0.0, 1.0), u.clamp(0.0, 1.0));
+        SegmentIntersection::Point { point: a.0 + r * t, t, u }
+    })
+}
+
+/// PCG step identical to macroquad's global `rand`, but local, so seeded generators are
+/// reproducible regardless of what else uses the global RNG.
+pub(crate) struct SeededRng(u64);
+
+impl SeededRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut rng = SeededRng(0);
+        rng.next_u32();
+        rng.0 = rng.0.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        let old_state = self.0;
+        self.0 = old_state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        xor_shifted.rotate_right((old_state >> 59) as u32)
+    }
+    /// Uniform in `0.0..1.0`.
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+    /// Uniform index in `0..high`.
+    pub(crate) fn gen_index(&mut self, high: usize) -> usize {
+        (self.next_u32() as usize) % high
+    }
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.gen_index(i + 1));
+        }
+    }
+}
+
+/// `vector` mirrored across the line through the origin with unit `normal`.
+pub fn mirror(vector: Vec2, normal: Vec2) -> Vec2 {
+    vector - (2.0 * normal * vector.dot(normal))
+}
+
+pub fn reflect(direction: Vec2, normal: Vec2) -> Vec2 {
+    let res = mirror(direction, normal);
+    debug_assert!(res.is_normalized(), "res direction not normal: {}, normal is {:?}", res, res.normalize());
+    res.normalize()
+}
+
+/// Sine of the lowest angle above a surface `scatter` leaves a ray at.
+const MIN_SCATTER_ELEVATION: f64 = 1e-3;
+
+/// Unit `direction`, leaving a surface with unit `normal` on its side, turned by `angle`
+/// radians. A turn that would dive into the surface is held just above it instead.
+pub fn scatter(direction: DVec2, normal: DVec2, angle: f64) -> DVec2 {
+    let turned = DVec2::from_angle(angle).rotate(direction).normalize();
+    let elevation = turned.dot(normal);
+    if elevation >= MIN_SCATTER_ELEVATION { return turned; }
+    let along = (turned - normal * elevation).try_normalize().unwrap_or(normal.perp());
+    (along * (1.0 - MIN_SCATTER_ELEVATION.powi(2)).sqrt() + normal * MIN_SCATTER_ELEVATION).normalize()
+}
+
+/// `reflect` in double precision.
+pub fn reflect64(direction: DVec2, normal: DVec2) -> DVec2 {
+    (direction - (2.0 * normal * direction.dot(normal))).normalize()
+}
+
+pub fn refract(direction: Vec2, normal: Vec2, eta: f32) -> Option<Vec2> {
+    let dot = direction.dot(normal);
+    let k = 1.0 - eta.powi(2) * (1.0 - dot.powi(2));
+    if k < 0.0 { return None; }
+    Some(eta * direction - (eta * dot + k.sqrt()) * normal)
+}
+
+/// `refract` in double precision.
+pub fn refract64(direction: DVec2, normal: DVec2, eta: f64) -> Option<DVec2> {
+    let dot = direction.dot(normal);
+    let k = 1.0 - eta.powi(2) * (1.0 - dot.powi(2));
+    if k < 0.0 { return None; }
+    Some(eta * direction - (eta * dot + k.sqrt()) * normal)
+}
+
+/// Schlick approximation of the reflected fraction, blended towards `object_reflectivity`.
+pub fn fresnel_reflect_amount(n1: f32, n2: f32, normal: Vec2, incident: Vec2, object_reflectivity: f32) -> f32
+{
+    let mut r0 = (n1 - n2) / (n1 + n2);
+    r0 *= r0;
+    let mut cos_x = normal.dot(incident);
+    if n1 > n2
+    {
+        let n = n1 / n2;
+        let sin_t2 = n * n * (1.0 - cos_x * cos_x);
+        // Total internal reflection
+        if sin_t2 > 1.0 {
+            return 1.0;
+        }
+        cos_x = (1.0 - sin_t2).sqrt();
+    }
+    let x = 1.0 - cos_x;
+    let ret = r0 + (1.0 - r0) * x * x * x * x * x;
+
+    // adjust reflect multiplier for object reflectivity
+    object_reflectivity + (1.0 - object_reflectivity) * ret
+}
+
+/// Merges chains of segments meeting at a node shared by exactly two of them, going in the same
+/// direction and with the same `EdgeState`, into single segments. Nodes are matched by exact
+/// position; junctions (three or more segments) and corners are kept. Exact duplicates are
+/// dropped first.
+pub fn merge_collinear_segments(segments: &[Segment]) -> Vec<Segment> {
+    let key = |point: Vec2| (point.x.to_bits(), point.y.to_bits());
+    let mut seen = HashSet::new();
+    let segments: Vec<Segment> = segments.iter()
+        .filter(|segment| {
+            let (a, b) = (key(segment.0), key(segment.1));
+            seen.insert((a.min(b), a.max(b), segment.2))
+        })
+        .copied()
+        .collect();
+    let far_end = |segment: Segment, node: Vec2| if key(segment.0) == key(node) { segment.1 } else { segment.0 };
+    let mut incident: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        incident.entry(key(segment.0)).or_default().push(i);
+        incident.entry(key(segment.1)).or_default().push(i);
+    }
+    // the segment carrying `index` straight on through `node`, if `node` is interior to a run
+    let continuation = |index: usize, node: Vec2| -> Option<usize> {
+        let edges = &incident[&key(node)];
+        if edges.len() != 2 { return None; }
+        let other = if edges[0] == index { edges[1] } else { edges[0] };
+        let (current, next) = (segments[index], segments[other]);
+        if other == index || current.2 != next.2 { return None; }
+        let back = (far_end(current, node) - node).normalize_or_zero();
+        let ahead = (far_end(next, node) - node).normalize_or_zero();
+        (back.perp_dot(ahead).abs() < ENDPOINT_EPSILON && back.dot(ahead) < 0.0).then_some(other)
+    };
+
+    let mut used = vec![false; segments.len()];
+    let mut merged = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        if used[i] { continue; }
+        used[i] = true;
+        let mut ends = [segment.0, segment.1];
+        for end in &mut ends {
+            let mut current = i;
+            while let Some(next) = continuation(current, *end) {
+                if used[next] { break; }
+                used[next] = true;
+                *end = far_end(segments[next], *end);
+                current = next;
+            }
+        }
+        merged.push(Segment(ends[0], ends[1], segment.2));
+    }
+    merged
+}
+
+pub fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared == 0.0 { return point.distance(start); }
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
+/// `distance_to_segment` in double precision.
+pub fn distance_to_segment64(point: DVec2, start: DVec2, end: DVec2) -> f64 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared == 0.0 { return point.distance(start); }
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
@@ -0,0 +1,74 @@
+// Pieces of a network lifted out and placed again elsewhere, for the array tools.
+
+use std::f32::consts::TAU;
+
+use macroquad::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::core::EdgeState;
+use crate::rotate;
+
+/// Nodes and the edges among them, detached from any network. Edge ends index into `nodes`,
+/// not into the network the fragment was taken from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkFragment {
+    /// Position and radius of every node.
+    pub nodes: Vec<(Vec2, f32)>,
+    pub edges: Vec<(usize, usize, EdgeState)>,
+}
+
+impl NetworkFragment {
+    /// Mean position of the nodes, `Vec2::ZERO` for an empty fragment.
+    pub fn centroid(&self) -> Vec2 {
+        if self.nodes.is_empty() { return Vec2::ZERO; }
+        self.nodes.iter().fold(Vec2::ZERO, |sum, (position, _)| sum + *position) / self.nodes.len() as f32
+    }
+
+    /// The same fragment with every node moved through `transform`.
+    pub fn transformed(&self, transform: impl Fn(Vec2) -> Vec2) -> Self {
+        Self {
+            nodes: self.nodes.iter().map(|&(position, radius)| (transform(position), radius)).collect(),
+            edges: self.edges.clone(),
+        }
+    }
+}
+
+/// How `NodeNetwork::add_array` lays out its copies.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArrayLayout {
+    /// Copy `k` is moved by `k * offset`.
+    Linear { offset: Vec2, copies: usize },
+    /// The copies and the original spaced evenly around `center`, so 11 copies make 12 in all.
+    /// With `rotate` each copy turns with its angle and keeps facing the center the way the
+    /// original does, otherwise it is only moved.
+    Radial { center: Vec2, copies: usize, rotate: bool },
+}
+
+impl ArrayLayout {
+    pub const fn copies(&self) -> usize {
+        match *self {
+            Self::Linear { copies, .. } | Self::Radial { copies, .. } => copies,
+        }
+    }
+
+    /// Where `position` of a structure centred on `centroid` ends up in copy `copy`, counting
+    /// from 1; copy 0 is the original.
+    pub fn place(&self, copy: usize, position: Vec2, centroid: Vec2) -> Vec2 {
+        match *self {
+            Self::Linear { offset, .. } => position + offset * copy as f32,
+            Self::Radial { center, copies, rotate } => {
+                let angle = TAU * copy as f32 / (copies + 1) as f32;
+                if rotate {
+                    rotate_about(position, center, angle)
+                } else {
+                    position + rotate_about(centroid, center, angle) - centroid
+                }
+            }
+        }
+    }
+}
+
+/// `position` turned by `angle` radians about `center`.
+pub fn rotate_about(position: Vec2, center: Vec2, angle: f32) -> Vec2 {
+    center + rotate(position - center, angle)
+}
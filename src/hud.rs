@@ -0,0 +1,121 @@
+// Frame timing instrumentation and the F3 performance overlay.
+
+use std::collections::VecDeque;
+
+use macroquad::color::{Color, WHITE};
+use macroquad::shapes::draw_rectangle;
+use macroquad::text::draw_text;
+
+/// Measures elapsed wall time: `Instant` on native, the frame clock on the web where `Instant`
+/// isn't available (so spans within one frame read as zero there).
+pub struct Stopwatch {
+    #[cfg(not(target_family = "wasm"))]
+    start: std::time::Instant,
+    #[cfg(target_family = "wasm")]
+    start: f64,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        return Self { start: std::time::Instant::now() };
+        #[cfg(target_family = "wasm")]
+        return Self { start: macroquad::time::get_time() };
+    }
+
+    pub fn micros(&self) -> f32 {
+        #[cfg(not(target_family = "wasm"))]
+        return self.start.elapsed().as_secs_f32() * 1e6;
+        #[cfg(target_family = "wasm")]
+        return ((macroquad::time::get_time() - self.start) * 1e6) as f32;
+    }
+}
+
+/// What one recompute cost.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    /// Turning the network into segments (and media) for the trace.
+    pub rebuild_micros: f32,
+    pub trace_micros: f32,
+    pub lines: usize,
+    pub segments: usize,
+}
+
+/// Rolling window of recent `FrameTimings`, drawn as a small overlay when visible.
+pub struct PerfHud {
+    pub visible: bool,
+    samples: VecDeque<FrameTimings>,
+    /// Time spent issuing the beam's draw calls, one per frame rather than per recompute.
+    draw_samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl PerfHud {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            visible: false,
+            samples: VecDeque::with_capacity(capacity),
+            draw_samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn record(&mut self, timings: FrameTimings) {
+        if self.samples.len() == self.capacity { self.samples.pop_front(); }
+        self.samples.push_back(timings);
+    }
+
+    /// How long drawing the beam took this frame, on the CPU; the GPU works later.
+    pub fn record_draw(&mut self, micros: f32) {
+        if self.draw_samples.len() == self.capacity { self.draw_samples.pop_front(); }
+        self.draw_samples.push_back(micros);
+    }
+
+    /// Latest and mean beam draw time, see `record_draw`.
+    pub fn draw_micros(&self) -> Option<(f32, f32)> {
+        let latest = *self.draw_samples.back()?;
+        Some((latest, self.draw_samples.iter().sum::<f32>() / self.draw_samples.len() as f32))
+    }
+
+    pub fn latest(&self) -> Option<FrameTimings> {
+        self.samples.back().copied()
+    }
+
+    /// Mean over the window; counts are rounded down.
+    pub fn average(&self) -> Option<FrameTimings> {
+        let count = self.samples.len();
+        if count == 0 { return None; }
+        let sum = self.samples.iter().fold(FrameTimings::default(), |sum, sample| FrameTimings {
+            rebuild_micros: sum.rebuild_micros + sample.rebuild_micros,
+            trace_micros: sum.trace_micros + sample.trace_micros,
+            lines: sum.lines + sample.lines,
+            segments: sum.segments + sample.segments,
+        });
+        Some(FrameTimings {
+            rebuild_micros: sum.rebuild_micros / count as f32,
+            trace_micros: sum.trace_micros / count as f32,
+            lines: sum.lines / count,
+            segments: sum.segments / count,
+        })
+    }
+
+    /// Overlay in the bottom left corner, in screen space. `lines_drawn` is this frame's count,
+    /// which can differ from the last trace while animating or paused.
+    pub fn draw(&self, lines_drawn: usize, screen_height: f32) {
+        if !self.visible { return; }
+        let (Some(latest), Some(average)) = (self.latest(), self.average()) else { return; };
+        let draw = self.draw_micros().unwrap_or_default();
+        let rows = [
+            format!("rebuild {:>8.0} us  avg {:>8.0}", latest.rebuild_micros, average.rebuild_micros),
+            format!("trace   {:>8.0} us  avg {:>8.0}", latest.trace_micros, average.trace_micros),
+            format!("draw    {:>8.0} us  avg {:>8.0}", draw.0, draw.1),
+            format!("lines drawn {}  traced {}", lines_drawn, latest.lines),
+            format!("segments {}", latest.segments),
+        ];
+        let top = screen_height - 20.0 * rows.len() as f32 - 16.0;
+        draw_rectangle(10.0, top, 360.0, 20.0 * rows.len() as f32 + 10.0, Color::new(0.0, 0.0, 0.0, 0.6));
+        for (i, row) in rows.iter().enumerate() {
+            draw_text(row, 18.0, top + 20.0 * (i + 1) as f32, 20.0, WHITE);
+        }
+    }
+}
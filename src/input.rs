@@ -0,0 +1,405 @@
+// Named actions and the keys or mouse buttons that trigger them.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use macroquad::hash;
+use macroquad::input::{get_last_key_pressed, is_key_down, is_key_pressed, is_key_released, is_mouse_button_down,
+                       is_mouse_button_pressed, is_mouse_button_released, KeyCode, mouse_position, MouseButton};
+use macroquad::math::Vec2;
+use macroquad::ui::{root_ui, widgets};
+use serde::{Deserialize, Serialize};
+
+/// Something the user can trigger from the keyboard or mouse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    ToggleUi,
+    ToggleCollisions,
+    FollowMouse,
+    /// Tapped, pauses or resumes the simulation.
+    Pause,
+    Step,
+    PerfHud,
+    SceneStats,
+    /// Pressed, shows or hides the group outliner.
+    Outliner,
+    /// Pressed, shows or hides the minimap.
+    Minimap,
+    /// Pressed, shows or hides the scale bar and cursor coordinates.
+    ScaleBar,
+    Screenshot,
+    ResetCamera,
+    /// Held, previews the line of sight from the laser to the cursor.
+    SightLine,
+    Measure,
+    /// Held, a left drag pans instead of editing.
+    PanModifier,
+    /// Dragged, pans the camera.
+    PanButton,
+    /// Clicked, removes the node or edge under the cursor.
+    DeleteHovered,
+    /// Clicked, selects a node, connects it to the selected one, or places a new node.
+    ConnectSelect,
+    /// Clicked, cycles the state of the edge under the cursor.
+    CycleEdgeState,
+    /// Pressed, stops mirroring the node under the cursor (or the selected one) and its twin.
+    DetachTwin,
+    /// Pressed, cycles the lock of the edge under the cursor: length, angle, both, none.
+    LockEdge,
+    /// Pressed, pins or unpins the node under the cursor.
+    PinNode,
+    /// Pressed, turns chain mode on or off, see `NodeNetwork::set_chain_mode`.
+    ChainMode,
+    /// Held, a left click on a node locks the laser's aim onto it and elsewhere releases it.
+    TargetLock,
+    /// Pressed, turns the bounce inspector on or off, see `Protractor`.
+    Inspect,
+    /// Held, a left drag from one node to another connects them.
+    QuickConnect,
+    /// Held, a left click on a node adds it to the selection or takes it out, elsewhere clears it.
+    MultiSelect,
+    /// Pressed, makes the hovered edge reflective; held, paints every edge passed over.
+    EdgeState1,
+    /// As `EdgeState1`, absorptive.
+    EdgeState2,
+    /// As `EdgeState1`, transparent.
+    EdgeState3,
+    /// Held, a drag anywhere on the laser turns it instead of moving it.
+    RotateLaser,
+    /// Pressed, moves the selected node a unit left.
+    NudgeLeft,
+    NudgeRight,
+    NudgeUp,
+    NudgeDown,
+    /// Held, nudges move ten units.
+    NudgeCoarse,
+    /// Held, nudges move a tenth of a unit.
+    NudgeFine,
+    /// Held, drags and placements keep to an axis and laser turns to whole steps.
+    Snap,
+    /// Held, the mouse wheel zooms instead of scrolling.
+    WheelZoom,
+    /// Held, the mouse wheel scrolls sideways.
+    WheelHorizontal,
+}
+
+impl Action {
+    pub const ALL: [Action; 40] = [Action::ToggleUi, Action::ToggleCollisions, Action::FollowMouse, Action::Pause,
+        Action::Step, Action::PerfHud, Action::SceneStats, Action::Outliner, Action::Minimap, Action::ScaleBar,
+        Action::Screenshot,
+        Action::ResetCamera, Action::SightLine, Action::Measure,
+        Action::PanModifier, Action::PanButton, Action::DeleteHovered, Action::ConnectSelect, Action::CycleEdgeState,
+        Action::DetachTwin, Action::LockEdge, Action::PinNode,
+        Action::ChainMode, Action::TargetLock, Action::Inspect, Action::QuickConnect,
+        Action::MultiSelect, Action::EdgeState1, Action::EdgeState2, Action::EdgeState3, Action::RotateLaser,
+        Action::NudgeLeft, Action::NudgeRight, Action::NudgeUp, Action::NudgeDown, Action::NudgeCoarse,
+        Action::NudgeFine, Action::Snap, Action::WheelZoom, Action::WheelHorizontal];
+
+    /// Pairs meant to share a binding because one is a tap or click and the other a hold or drag,
+    /// or because one only modifies keys the other doesn't.
+    const SHARED: [(Action, Action); 7] = [(Action::Pause, Action::PanModifier),
+        (Action::PanButton, Action::DeleteHovered), (Action::MultiSelect, Action::NudgeCoarse),
+        (Action::QuickConnect, Action::NudgeFine), (Action::Snap, Action::WheelZoom),
+        (Action::MultiSelect, Action::WheelHorizontal), (Action::NudgeCoarse, Action::WheelHorizontal)];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Action::ToggleUi => "Toggle options",
+            Action::ToggleCollisions => "Toggle collisions",
+            Action::FollowMouse => "Follow mouse",
+            Action::Pause => "Pause",
+            Action::Step => "Step",
+            Action::PerfHud => "Performance HUD",
+            Action::SceneStats => "Scene statistics",
+            Action::Outliner => "Outliner",
+            Action::Minimap => "Minimap",
+            Action::ScaleBar => "Scale bar",
+            Action::Screenshot => "Screenshot",
+            Action::ResetCamera => "Reset camera",
+            Action::SightLine => "Line of sight",
+            Action::Measure => "Measure",
+            Action::PanModifier => "Pan modifier",
+            Action::PanButton => "Pan",
+            Action::DeleteHovered => "Delete",
+            Action::ConnectSelect => "Connect / select",
+            Action::CycleEdgeState => "Cycle edge state",
+            Action::DetachTwin => "Detach twin",
+            Action::LockEdge => "Lock edge",
+            Action::PinNode => "Pin node",
+            Action::ChainMode => "Chain mode",
+            Action::TargetLock => "Target lock",
+            Action::Inspect => "Inspect bounce",
+            Action::QuickConnect => "Quick connect",
+            Action::MultiSelect => "Multi-select",
+            Action::EdgeState1 => "Paint reflective",
+            Action::EdgeState2 => "Paint absorptive",
+            Action::EdgeState3 => "Paint transparent",
+            Action::RotateLaser => "Rotate laser",
+            Action::NudgeLeft => "Nudge left",
+            Action::NudgeRight => "Nudge right",
+            Action::NudgeUp => "Nudge up",
+            Action::NudgeDown => "Nudge down",
+            Action::NudgeCoarse => "Nudge by ten",
+            Action::NudgeFine => "Nudge by a tenth",
+            Action::Snap => "Snap",
+            Action::WheelZoom => "Wheel zooms",
+            Action::WheelHorizontal => "Wheel scrolls sideways",
+        }
+    }
+
+    pub const fn default_binding(self) -> Binding {
+        match self {
+            Action::ToggleUi => Binding::Key(KeyCode::Tab),
+            Action::ToggleCollisions => Binding::Key(KeyCode::CapsLock),
+            Action::FollowMouse => Binding::Key(KeyCode::F),
+            Action::Pause | Action::PanModifier => Binding::Key(KeyCode::Space),
+            Action::Step => Binding::Key(KeyCode::N),
+            Action::PerfHud => Binding::Key(KeyCode::F3),
+            Action::SceneStats => Binding::Key(KeyCode::F4),
+            Action::Outliner => Binding::Key(KeyCode::F5),
+            Action::Minimap => Binding::Key(KeyCode::F6),
+            Action::ScaleBar => Binding::Key(KeyCode::F7),
+            Action::Screenshot => Binding::Key(KeyCode::F12),
+            Action::ResetCamera => Binding::Key(KeyCode::Home),
+            Action::SightLine => Binding::Key(KeyCode::L),
+            Action::Measure => Binding::Key(KeyCode::M),
+            Action::PanButton | Action::DeleteHovered => Binding::Mouse(MouseButton::Middle),
+            Action::ConnectSelect => Binding::Mouse(MouseButton::Right),
+            Action::CycleEdgeState => Binding::Mouse(MouseButton::Left),
+            Action::DetachTwin => Binding::Key(KeyCode::B),
+            Action::LockEdge => Binding::Key(KeyCode::K),
+            Action::PinNode => Binding::Key(KeyCode::P),
+            Action::ChainMode => Binding::Key(KeyCode::C),
+            Action::TargetLock => Binding::Key(KeyCode::T),
+            Action::Inspect => Binding::Key(KeyCode::I),
+            Action::QuickConnect | Action::NudgeFine => Binding::Key(KeyCode::LeftAlt),
+            Action::MultiSelect | Action::NudgeCoarse | Action::WheelHorizontal => Binding::Key(KeyCode::LeftShift),
+            Action::Snap | Action::WheelZoom => Binding::Key(KeyCode::LeftControl),
+            Action::EdgeState1 => Binding::Key(KeyCode::Key1),
+            Action::EdgeState2 => Binding::Key(KeyCode::Key2),
+            Action::EdgeState3 => Binding::Key(KeyCode::Key3),
+            Action::RotateLaser => Binding::Key(KeyCode::R),
+            Action::NudgeLeft => Binding::Key(KeyCode::Left),
+            Action::NudgeRight => Binding::Key(KeyCode::Right),
+            Action::NudgeUp => Binding::Key(KeyCode::Up),
+            Action::NudgeDown => Binding::Key(KeyCode::Down),
+        }
+    }
+
+    fn shares_binding_with(self, other: Action) -> bool {
+        Self::SHARED.iter().any(|&pair| pair == (self, other) || pair == (other, self))
+    }
+}
+
+/// A key or mouse button. Stored by name, e.g. `"Tab"` or `"MouseMiddle"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// Every key a binding can be saved with.
+const KEYS: [KeyCode; 120] = [
+    KeyCode::Space, KeyCode::Apostrophe, KeyCode::Comma, KeyCode::Minus, KeyCode::Period,
+    KeyCode::Slash, KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Semicolon,
+    KeyCode::Equal, KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F,
+    KeyCode::G, KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V,
+    KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z, KeyCode::LeftBracket, KeyCode::Backslash,
+    KeyCode::RightBracket, KeyCode::GraveAccent, KeyCode::World1, KeyCode::World2, KeyCode::Escape,
+    KeyCode::Enter, KeyCode::Tab, KeyCode::Backspace, KeyCode::Insert, KeyCode::Delete,
+    KeyCode::Right, KeyCode::Left, KeyCode::Down, KeyCode::Up, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::Home, KeyCode::End, KeyCode::CapsLock, KeyCode::ScrollLock, KeyCode::NumLock,
+    KeyCode::PrintScreen, KeyCode::Pause, KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4,
+    KeyCode::F5, KeyCode::F6, KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11,
+    KeyCode::F12, KeyCode::F13, KeyCode::F14, KeyCode::F15, KeyCode::F16, KeyCode::F17,
+    KeyCode::F18, KeyCode::F19, KeyCode::F20, KeyCode::F21, KeyCode::F22, KeyCode::F23,
+    KeyCode::F24, KeyCode::F25, KeyCode::Kp0, KeyCode::Kp1, KeyCode::Kp2, KeyCode::Kp3,
+    KeyCode::Kp4, KeyCode::Kp5, KeyCode::Kp6, KeyCode::Kp7, KeyCode::Kp8, KeyCode::Kp9,
+    KeyCode::KpDecimal, KeyCode::KpDivide, KeyCode::KpMultiply, KeyCode::KpSubtract,
+    KeyCode::KpAdd, KeyCode::KpEnter, KeyCode::KpEqual, KeyCode::LeftShift, KeyCode::LeftControl,
+    KeyCode::LeftAlt, KeyCode::LeftSuper, KeyCode::RightShift, KeyCode::RightControl,
+    KeyCode::RightAlt, KeyCode::RightSuper, KeyCode::Menu
+];
+const MOUSE_BUTTONS: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+/// The same modifier on the other side of the keyboard, which counts as the same key.
+const fn other_side(key: KeyCode) -> Option<KeyCode> {
+    match key {
+        KeyCode::LeftShift => Some(KeyCode::RightShift),
+        KeyCode::RightShift => Some(KeyCode::LeftShift),
+        KeyCode::LeftControl => Some(KeyCode::RightControl),
+        KeyCode::RightControl => Some(KeyCode::LeftControl),
+        KeyCode::LeftAlt => Some(KeyCode::RightAlt),
+        KeyCode::RightAlt => Some(KeyCode::LeftAlt),
+        KeyCode::LeftSuper => Some(KeyCode::RightSuper),
+        KeyCode::RightSuper => Some(KeyCode::LeftSuper),
+        _ => None,
+    }
+}
+
+impl Binding {
+    pub fn is_pressed(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(key) || other_side(key).is_some_and(is_key_pressed),
+            Binding::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+
+    pub fn is_down(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(key) || other_side(key).is_some_and(is_key_down),
+            Binding::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+
+    pub fn is_released(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_released(key) || other_side(key).is_some_and(is_key_released),
+            Binding::Mouse(button) => is_mouse_button_released(button),
+        }
+    }
+
+    /// Whether both trigger on the same key or button, counting either side's modifier as one.
+    pub fn overlaps(self, other: Binding) -> bool {
+        self == other || matches!((self, other), (Binding::Key(a), Binding::Key(b)) if other_side(a) == Some(b))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(button) => write!(f, "Mouse{:?}", button),
+        }
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        KEYS.into_iter().map(Binding::Key)
+            .chain(MOUSE_BUTTONS.into_iter().map(Binding::Mouse))
+            .find(|binding| binding.to_string() == name)
+            .ok_or_else(|| format!("unknown key or button {:?}", name))
+    }
+}
+
+/// Which binding triggers each action; actions not in the map use their default.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: BTreeMap<Action, Binding>,
+}
+
+impl InputMap {
+    pub fn binding(&self, action: Action) -> Binding {
+        self.bindings.get(&action).copied().unwrap_or(action.default_binding())
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        if binding == action.default_binding() {
+            self.bindings.remove(&action);
+        } else {
+            self.bindings.insert(action, binding);
+        }
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.binding(action).is_pressed()
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.binding(action).is_down()
+    }
+
+    pub fn is_released(&self, action: Action) -> bool {
+        self.binding(action).is_released()
+    }
+
+    /// Pairs of actions bound to the same key or button that are not meant to share it.
+    pub fn conflicts(&self) -> Vec<(Action, Action)> {
+        let mut conflicts = Vec::new();
+        for (i, &a) in Action::ALL.iter().enumerate() {
+            for &b in &Action::ALL[i + 1..] {
+                if self.binding(a).overlaps(self.binding(b)) && !a.shares_binding_with(b) {
+                    conflicts.push((a, b));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// Window listing every binding; clicking one waits for the next key or button to replace it.
+#[derive(Default)]
+pub struct BindingsPanel {
+    pub visible: bool,
+    capturing: Option<Action>,
+}
+
+impl BindingsPanel {
+    /// While waiting for a key, hotkeys elsewhere should stay quiet.
+    pub const fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    pub fn ui(&mut self, input: &mut InputMap) {
+        if !self.visible {
+            self.capturing = None;
+            return;
+        }
+        if let Some(action) = self.capturing {
+            self.capture(action, input);
+        }
+        let conflicts = input.conflicts();
+        widgets::Window::new(hash!(), Vec2::new(710., 0.), Vec2::new(260., 400.))
+            .label("Key bindings")
+            .ui(&mut root_ui(), |ui| {
+                for action in Action::ALL {
+                    let conflicting = conflicts.iter().any(|&(a, b)| a == action || b == action);
+                    let text = if self.capturing == Some(action) {
+                        format!("{}: press a key...", action.label())
+                    } else {
+                        format!("{}{}: {}", if conflicting { "(!) " } else { "" }, action.label(), input.binding(action))
+                    };
+                    if ui.button(None, text) { self.capturing = Some(action); }
+                }
+                ui.separator();
+                for (a, b) in &conflicts {
+                    ui.label(None, &format!("Conflict: {} and {}", a.label(), b.label()));
+                }
+                if ui.button(None, "Reset to defaults") {
+                    *input = InputMap::default();
+                    self.capturing = None;
+                }
+            });
+    }
+
+    /// Escape cancels; a left click only counts outside the UI, where it can't be the click
+    /// that started capturing.
+    fn capture(&mut self, action: Action, input: &mut InputMap) {
+        if is_key_pressed(KeyCode::Escape) {
+            self.capturing = None;
+            return;
+        }
+        let over_ui = root_ui().is_mouse_over(mouse_position().into());
+        let binding = get_last_key_pressed().map(Binding::Key)
+            .or_else(|| MOUSE_BUTTONS.into_iter()
+                .filter(|&button| button != MouseButton::Left || !over_ui)
+                .find(|&button| is_mouse_button_pressed(button))
+                .map(Binding::Mouse));
+        if let Some(binding) = binding {
+            input.bind(action, binding);
+            self.capturing = None;
+        }
+    }
+}
@@ -1,11 +1,17 @@
 // perfect labyrinth - https://en.wikipedia.org/wiki/Perfect_labyrinth
 
 
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, VecDeque};
 use std::fmt::Debug;
 
-use macroquad::rand::{ChooseRandom, srand};
+use macroquad::color::Color;
+use macroquad::shapes::{draw_line, draw_rectangle};
+use macroquad::text::draw_text;
+use serde::{Deserialize, Serialize};
 
+use crate::core::SeededRng;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Side {
     Top,
     Bottom,
@@ -13,25 +19,27 @@ pub enum Side {
     Right,
 }
 
+/// Wall segment as `((x1, y1), (x2, y2))` in labyrinth-local coordinates.
+pub type Line = ((f32, f32), (f32, f32));
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Cell {
     sides: u8,
 }
 
-#[allow(dead_code)]
 impl Cell {
-    const fn new() -> Self {
+    pub const fn new() -> Self {
         Cell {
             sides: 0b0000_1111 // all sides closed (top, bottom, left, right)
         }
     }
-    const fn new_empty() -> Self {
+    pub const fn new_empty() -> Self {
         Cell {
             sides: 0b0000_0000
         }
     }
 
-    fn open(&mut self, side: Side) {
+    pub fn open(&mut self, side: Side) {
         match side {
             Side::Top => self.sides &= 0b1111_0111,
             Side::Bottom => self.sides &= 0b1111_1011,
@@ -40,32 +48,132 @@ impl Cell {
         }
     }
 
-    const fn is_open(self, side: Side) -> bool {
+    pub const fn is_open(self, side: Side) -> bool {
         match side {
             Side::Top => self.sides & 0b0000_1000 == 0,
             Side::Bottom => self.sides & 0b0000_0100 == 0,
             Side::Left => self.sides & 0b0000_0010 == 0,
-            Side::Right => self.sides & 0b0001_0001 == 0
+            Side::Right => self.sides & 0b0000_0001 == 0
         }
     }
-    const fn is_closed(self, side: Side) -> bool {
+    pub const fn is_closed(self, side: Side) -> bool {
         !self.is_open(side)
     }
-    const fn get_sides(&self) -> u8 {
+    pub const fn get_sides(&self) -> u8 {
         self.sides
     }
 }
 
+impl Default for Cell {
+    fn default() -> Self {
+        Cell::new()
+    }
+}
+
 impl Debug for Cell {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Cell {{ sides: {:b} }}", self.sides)
     }
 }
 
+impl Side {
+    pub const fn opposite(self) -> Side {
+        match self {
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Algorithm {
+    #[default]
+    DepthFirst,
+    Prim,
+    Kruskal,
+    Wilson,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [Algorithm::DepthFirst, Algorithm::Prim, Algorithm::Kruskal, Algorithm::Wilson];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Algorithm::DepthFirst => "Depth first",
+            Algorithm::Prim => "Prim",
+            Algorithm::Kruskal => "Kruskal",
+            Algorithm::Wilson => "Wilson",
+        }
+    }
+}
+
 pub struct Labyrinth {
     pub cell_size: f32,
     pub size: (usize, usize), // (width, height)
-    cells: Vec<Vec<Cell>>,
+    cells: Vec<Vec<Cell>>, // indexed [y][x]
+    pub entrance: Option<(usize, usize)>,
+    pub exit: Option<(usize, usize)>,
+}
+
+/// Tries per room `Labyrinth::carve_rooms` makes at placing it.
+const ROOM_ATTEMPTS: usize = 30;
+
+/// Rectangle of cells opened into one area by `Labyrinth::carve_rooms`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Room {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Room {
+    pub const fn center(&self) -> (usize, usize) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+    pub const fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+    /// Overlapping or sharing a side, so the two would open into one area.
+    const fn touches(&self, other: &Room) -> bool {
+        self.x <= other.x + other.width && other.x <= self.x + self.width
+            && self.y <= other.y + other.height && other.y <= self.y + self.height
+    }
+}
+
+/// Everything that decides what a built labyrinth looks like, the seed included, so the same
+/// one can be built again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LabyrinthSpec {
+    pub cell_size: f32,
+    pub size: (usize, usize),
+    pub algorithm: Algorithm,
+    /// Opened at the first cell of its side.
+    pub entrance: Option<Side>,
+    /// Opened at the last cell of its side.
+    pub exit: Option<Side>,
+    /// Fraction of dead ends braided away, see `Labyrinth::braid`.
+    pub loopiness: f32,
+    pub seed: u64,
+}
+
+impl LabyrinthSpec {
+    /// The closed grid with its openings, not generated yet.
+    pub fn layout(&self) -> Labyrinth {
+        let mut labyrinth = Labyrinth::new(self.cell_size, self.size);
+        if let Some(side) = self.entrance { labyrinth.open_entrance(side, 0); }
+        if let Some(side) = self.exit { labyrinth.open_exit(side, usize::MAX); }
+        labyrinth
+    }
+
+    pub fn build(&self) -> Labyrinth {
+        let mut labyrinth = self.layout();
+        labyrinth.generate(self.algorithm, self.seed);
+        labyrinth.braid(self.loopiness, self.seed);
+        labyrinth
+    }
 }
 
 impl Labyrinth {
@@ -73,14 +181,289 @@ impl Labyrinth {
         Labyrinth {
             cell_size,
             size,
-            cells: vec![vec![Cell::new(); size.1]; size.0],
+            cells: vec![vec![Cell::new(); size.0]; size.1],
+            entrance: None,
+            exit: None,
         }
     }
     pub fn get_cells(&self) -> &Vec<Vec<Cell>> {
         &self.cells
     }
 
-    pub fn get_as_lines_explicit(&self) -> Vec<((f32, f32), (f32, f32))> {
+    /// Cell at position `index` along the boundary `side` (left to right, top to bottom),
+    /// clamped to the labyrinth.
+    pub fn boundary_cell(&self, side: Side, index: usize) -> (usize, usize) {
+        let (width, height) = self.size;
+        match side {
+            Side::Top => (index.min(width - 1), 0),
+            Side::Bottom => (index.min(width - 1), height - 1),
+            Side::Left => (0, index.min(height - 1)),
+            Side::Right => (width - 1, index.min(height - 1)),
+        }
+    }
+
+    fn open_boundary(&mut self, side: Side, index: usize) -> (usize, usize) {
+        let (x, y) = self.boundary_cell(side, index);
+        self.cells[y][x].open(side);
+        (x, y)
+    }
+
+    /// Removes the outer wall of the `index`-th cell along `side` so the laser can get in.
+    pub fn open_entrance(&mut self, side: Side, index: usize) {
+        self.entrance = Some(self.open_boundary(side, index));
+    }
+
+    pub fn open_exit(&mut self, side: Side, index: usize) {
+        self.exit = Some(self.open_boundary(side, index));
+    }
+
+    /// Shortest path of cells from `start` to `end` through open sides (BFS), both inclusive.
+    pub fn solve(&self, start: (usize, usize), end: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let (width, height) = self.size;
+        if start.0 >= width || start.1 >= height || end.0 >= width || end.1 >= height { return None; }
+        let mut came_from: Vec<Vec<Option<(usize, usize)>>> = vec![vec![None; width]; height];
+        came_from[start.1][start.0] = Some(start);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            if cell == end {
+                let mut path = vec![end];
+                let mut current = end;
+                while current != start {
+                    current = came_from[current.1][current.0].unwrap();
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, side) in self.neighbours(cell) {
+                if self.cells[cell.1][cell.0].is_open(side) && came_from[next.1][next.0].is_none() {
+                    came_from[next.1][next.0] = Some(cell);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// World-space points through the centres of the cells in `path`.
+    pub fn path_as_polyline(path: &[(usize, usize)], cell_size: f32, (offset_x, offset_y): (f32, f32)) -> Vec<(f32, f32)> {
+        path.iter()
+            .map(|&(x, y)| (offset_x + (x as f32 + 0.5) * cell_size, offset_y + (y as f32 + 0.5) * cell_size))
+            .collect()
+    }
+
+    /// In-bounds neighbours of `(x, y)` together with the side of `(x, y)` they lie behind.
+    fn neighbours(&self, (x, y): (usize, usize)) -> Vec<((usize, usize), Side)> {
+        let mut neighbours = Vec::with_capacity(4);
+        if y > 0 { neighbours.push(((x, y - 1), Side::Top)); }
+        if y + 1 < self.size.1 { neighbours.push(((x, y + 1), Side::Bottom)); }
+        if x > 0 { neighbours.push(((x - 1, y), Side::Left)); }
+        if x + 1 < self.size.0 { neighbours.push(((x + 1, y), Side::Right)); }
+        neighbours
+    }
+
+    /// Removes the wall between `(x, y)` and the neighbour behind `side`.
+    fn carve(&mut self, (x, y): (usize, usize), side: Side) {
+        let (next_x, next_y) = match side {
+            Side::Top => (x, y - 1),
+            Side::Bottom => (x, y + 1),
+            Side::Left => (x - 1, y),
+            Side::Right => (x + 1, y),
+        };
+        self.cells[y][x].open(side);
+        self.cells[next_y][next_x].open(side.opposite());
+    }
+
+    pub fn generate(&mut self, algorithm: Algorithm, seed: u64) {
+        match algorithm {
+            Algorithm::DepthFirst => self.generate_depth_first(seed),
+            Algorithm::Prim => self.generate_prim(seed),
+            Algorithm::Kruskal => self.generate_kruskal(seed),
+            Algorithm::Wilson => self.generate_wilson(seed),
+        }
+    }
+
+    /// Stepwise depth-first generation, for watching the maze carve itself. Running it to
+    /// completion gives the same maze as `generate(Algorithm::DepthFirst, seed)`.
+    pub fn generator_depth_first(self, seed: u64) -> LabyrinthGenerator {
+        let state = DepthFirstState::new(&self, seed);
+        LabyrinthGenerator { labyrinth: self, state }
+    }
+
+    /// Cells with exactly three closed sides.
+    pub fn dead_ends(&self) -> Vec<(usize, usize)> {
+        let mut dead_ends = Vec::new();
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if cell.get_sides().count_ones() == 3 { dead_ends.push((x, y)); }
+            }
+        }
+        dead_ends
+    }
+
+    /// Adds loops by removing a fraction `p` (0..=1) of the dead ends, each opened into a random
+    /// neighbour. Boundary walls are never opened.
+    pub fn braid(&mut self, p: f32, seed: u64) {
+        let mut rng = SeededRng::new(seed);
+        let mut dead_ends = self.dead_ends();
+        rng.shuffle(&mut dead_ends);
+        let count = (p.clamp(0.0, 1.0) * dead_ends.len() as f32).round() as usize;
+        for cell in dead_ends.into_iter().take(count) {
+            // an earlier opening may already have fixed this one
+            if self.cells[cell.1][cell.0].get_sides().count_ones() != 3 { continue; }
+            let closed: Vec<Side> = self.neighbours(cell).into_iter()
+                .map(|(_, side)| side)
+                .filter(|side| self.cells[cell.1][cell.0].is_closed(*side))
+                .collect();
+            if closed.is_empty() { continue; }
+            self.carve(cell, closed[rng.gen_index(closed.len())]);
+        }
+    }
+
+    /// Places up to `count` rooms of `min_size..=max_size` cells a side at random, none touching
+    /// another, opens every wall inside them and joins each to the next with an L-shaped
+    /// corridor. Cells left out stay closed. Returns the rooms in the order they are joined.
+    pub fn carve_rooms(&mut self, count: usize, min_size: usize, max_size: usize, seed: u64) -> Vec<Room> {
+        let mut rng = SeededRng::new(seed);
+        let (width, height) = self.size;
+        let min_size = min_size.max(1);
+        let max_size = max_size.max(min_size);
+        let mut rooms: Vec<Room> = Vec::with_capacity(count);
+        // rejection sampling, giving up on a crowded grid rather than looping forever
+        for _ in 0..count * ROOM_ATTEMPTS {
+            if rooms.len() == count { break; }
+            let room_width = (min_size + rng.gen_index(max_size - min_size + 1)).min(width);
+            let room_height = (min_size + rng.gen_index(max_size - min_size + 1)).min(height);
+            let room = Room {
+                x: rng.gen_index(width - room_width + 1),
+                y: rng.gen_index(height - room_height + 1),
+                width: room_width,
+                height: room_height,
+            };
+            if rooms.iter().all(|other| !room.touches(other)) { rooms.push(room); }
+        }
+
+        for room in &rooms {
+            for y in room.y..room.y + room.height {
+                for x in room.x..room.x + room.width {
+                    if x + 1 < room.x + room.width { self.carve((x, y), Side::Right); }
+                    if y + 1 < room.y + room.height { self.carve((x, y), Side::Bottom); }
+                }
+            }
+        }
+        for pair in rooms.windows(2) {
+            let (from, to) = (pair[0].center(), pair[1].center());
+            if rng.gen_index(2) == 0 {
+                self.carve_straight(from, (to.0, from.1));
+                self.carve_straight((to.0, from.1), to);
+            } else {
+                self.carve_straight(from, (from.0, to.1));
+                self.carve_straight((from.0, to.1), to);
+            }
+        }
+        rooms
+    }
+
+    /// Opens every wall between `from` and `to`, which share a row or a column.
+    fn carve_straight(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let mut cell = from;
+        while cell != to {
+            let side = if cell.0 < to.0 { Side::Right } else if cell.0 > to.0 { Side::Left }
+                else if cell.1 < to.1 { Side::Bottom } else { Side::Top };
+            self.carve(cell, side);
+            cell = match side {
+                Side::Right => (cell.0 + 1, cell.1),
+                Side::Left => (cell.0 - 1, cell.1),
+                Side::Bottom => (cell.0, cell.1 + 1),
+                Side::Top => (cell.0, cell.1 - 1),
+            };
+        }
+    }
+
+    /// Randomized Prim: grows the maze from a random cell by opening random frontier walls.
+    pub fn generate_prim(&mut self, seed: u64) {
+        let mut rng = SeededRng::new(seed);
+        let mut visited = vec![vec![false; self.size.0]; self.size.1];
+        let start = (rng.gen_index(self.size.0), rng.gen_index(self.size.1));
+        visited[start.1][start.0] = true;
+        let mut frontier: Vec<_> = self.neighbours(start)
+            .into_iter().map(|(next, side)| (start, side, next)).collect();
+
+        while !frontier.is_empty() {
+            let (cell, side, next) = frontier.swap_remove(rng.gen_index(frontier.len()));
+            if visited[next.1][next.0] { continue; }
+            visited[next.1][next.0] = true;
+            self.carve(cell, side);
+            for (after, after_side) in self.neighbours(next) {
+                if !visited[after.1][after.0] { frontier.push((next, after_side, after)); }
+            }
+        }
+    }
+
+    /// Randomized Kruskal: opens walls in random order whenever they join two separate regions.
+    pub fn generate_kruskal(&mut self, seed: u64) {
+        let mut rng = SeededRng::new(seed);
+        let (width, height) = self.size;
+        let mut walls = Vec::with_capacity(2 * width * height);
+        for y in 0..height {
+            for x in 0..width {
+                if x + 1 < width { walls.push(((x, y), Side::Right)); }
+                if y + 1 < height { walls.push(((x, y), Side::Bottom)); }
+            }
+        }
+        rng.shuffle(&mut walls);
+
+        let mut parents: Vec<usize> = (0..width * height).collect();
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        for ((x, y), side) in walls {
+            let next = if side == Side::Right { (x + 1, y) } else { (x, y + 1) };
+            let a = root(&mut parents, y * width + x);
+            let b = root(&mut parents, next.1 * width + next.0);
+            if a == b { continue; }
+            parents[a] = b;
+            self.carve((x, y), side);
+        }
+    }
+
+    /// Wilson: loop-erased random walks from every unvisited cell until they hit the maze,
+    /// giving a uniformly random spanning tree.
+    pub fn generate_wilson(&mut self, seed: u64) {
+        let mut rng = SeededRng::new(seed);
+        let (width, height) = self.size;
+        let mut in_maze = vec![vec![false; width]; height];
+        in_maze[rng.gen_index(height)][rng.gen_index(width)] = true;
+        // direction the walk last left each cell in; overwriting it erases loops
+        let mut walk_exit: Vec<Vec<Option<Side>>> = vec![vec![None; width]; height];
+
+        for y in 0..height {
+            for x in 0..width {
+                if in_maze[y][x] { continue; }
+                let mut cell = (x, y);
+                while !in_maze[cell.1][cell.0] {
+                    let neighbours = self.neighbours(cell);
+                    let (next, side) = neighbours[rng.gen_index(neighbours.len())];
+                    walk_exit[cell.1][cell.0] = Some(side);
+                    cell = next;
+                }
+                let mut cell = (x, y);
+                while !in_maze[cell.1][cell.0] {
+                    in_maze[cell.1][cell.0] = true;
+                    let side = walk_exit[cell.1][cell.0].unwrap();
+                    self.carve(cell, side);
+                    cell = self.neighbours(cell).into_iter()
+                        .find(|(_, s)| *s == side).unwrap().0;
+                }
+            }
+        }
+    }
+
+    pub fn get_as_lines_explicit(&self) -> Vec<Line> {
         let mut lines = Vec::new();
         let cell_size = self.cell_size;
 
@@ -108,7 +491,7 @@ impl Labyrinth {
         }
         lines
     }
-    pub fn get_as_lines(&self) -> Vec<((f32, f32), (f32, f32))> {
+    pub fn get_as_lines(&self) -> Vec<Line> {
         let mut lines = Vec::new();
         let cell_size = self.cell_size;
         
@@ -224,97 +607,257 @@ impl Labyrinth {
 
         lines
     }
-    pub fn generate_depth_first2(&mut self) {
-        let mut visited = vec![vec![false; self.size.0]; self.size.1];
+    /// Walls `get_as_lines` gets wrong, as one cell side each: those of `get_as_lines_explicit`
+    /// its merged runs miss, and those the runs cover that no cell has. Both empty when the
+    /// merging is right.
+    pub fn diff_lines(&self) -> (Vec<Line>, Vec<Line>) {
+        let explicit = self.unit_sides(&self.get_as_lines_explicit());
+        let merged = self.unit_sides(&self.get_as_lines());
+        let to_line = |((x1, y1), (x2, y2)): ((i64, i64), (i64, i64))| {
+            let size = self.cell_size;
+            ((x1 as f32 * size, y1 as f32 * size), (x2 as f32 * size, y2 as f32 * size))
+        };
+        (explicit.difference(&merged).copied().map(to_line).collect(),
+         merged.difference(&explicit).copied().map(to_line).collect())
+    }
 
-        let mut stack = VecDeque::new();
-        stack.push_back((0, 0));
-        const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-        let mut directions = DIRECTIONS.to_vec();
-        srand(12);
-        while let Some((x, y)) = stack.pop_front() {
-            directions.shuffle();
-            for (d_x, d_y) in &directions {
-                let next_x = x + d_x;
-                let next_y = y + d_y;
-                if next_x < 0 || next_y < 0 ||
-                    next_x >= self.size.0 as i32 || next_y >= self.size.1 as i32 { continue; }
-                let next_x = next_x as usize;
-                let next_y = next_y as usize;
-                if visited[next_y][next_x] { continue; }
-                visited[next_y][next_x] = true;
-                stack.push_front((x, y));
-                stack.push_front((next_x as i32, next_y as i32));
-                match (d_x, d_y) {
-                    (0, -1) => {
-                        self.cells[y as usize][x as usize].open(Side::Top);
-                        self.cells[next_y][next_x].open(Side::Bottom)
-                    }
-                    (0, 1) => {
-                        self.cells[y as usize][x as usize].open(Side::Bottom);
-                        self.cells[next_y][next_x].open(Side::Top)
-                    }
-                    (1, 0) => {
-                        self.cells[y as usize][x as usize].open(Side::Right);
-                        self.cells[next_y][next_x].open(Side::Left)
-                    }
-                    (-1, 0) => {
-                        self.cells[y as usize][x as usize].open(Side::Left);
-                        self.cells[next_y][next_x].open(Side::Right)
+    /// `lines` cut into cell sides, in grid coordinates with the lower corner first.
+    fn unit_sides(&self, lines: &[Line]) -> BTreeSet<((i64, i64), (i64, i64))> {
+        let grid = |value: f32| (value / self.cell_size).round() as i64;
+        let mut sides = BTreeSet::new();
+        for &((x1, y1), (x2, y2)) in lines {
+            let (start, end) = ((grid(x1), grid(y1)), (grid(x2), grid(y2)));
+            let (start, end) = (start.min(end), start.max(end));
+            let step = ((end.0 - start.0).signum(), (end.1 - start.1).signum());
+            let mut corner = start;
+            while corner != end {
+                let next = (corner.0 + step.0, corner.1 + step.1);
+                sides.insert((corner, next));
+                corner = next;
+            }
+        }
+        sides
+    }
+
+    /// Cell containing `point`, in labyrinth-local coordinates.
+    pub fn cell_at(&self, (x, y): (f32, f32)) -> Option<(usize, usize)> {
+        if x < 0.0 || y < 0.0 { return None; }
+        let cell = ((x / self.cell_size) as usize, (y / self.cell_size) as usize);
+        (cell.0 < self.size.0 && cell.1 < self.size.1).then_some(cell)
+    }
+
+    /// Every side of every cell straight from its bits, closed ones solid and open ones faint
+    /// and dashed, with the cell coordinates, for checking generators and `get_as_lines`.
+    /// `highlight` is filled in.
+    pub fn debug_draw(&self, (offset_x, offset_y): (f32, f32), highlight: Option<(usize, usize)>) {
+        const CLOSED: Color = Color::new(1.0, 0.3, 0.3, 0.9);
+        const OPEN: Color = Color::new(0.3, 1.0, 0.3, 0.35);
+        const HIGHLIGHT: Color = Color::new(1.0, 1.0, 0.0, 0.25);
+        let size = self.cell_size;
+        // a little inside the cell, so the two cells sharing a side don't draw over each other
+        let inset = size * 0.08;
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let (left, top) = (offset_x + x as f32 * size, offset_y + y as f32 * size);
+                let (right, bottom) = (left + size, top + size);
+                if highlight == Some((x, y)) { draw_rectangle(left, top, size, size, HIGHLIGHT); }
+                let sides = [
+                    (Side::Top, (left + inset, top + inset), (right - inset, top + inset)),
+                    (Side::Bottom, (left + inset, bottom - inset), (right - inset, bottom - inset)),
+                    (Side::Left, (left + inset, top + inset), (left + inset, bottom - inset)),
+                    (Side::Right, (right - inset, top + inset), (right - inset, bottom - inset)),
+                ];
+                for (side, start, end) in sides {
+                    if cell.is_closed(side) {
+                        draw_line(start.0, start.1, end.0, end.1, 1.5, CLOSED);
+                    } else {
+                        draw_dashed_line(start, end, size / 8.0, OPEN);
                     }
-                    _ => unreachable!()
                 }
-                break;
+                draw_text(&format!("{},{}", x, y), left + inset * 1.5, top + size / 2.0, (size / 4.0).max(6.0), OPEN);
             }
         }
-    }    
-    pub fn generate_depth_first(&mut self) {
-        let mut visited = vec![vec![false; self.size.0]; self.size.1];
+    }
+    /// Backtracker that prefers turning, the same maze for the same `seed`.
+    pub fn generate_depth_first(&mut self, seed: u64) {
+        let mut state = DepthFirstState::new(self, seed);
+        while !state.step(self) {}
+    }
+}
+fn draw_dashed_line(start: (f32, f32), end: (f32, f32), dash: f32, color: Color) {
+    let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+    let dashes = (length / dash / 2.0).ceil().max(1.0) as usize;
+    let point = |t: f32| (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t);
+    for i in 0..dashes {
+        let (from, to) = (point(i as f32 / dashes as f32), point((i as f32 + 0.5) / dashes as f32));
+        draw_line(from.0, from.1, to.0, to.1, 1.0, color);
+    }
+}
 
-        let mut stack = VecDeque::new();
-        stack.push_back((0, 0));
-        const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-        let mut directions = DIRECTIONS.to_vec();
-        // srand(12);
-        let mut last_dir = (0, -1);
-        while let Some((x, y)) = stack.pop_front() {
-            directions.shuffle();
-            for (d_x, d_y) in &directions {
-                if last_dir == (*d_x, *d_y) { continue; }
-                let next_x = x + d_x;
-                let next_y = y + d_y;
-                if next_x < 0 || next_y < 0 ||
-                    next_x >= self.size.0 as i32 || next_y >= self.size.1 as i32 { continue; }
-                let next_x = next_x as usize;
-                let next_y = next_y as usize;
-                if visited[next_y][next_x] { continue; }
-                visited[next_y][next_x] = true;
-                last_dir = (*d_x, *d_y);
-                stack.push_front((x, y));
-                stack.push_front((next_x as i32, next_y as i32));
-                // debug!("({}, {})->({}, {}) ({},{}) {:?}",x,y, next_x, next_y, d_x, d_y , last_dir);
-                match (d_x, d_y) {
-                    (0, -1) => {
-                        self.cells[y as usize][x as usize].open(Side::Top);
-                        self.cells[next_y][next_x].open(Side::Bottom)
-                    }
-                    (0, 1) => {
-                        self.cells[y as usize][x as usize].open(Side::Bottom);
-                        self.cells[next_y][next_x].open(Side::Top)
-                    }
-                    (1, 0) => {
-                        self.cells[y as usize][x as usize].open(Side::Right);
-                        self.cells[next_y][next_x].open(Side::Left)
-                    }
-                    (-1, 0) => {
-                        self.cells[y as usize][x as usize].open(Side::Left);
-                        self.cells[next_y][next_x].open(Side::Right)
-                    }
-                    _ => unreachable!()
+struct DepthFirstState {
+    rng: SeededRng,
+    visited: Vec<Vec<bool>>,
+    stack: VecDeque<(usize, usize)>,
+    last_side: Side,
+}
+
+impl DepthFirstState {
+    fn new(labyrinth: &Labyrinth, seed: u64) -> Self {
+        let mut visited = vec![vec![false; labyrinth.size.0]; labyrinth.size.1];
+        visited[0][0] = true;
+        Self { rng: SeededRng::new(seed), visited, stack: VecDeque::from([(0, 0)]), last_side: Side::Top }
+    }
+
+    /// Carves one passage, backtracking over dead ends as needed. Returns `true` once done.
+    fn step(&mut self, labyrinth: &mut Labyrinth) -> bool {
+        while let Some(cell) = self.stack.pop_front() {
+            let mut neighbours = labyrinth.neighbours(cell);
+            self.rng.shuffle(&mut neighbours);
+            // prefer turning, but keep going straight as a last resort so no cell is stranded
+            neighbours.sort_by_key(|(_, side)| *side == self.last_side);
+            if let Some(&(next, side)) = neighbours.iter().find(|(next, _)| !self.visited[next.1][next.0]) {
+                self.visited[next.1][next.0] = true;
+                self.last_side = side;
+                self.stack.push_front(cell);
+                self.stack.push_front(next);
+                labyrinth.carve(cell, side);
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A labyrinth being generated one carve at a time; it is a valid partial maze after every step.
+pub struct LabyrinthGenerator {
+    labyrinth: Labyrinth,
+    state: DepthFirstState,
+}
+
+impl LabyrinthGenerator {
+    /// Performs one carve, returns whether generation is finished.
+    pub fn step(&mut self) -> bool {
+        self.state.step(&mut self.labyrinth)
+    }
+    pub fn is_finished(&self) -> bool {
+        self.state.stack.is_empty()
+    }
+    pub fn labyrinth(&self) -> &Labyrinth {
+        &self.labyrinth
+    }
+    pub fn into_labyrinth(self) -> Labyrinth {
+        self.labyrinth
+    }
+}
+
+/// A round labyrinth of `rings` concentric rings, each split into `sectors` cells.
+///
+/// Cells reuse `Side`: `Top` faces the centre, `Bottom` faces outwards, `Left` and `Right` are
+/// the previous and next sector (counter-clockwise and clockwise on screen).
+pub struct PolarLabyrinth {
+    pub ring_width: f32,
+    pub rings: usize,
+    pub sectors: usize,
+    /// Number of chords approximating each cell's arc in `get_as_lines`.
+    pub subdivisions: usize,
+    cells: Vec<Vec<Cell>>, // indexed [ring][sector]
+}
+
+impl PolarLabyrinth {
+    pub fn new(ring_width: f32, rings: usize, sectors: usize) -> Self {
+        PolarLabyrinth {
+            ring_width,
+            rings,
+            sectors,
+            subdivisions: 4,
+            cells: vec![vec![Cell::new(); sectors]; rings],
+        }
+    }
+    pub fn get_cells(&self) -> &Vec<Vec<Cell>> {
+        &self.cells
+    }
+
+    /// Opens the innermost wall of `sector`, letting a laser in the centre out into the maze.
+    pub fn open_center(&mut self, sector: usize) {
+        self.cells[0][sector % self.sectors].open(Side::Top);
+    }
+
+    /// Opens the outermost wall of `sector`.
+    pub fn open_rim(&mut self, sector: usize) {
+        self.cells[self.rings - 1][sector % self.sectors].open(Side::Bottom);
+    }
+
+    /// Neighbours of `(ring, sector)` together with the side they lie behind; sectors wrap around.
+    pub fn neighbours(&self, (ring, sector): (usize, usize)) -> Vec<((usize, usize), Side)> {
+        let mut neighbours = Vec::with_capacity(4);
+        if ring > 0 { neighbours.push(((ring - 1, sector), Side::Top)); }
+        if ring + 1 < self.rings { neighbours.push(((ring + 1, sector), Side::Bottom)); }
+        if self.sectors > 1 {
+            neighbours.push(((ring, (sector + self.sectors - 1) % self.sectors), Side::Left));
+            neighbours.push(((ring, (sector + 1) % self.sectors), Side::Right));
+        }
+        neighbours
+    }
+
+    fn carve(&mut self, (ring, sector): (usize, usize), ((next_ring, next_sector), side): ((usize, usize), Side)) {
+        self.cells[ring][sector].open(side);
+        self.cells[next_ring][next_sector].open(side.opposite());
+    }
+
+    /// Depth-first (recursive backtracker) over the polar adjacency.
+    pub fn generate_depth_first(&mut self, seed: u64) {
+        if self.rings == 0 || self.sectors == 0 { return; }
+        let mut rng = SeededRng::new(seed);
+        let mut visited = vec![vec![false; self.sectors]; self.rings];
+        visited[0][0] = true;
+        let mut stack = vec![(0, 0)];
+        while let Some(&cell) = stack.last() {
+            let mut neighbours = self.neighbours(cell);
+            rng.shuffle(&mut neighbours);
+            match neighbours.into_iter().find(|((ring, sector), _)| !visited[*ring][*sector]) {
+                Some(neighbour) => {
+                    visited[neighbour.0.0][neighbour.0.1] = true;
+                    self.carve(cell, neighbour);
+                    stack.push(neighbour.0);
                 }
-                break;
+                None => { stack.pop(); }
             }
         }
-        // debug!("visited {:?}", visited);
     }
-}
\ No newline at end of file
+
+    /// Wall segments around `center`, the first ring starting at `inner_radius`. Ring walls are
+    /// `subdivisions` chords per cell, radial walls are straight. Every vertex is computed from
+    /// the same (radius, angle) indices, so shared endpoints are bit-identical.
+    pub fn get_as_lines(&self, center: (f32, f32), inner_radius: f32) -> Vec<Line> {
+        if self.rings == 0 || self.sectors == 0 { return Vec::new(); }
+        let subdivisions = self.subdivisions.max(1);
+        let steps = self.sectors * subdivisions;
+        let vertex = |boundary: usize, step: usize| {
+            let radius = inner_radius + boundary as f32 * self.ring_width;
+            let angle = std::f32::consts::TAU * (step % steps) as f32 / steps as f32;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        };
+        let mut lines = Vec::new();
+        for boundary in 0..=self.rings {
+            for sector in 0..self.sectors {
+                let closed = if boundary == self.rings {
+                    self.cells[boundary - 1][sector].is_closed(Side::Bottom)
+                } else {
+                    self.cells[boundary][sector].is_closed(Side::Top)
+                };
+                if !closed { continue; }
+                for step in sector * subdivisions..(sector + 1) * subdivisions {
+                    lines.push((vertex(boundary, step), vertex(boundary, step + 1)));
+                }
+            }
+        }
+        for (ring, row) in self.cells.iter().enumerate() {
+            for (sector, cell) in row.iter().enumerate() {
+                if cell.is_closed(Side::Left) {
+                    lines.push((vertex(ring, sector * subdivisions), vertex(ring + 1, sector * subdivisions)));
+                }
+            }
+        }
+        lines
+    }
+}
@@ -1,22 +1,72 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::ptr::addr_of_mut;
 
 // #[cfg(not(target_family = "wasm"))]
-use log::{debug, error};
-use macroquad::color::{BLACK, Color, DARKGRAY, SKYBLUE, WHITE};
+use log::{debug, error, info, warn};
+use macroquad::color::{Color, hsl_to_rgb, ORANGE, WHITE, YELLOW};
 use macroquad::hash;
-use macroquad::input::{is_key_down, is_mouse_button_pressed, is_mouse_button_released,
+use macroquad::input::{is_key_pressed, is_mouse_button_pressed, is_mouse_button_released,
                        KeyCode, mouse_position as other_mouse_position, MouseButton};
-use macroquad::math::{Vec2, vec2};
-use macroquad::prelude::{draw_text, ImageFormat};
-use macroquad::shapes::{draw_line};
+use macroquad::math::{Rect, Vec2, vec2, vec3};
+use macroquad::models::{draw_mesh, Mesh, Vertex};
+use macroquad::prelude::{draw_text, measure_text};
+use macroquad::shapes::{draw_circle, draw_circle_lines, draw_line, draw_rectangle, draw_triangle};
 use macroquad::texture::{draw_texture_ex,
                          DrawTextureParams,
                          Texture2D};
-use macroquad::ui::{root_ui, widgets};
+use macroquad::time::get_time;
+use macroquad::ui::{root_ui, Ui, widgets};
 use macroquad::window::{screen_height, screen_width};
-
-mod labyrinth;
+use serde::{Deserialize, Serialize};
+
+pub use crate::core::{CollisionInfo, EdgeState, first_obstruction, line_of_sight, Ray, reflect, refract, Segment,
+                      TraceResult};
+use crate::cache::TraceKey;
+use crate::fragment::{ArrayLayout, NetworkFragment};
+use crate::input::{Action, InputMap};
+use crate::labyrinth::{Labyrinth, LabyrinthSpec};
+use crate::offset::Offset;
+use crate::observe::{EventQueue, NetworkEvent};
+use crate::replay::{Event, Journal};
+use crate::schedule::BudgetController;
+use crate::static_geometry::StaticGeometry;
+use crate::stats::NetworkStats;
+use crate::symmetry::SymmetryAxis;
+use crate::core::{AbsorptionCurve, Attenuation, DEFAULT_LAYER, DEFAULT_THICKNESS, EmitterConfig, Interaction,
+                  merge_collinear_segments, Precision, segment_intersection, SegmentIntersection, SimConfig, Surfaces,
+                  Termination, trace_lights_into, trace_lights_on_layers, TraceScratch, TraceStats, Watchdog};
+
+pub mod assets;
+pub mod background;
+pub mod batch;
+pub mod cache;
+pub mod core;
+pub mod fragment;
+pub mod hud;
+pub mod input;
+pub mod labyrinth;
+pub mod measure;
+pub mod minimap;
+mod misc_ui;
+pub mod observe;
+pub mod offset;
+pub mod outliner;
+pub mod pattern;
+pub mod polygon;
+pub mod presets;
+pub mod protractor;
+mod render;
+pub mod replay;
+pub mod sandbox;
+pub mod scale_bar;
+pub mod schedule;
+pub mod settings;
+pub mod static_geometry;
+pub mod stats;
+pub mod symmetry;
+pub mod theme;
+pub mod tutorial;
+pub mod view;
 
 // #[cfg(target_family = "wasm")]
 // use macroquad::logging::info;
@@ -32,6 +82,40 @@ mod labyrinth;
 static mut OBJECT_REFLECTIVITY: f32 = 1.0;
 // static mut ESTIMATE_MILLIS: f32 = 1.0;
 static mut MAX_RAYS: f32 = 1000.0;
+/// Bounces one ray is followed for, see `SimConfig::max_bounces_per_ray`.
+static mut MAX_BOUNCES: f32 = 1000.0;
+static mut THICK_EDGES: bool = false;
+/// Thickness new edges are drawn at, see `Edge::thickness`.
+static mut EDGE_THICKNESS: f32 = DEFAULT_THICKNESS;
+static mut DOUBLE_PRECISION: bool = false;
+static mut SHOW_ANALYTICS: bool = false;
+static mut ANIMATE_PROPAGATION: bool = false;
+static mut PROPAGATION_SPEED: f32 = 1000.0;
+static mut LOOP_PROPAGATION: bool = true;
+static mut EXPOSURE: f32 = 2.0;
+static mut REFRACTIVE_INDEX: f32 = 1.5;
+/// In degrees.
+static mut MAX_GLOSS_ANGLE: f32 = 30.0;
+static mut GLOSS_SAMPLES: f32 = 1.0;
+static mut GLOSS_SEED: f32 = 0.0;
+/// The ray budget follows `TARGET_TRACE_MS` instead of `MAX_RAYS`, see `Laser::tune_ray_budget`.
+static mut AUTO_RAY_BUDGET: bool = false;
+static mut TARGET_TRACE_MS: f32 = 4.0;
+/// Traces running longer than this are cut short, see `Watchdog`.
+static mut TRACE_TIME_LIMIT_MS: f32 = 250.0;
+/// Screen pixels a dash or hatch spacing never shrinks below, however far out the camera is.
+const MIN_PATTERN_PIXELS: f32 = 6.0;
+/// Pixels the mouse may move between press and release for it to still count as a click.
+const CLICK_SLOP: f32 = 4.0;
+const DOUBLE_CLICK_SECONDS: f64 = 0.3;
+/// Screen pixels within which a dropped node welds onto another, or a placed one onto the symmetry axis.
+const MERGE_SNAP_RADIUS: f32 = 10.0;
+const SYMMETRY_AXIS_COLOR: Color = Color::new(0.4, 0.8, 1.0, 0.5);
+const LOCK_COLOR: Color = Color::new(1.0, 0.8, 0.2, 1.0);
+/// Edges shorter than this (in world units) are not traced.
+pub const MIN_EDGE_LENGTH: f32 = 1e-3;
+/// Opacity of the fill of a closed transparent loop, relative to its edges.
+const FILL_ALPHA: f32 = 0.3;
 static mut CAMERA_TARGET: Vec2 = vec2(0.0, 0.0);
 static mut ZOOM: f32 = 1.0;
 
@@ -42,20 +126,13 @@ fn mouse_position() -> (f32, f32) {
 }
 
 unsafe fn screen_to_world(mouse_pos: (f32, f32)) -> (f32, f32) {
-    let (sx, sy) = mouse_pos;
-    let screen_center = vec2(screen_width() / 2.0, screen_height() / 2.0);
-    (
-        (sx - screen_center.x) / ZOOM + CAMERA_TARGET.x,
-        (sy - screen_center.y) / ZOOM + CAMERA_TARGET.y,
-    )
+    let screen_size = vec2(screen_width(), screen_height());
+    tuple2vec(view::screen_to_world(vec2tuple(mouse_pos), screen_size, CAMERA_TARGET, ZOOM))
 }
 
-unsafe fn world_to_screen((x, y): (f32, f32)) -> (f32, f32) {
-
-    let screen_center = vec2(screen_width() / 2.0, screen_height() / 2.0);
-    let (x, y) = (x - CAMERA_TARGET.x, y - CAMERA_TARGET.y);
-    (x * ZOOM + screen_center.x, y * ZOOM + screen_center.y)
-
+unsafe fn world_to_screen(position: (f32, f32)) -> (f32, f32) {
+    let screen_size = vec2(screen_width(), screen_height());
+    tuple2vec(view::world_to_screen(vec2tuple(position), screen_size, CAMERA_TARGET, ZOOM))
 }
 
 #[derive(Clone, Default, Debug)]
@@ -67,16 +144,96 @@ pub struct Node {
     is_hovered: bool,
     is_dragged: bool,
     dragged_start_pos: Vec2,
+    animation: Option<Animation>,
+    /// Rest position `Animation::Oscillate` swings around.
+    anchor: Vec2,
+    /// Pinned nodes can't be dragged with the mouse, but can still be deleted and connected.
+    pinned: bool,
+    /// Name of the group the node is in, see `NodeNetwork::assign_group`.
+    group: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub enum EdgeState {
-    #[default]
-    Reflective,
-    Absorptive,
-    Transparent,
+/// Motion attached to a node, evaluated from the global time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Animation {
+    /// Circles `center` counterclockwise at `angular_speed` radians a second, starting at
+    /// angle `phase`.
+    Orbit { center: Vec2, radius: f32, angular_speed: f32, phase: f32 },
+    /// Swings `amplitude` either way along `axis` around the node's rest position, `speed`
+    /// radians of the sine a second.
+    Oscillate { axis: Vec2, amplitude: f32, speed: f32 },
+}
+
+impl Animation {
+    pub const LABELS: [&'static str; 3] = ["None", "Orbit", "Oscillate"];
+
+    /// An orbit of `radius` that passes through `position` at time 0.
+    pub fn orbit_through(position: Vec2, radius: f32, angular_speed: f32) -> Self {
+        Animation::Orbit { center: position - vec2(radius, 0.0), radius, angular_speed, phase: 0.0 }
+    }
+
+    /// Position at `time` seconds for a node resting at `anchor`.
+    pub fn position_at(self, anchor: Vec2, time: f64) -> Vec2 {
+        match self {
+            Animation::Orbit { center, radius, angular_speed, phase } =>
+                center + Vec2::from_angle(phase + (angular_speed as f64 * time) as f32) * radius,
+            Animation::Oscillate { axis, amplitude, speed } =>
+                anchor + axis.normalize_or_zero() * amplitude * (speed as f64 * time).sin() as f32,
+        }
+    }
+
+    /// Index into `LABELS`, with `None` as 0.
+    const fn kind(animation: Option<Self>) -> usize {
+        match animation {
+            None => 0,
+            Some(Animation::Orbit { .. }) => 1,
+            Some(Animation::Oscillate { .. }) => 2,
+        }
+    }
+}
+
+/// What a locked edge keeps while one of its ends is dragged; the other end is moved to match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockKind {
+    Length,
+    /// The absolute angle: the other end slides along the edge's line.
+    Angle,
+    /// Length and angle, the other end moves along rigidly.
+    Rigid,
 }
 
+impl LockKind {
+    /// The next lock the lock hotkey gives an edge locked with `lock`, ending unlocked.
+    pub const fn cycle(lock: Option<Self>) -> Option<Self> {
+        match lock {
+            None => Some(LockKind::Length),
+            Some(LockKind::Length) => Some(LockKind::Angle),
+            Some(LockKind::Angle) => Some(LockKind::Rigid),
+            Some(LockKind::Rigid) => None,
+        }
+    }
+}
+
+/// A `LockKind` with the edge's `b - a` as it was when locked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct EdgeLock {
+    kind: LockKind,
+    vector: Vec2,
+}
+
+impl EdgeLock {
+    /// Where the end at `other` goes when the end at `fixed` stays; `fixed_is_a` tells which end
+    /// of the edge `fixed` is.
+    fn solve(self, fixed: Vec2, other: Vec2, fixed_is_a: bool) -> Vec2 {
+        let reference = if fixed_is_a { self.vector } else { -self.vector };
+        let direction = reference.normalize();
+        match self.kind {
+            LockKind::Length => fixed + (other - fixed).try_normalize().unwrap_or(direction) * reference.length(),
+            LockKind::Angle => fixed + direction * (other - fixed).dot(direction).max(MIN_EDGE_LENGTH),
+            LockKind::Rigid => fixed + reference,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Edge {
@@ -86,24 +243,51 @@ pub struct Edge {
     thickness: f32,
     is_hovered: bool,
     state: EdgeState,
-}
-
-#[derive(PartialEq, Debug, Clone, Copy)]
-pub struct Segment(Vec2, Vec2, EdgeState);
-
-#[derive(PartialEq, Debug, Clone, Copy)]
-pub struct CollisionInfo {
-    pub position: Vec2,
-    pub normal: Vec2,
+    is_border: bool,
+    lock: Option<EdgeLock>,
+    /// 0 for a perfect mirror up to 1 for the widest scatter, see `trace_with_surfaces`.
+    roughness: f32,
+    /// Bitmask of the layers the edge is on, see `Surfaces::layers`.
+    layer: u8,
+    /// Hidden edges still collide, they are only drawn while hovered.
+    visible: bool,
+    /// State seen from the back, see `Surfaces::back_states`; `None` behaves as `state` from
+    /// both sides.
+    back_state: Option<EdgeState>,
+    /// Light the edge gives off itself, see `Surfaces::emitters`.
+    emitter: Option<EmitterConfig>,
+    /// Light the edge absorbs when reflecting, see `Surfaces::absorption`; `None` is lossless.
+    absorption: Option<AbsorptionCurve>,
+    /// Name of the group the edge is in, see `NodeNetwork::assign_group`.
+    group: Option<String>,
 }
 
 impl Edge {
+    /// Range the edge inspector and `NodeNetwork::set_edge_thickness` keep thickness in.
+    pub const MIN_THICKNESS: f32 = 0.5;
+    pub const MAX_THICKNESS: f32 = 20.0;
+
     pub const fn new(a: usize, b: usize) -> Self {
-        Self { a, b, color: WHITE, thickness: 5.0, is_hovered: false, state: EdgeState::Reflective }
+        Self::new_with_state(a, b, EdgeState::Reflective)
     }
 
     pub const fn new_with_state(a: usize, b: usize, state: EdgeState) -> Self {
-        Self { a, b, color: WHITE, thickness: 5.0, is_hovered: false, state }
+        Self { a, b, color: WHITE, thickness: DEFAULT_THICKNESS, is_hovered: false, state, is_border: false, lock: None,
+            roughness: 0.0, layer: DEFAULT_LAYER, visible: true, back_state: None, emitter: None, absorption: None,
+            group: None }
+    }
+
+    pub const fn endpoints(&self) -> (usize, usize) {
+        (self.a, self.b)
+    }
+
+    pub const fn state(&self) -> EdgeState {
+        self.state
+    }
+
+    /// Width the edge is drawn and hovered at, in world units, see `Surfaces::thickness`.
+    pub const fn thickness(&self) -> f32 {
+        self.thickness
     }
 
     pub fn set_state(&mut self, state: EdgeState) {
@@ -111,83 +295,588 @@ impl Edge {
         self.state = state
     }
 
+    pub const fn back_state(&self) -> Option<EdgeState> {
+        self.back_state
+    }
+
+    /// States of the front and back face, the front being the side `(b - a).perp()` points to.
+    pub fn faces(&self) -> (EdgeState, EdgeState) {
+        (self.state, self.back_state.unwrap_or(self.state))
+    }
+
+    pub const fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    pub const fn emitter(&self) -> Option<EmitterConfig> {
+        self.emitter
+    }
+
+    pub const fn absorption(&self) -> Option<AbsorptionCurve> {
+        self.absorption
+    }
+
+    pub const fn layer(&self) -> u8 {
+        self.layer
+    }
+
+    pub const fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn lock(&self) -> Option<LockKind> {
+        self.lock.map(|lock| lock.kind)
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// Part of the world border maintained by `NodeNetwork::add_bounds`.
+    pub const fn is_border(&self) -> bool {
+        self.is_border
+    }
+
     pub fn cycle_state(&mut self) {
-        self.set_state(match self.state {
-            EdgeState::Reflective => EdgeState::Absorptive,
-            EdgeState::Absorptive => EdgeState::Transparent,
-            EdgeState::Transparent => EdgeState::Reflective,
-        })
+        self.set_state(self.state.next())
     }
 
-    fn draw(&self, start: Vec2, end: Vec2,  edge_thickness: f32) {
-        draw_line(start.x, start.y, end.x, end.y, edge_thickness, self.color);
+    /// Draws the edge from `start` to `end` as seen at `zoom`, which keeps patterns visible.
+    fn draw(&self, start: Vec2, end: Vec2, zoom: f32) {
+        if !self.visible && !self.is_hovered { return; }
+        let edge_thickness = self.thickness;
+        let outline = theme::current().absorptive_outline;
+        if outline.a > 0.0 && (self.state == EdgeState::Absorptive || self.back_state == Some(EdgeState::Absorptive)) {
+            draw_line(start.x, start.y, end.x, end.y, edge_thickness + 2.0, outline);
+        }
+        match self.back_state.filter(|_| !self.is_hovered) {
+            // split lengthwise, each half on the side of its face
+            Some(back) => {
+                let offset = (end - start).perp().normalize_or_zero() * edge_thickness / 4.0;
+                let (front_start, front_end) = (start + offset, end + offset);
+                let (back_start, back_end) = (start - offset, end - offset);
+                draw_state_line(front_start, front_end, edge_thickness / 2.0, self.color, self.state, zoom);
+                draw_state_line(back_start, back_end, edge_thickness / 2.0, state_color(back), back, zoom);
+            }
+            None => draw_state_line(start, end, edge_thickness, self.color, self.state, zoom),
+        }
+        if let Some(EmitterConfig { color: [r, g, b], .. }) = self.emitter {
+            // a glowing strip along the emitting face
+            let offset = (end - start).perp().normalize_or_zero() * edge_thickness;
+            let (glow_start, glow_end) = (start + offset, end + offset);
+            draw_line(glow_start.x, glow_start.y, glow_end.x, glow_end.y, edge_thickness, Color::new(r, g, b, 0.8));
+        }
+        if self.lock.is_some() {
+            draw_padlock((start + end) / 2.0, edge_thickness.max(1.0) * 2.0 + 2.0);
+        }
     }
     pub(crate) fn update(&mut self, delta: f32) {
-        let target_color = if self.is_hovered { SKYBLUE } else {
-            match self.state {
-                EdgeState::Reflective => WHITE,
-                EdgeState::Absorptive => BLACK,
-                EdgeState::Transparent => Color::new(1.0, 1.0, 1.0, 0.5),
+        let target_color = if self.is_hovered { theme::current().hover } else { state_color(self.state) };
+        smooth_color_toward(&mut self.color, target_color, theme::current().color_half_life, delta);
+    }
+}
+
+/// A line of an edge face in `state`, with the pattern of the state when the theme asks for
+/// them: transparent dashed, absorptive hatched, reflective solid.
+fn draw_state_line(start: Vec2, end: Vec2, thickness: f32, color: Color, state: EdgeState, zoom: f32) {
+    let theme = theme::current();
+    let length = theme.dash_length.max(MIN_PATTERN_PIXELS / zoom);
+    match state {
+        EdgeState::Transparent if theme.edge_patterns => {
+            for (a, b) in pattern::dashes(start, end, length, length / 2.0) {
+                draw_line(a.x, a.y, b.x, b.y, thickness, color);
             }
-        };
-        lerp_color_in_place(&mut self.color, target_color, delta / 0.10);
+        }
+        EdgeState::Absorptive if theme.edge_patterns => {
+            draw_line(start.x, start.y, end.x, end.y, thickness, color);
+            // the strokes stick out past the line, so they show whatever the thickness
+            for (a, b) in pattern::hatches(start, end, length, thickness * 2.0 + 6.0 / zoom) {
+                draw_line(a.x, a.y, b.x, b.y, (thickness / 3.0).max(1.0 / zoom), color);
+            }
+        }
+        _ => draw_line(start.x, start.y, end.x, end.y, thickness, color),
     }
 }
 
+/// Thickness hand-drawn and imported edges start out with, a simulation setting.
+pub fn new_edge_thickness() -> f32 {
+    unsafe { EDGE_THICKNESS }
+}
+
+fn state_color(state: EdgeState) -> Color {
+    theme::current().state_color(state)
+}
+
 pub struct NodeNetwork {
     pub nodes: HashMap<usize, Node>,
     pub connections: Vec<Edge>,
-    texture: Texture2D,
+    texture: Option<Texture2D>,
     dragged_node: Option<usize>,
     selected_node: Option<usize>,
     key: usize,
+    bounds: Option<[usize; 4]>, // border corner nodes, clockwise from the top left
+    generation: u64,
+    middle_press: Option<Vec2>, // screen position, a middle click only deletes if it didn't pan
+    inspector: NodeInspector,
+    /// In chain mode every right click on empty space adds a node joined to the previous one.
+    chain_mode: bool,
+    /// Where the next chain link starts, `None` until the first node of a chain is placed.
+    chain_last: Option<usize>,
+    /// State of the edges chain mode builds.
+    pub chain_state: EdgeState,
+    /// New connections crossing existing edges are split at every crossing, see
+    /// `add_connection`.
+    pub split_crossings: bool,
+    /// Node a quick-connect drag started on, see `begin_quick_connect`.
+    quick_connect: Option<usize>,
+    /// Nodes picked with multi-select (Shift) clicks, moved together by dragging any of them.
+    selection: BTreeSet<usize>,
+    group_drag: Option<GroupDrag>,
+    /// Changes queued for an embedding app, see `set_observed`.
+    observer: EventQueue,
+    /// Where the nodes moved by the drag going on were before it, reported on the drop.
+    moved_from: BTreeMap<usize, Vec2>,
+    /// Last clicked edge and its ends, shown in the edge inspector until it is gone.
+    inspected_edge: Option<(usize, (usize, usize))>,
+    static_geometry: StaticGeometry,
+    last_edge_click: Option<EdgeClick>,
+    measure: Option<MeasureTool>,
+    paint: Option<EdgePaint>,
+    /// Camera zoom as of the last `update_camera`, screen pixels per world unit.
+    zoom: f32,
+    /// Whether `Action::Snap` was held as of the last `update`.
+    snapping: bool,
+    /// Smallest on-screen radius, in pixels, a node can be grabbed within at any zoom.
+    pub min_node_screen_radius: f32,
+    /// Hovered nodes grow to at most this on-screen radius, in pixels.
+    pub max_node_hover_screen_radius: f32,
+    /// Smallest on-screen width, in pixels, of the band an edge is hovered within.
+    pub min_edge_screen_width: f32,
+    journal: Journal,
+    /// Axis of the symmetric build mode, see `set_symmetry`.
+    symmetry: Option<SymmetryAxis>,
+    /// Each mirrored node to its twin and back.
+    twins: HashMap<usize, usize>,
+    /// Triangles filling the closed transparent loops, see `refresh_fills`.
+    fills: Vec<[Vec2; 3]>,
+    /// The loops `fills` covers, in order round each, with their share of it.
+    fill_loops: Vec<(Vec<usize>, Vec<[Vec2; 3]>)>,
+    fills_generation: Option<u64>,
+    /// Loops that couldn't be filled, so each is only warned about once.
+    unfillable: HashSet<Vec<usize>>,
+    /// Every named group, see `assign_group`.
+    groups: BTreeMap<String, Group>,
+    /// Group picked in the outliner, see `selected_structure`.
+    selected_group: Option<String>,
 }
 
+/// How a named group of nodes and edges shows up, e.g. an imported maze or a lens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    /// Hidden groups are neither drawn nor picked.
+    pub visible: bool,
+    /// Groups that don't collide are left out of the trace, see `traced_edges`.
+    pub collides: bool,
+}
 
+impl Default for Group {
+    fn default() -> Self {
+        Self { visible: true, collides: true }
+    }
+}
 
+/// What is under the cursor, see `NodeNetwork::pick`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickResult {
+    Node(usize),
+    /// Index into `connections`.
+    Edge(usize),
+}
 
+/// The first half of a potential double click on an edge.
+struct EdgeClick {
+    time: f64,
+    edge: usize,
+    /// Faces before the click cycled one, restored if the click turns out to be a double click.
+    state: EdgeState,
+    back_state: Option<EdgeState>,
+}
 
-#[derive(Clone, Copy, Debug)]
-pub struct Ray {
-    origin: Vec2,
-    direction: Vec2,
-    color: Color,
+/// An edge state key held down over edges, restating every edge the cursor passes over.
+struct EdgePaint {
+    action: Action,
+    state: EdgeState,
+    changed: bool,
+}
+
+/// Actions setting the hovered edge's state, in `EdgeState` order.
+const EDGE_STATE_ACTIONS: [(Action, EdgeState); 3] = [(Action::EdgeState1, EdgeState::Reflective),
+    (Action::EdgeState2, EdgeState::Absorptive), (Action::EdgeState3, EdgeState::Transparent)];
+
+/// Text being edited in the node inspector, reloaded whenever the selection changes.
+#[derive(Default)]
+struct NodeInspector {
+    node: Option<usize>,
+    x: String,
+    y: String,
+    invalid: bool,
+    /// The "place connected node" popup, kept open across selections until closed.
+    offset: Option<OffsetEntry>,
+}
+
+/// Offset being typed in for a node connected to the selected one, see `Offset::parse`.
+#[derive(Default)]
+struct OffsetEntry {
+    /// Index into `Offset::LABELS`.
+    kind: usize,
+    first: String,
+    second: String,
+    invalid: bool,
+}
+
+/// Points picked by the measure tool (key M), in world space. Holds at most two.
+#[derive(Default)]
+struct MeasureTool {
+    points: Vec<Vec2>,
 }
 
+/// A drag moving every selected node, see `NodeNetwork::begin_group_drag`.
+struct GroupDrag {
+    /// World position the drag started at.
+    grab: Vec2,
+    /// The moving nodes and where each was when the drag started.
+    origins: Vec<(usize, Vec2)>,
+}
+
+
+
+
 pub struct Laser {
     position: Vec2,
     direction: Vec2,
     ray: Ray,
     pub thickness: f32,
-    texture: Texture2D,
+    texture: Option<Texture2D>,
+    color: Color,
+    is_hovered: bool,
+    drag: Option<LaserDrag>,
+    follow_mouse: bool,
+    aim_locked: bool,
+    /// Node the laser keeps aiming at, see `follow_target`.
+    target_node: Option<usize>,
+    /// Node the laser sits on and moves with, see `follow_mount`.
+    mount: Option<usize>,
+    /// Neighbour of the mount node the laser points at, along the edge between them.
+    mount_align: Option<usize>,
+    pub color_mode: RayColorMode,
+    /// Multiplier on the beam color in the light buffer; above 1 it saturates towards white.
+    brightness: f32,
+    pub modulation: Modulation,
+    pub attenuation: Attenuation,
+    /// Draw the beam as quads fading out towards their sides, see `draw_rays_soft`.
+    pub soft_beam: bool,
+    /// Soft beams narrow as their intensity drops.
+    pub taper_beam: bool,
+    /// Draw the beam from meshes built once per trace, see `line_meshes`, rather than a
+    /// `draw_line` per line.
+    pub meshed_beam: bool,
+    /// Light coming back at the laser is absorbed by its housing, see `housing`.
+    pub occluder: bool,
+    /// Off, the laser adds no beam of its own and only emitting edges light the scene.
+    pub beam_on: bool,
+    /// Off, the beam is frozen as last traced and ignores changes to the scene.
+    pub collisions: bool,
+    /// Layers the beam collides with, see `Surfaces::layers`.
+    pub collision_mask: u8,
+    pub sweep: Sweep,
+    sweep_accumulation: SweepAccumulation,
+    pub light_area: LightArea,
+    /// How far ahead of `position` the beam starts, clear of the sprite.
+    muzzle_offset: f32,
+    /// Width and height of the sprite in world units.
+    sprite_size: f32,
+    /// Ray budget used instead of `MAX_RAYS` while the auto budget is on.
+    budget: BudgetController,
+    /// File the "Export trace CSV" button writes to, see `TraceResult::write_csv`.
+    trace_csv: String,
+    /// Ray-segment tests a trace gets through per microsecond, measured from past traces, to
+    /// turn the time limit into a `Watchdog::Tests` where there is no clock.
+    tests_per_micro: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LaserDrag {
+    /// Moving the whole laser; keeps the grab point under the cursor.
+    Move { offset: Vec2 },
+    Rotate,
 }
 
 impl Laser {
     pub const MAX_DISTANCE: f32 = 20_000.0;
-
+    pub const DEFAULT_SPRITE_SIZE: f32 = 80.0;
+    pub const DEFAULT_MUZZLE_OFFSET: f32 = 35.0;
+    const HANDLE_RADIUS: f32 = 8.0;
+    /// Guess at `tests_per_micro` until a trace has been timed.
+    const DEFAULT_TESTS_PER_MICRO: f32 = 100.0;
+    /// Traces quicker than this are too short to time on the web's frame clock.
+    const MIN_CALIBRATION_MICROS: f32 = 1000.0;
+    const ROTATION_SNAP_DEGREES: f32 = 5.0;
+    const TARGET_RING_RADIUS: f32 = 14.0;
+    /// Radius of an absorber glow at full intensity, on top of the beam thickness.
+    const GLOW_RADIUS: f32 = 12.0;
+
+    /// A laser with the embedded sprite.
     pub fn new(position: Vec2, direction: Vec2) -> Self {
+        Self::with_textures(position, direction, assets::embedded_laser())
+    }
+
+    /// A laser drawn with `texture`, e.g. from `assets::Textures::load`.
+    pub fn with_textures(position: Vec2, direction: Vec2, texture: Texture2D) -> Self {
+        Self { texture: Some(texture), ..Self::headless(position, direction) }
+    }
+
+    /// A laser without its sprite, for tests and tools that never draw.
+    pub fn headless(position: Vec2, direction: Vec2) -> Self {
         Self {
             position,
             direction,
-            ray: Ray { origin: position + direction * 35.0, direction, color: Color::new(1.0, 0., 0., 1.) },
+            ray: Ray { origin: position + direction * Self::DEFAULT_MUZZLE_OFFSET, direction,
+                       color: Color::new(1.0, 0., 0., 1.) },
             thickness: 5.0,
-            texture: Texture2D::from_file_with_format(
-                include_bytes!("../assets/laser.png"),
-                Some(ImageFormat::Png),
-            ),
+            texture: None,
+            color: WHITE,
+            is_hovered: false,
+            drag: None,
+            follow_mouse: false,
+            aim_locked: false,
+            target_node: None,
+            mount: None,
+            mount_align: None,
+            color_mode: RayColorMode::default(),
+            brightness: 1.0,
+            modulation: Modulation::Off,
+            attenuation: Attenuation::None,
+            soft_beam: false,
+            taper_beam: false,
+            meshed_beam: true,
+            occluder: true,
+            beam_on: true,
+            collisions: true,
+            collision_mask: u8::MAX,
+            sweep: Sweep::default(),
+            sweep_accumulation: SweepAccumulation::default(),
+            light_area: LightArea::Off,
+            muzzle_offset: Self::DEFAULT_MUZZLE_OFFSET,
+            sprite_size: Self::DEFAULT_SPRITE_SIZE,
+            budget: BudgetController::new(1000, 4000.0),
+            trace_csv: "trace.csv".to_string(),
+            tests_per_micro: Self::DEFAULT_TESTS_PER_MICRO,
+        }
+    }
+
+    pub const fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    /// Where the beam leaves the laser.
+    pub const fn muzzle(&self) -> Vec2 {
+        self.ray.origin
+    }
+
+    pub const fn muzzle_offset(&self) -> f32 {
+        self.muzzle_offset
+    }
+
+    pub fn set_muzzle_offset(&mut self, offset: f32) {
+        self.muzzle_offset = offset;
+        self.update_muzzle();
+    }
+
+    pub const fn sprite_size(&self) -> f32 {
+        self.sprite_size
+    }
+
+    pub fn set_sprite_size(&mut self, size: f32) {
+        self.sprite_size = size;
+    }
+
+    /// Moves the beam origin along after the position, direction or offset changed. Every way
+    /// of emitting goes through here, so no ray starts inside the housing.
+    fn update_muzzle(&mut self) {
+        self.ray.origin = self.position + self.ray.direction * self.muzzle_offset;
+    }
+
+    /// Origin and direction of the beam, for telling whether a trace is stale.
+    pub fn aim(&self) -> (Vec2, Vec2) {
+        (self.ray.origin, self.ray.direction)
+    }
+
+    pub fn toggle_follow_mouse(&mut self) {
+        self.follow_mouse = !self.follow_mouse;
+        self.aim_locked = false;
+        if self.follow_mouse { self.target_node = None; }
+    }
+
+    /// While follow mode is on, aims at the cursor every frame until a click locks the aim.
+    /// `suspended` pauses tracking, e.g. while the cursor is over a window or dragging a node.
+    pub fn follow_mouse(&mut self, suspended: bool) {
+        if !self.follow_mouse || self.aim_locked || suspended || self.drag.is_some() || self.target_node.is_some() {
+            return;
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.aim_locked = true;
+            return;
         }
+        self.look_at(vec2tuple(mouse_position()));
     }
 
-    pub fn ui(&mut self) {
+    pub const fn target(&self) -> Option<usize> {
+        self.target_node
+    }
+
+    /// Locks the aim onto `node`, or releases it with `None`. Locking stops following the mouse.
+    pub fn set_target(&mut self, node: Option<usize>) {
+        self.target_node = node;
+        if node.is_some() { self.follow_mouse = false; }
+    }
+
+    /// Aims at the target node, call every frame. A target that no longer exists is released.
+    pub fn follow_target(&mut self, network: &NodeNetwork) {
+        let Some(node) = self.target_node else { return; };
+        match network.node_position(node) {
+            Some(position) => self.look_at(position),
+            None => self.target_node = None,
+        }
+    }
+
+    pub const fn mount(&self) -> Option<usize> {
+        self.mount
+    }
+
+    pub const fn mount_align(&self) -> Option<usize> {
+        self.mount_align
+    }
+
+    /// Sits the laser on `node`, so it moves wherever the node is moved. The direction stays
+    /// the laser's own unless aligned, see `align_along`.
+    pub fn attach_to_node(&mut self, node: usize) {
+        if self.mount != Some(node) { self.mount_align = None; }
+        self.mount = Some(node);
+    }
+
+    pub fn detach(&mut self) {
+        (self.mount, self.mount_align) = (None, None);
+    }
+
+    /// Points the mounted laser along the edge from its node to `neighbour`, or lets it turn
+    /// freely again with `None`. Dropped when the two aren't connected.
+    pub fn align_along(&mut self, neighbour: Option<usize>) {
+        self.mount_align = neighbour.filter(|_| self.mount.is_some());
+    }
+
+    /// Moves the laser onto its mount node, and aims along its aligned edge, call every frame.
+    /// A mount node that no longer exists detaches the laser, an edge that doesn't the alignment.
+    pub fn follow_mount(&mut self, network: &NodeNetwork) {
+        let Some(node) = self.mount else { return; };
+        let Some(position) = network.node_position(node) else {
+            self.detach();
+            return;
+        };
+        self.position = position;
+        self.update_muzzle();
+        let Some(neighbour) = self.mount_align else { return; };
+        let connected = network.connections.iter()
+            .any(|edge| edge.endpoints() == (node, neighbour) || edge.endpoints() == (neighbour, node));
+        match network.node_position(neighbour).filter(|_| connected) {
+            Some(position) => self.look_at(position),
+            None => self.mount_align = None,
+        }
+    }
+
+    /// Ring around the target node, in world space.
+    pub fn draw_target(&self, network: &NodeNetwork) {
+        let Some(position) = self.target_node.and_then(|node| network.node_position(node)) else { return; };
+        draw_circle_lines(position.x, position.y, Self::TARGET_RING_RADIUS, 2.0, Color { a: 0.8, ..self.ray.color });
+    }
+
+    fn handle_position(&self) -> Vec2 {
+        self.position + self.ray.direction * self.sprite_size / 2.0
+    }
+
+    /// Drag to move, unless mounted on a node, drag the tip handle (or hold `RotateLaser`) to
+    /// rotate, `Snap` snaps the rotation.
+    /// Returns whether the laser has the mouse, so nodes underneath can leave it alone.
+    pub fn update(&mut self, delta: f32, input: &InputMap) -> bool {
+        let mouse_pos = vec2tuple(mouse_position());
+        let on_handle = mouse_pos.distance(self.handle_position()) <= Self::HANDLE_RADIUS * 1.5;
+        // a mounted laser is moved by dragging its node, which the body leaves the mouse to
+        let on_body = self.mount.is_none() && mouse_pos.distance(self.position) <= self.sprite_size / 2.0;
+        self.is_hovered = on_handle || on_body;
+        if self.is_hovered && self.drag.is_none() && is_mouse_button_pressed(MouseButton::Left) {
+            self.drag = Some(if on_handle || input.is_down(Action::RotateLaser) { LaserDrag::Rotate } else {
+                LaserDrag::Move { offset: self.position - mouse_pos }
+            });
+            // turning the laser by hand releases the target it was locked onto and its alignment
+            if matches!(self.drag, Some(LaserDrag::Rotate)) { (self.target_node, self.mount_align) = (None, None); }
+        }
+        if is_mouse_button_released(MouseButton::Left) { self.drag = None; }
+        match self.drag {
+            Some(LaserDrag::Move { offset }) => self.position = mouse_pos + offset,
+            Some(LaserDrag::Rotate) => {
+                self.look_at(mouse_pos);
+                if input.is_down(Action::Snap) {
+                    let step = Self::ROTATION_SNAP_DEGREES.to_radians();
+                    let angle = (self.ray.direction.y.atan2(self.ray.direction.x) / step).round() * step;
+                    self.direction = Vec2::from_angle(angle);
+                    self.ray.direction = self.direction;
+                }
+            }
+            None => {}
+        }
+        self.update_muzzle();
+        let target_color = if self.is_hovered || self.drag.is_some() { theme::current().hover } else {
+            Color { a: 1.0, ..self.ray.color }
+        };
+        smooth_color_toward(&mut self.color, target_color, theme::current().color_half_life, delta);
+        self.is_hovered || self.drag.is_some()
+    }
+
+    /// The laser window. `selected_node` is what "lock onto selected node" locks onto.
+    /// The laser window. `trace` is the latest trace, for exporting.
+    pub fn ui(&mut self, selected_node: Option<usize>, trace: &TraceResult) {
         let mut rotation = self.ray.direction.y.atan2(self.ray.direction.x).to_degrees();
         if rotation < 0.0 { rotation += 360.0; }
-        widgets::Window::new(hash!(), Vec2::new(0., 0.), Vec2::new(400., 100.))
+        widgets::Window::new(hash!(), Vec2::new(0., 0.), Vec2::new(400., 200.))
             .label("Laser")
-            .ui(&mut *root_ui(), |ui| {
-                ui.slider(hash!(), "pos x", 0.0f32..screen_width(), &mut self.position.x);
-                ui.slider(hash!(), "pos y", 0.0f32..screen_height(), &mut self.position.y);
+            .ui(&mut root_ui(), |ui| {
+                // world coordinates, the camera can go anywhere
+                ui.drag(hash!(), "pos x", None, &mut self.position.x);
+                ui.drag(hash!(), "pos y", None, &mut self.position.y);
                 ui.slider(hash!(), "rotation", 0.0f32..360.0f32, &mut rotation);
                 ui.slider(hash!(), "thickness", 0.01f32..10.0f32, &mut self.thickness);
+                ui.checkbox(hash!(), "soft beam", &mut self.soft_beam);
+                ui.checkbox(hash!(), "taper with intensity", &mut self.taper_beam);
+                ui.checkbox(hash!(), "meshed beam", &mut self.meshed_beam);
+                ui.slider(hash!(), "muzzle offset", 0.0f32..100.0, &mut self.muzzle_offset);
+                ui.checkbox(hash!(), "housing blocks light", &mut self.occluder);
+                ui.checkbox(hash!(), "beam on", &mut self.beam_on);
+                ui.checkbox(hash!(), "collisions (CapsLock)", &mut self.collisions);
+                let mut light_area = LightArea::ALL.iter().position(|area| *area == self.light_area).unwrap_or(0);
+                ui.combo_box(hash!(), "point light", &LightArea::ALL.map(LightArea::label), &mut light_area);
+                self.light_area = LightArea::ALL[light_area];
+                ui.checkbox(hash!(), "sweep", &mut self.sweep.enabled);
+                if self.sweep.enabled {
+                    let mut steps = self.sweep.steps as f32;
+                    ui.slider(hash!(), "sweep from", -180.0f32..180.0, &mut self.sweep.start);
+                    ui.slider(hash!(), "sweep to", -180.0f32..180.0, &mut self.sweep.end);
+                    ui.slider(hash!(), "sweep steps", 1.0f32..360.0, &mut steps);
+                    self.sweep.steps = steps.round() as usize;
+                    ui.checkbox(hash!(), "accumulate over frames", &mut self.sweep.accumulate);
+                    if self.sweep.accumulate && ui.button(None, "Clear accumulation") {
+                        self.clear_sweep_accumulation();
+                    }
+                }
+                ui.slider(hash!(), "sprite size", 10.0f32..200.0, &mut self.sprite_size);
                 // unsafe { ui.slider(hash!(), "OBJECT_REFLECTIVITY ", 0.00f32..1.0f32, &mut *addr_of_mut!(OBJECT_REFLECTIVITY)); }
 
                 // not allow in web
@@ -202,27 +891,150 @@ impl Laser {
                 //                   &mut *addr_of_mut!(ESTIMATE_MILLIS));
                 //     }
                 // }
-                unsafe {
-                    ui.slider(hash!(), "max rays", 1.0f32..100_000.0f32,
-                              &mut *addr_of_mut!(MAX_RAYS));
+                let was_auto = unsafe { AUTO_RAY_BUDGET };
+                unsafe { ui.checkbox(hash!(), "auto ray budget", &mut *addr_of_mut!(AUTO_RAY_BUDGET)); }
+                if unsafe { AUTO_RAY_BUDGET } {
+                    // start from the hand-tuned budget rather than wherever the last run left off
+                    if !was_auto { self.budget = BudgetController::new(unsafe { MAX_RAYS } as u32, 0.0); }
+                    ui.label(None, &format!("max rays {} (auto)", self.budget.budget()));
+                    unsafe {
+                        ui.slider(hash!(), "target trace (ms)", 0.5f32..30.0, &mut *addr_of_mut!(TARGET_TRACE_MS));
+                    }
+                } else {
+                    unsafe {
+                        ui.slider(hash!(), "max rays", 1.0f32..100_000.0f32,
+                                  &mut *addr_of_mut!(MAX_RAYS));
+                    }
                 }
                 unsafe { MAX_RAYS = MAX_RAYS.round(); }
+                unsafe {
+                    ui.slider(hash!(), "max bounces per ray", 1.0f32..1000.0, &mut *addr_of_mut!(MAX_BOUNCES));
+                    MAX_BOUNCES = MAX_BOUNCES.round();
+                }
+                unsafe {
+                    ui.slider(hash!(), "trace time limit (ms)", 10.0f32..2000.0,
+                              &mut *addr_of_mut!(TRACE_TIME_LIMIT_MS));
+                }
+                unsafe { ui.checkbox(hash!(), "thick walls collide", &mut *addr_of_mut!(THICK_EDGES)); }
+                unsafe {
+                    ui.slider(hash!(), "new edge thickness", Edge::MIN_THICKNESS..Edge::MAX_THICKNESS,
+                              &mut *addr_of_mut!(EDGE_THICKNESS));
+                }
+                unsafe { ui.checkbox(hash!(), "double precision", &mut *addr_of_mut!(DOUBLE_PRECISION)); }
+                unsafe { ui.slider(hash!(), "refractive index", 1.0f32..3.0, &mut *addr_of_mut!(REFRACTIVE_INDEX)); }
+                unsafe { ui.slider(hash!(), "max gloss angle", 0.0f32..90.0, &mut *addr_of_mut!(MAX_GLOSS_ANGLE)); }
+                unsafe { ui.slider(hash!(), "gloss samples", 1.0f32..4.0, &mut *addr_of_mut!(GLOSS_SAMPLES)); }
+                unsafe { ui.slider(hash!(), "gloss seed", 0.0f32..1000.0, &mut *addr_of_mut!(GLOSS_SEED)); }
+                unsafe {
+                    GLOSS_SAMPLES = GLOSS_SAMPLES.round();
+                    GLOSS_SEED = GLOSS_SEED.round();
+                }
+                unsafe { ui.slider(hash!(), "exposure", 0.1f32..10.0f32, &mut *addr_of_mut!(EXPOSURE)); }
+                ui.slider(hash!(), "beam red", 0.0f32..1.0, &mut self.ray.color.r);
+                ui.slider(hash!(), "beam green", 0.0f32..1.0, &mut self.ray.color.g);
+                ui.slider(hash!(), "beam blue", 0.0f32..1.0, &mut self.ray.color.b);
+                ui.slider(hash!(), "brightness", 0.1f32..8.0, &mut self.brightness);
+                let (mut hz, mut duty) = self.modulation.params();
+                let mut modulation = self.modulation.kind();
+                ui.combo_box(hash!(), "modulation", &Modulation::LABELS, &mut modulation);
+                ui.slider(hash!(), "frequency (Hz)", 0.1f32..10.0, &mut hz);
+                ui.slider(hash!(), "duty cycle", 0.0f32..1.0, &mut duty);
+                self.modulation = Modulation::from_parts(modulation, hz, duty);
+                let mut attenuation = self.attenuation.kind();
+                let mut coefficient = self.attenuation.coefficient();
+                ui.combo_box(hash!(), "attenuation", &Attenuation::LABELS, &mut attenuation);
+                match attenuation {
+                    1 => ui.slider(hash!(), "loss per unit", 0.0f32..0.01, &mut coefficient),
+                    2 => ui.slider(hash!(), "falloff scale", 10.0f32..5000.0, &mut coefficient),
+                    _ => {}
+                }
+                if attenuation != self.attenuation.kind() {
+                    coefficient = if attenuation == 1 { 0.001 } else { 500.0 };
+                }
+                self.attenuation = Attenuation::from_parts(attenuation, coefficient);
+                let mut color_mode = RayColorMode::ALL.iter().position(|mode| *mode == self.color_mode).unwrap_or(0);
+                ui.combo_box(hash!(), "ray color", &RayColorMode::ALL.map(RayColorMode::label), &mut color_mode);
+                self.color_mode = RayColorMode::ALL[color_mode];
+                let mut follow_mouse = self.follow_mouse;
+                ui.checkbox(hash!(), "follow mouse (F)", &mut follow_mouse);
+                if follow_mouse != self.follow_mouse { self.toggle_follow_mouse(); }
+                match self.target_node {
+                    Some(node) => {
+                        ui.label(None, &format!("locked onto node {}", node));
+                        if ui.button(None, "Release target") { self.target_node = None; }
+                    }
+                    None => {
+                        let lock = ui.button(None, "Lock onto selected node (T + click)");
+                        if let (true, Some(node)) = (lock, selected_node) { self.set_target(Some(node)); }
+                    }
+                }
+                match self.mount {
+                    Some(node) => {
+                        ui.label(None, &format!("mounted on node {}", node));
+                        if ui.button(None, "Detach from node") { self.detach(); }
+                        match self.mount_align {
+                            Some(neighbour) => {
+                                ui.label(None, &format!("aligned towards node {}", neighbour));
+                                if ui.button(None, "Turn freely") { self.mount_align = None; }
+                            }
+                            None => {
+                                let align = ui.button(None, "Align along edge to selected node");
+                                if let (true, Some(neighbour)) = (align, selected_node) {
+                                    self.align_along(Some(neighbour));
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        let mount = ui.button(None, "Mount on selected node");
+                        if let (true, Some(node)) = (mount, selected_node) { self.attach_to_node(node); }
+                    }
+                }
+                unsafe { ui.checkbox(hash!(), "path analytics", &mut *addr_of_mut!(SHOW_ANALYTICS)); }
+                unsafe { ui.checkbox(hash!(), "propagation animation", &mut *addr_of_mut!(ANIMATE_PROPAGATION)); }
+                unsafe {
+                    ui.slider(hash!(), "speed (units/s)", 10.0f32..5000.0f32,
+                              &mut *addr_of_mut!(PROPAGATION_SPEED));
+                }
+                unsafe { ui.checkbox(hash!(), "loop animation", &mut *addr_of_mut!(LOOP_PROPAGATION)); }
+                // no file system on the web
+                #[cfg(not(target_family = "wasm"))]
+                {
+                    ui.input_text(hash!(), "trace file", &mut self.trace_csv);
+                    if ui.button(None, "Export trace CSV") { self.export_trace_csv(trace); }
+                }
             });
         self.direction = Vec2::from_angle(rotation.to_radians());
-        self.ray.origin = self.position;
         self.ray.direction = self.direction;
+        self.update_muzzle();
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn export_trace_csv(&self, trace: &TraceResult) {
+        let path = self.trace_csv.trim();
+        let written = std::fs::File::create(path).map(std::io::BufWriter::new)
+            .and_then(|mut file| trace.write_csv(&mut file).and_then(|()| std::io::Write::flush(&mut file)));
+        match written {
+            Ok(()) => info!("Saved trace to {}", path),
+            Err(e) => error!("Could not save trace to {}: {}", path, e),
+        }
     }
 
 
     pub fn draw_laser_texture(&self) {
         let center = Vec2::new(self.position.x, self.position.y);
-        let size = 80.0; // in pixels
+        let size = self.sprite_size;
         let top_left = center - Vec2::new(size, size) / 2.0;
+        if self.is_hovered || self.drag.is_some() {
+            let handle = self.handle_position();
+            draw_circle(handle.x, handle.y, Self::HANDLE_RADIUS, self.color);
+        }
+        let Some(texture) = &self.texture else { return; };
         draw_texture_ex(
-            &self.texture,
+            texture,
             top_left.x,
             top_left.y,
-            WHITE,
+            self.color,
             DrawTextureParams {
                 dest_size: Some(Vec2::new(size, size)),
                 rotation: self.ray.direction.y.atan2(self.ray.direction.x),
@@ -237,7 +1049,7 @@ impl Laser {
     //     let mut prev_pos = self.position;
     //     let mut t = 0.0;
     //     let max_rays = unsafe { MAX_RAYS };
-    //     // draw_text(format!("{} {:?}", points.len(), points.iter().take(5).collect::<Vec<_>>()).as_str(), 20.0, 60.0, 30.0, DARKGRAY);
+    //     // draw_text(format!("{} {:?}", points.len(), points.iter().take(5).collect::<Vec<_>>()).as_str(), 20.0, 60.0, 30.0, theme::current().text);
     //     // debug!("{} {:?}", points.len(), points.iter().take(5).collect::<Vec<_>>());
     //     for pos in points.iter() {
     //         let color = hsl_to_rgb(t / max_rays, 1.0, 0.5);
@@ -250,8 +1062,9 @@ impl Laser {
     // }
     // 
     pub fn draw_rays_new(&mut self, other: &[Segment]) {
-        let lines = self.solve_collisions(other);
-        draw_text(format!("Rays: {}", lines.len()).as_str(), 20.0, 20.0, 30.0, DARKGRAY);
+        let result = self.solve_collisions(other);
+        let lines = result.lines();
+        draw_text(format!("Rays: {}", lines.len()).as_str(), 20.0, 20.0, 30.0, theme::current().text);
 
         // let ray = self.ray;
         // self.ray.origin += Vec2::Y * 2.0;
@@ -268,14 +1081,152 @@ impl Laser {
                       line.2);
         }
     }
-    pub fn draw_rays_explicit(&mut self, collisions: &[(Vec2, Vec2, Color)]) {
-        let lines = collisions;
-        draw_text(format!("Rays: {}", lines.len()).as_str(), 20.0, 20.0, 30.0, DARKGRAY);
+    /// Every line as a quad `thickness` wide in world space, full color along the middle and
+    /// fading to nothing at the sides. With `taper_beam` the width follows the remaining
+    /// intensity; it never gets thinner than `min_width`, e.g. one screen pixel.
+    pub fn draw_rays_soft(&self, collisions: &TraceResult, min_width: f32) {
+        draw_text(format!("Rays: {}", collisions.lines().len()).as_str(), 20.0, 20.0, 30.0, theme::current().text);
+        self.draw_soft_lines(collisions, min_width);
+    }
+    /// Just the soft beam, without the ray counter.
+    pub fn draw_soft_lines(&self, collisions: &TraceResult, min_width: f32) {
+        // well within macroquad's per-draw vertex and index limits
+        const SEGMENTS_PER_MESH: usize = 256;
+        let quads: Vec<([Vec2; 4], Color)> = collisions.hits.iter().zip(collisions.lines())
+            .filter_map(|(hit, &(start, end, color))| {
+                let intensity = if self.taper_beam { hit.color.a.clamp(0.0, 1.0) } else { 1.0 };
+                Some((beam_quad(start, end, (self.thickness * intensity).max(min_width))?, color))
+            })
+            .collect();
+        for chunk in quads.chunks(SEGMENTS_PER_MESH) {
+            let mut mesh = Mesh { vertices: Vec::with_capacity(chunk.len() * 6), indices: Vec::new(), texture: None };
+            for ([left_start, left_end, right_end, right_start], color) in chunk {
+                let base = mesh.vertices.len() as u16;
+                let clear = Color { a: 0.0, ..*color };
+                let middle = [(*left_start + *right_start) / 2.0, (*left_end + *right_end) / 2.0];
+                for (position, color) in [(*left_start, clear), (*left_end, clear), (middle[0], *color),
+                    (middle[1], *color), (*right_start, clear), (*right_end, clear)] {
+                    mesh.vertices.push(Vertex { position: vec3(position.x, position.y, 0.0), uv: Vec2::ZERO, color });
+                }
+                mesh.indices.extend([0, 1, 2, 1, 3, 2, 2, 3, 4, 3, 5, 4].map(|i| base + i));
+            }
+            draw_mesh(&mesh);
+        }
+    }
+    pub fn draw_rays_explicit(&self, collisions: &[(Vec2, Vec2, Color)]) {
+        draw_text(format!("Rays: {}", collisions.len()).as_str(), 20.0, 20.0, 30.0, theme::current().text);
+        self.draw_lines(collisions);
+    }
+    /// `draw_rays_explicit` with the lines already turned into `meshes` by `line_meshes`.
+    pub fn draw_rays_meshed(&self, lines: usize, meshes: &[Mesh]) {
+        draw_text(format!("Rays: {}", lines).as_str(), 20.0, 20.0, 30.0, theme::current().text);
+        for mesh in meshes {
+            draw_mesh(mesh);
+        }
+    }
+    /// The region lit by the laser as a point light, `polygon` from `core::visibility_polygon`,
+    /// in the beam color at `intensity`.
+    pub fn draw_light_area(&self, polygon: &[Vec2], intensity: f32) {
+        let color = Color { a: LIGHT_AREA_ALPHA * intensity, ..self.ray.color };
+        fan_meshes(self.position, polygon, color).iter().for_each(draw_mesh);
+    }
+    /// Just the beam, without the ray counter.
+    pub fn draw_lines(&self, lines: &[(Vec2, Vec2, Color)]) {
         for line in lines.iter() {
             draw_line(line.0.x, line.0.y, line.1.x, line.1.y, self.thickness,
                       line.2);
         }
     }
+    /// Multiplier for the `brightness` uniform of the light material.
+    pub const fn brightness(&self) -> f32 {
+        self.brightness
+    }
+    /// Scale applied to accumulated light before tone mapping.
+    pub fn exposure(&self) -> f32 {
+        unsafe { EXPOSURE }
+    }
+    /// Whether rays should be drawn with `draw_rays_animated` rather than all at once.
+    pub fn animates_propagation(&self) -> bool {
+        unsafe { ANIMATE_PROPAGATION }
+    }
+    /// Draws only as far as light moving at the set speed gets in `elapsed_time` seconds, cutting
+    /// the line the wavefront is on. When looping, starts over once every bounce was reached.
+    pub fn draw_rays_animated(&self, collisions: &TraceResult, elapsed_time: f32) {
+        let (speed, looping) = unsafe { (PROPAGATION_SPEED, LOOP_PROPAGATION) };
+        let mut front = speed * elapsed_time;
+        if looping {
+            // escaped lines run out to MAX_DISTANCE, waiting for them would stall the loop
+            let period = collisions.hits.iter()
+                .filter(|hit| hit.interaction != Interaction::Escaped)
+                .map(|hit| hit.path_length)
+                .fold(0.0, f32::max);
+            if period > 0.0 { front %= period; }
+        }
+        draw_text(format!("Rays: {}", collisions.hits.len()).as_str(), 20.0, 20.0, 30.0, theme::current().text);
+        for (hit, &(.., color)) in collisions.hits.iter().zip(collisions.lines()) {
+            let length = hit.origin.distance(hit.position);
+            let start = hit.path_length - length;
+            if start >= front { continue; }
+            let end = if hit.path_length <= front { hit.position } else {
+                hit.origin.lerp(hit.position, (front - start) / length)
+            };
+            draw_line(hit.origin.x, hit.origin.y, end.x, end.y, self.thickness, color);
+        }
+    }
+    /// Soft additive spots where the beam is absorbed, growing with the intensity arriving
+    /// there. Meant for the light material pass, like the rays.
+    pub fn draw_absorber_glow(&self, result: &TraceResult) {
+        const RINGS: usize = 4;
+        for (hit, color) in result.absorbed() {
+            let intensity = color.a.clamp(0.0, 1.0);
+            let radius = self.thickness + Self::GLOW_RADIUS * intensity;
+            // stacked discs, so the middle adds up brighter than the rim
+            for ring in (1..=RINGS).rev() {
+                let ring_color = Color { a: color.a / RINGS as f32, ..color };
+                draw_circle(hit.position.x, hit.position.y, radius * ring as f32 / RINGS as f32, ring_color);
+            }
+        }
+    }
+    /// Bounce numbers at each interaction point, in world space. Only with analytics enabled.
+    pub fn draw_bounce_numbers(&self, result: &TraceResult) {
+        if unsafe { !SHOW_ANALYTICS } { return; }
+        // past a few hundred labels it is an unreadable blob anyway
+        for hit in result.hits.iter().filter(|hit| hit.interaction != Interaction::Escaped).take(300) {
+            draw_text(&(hit.bounce + 1).to_string(), hit.position.x + 4.0, hit.position.y - 4.0, 16.0, WHITE);
+        }
+    }
+    /// Warning marks where branches were cut off by the ray budget, in world space.
+    pub fn draw_truncated_ends(&self, result: &TraceResult) {
+        for position in result.truncated_ends().take(300) {
+            draw_circle_lines(position.x, position.y, 8.0, 2.0, ORANGE);
+            draw_text("!", position.x - 3.0, position.y + 6.0, 20.0, ORANGE);
+        }
+    }
+    /// Banner under the ray counter when the last trace ran out of rays or time, in screen space.
+    pub fn draw_budget_warning(&self, result: &TraceResult) {
+        if result.stats.aborted {
+            draw_text("Trace aborted (scene too complex) - reduce max rays or simplify the geometry",
+                      20.0, 60.0, 24.0, ORANGE);
+            return;
+        }
+        if !result.stats.budget_exhausted { return; }
+        draw_text(&format!("Ray budget exhausted, {} branches cut - increase max rays", result.stats.budget),
+                  20.0, 60.0, 24.0, ORANGE);
+    }
+    /// Path summary under the ray counter, in screen space. Only with analytics enabled.
+    pub fn draw_analytics_summary(&self, result: &TraceResult) {
+        if unsafe { !SHOW_ANALYTICS } { return; }
+        let mut lines = vec![
+            format!("Optical path length: {:.0}", result.total_path_length()),
+            format!("Deepest bounce: {}", result.deepest_bounce().map_or(0, |bounce| bounce + 1)),
+        ];
+        for reason in Termination::ALL {
+            lines.push(format!("{:?}: {}", reason, result.terminations(reason)));
+        }
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, 20.0, 80.0 + i as f32 * 20.0, 24.0, theme::current().text);
+        }
+    }
     // 
     // 
     // pub fn draw2(&mut self, other: &Vec<Segment>, time: f64) {
@@ -337,93 +1288,223 @@ impl Laser {
     // 
     //     (collision_points, self.ray.direction)
     // }
-    pub fn solve_collisions(&self, segments: &[Segment]) -> Vec<(Vec2, Vec2, Color)> {
-        let ray = self.ray;
-        let mut ray_stack: VecDeque<(Ray, Option<Segment>)> = [(ray, None)].into();
-        let mut lines_stack: Vec<(Vec2, Vec2, Color)> = Vec::new();
-        while let Some((ray, segment)) = ray_stack.pop_front() {
-            // if ray.color.a <= f32::EPSILON { continue; }
-            if ray.color.a <= 0.1f32 { continue; }
-            debug_assert!(ray.direction.is_normalized(),
-                          "ray not normal: {}, normal is {:?}, len is {:}",
-                          ray.direction, ray.direction.normalize(), ray.direction.length());
-            if let Some((collision, segment)) = Self::find_closest_segment_new(ray, segments, segment.as_ref()) {
-                debug_assert!(collision.normal.is_normalized(),
-                              "not normal: {}, normal is {:?} {:?}",
-                              collision.normal, collision.normal.normalize(), collision);
-                match segment.2 {
-                    EdgeState::Reflective => {
-                        ray_stack.push_back((Ray {
-                            origin: collision.position,
-                            direction: reflect(ray.direction, collision.normal),
-                            color: ray.color, // TODO: use segment color
-                        }, Some(*segment)));
-                    }
-                    EdgeState::Transparent => {
-                        let is_critical = collision.normal.dot(ray.direction).abs().acos() == 0.8509;
-                        let fresnel = ray.direction.dot(collision.normal).powi(6) * 0.97;
-                        // debug!("{}", FresnelReflectAmount(1.0, 1.33, collision.normal, ray.direction));
-                        ray_stack.push_back((Ray {
-                            origin: collision.position,
-                            direction: reflect(ray.direction, collision.normal),
-                            color: {
-                                if is_critical {
-                                    ray.color
-                                } else { (ray.color.to_vec() * (1.0 - fresnel)).to_array().into() }
-                            }, // TODO: use segment color
-                        }, Some(*segment)));
-                        if !is_critical {
-                            // debug!("refract {:}, arcsin {}", refract, (1.0f32 / 1.33f32).asin());
-                            ray_stack.push_back((Ray {
-                                origin: collision.position,
-                                direction: ray.direction,
-                                color: (ray.color.to_vec() * fresnel).to_array().into(), // TODO: use segment color
-                            }, Some(*segment)));
-                        }
-                    }
-                    EdgeState::Absorptive => {}
-                }
-                lines_stack.push((ray.origin, collision.position, ray.color));
-            } else {
-                lines_stack.push((ray.origin, ray.origin + ray.direction * Self::MAX_DISTANCE, ray.color));
-            }
-            if lines_stack.len() >= unsafe { MAX_RAYS as usize } { break; }
+    pub fn solve_collisions(&self, segments: &[Segment]) -> TraceResult {
+        self.solve_collisions_with_media(segments, &[])
+    }
+
+    /// `solve_collisions` refracting through the closed shapes tagged in `media`, as returned by
+    /// `NodeNetwork::segment_media`.
+    pub fn solve_collisions_with_media(&self, segments: &[Segment], media: &[Option<usize>]) -> TraceResult {
+        self.solve_collisions_with_surfaces(segments, &Surfaces { media: media.to_vec(), ..Default::default() })
+    }
+
+    /// `solve_collisions_with_media` also scattering off rough edges, skipping edges on no
+    /// layer of `collision_mask` and lit by emitting edges, with `surfaces` as returned by
+    /// `NodeNetwork::surfaces`. With the beam off the emitting edges are the only light.
+    pub fn solve_collisions_with_surfaces(&self, segments: &[Segment], surfaces: &Surfaces) -> TraceResult {
+        let mut result = TraceResult::default();
+        self.solve_collisions_into(segments, surfaces, &mut TraceScratch::default(), &mut result);
+        result
+    }
+
+    /// `solve_collisions_with_surfaces` into `out`, reusing its buffers and those of `scratch`
+    /// instead of allocating new ones, for a caller tracing every frame.
+    pub fn solve_collisions_into(&self, segments: &[Segment], surfaces: &Surfaces, scratch: &mut TraceScratch,
+                                 out: &mut TraceResult) {
+        let stopwatch = hud::Stopwatch::start();
+        self.trace_turned_into(0.0, segments, surfaces, &self.sim_config(), scratch, out);
+        out.stats.trace_micros = stopwatch.micros();
+        self.modulate(out, 1.0);
+    }
+
+    /// `solve_collisions_with_surfaces` at every angle of `sweep`, the angles sharing the ray
+    /// budget and each one's lines colored by `Sweep::color`.
+    pub fn solve_collisions_sweep(&self, segments: &[Segment], surfaces: &Surfaces) -> TraceResult {
+        let stopwatch = hud::Stopwatch::start();
+        let mut result = self.trace_sweep(0..self.sweep.steps, segments, surfaces);
+        result.stats.trace_micros = stopwatch.micros();
+        self.modulate(&mut result, 1.0);
+        result
+    }
+
+    /// With an accumulating sweep, traces its next angle into the buffer kept between calls,
+    /// starting over whenever the network `generation`, the aim or the settings changed.
+    /// Returns whether the buffer changed; it is complete once it holds every angle.
+    pub fn accumulate_sweep(&mut self, generation: u64, segments: &[Segment], surfaces: &Surfaces) -> bool {
+        let key = self.trace_key(generation);
+        if self.sweep_accumulation.key != Some(key) {
+            self.sweep_accumulation = SweepAccumulation { key: Some(key), ..Default::default() };
         }
-        lines_stack
+        let step = self.sweep_accumulation.traced;
+        if step >= self.sweep.steps { return false; }
+        let stopwatch = hud::Stopwatch::start();
+        let traced = self.trace_sweep(step..step + 1, segments, surfaces);
+        let accumulation = &mut self.sweep_accumulation;
+        accumulation.result = TraceResult::combine([std::mem::take(&mut accumulation.result), traced]);
+        accumulation.result.stats.trace_micros = stopwatch.micros();
+        accumulation.traced += 1;
+        true
     }
 
-    fn find_closest_segment_new<'a>(
-        ray: Ray,
-        segments: &'a [Segment],
-        ray_origin_segment: Option<&'a Segment>,
-    ) -> Option<(CollisionInfo, &'a Segment)> {
-        let mut collision: CollisionInfo = CollisionInfo {
-            position: ray.origin + ray.direction * Self::MAX_DISTANCE,
-            normal: ray.direction,
-        };
-        let mut new_collision_segment: Option<&Segment> = None;
+    /// Whether the accumulated sweep holds every angle for the scene at `generation` as it is
+    /// now, so `accumulate_sweep` has nothing left to do.
+    pub fn sweep_complete(&self, generation: u64) -> bool {
+        self.sweep_accumulation.key == Some(self.trace_key(generation))
+            && self.sweep_accumulation.traced >= self.sweep.steps
+    }
 
-        for segment in segments.iter() {
-            if let Some(origin_segment) = ray_origin_segment {
-                if segment == origin_segment { continue; }
-            }
-            if let Some((col_position, col_normal)) = ray.collides_with((segment.0, segment.1)) {
-                if ray.origin.distance_squared(col_position) < ray.origin.distance_squared(collision.position) {
-                    new_collision_segment = Some(segment);
-                    collision = CollisionInfo { position: col_position, normal: col_normal };
-                }
+    /// What `accumulate_sweep` has traced so far.
+    pub fn accumulated_sweep(&self) -> &TraceResult {
+        &self.sweep_accumulation.result
+    }
+
+    /// Drops the accumulated sweep, so it is traced again from its first angle.
+    pub fn clear_sweep_accumulation(&mut self) {
+        self.sweep_accumulation = SweepAccumulation::default();
+    }
+
+    /// The angles `steps` of the sweep, the ray budget split evenly between all of its angles.
+    /// Emitting edges only shine in the first angle, so they don't add up over the sweep.
+    fn trace_sweep(&self, steps: std::ops::Range<usize>, segments: &[Segment], surfaces: &Surfaces) -> TraceResult {
+        let config = self.sim_config();
+        let config = SimConfig { max_rays: (config.max_rays / self.sweep.steps.max(1)).max(1), ..config };
+        let unlit = Surfaces { emitters: Vec::new(), ..surfaces.clone() };
+        TraceResult::combine(steps.map(|step| {
+            let surfaces = if step == 0 { surfaces } else { &unlit };
+            let mut result = self.trace_turned(self.sweep.angle(step).to_radians(), segments, surfaces, &config);
+            let color = self.sweep.color(step);
+            for hit in &mut result.hits { hit.color = Color { a: hit.color.a, ..color }; }
+            // combining builds the lines afresh from the recolored hits
+            result
+        }))
+    }
+
+    /// The trace with the laser, housing and all, turned `angle` radians off its aim.
+    fn trace_turned(&self, angle: f32, segments: &[Segment], surfaces: &Surfaces, config: &SimConfig) -> TraceResult {
+        let mut result = TraceResult::default();
+        self.trace_turned_into(angle, segments, surfaces, config, &mut TraceScratch::default(), &mut result);
+        result
+    }
+
+    fn trace_turned_into(&self, angle: f32, segments: &[Segment], surfaces: &Surfaces, config: &SimConfig,
+                         scratch: &mut TraceScratch, out: &mut TraceResult) {
+        let direction = Vec2::from_angle(angle).rotate(self.ray.direction);
+        let laser = self.beam_on.then_some(Ray { origin: self.position + direction * self.muzzle_offset, direction,
+                                                 ..self.ray });
+        match self.housing_facing(direction) {
+            Some(housing) => {
+                let mut with_housing = std::mem::take(&mut scratch.segments);
+                with_housing.clear();
+                with_housing.extend_from_slice(segments);
+                with_housing.extend(housing);
+                // the housing is on every layer, so no mask lets light through it
+                let mut layers = std::mem::take(&mut scratch.layers);
+                layers.clear();
+                layers.extend_from_slice(&surfaces.layers);
+                layers.resize(segments.len(), DEFAULT_LAYER);
+                layers.extend([u8::MAX; 3]);
+                trace_lights_on_layers(laser, &with_housing, surfaces, &layers, config, scratch, out);
+                out.forget_segments_from(segments.len());
+                (scratch.segments, scratch.layers) = (with_housing, layers);
             }
+            None => trace_lights_into(laser, segments, surfaces, config, scratch, out),
         }
+    }
 
-        if let Some(segment) = new_collision_segment {
-            Some((collision, segment))
-        } else { None }
+    /// With the auto budget on, adjusts the ray budget after a trace that took `last_micros`
+    /// towards one taking the target time.
+    pub fn tune_ray_budget(&mut self, last_micros: f32) {
+        if unsafe { !AUTO_RAY_BUDGET } { return; }
+        self.budget.target_micros = unsafe { TARGET_TRACE_MS } * 1000.0;
+        self.budget.update(last_micros);
     }
 
-    // fn find_closest_segment<'a>(
-    //     ray: Ray,
-    //     other: &'a [Segment],
-    //     ray_origin_segment: Option<&'a Segment>,
+    /// Measures how fast traces go from one that did `stats.segment_tests` tests in `micros`.
+    pub fn calibrate_watchdog(&mut self, stats: &TraceStats, micros: f32) {
+        if micros < Self::MIN_CALIBRATION_MICROS || stats.segment_tests == 0 { return; }
+        let rate = stats.segment_tests as f32 / micros;
+        self.tests_per_micro = self.tests_per_micro * 0.8 + rate * 0.2;
+    }
+
+    /// The time limit as a `Watchdog`: wall time on native, the calibrated count of tests on the
+    /// web.
+    fn watchdog(&self) -> Watchdog {
+        let micros = unsafe { TRACE_TIME_LIMIT_MS } * 1000.0;
+        #[cfg(not(target_family = "wasm"))]
+        return Watchdog::Micros(micros);
+        #[cfg(target_family = "wasm")]
+        return Watchdog::Tests((micros * self.tests_per_micro) as u64);
+    }
+
+    /// Settings `solve_collisions` traces with, taken from the global sliders.
+    fn sim_config(&self) -> SimConfig {
+        SimConfig {
+            max_rays: unsafe { if AUTO_RAY_BUDGET { self.budget.budget() as usize } else { MAX_RAYS as usize } },
+            max_bounces_per_ray: unsafe { MAX_BOUNCES as usize },
+            max_distance: Self::MAX_DISTANCE,
+            // edges collide at their own thickness, static geometry has none and takes the laser's
+            edge_radius: unsafe { THICK_EDGES }.then_some(self.thickness / 2.0),
+            refractive_index: unsafe { REFRACTIVE_INDEX },
+            precision: if unsafe { DOUBLE_PRECISION } { Precision::Double } else { Precision::Single },
+            seed: unsafe { GLOSS_SEED as u64 },
+            max_gloss_angle: unsafe { MAX_GLOSS_ANGLE.to_radians() },
+            gloss_samples: unsafe { GLOSS_SAMPLES as usize },
+            collision_mask: self.collision_mask,
+            attenuation: self.attenuation,
+            watchdog: self.watchdog(),
+            ..Default::default()
+        }
+    }
+
+    /// Identifies what `solve_collisions` would return against a network at `generation`.
+    pub fn trace_key(&self, generation: u64) -> TraceKey {
+        let key = TraceKey::new(self.ray, self.color_mode, generation, &self.sim_config())
+            .with((self.beam_on, self.sweep.key()));
+        match self.housing() {
+            Some(housing) => key.with(housing.map(|Segment(start, end, _)|
+                [start.x, start.y, end.x, end.y].map(f32::to_bits))),
+            None => key,
+        }
+    }
+
+    /// The absorptive box around the sprite that `solve_collisions` adds while `occluder` is
+    /// on: back and sides, open at the front so the beam leaves unhindered. Never part of the
+    /// network.
+    pub fn housing(&self) -> Option<[Segment; 3]> {
+        self.housing_facing(self.ray.direction)
+    }
+
+    fn housing_facing(&self, direction: Vec2) -> Option<[Segment; 3]> {
+        if !self.occluder { return None; }
+        let forward = direction.normalize_or_zero() * self.sprite_size / 2.0;
+        let side = forward.perp();
+        let (back, front) = (self.position - forward, self.position + forward);
+        Some([
+            Segment(back + side, back - side, EdgeState::Absorptive),
+            Segment(back + side, front + side, EdgeState::Absorptive),
+            Segment(back - side, front - side, EdgeState::Absorptive),
+        ])
+    }
+
+    /// Emission strength at `time` (from `get_time`) for the current modulation.
+    pub fn current_intensity(&self, time: f64) -> f32 {
+        self.modulation.intensity(time)
+    }
+
+    /// Recolors the lines of a trace for the beam emitted at `intensity`. Modulation only
+    /// changes brightness, so this is enough to follow it without retracing.
+    pub fn modulate(&self, result: &mut TraceResult, intensity: f32) {
+        let mode = self.color_mode;
+        result.recolor(|hit| {
+            let color = color_for(mode, hit.color, hit.bounce, hit.path_length, hit.color.a);
+            Color { a: color.a * intensity, ..color }
+        });
+    }
+
+    // fn find_closest_segment<'a>(
+    //     ray: Ray,
+    //     other: &'a [Segment],
+    //     ray_origin_segment: Option<&'a Segment>,
     // ) -> (Segment, Option<&'a Segment>) {
     //     let mut closest_point: Segment = Segment(
     //         ray.origin + ray.direction * Self::MAX_DISTANCE,
@@ -462,157 +1543,1655 @@ impl Laser {
     //     }
     //     None
     // }
+    /// Moves the laser to `position` and points it at `target`.
+    pub fn place(&mut self, position: Vec2, target: Vec2) {
+        self.position = position;
+        self.drag = None;
+        self.update_muzzle();
+        self.look_at(target);
+    }
     pub fn look_at(&mut self, position: Vec2) {
+        // looking at itself has no direction, keep the old one
+        let Some(direction) = (position - self.position).try_normalize() else { return; };
         self.direction = position - self.position;
-        self.ray.direction = self.direction.normalize_or_zero();
+        self.ray.direction = direction;
+        self.update_muzzle();
     }
-}
-
-impl Ray {
-    pub fn collides_with(&self, other: (Vec2, Vec2)) -> Option<(Vec2, Vec2)> {
-        let (start, end) = other;
-        let ray_dir = self.direction.normalize_or_zero();
-        let ray_dir_perp = ray_dir.perp();
-
-        let start_to_origin = self.origin - start;
-        let line_segment = end - start;
-        let denominator = line_segment.dot(ray_dir_perp);
-
-        if denominator.abs() < f32::EPSILON {
-            return None; // Lines are parallel, no collision
-        }
-
-        let t1 = line_segment.perp_dot(start_to_origin) / denominator;
-        let t2 = start_to_origin.dot(ray_dir_perp) / denominator;
-
-        if t1 >= 0.0 && t2 >= 0.0 && t2 <= 1.0 {
-            let collision = self.origin + ray_dir * t1;
-            let normal_to_collision: Vec2 = if (collision - start).length_squared() <= f32::EPSILON {
-                (collision - end).normalize().perp()
-            } else {
-                (collision - start).normalize().perp()
-            };
-            // draw_line(collision.x, collision.y, (collision.x + normal_to_collision.x * 100.0),
-            // (collision.y + normal_to_collision.y * 100.0), 5.0, WHITE);
-            Some((collision, normal_to_collision))
-        } else {
-            None
-        }
+    /// Moves the laser to `position` pointing along the unit vector `direction`, exactly, for
+    /// replaying a recorded pose.
+    pub fn set_pose(&mut self, position: Vec2, direction: Vec2) {
+        self.position = position;
+        self.direction = direction;
+        self.ray.direction = direction;
+        self.update_muzzle();
+        self.drag = None;
     }
 }
 
 impl NodeNetwork {
+    /// A network with the embedded node sprite.
     pub async fn new() -> Self {
-        let texture = Texture2D::from_file_with_format(
-            include_bytes!("../assets/node2.png"), Some(ImageFormat::Png));
-        // let texture = load_texture("E:\\CLion\\ray_cast\\assets\\node2.png").await.unwrap();
+        Self::with_texture(assets::embedded_node())
+    }
+    /// A network drawing its nodes with `texture`, e.g. from `assets::Textures::load`.
+    pub fn with_texture(texture: Texture2D) -> Self {
+        Self { texture: Some(texture), ..Self::headless() }
+    }
+    /// A network without the node sprite, for tests and tools that never draw.
+    pub fn headless() -> Self {
         Self {
             nodes: HashMap::new(),
             connections: Vec::new(),
-            texture,
+            texture: None,
             dragged_node: None,
             selected_node: None,
             key: 0,
+            bounds: None,
+            generation: 0,
+            middle_press: None,
+            inspector: NodeInspector::default(),
+            inspected_edge: None,
+            static_geometry: StaticGeometry::default(),
+            chain_mode: false,
+            chain_last: None,
+            chain_state: EdgeState::Reflective,
+            split_crossings: false,
+            quick_connect: None,
+            selection: BTreeSet::new(),
+            group_drag: None,
+            observer: EventQueue::default(),
+            moved_from: BTreeMap::new(),
+            last_edge_click: None,
+            measure: None,
+            paint: None,
+            zoom: 1.0,
+            snapping: false,
+            min_node_screen_radius: 6.0,
+            max_node_hover_screen_radius: 24.0,
+            min_edge_screen_width: 5.0,
+            journal: Journal::default(),
+            symmetry: None,
+            twins: HashMap::new(),
+            fills: Vec::new(),
+            fill_loops: Vec::new(),
+            fills_generation: None,
+            unfillable: HashSet::new(),
+            groups: BTreeMap::new(),
+            selected_group: None,
         }
     }
-    pub fn clean(&mut self) {
-        self.nodes.clear();
-        self.connections.clear();
+    /// Bumped whenever nodes or edges are added, removed, moved or change state, so anything
+    /// derived from the scene can tell it is stale.
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// Removes every node and edge, except the world border when `keep_bounds` is set.
+    pub fn clean(&mut self, keep_bounds: bool) {
+        self.journal.log(|| Event::Clean { keep_bounds });
+        self.generation += 1;
+        self.twins.clear();
         self.dragged_node = None;
         self.selected_node = None;
+        self.selected_group = None;
+        self.selection.clear();
+        self.group_drag = None;
+        self.groups.clear();
+        self.static_geometry.segments.clear();
+        let bounds = self.bounds.filter(|_| keep_bounds);
+        self.observer.retain_edges(&mut self.connections, |edge| bounds.is_some() && edge.is_border);
+        let mut removed: Vec<usize> = self.nodes.keys().copied()
+            .filter(|index| !bounds.is_some_and(|bounds| bounds.contains(index)))
+            .collect();
+        removed.sort_unstable();
+        for id in removed {
+            self.nodes.remove(&id);
+            self.observer.push(|| NetworkEvent::NodeRemoved { id });
+        }
+        if bounds.is_some() { return; }
         self.key = 0;
+        self.bounds = None;
+    }
+    /// Surrounds `rect` with four border edges in `state`. If the border already exists its
+    /// nodes are moved to the new rect instead of creating another one.
+    pub fn add_bounds(&mut self, rect: Rect, state: EdgeState) {
+        // called every frame to follow the settings, only actual changes are logged
+        let generation = self.generation;
+        self.journal.mute();
+        self.place_bounds(rect, state);
+        self.journal.unmute();
+        if self.generation != generation {
+            self.journal.log(|| Event::Bounds { position: rect.point(), size: rect.size(), state });
+        }
+    }
+    fn place_bounds(&mut self, rect: Rect, state: EdgeState) {
+        let corners = [rect.point(), vec2(rect.right(), rect.top()),
+            vec2(rect.right(), rect.bottom()), vec2(rect.left(), rect.bottom())];
+        if let Some(bounds) = self.bounds.filter(|bounds| bounds.iter().all(|i| self.nodes.contains_key(i))) {
+            for (index, corner) in bounds.iter().zip(corners) {
+                let node = self.nodes.get_mut(index).unwrap();
+                if node.position != corner {
+                    let (id, from) = (*index, node.position);
+                    node.position = corner;
+                    self.observer.push(|| NetworkEvent::NodeMoved { id, from, to: corner });
+                    self.generation += 1;
+                }
+            }
+            for (index, edge) in self.connections.iter_mut().enumerate()
+                .filter(|(_, edge)| edge.is_border && edge.state != state) {
+                self.observer.edge_restated(index, edge.state, state);
+                edge.set_state(state);
+                self.generation += 1;
+            }
+            return;
+        }
+        self.remove_bounds(); // whatever is left of a border the user took apart
+        let bounds = corners.map(|corner| self.add_node(corner));
+        for i in 0..bounds.len() {
+            self.generation += 1;
+            self.push_edge(Edge {
+                is_border: true,
+                ..Edge::new_with_state(bounds[i], bounds[(i + 1) % bounds.len()], state)
+            });
+        }
+        self.bounds = Some(bounds);
+    }
+    pub fn remove_bounds(&mut self) {
+        if let Some(bounds) = self.bounds.take() {
+            self.record_as(|| Event::RemoveBounds, |network| {
+                for index in bounds {
+                    network.remove_node(index);
+                }
+            });
+        }
+    }
+    /// Starts or stops logging edits for `take_events`, see `replay::Recorder`.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.journal.set_recording(recording);
+    }
+    pub const fn is_recording(&self) -> bool {
+        self.journal.is_recording()
+    }
+    /// Edits logged since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.journal.take()
+    }
+    /// Runs `edit`, logging it as `event` rather than as the smaller edits it is made of.
+    pub(crate) fn record_as<R>(&mut self, event: impl FnOnce() -> Event, edit: impl FnOnce(&mut Self) -> R) -> R {
+        self.journal.log(event);
+        self.journal.mute();
+        let result = edit(self);
+        self.journal.unmute();
+        result
     }
+    /// Queues a `NetworkEvent` for every change from now on, for an embedding app to drain with
+    /// `take_network_events`. Turning it off drops whatever is queued.
+    pub fn set_observed(&mut self, observed: bool) {
+        self.observer.set_observed(observed);
+        self.moved_from.clear();
+    }
+    /// Changes queued since the last call, oldest first. Empty unless observed.
+    pub fn take_network_events(&mut self) -> Vec<NetworkEvent> {
+        self.observer.take()
+    }
+    /// Notes that `node` moved away from `from`, to be reported by `report_moves`. The first
+    /// note since the last report counts, so a drag comes out as one move.
+    fn note_move(&mut self, node: usize, from: Vec2) {
+        if self.observer.is_observed() { self.moved_from.entry(node).or_insert(from); }
+    }
+    /// Reports the noted moves of nodes that are still there and not back where they were.
+    fn report_moves(&mut self) {
+        for (id, from) in std::mem::take(&mut self.moved_from) {
+            let Some(to) = self.node_position(id).filter(|&to| to != from) else { continue; };
+            self.observer.push(|| NetworkEvent::NodeMoved { id, from, to });
+        }
+    }
+    fn push_edge(&mut self, edge: Edge) {
+        self.insert_edge(self.connections.len(), edge);
+    }
+    fn insert_edge(&mut self, index: usize, edge: Edge) {
+        self.observer.edge_added(index, &edge);
+        self.connections.insert(index, edge);
+    }
+    /// Sets the state of edge `index`, reporting it; not logged.
+    fn restate_edge(&mut self, index: usize, state: EdgeState) {
+        let Some(edge) = self.connections.get_mut(index) else { return; };
+        self.observer.edge_restated(index, edge.state, state);
+        edge.set_state(state);
+    }
+    /// # Safety
+    /// Writes the global camera state used for mouse picking; call from the main thread only.
     pub unsafe fn update_camera(&mut self, camera_target: Vec2, zoom: f32) {
         CAMERA_TARGET = camera_target;
         ZOOM = zoom;
+        self.zoom = zoom;
     }
-    pub fn update(&mut self, _delta: f32) {
-        self.handle_mouse();
-        self.handle_selection();
+    pub fn update(&mut self, _delta: f32, input: &InputMap) {
+        self.snapping = input.is_down(Action::Snap);
+        if self.handle_measure(input) { return; }
+        if self.handle_quick_connect(input) { return; }
+        let time = get_time();
+        if self.dragged_node.is_some() || self.group_drag.is_some() { self.generation += 1; }
+        if self.handle_multi_select(input) { return; }
+        self.handle_mouse(input);
+        self.handle_selection(input);
         let mouse_pos = vec2tuple(mouse_position());
         if self.dragged_node.is_some() && is_mouse_button_released(MouseButton::Left) {
             if let Some(node_index) = self.dragged_node {
                 if let Some(node) = self.nodes.get_mut(&node_index) {
                     node.is_dragged = false;
+                    self.journal.log(|| Event::MoveNode { node: node_index, position: node.position });
+                    let from = node.dragged_start_pos;
+                    self.note_move(node_index, from);
+                }
+                if let Some((twin, position)) = self.twin_placement(node_index) {
+                    self.journal.log(|| Event::MoveNode { node: twin, position });
+                }
+                for moved in self.solve_locks(node_index) {
+                    self.journal.log(|| Event::MoveNode { node: moved, position: self.nodes[&moved].position });
+                }
+                self.report_moves();
+                // dropped onto another node: weld them together
+                if let Some(target) = self.node_position(node_index).and_then(|position| self.node_near(position, node_index)) {
+                    self.merge_nodes(target, node_index);
                 }
             }
             self.dragged_node = None;
         }
 
-        let mut is_some_hovered_node = false;
+        let max_hover_radius = self.max_node_hover_screen_radius / self.zoom;
+        for node in self.nodes.values_mut() {
+            node.update(_delta, time, max_hover_radius, self.snapping);
+        }
+        // the twin of a dragged node follows along; logged when it is dropped
+        if let Some((twin, position)) = self.dragged_node.and_then(|node| self.twin_placement(node)) {
+            if let Some(node) = self.nodes.get_mut(&twin) {
+                let from = node.position;
+                node.position = position;
+                node.anchor = position;
+                self.note_move(twin, from);
+            }
+        }
+        if let Some(node) = self.dragged_node { self.solve_locks(node); }
+        if input.is_pressed(Action::PinNode) {
+            if let Some(PickResult::Node(node)) = self.pick(vec2tuple(mouse_position())) {
+                self.set_pinned(node, !self.nodes[&node].pinned);
+            }
+        }
+        if input.is_pressed(Action::DetachTwin) {
+            let hovered = match self.pick(vec2tuple(mouse_position())) {
+                Some(PickResult::Node(node)) => Some(node),
+                _ => self.selected_node,
+            };
+            if let Some(node) = hovered { self.detach_twin(node); }
+        }
+        let picked = self.pick(mouse_pos);
+        let is_some_hovered_node = matches!(picked, Some(PickResult::Node(_)));
         for (i, node) in self.nodes.iter_mut() {
-            node.update(_delta);
-            node.is_hovered = node.contains(mouse_pos);
-            if node.is_hovered {
-                is_some_hovered_node = true;
-                if is_mouse_button_pressed(MouseButton::Left)
-                    && !node.is_dragged
-                    && self.dragged_node.is_none() {
-                    self.dragged_node = Some(*i);
-                    node.is_dragged = true;
-                }
+            node.is_hovered = picked == Some(PickResult::Node(*i));
+            if node.is_hovered
+                && is_mouse_button_pressed(MouseButton::Left)
+                && !node.is_dragged
+                && !node.pinned
+                && self.dragged_node.is_none() {
+                self.dragged_node = Some(*i);
+                node.is_dragged = true;
             }
         }
 
-        for edge in &mut self.connections {
+        let mut clicked_edge = None;
+        let mut locked_edge = None;
+        let mut dangling = false;
+        for (i, edge) in self.connections.iter_mut().enumerate() {
             edge.update(_delta);
-            let pos1 = self.nodes[&edge.a].position;
-            let pos2 = match self.nodes.get(&edge.b) {
-                Some(x) => x.position,
-                None => {
-                    error!("Edge b in ({:?}) does not exist", edge);
-                    error!("{:?}", self.nodes);
-                    Vec2::new(0.0, 0.0)
-                }
-            };
-            edge.is_hovered = Self::point_line_collision(mouse_pos, pos1, pos2, edge.thickness);
+            if !self.nodes.contains_key(&edge.a) || !self.nodes.contains_key(&edge.b) {
+                error!("Edge ({:?}) points at a missing node, removing it", edge);
+                dangling = true;
+                continue;
+            }
+            edge.is_hovered = picked == Some(PickResult::Edge(i));
 
-            if edge.is_hovered && !is_some_hovered_node &&
-                is_mouse_button_pressed(MouseButton::Left) {
-                edge.cycle_state();
+            if edge.is_hovered && input.is_pressed(Action::LockEdge) {
+                locked_edge = Some((i, LockKind::cycle(edge.lock())));
+            }
+            if edge.is_hovered && input.is_pressed(Action::CycleEdgeState) {
+                clicked_edge = Some((i, edge.state, edge.back_state));
+                let (start, end) = (self.nodes[&edge.a].position, self.nodes[&edge.b].position);
+                // two-sided edges cycle only the face on the cursor's side
+                match edge.back_state {
+                    Some(back) if (mouse_pos - start).dot((end - start).perp()) < 0.0 => {
+                        edge.back_state = Some(back.next());
+                        self.journal.log(|| Event::SetEdgeBackState { edge: i, state: edge.back_state });
+                    }
+                    _ => {
+                        let old = edge.state;
+                        edge.cycle_state();
+                        self.observer.edge_restated(i, old, edge.state);
+                        self.journal.log(|| Event::SetEdgeState { edge: i, state: edge.state });
+                    }
+                }
+                self.generation += 1;
+            }
+        }
+        if let Some((edge, state, back_state)) = clicked_edge {
+            self.inspected_edge = Some((edge, self.connections[edge].endpoints()));
+            self.mirror_edge_state(edge);
+            self.handle_edge_click(edge, (state, back_state), mouse_pos);
+        }
+        if let Some((edge, kind)) = locked_edge { self.lock_edge(edge, kind); }
+        if dangling { self.remove_dangling_edges(); }
+        self.handle_paint(is_some_hovered_node, input);
+    }
+    /// `EdgeState1`/`2`/`3` set the hovered edge to Reflective/Absorptive/Transparent; keeping
+    /// the key down while moving paints every edge passed over. The whole stroke is one change.
+    fn handle_paint(&mut self, is_some_hovered_node: bool, input: &InputMap) {
+        if self.paint.is_none() && !root_ui().is_mouse_over(other_mouse_position().into()) {
+            self.paint = EDGE_STATE_ACTIONS.iter()
+                .find(|(action, _)| input.is_pressed(*action))
+                .map(|&(action, state)| EdgePaint { action, state, changed: false });
+        }
+        let Some(paint) = &mut self.paint else { return; };
+        let mut painted = Vec::new();
+        if !is_some_hovered_node {
+            for (i, edge) in self.connections.iter_mut().enumerate()
+                .filter(|(_, edge)| edge.is_hovered && edge.state != paint.state) {
+                self.observer.edge_restated(i, edge.state, paint.state);
+                edge.set_state(paint.state);
+                self.journal.log(|| Event::SetEdgeState { edge: i, state: paint.state });
+                paint.changed = true;
+                painted.push(i);
             }
         }
+        for edge in painted { self.mirror_edge_state(edge); }
+        let Some(paint) = &mut self.paint else { return; };
+        if !input.is_down(paint.action) {
+            if paint.changed { self.generation += 1; }
+            self.paint = None;
+        }
+    }
+    /// Sets every edge except the world border to `state`.
+    pub fn set_all_states(&mut self, state: EdgeState) {
+        self.journal.log(|| Event::SetAllStates { state });
+        for (index, edge) in self.connections.iter_mut().enumerate().filter(|(_, edge)| !edge.is_border) {
+            self.observer.edge_restated(index, edge.state, state);
+            edge.set_state(state);
+        }
+        self.generation += 1;
+    }
+    /// A second click on the same edge soon after the first splits it under the cursor instead
+    /// of cycling its state again.
+    fn handle_edge_click(&mut self, edge: usize, (state, back_state): (EdgeState, Option<EdgeState>), mouse_pos: Vec2) {
+        let now = get_time();
+        let is_double = self.last_edge_click.as_ref().is_some_and(|click|
+            click.edge == edge && now - click.time <= DOUBLE_CLICK_SECONDS);
+        if !is_double {
+            self.last_edge_click = Some(EdgeClick { time: now, edge, state, back_state });
+            return;
+        }
+        let click = self.last_edge_click.take().unwrap();
+        self.set_edge_state(edge, click.state);
+        self.set_edge_back_state(edge, click.back_state);
+        let (start, end) = (self.nodes[&self.connections[edge].a].position, self.nodes[&self.connections[edge].b].position);
+        let t = ((mouse_pos - start).dot(end - start) / (end - start).length_squared()).clamp(0.0, 1.0);
+        self.split_edge(edge, start.lerp(end, t));
+    }
+    /// Replaces edge `edge_index` by two edges meeting at a new node at `position`, both keeping
+    /// the original's state and thickness. The halves take the original's place in
+    /// `connections`. Returns the new node.
+    pub fn split_edge(&mut self, edge_index: usize, position: Vec2) -> usize {
+        let twin = self.twin_edge(edge_index);
+        let node = self.split_single_edge(edge_index, position);
+        if let (Some(twin), Some(axis)) = (twin, self.symmetry) {
+            // the halves of the split edge shifted everything after it by one
+            let twin = if twin > edge_index { twin + 1 } else { twin };
+            let twin_node = self.split_single_edge(twin, axis.mirror(position));
+            self.link_twins(node, twin_node);
+        }
+        node
+    }
+    fn split_single_edge(&mut self, edge_index: usize, position: Vec2) -> usize {
+        let node = self.record_as(|| Event::SplitEdge { edge: edge_index, position },
+                                  |network| network.add_node(position));
+        self.split_edge_at(edge_index, node);
+        node
+    }
+    /// Rewires edge `edge_index` into two edges meeting at `node`, the second right after it.
+    fn split_edge_at(&mut self, edge_index: usize, node: usize) {
+        let edge = self.connections[edge_index].clone();
+        // a lock describes the whole edge, not its halves
+        self.connections[edge_index] = Edge { b: node, is_hovered: false, lock: None, ..edge.clone() };
+        self.observer.push(|| NetworkEvent::EdgeRemoved { index: edge_index });
+        self.observer.edge_added(edge_index, &self.connections[edge_index]);
+        self.insert_edge(edge_index + 1, Edge { a: node, is_hovered: false, lock: None, ..edge });
+        self.generation += 1;
+    }
+    /// Sets edge `edge_index` (into `connections`) to `state`.
+    pub fn set_edge_state(&mut self, edge_index: usize, state: EdgeState) {
+        if edge_index >= self.connections.len() { return; }
+        self.restate_edge(edge_index, state);
+        self.journal.log(|| Event::SetEdgeState { edge: edge_index, state });
+        self.generation += 1;
+        self.mirror_edge_state(edge_index);
+    }
+    /// Sets the state of the back face of edge `edge_index` (into `connections`), `None` making it
+    /// behave the same from both sides again.
+    pub fn set_edge_back_state(&mut self, edge_index: usize, state: Option<EdgeState>) {
+        let Some(edge) = self.connections.get_mut(edge_index) else { return; };
+        if edge.back_state == state { return; }
+        edge.back_state = state;
+        self.journal.log(|| Event::SetEdgeBackState { edge: edge_index, state });
+        self.generation += 1;
+        self.mirror_edge_state(edge_index);
+    }
+    /// Swaps the states of the front and back face of edge `edge_index` (into `connections`).
+    pub fn flip_edge_faces(&mut self, edge_index: usize) {
+        let Some(edge) = self.connections.get_mut(edge_index) else { return; };
+        let Some(back) = edge.back_state else { return; };
+        (edge.state, edge.back_state) = (back, Some(edge.state));
+        self.journal.log(|| Event::FlipEdgeFaces { edge: edge_index });
+        self.generation += 1;
+        self.mirror_edge_state(edge_index);
+    }
+    /// Sets how rough edge `edge_index` (into `connections`) reflects, clamped to 0..=1. Its
+    /// twin follows.
+    pub fn set_edge_roughness(&mut self, edge_index: usize, roughness: f32) {
+        let roughness = roughness.clamp(0.0, 1.0);
+        let twin = self.twin_edge(edge_index);
+        for edge in [Some(edge_index), twin].into_iter().flatten() {
+            let Some(target) = self.connections.get_mut(edge) else { continue; };
+            if target.roughness == roughness { continue; }
+            target.roughness = roughness;
+            self.journal.log(|| Event::SetEdgeRoughness { edge, roughness });
+            self.generation += 1;
+        }
+    }
+    /// Sets the thickness of edge `edge_index` (into `connections`), kept within
+    /// `Edge::MIN_THICKNESS` and `Edge::MAX_THICKNESS`. Its twin follows.
+    pub fn set_edge_thickness(&mut self, edge_index: usize, thickness: f32) {
+        let thickness = thickness.clamp(Edge::MIN_THICKNESS, Edge::MAX_THICKNESS);
+        let twin = self.twin_edge(edge_index);
+        for edge in [Some(edge_index), twin].into_iter().flatten() {
+            let Some(target) = self.connections.get_mut(edge) else { continue; };
+            if target.thickness == thickness { continue; }
+            target.thickness = thickness;
+            self.journal.log(|| Event::SetEdgeThickness { edge, thickness });
+            self.generation += 1;
+        }
+    }
+    /// Makes edge `edge_index` (into `connections`) give off light as `emitter` says, or stops
+    /// it with `None`. Its twin follows.
+    pub fn set_edge_emitter(&mut self, edge_index: usize, emitter: Option<EmitterConfig>) {
+        let twin = self.twin_edge(edge_index);
+        for edge in [Some(edge_index), twin].into_iter().flatten() {
+            let Some(target) = self.connections.get_mut(edge) else { continue; };
+            if target.emitter == emitter { continue; }
+            target.emitter = emitter;
+            self.journal.log(|| Event::SetEdgeEmitter { edge, emitter });
+            self.generation += 1;
+        }
+    }
+    /// Gives edge `edge_index` (into `connections`) the angle dependent absorption `curve` when
+    /// reflecting, or makes it lossless again with `None`. Its twin follows.
+    pub fn set_edge_absorption(&mut self, edge_index: usize, curve: Option<AbsorptionCurve>) {
+        let twin = self.twin_edge(edge_index);
+        for edge in [Some(edge_index), twin].into_iter().flatten() {
+            let Some(target) = self.connections.get_mut(edge) else { continue; };
+            if target.absorption == curve { continue; }
+            target.absorption = curve;
+            self.journal.log(|| Event::SetEdgeAbsorption { edge, curve });
+            self.generation += 1;
+        }
+    }
+    /// Puts edge `edge_index` (into `connections`) on the layers in the bitmask `layer` and shows
+    /// or hides it. Its twin follows.
+    pub fn set_edge_layer(&mut self, edge_index: usize, layer: u8, visible: bool) {
+        let twin = self.twin_edge(edge_index);
+        for edge in [Some(edge_index), twin].into_iter().flatten() {
+            let Some(target) = self.connections.get_mut(edge) else { continue; };
+            if (target.layer, target.visible) == (layer, visible) { continue; }
+            (target.layer, target.visible) = (layer, visible);
+            self.journal.log(|| Event::SetEdgeLayer { edge, layer, visible });
+            self.generation += 1;
+        }
+    }
+    /// Locks edge `edge_index` (into `connections`) to its current length, angle or both, or
+    /// unlocks it with `None`. Refused, returning false, for an edge too short to have an angle
+    /// or one whose ends are already joined by locked edges: a loop of locks can't be solved
+    /// one edge at a time.
+    pub fn lock_edge(&mut self, edge_index: usize, kind: Option<LockKind>) -> bool {
+        let Some(edge) = self.connections.get(edge_index) else { return false; };
+        let lock = match kind {
+            None => None,
+            Some(kind) => {
+                let (Some(a), Some(b)) = (self.node_position(edge.a), self.node_position(edge.b)) else { return false; };
+                if a.distance(b) < MIN_EDGE_LENGTH { return false; }
+                if self.locks_connect(edge.a, edge.b, edge_index) {
+                    debug!("Refusing to lock edge {}, it would close a loop of locked edges", edge_index);
+                    return false;
+                }
+                Some(EdgeLock { kind, vector: b - a })
+            }
+        };
+        self.journal.log(|| Event::LockEdge { edge: edge_index, kind });
+        self.connections[edge_index].lock = lock;
+        true
     }
+    /// Whether locked edges other than `except` join `a` to `b`.
+    fn locks_connect(&self, a: usize, b: usize, except: usize) -> bool {
+        let mut seen = HashSet::from([a]);
+        let mut stack = vec![a];
+        while let Some(node) = stack.pop() {
+            if node == b { return true; }
+            for (i, edge) in self.connections.iter().enumerate() {
+                if i == except || edge.lock.is_none() { continue; }
+                let next = if edge.a == node { edge.b } else if edge.b == node { edge.a } else { continue; };
+                if seen.insert(next) { stack.push(next); }
+            }
+        }
+        false
+    }
+    pub fn is_pinned(&self, node: usize) -> bool {
+        self.nodes.get(&node).is_some_and(|node| node.pinned)
+    }
+    /// Pins or unpins `node` and its twin.
+    pub fn set_pinned(&mut self, node: usize, pinned: bool) {
+        for node in [Some(node), self.twin(node)].into_iter().flatten() {
+            let Some(target) = self.nodes.get_mut(&node) else { continue; };
+            if target.pinned == pinned { continue; }
+            target.pinned = pinned;
+            self.journal.log(|| Event::PinNode { node, pinned });
+        }
+    }
+    /// Pins or unpins every node.
+    pub fn pin_all(&mut self, pinned: bool) {
+        self.journal.log(|| Event::PinAll { pinned });
+        for node in self.nodes.values_mut() {
+            node.pinned = pinned;
+        }
+    }
+    /// Moves `node` the way dragging it would: the far ends of its locked edges follow to keep
+    /// the locks, and the far ends of theirs in turn.
+    /// Pinned nodes stay put.
+    pub fn drag_node(&mut self, node: usize, position: Vec2) {
+        if self.is_pinned(node) { return; }
+        self.move_node(node, position);
+        for moved in self.solve_locks(node) {
+            self.journal.log(|| Event::MoveNode { node: moved, position: self.nodes[&moved].position });
+        }
+        self.report_moves();
+    }
+    /// Corrects the nodes held by locked edges to `from`, breadth first out from it, each node
+    /// once. Returns the moved nodes; not logged, a drag logs them when dropped.
+    fn solve_locks(&mut self, from: usize) -> Vec<usize> {
+        let mut placed = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        let mut moved = Vec::new();
+        while let Some(fixed) = queue.pop_front() {
+            let Some(fixed_position) = self.node_position(fixed) else { continue; };
+            let targets: Vec<(usize, Vec2)> = self.connections.iter()
+                .filter(|edge| edge.a == fixed || edge.b == fixed)
+                .filter_map(|edge| {
+                    let other = if edge.a == fixed { edge.b } else { edge.a };
+                    Some((other, edge.lock?.solve(fixed_position, self.node_position(other)?, edge.a == fixed)))
+                })
+                .collect();
+            for (other, position) in targets {
+                if !placed.insert(other) { continue; }
+                if let Some(node) = self.nodes.get_mut(&other) {
+                    let from = node.position;
+                    node.position = position;
+                    node.anchor = position;
+                    self.note_move(other, from);
+                }
+                moved.push(other);
+                queue.push_back(other);
+            }
+        }
+        if !moved.is_empty() { self.generation += 1; }
+        moved
+    }
+    /// Gives the twin of edge `edge_index`, if any, the same state.
+    fn mirror_edge_state(&mut self, edge_index: usize) {
+        let Some(twin) = self.twin_edge(edge_index) else { return; };
+        let edge = &self.connections[edge_index];
+        // a mirror image has its faces swapped, unless its ends are stored the other way round
+        // too, which swaps them back
+        let swapped = self.mirror_node(edge.a) == Some(self.connections[twin].a);
+        let (state, back_state) = match edge.back_state {
+            Some(back) if swapped => (back, Some(edge.state)),
+            back_state => (edge.state, back_state),
+        };
+        if self.connections[twin].state != state {
+            self.restate_edge(twin, state);
+            self.journal.log(|| Event::SetEdgeState { edge: twin, state });
+            self.generation += 1;
+        }
+        if self.connections[twin].back_state != back_state {
+            self.connections[twin].back_state = back_state;
+            self.journal.log(|| Event::SetEdgeBackState { edge: twin, state: back_state });
+            self.generation += 1;
+        }
+    }
+    /// Removes edge `edge_index` (into `connections`), keeping its nodes. Its twin goes too.
+    pub fn remove_edge(&mut self, edge_index: usize) {
+        if edge_index >= self.connections.len() { return; }
+        let twin = self.twin_edge(edge_index);
+        // the later index first, so the other one still points at the right edge
+        for edge in [Some(edge_index.max(twin.unwrap_or(0))), twin.map(|twin| twin.min(edge_index))].into_iter().flatten() {
+            self.journal.log(|| Event::RemoveEdge { edge });
+            self.connections.remove(edge);
+            self.observer.push(|| NetworkEvent::EdgeRemoved { index: edge });
+        }
+        self.generation += 1;
+    }
+    /// Collapses node `b` into node `a`: `b`'s edges are rewired to `a`, dropping any that would
+    /// become loops or duplicate an existing edge, then `b` is removed.
+    pub fn merge_nodes(&mut self, a: usize, b: usize) {
+        if a == b || !self.nodes.contains_key(&a) || !self.nodes.contains_key(&b) { return; }
+        self.journal.log(|| Event::MergeNodes { a, b });
+        // merging twins merges their twins too, rather than deleting them along with `b`
+        let twins = (self.detach_twin(a), self.detach_twin(b));
+        // a rewired edge is reported as removed and added back in its place
+        for (index, edge) in self.connections.iter_mut().enumerate().filter(|(_, edge)| edge.a == b || edge.b == b) {
+            if edge.a == b { edge.a = a; }
+            if edge.b == b { edge.b = a; }
+            self.observer.push(|| NetworkEvent::EdgeRemoved { index });
+            self.observer.edge_added(index, edge);
+        }
+        let mut seen = HashSet::new();
+        self.observer.retain_edges(&mut self.connections,
+                                   |edge| edge.a != edge.b && seen.insert((edge.a.min(edge.b), edge.a.max(edge.b))));
+        self.journal.mute();
+        self.remove_node(b);
+        self.journal.unmute();
+        if self.selected_node == Some(b) { self.selected_node = None; }
+        if self.dragged_node == Some(b) { self.dragged_node = None; }
+        self.generation += 1;
+        match twins {
+            (Some(twin_a), Some(twin_b)) if twin_a != b && twin_b != a => {
+                self.merge_nodes(twin_a, twin_b);
+                self.link_twins(a, twin_a);
+            }
+            (Some(twin_a), _) if twin_a != b => self.link_twins(a, twin_a),
+            _ => {}
+        }
+    }
+    /// Closest other node within the merge snap radius of `position`.
+    fn node_near(&self, position: Vec2, exclude: usize) -> Option<usize> {
+        let radius = MERGE_SNAP_RADIUS / self.zoom;
+        self.nodes.iter()
+            .filter(|(i, node)| **i != exclude && node.position.distance(position) <= radius)
+            .min_by(|(_, a), (_, b)| a.position.distance(position).total_cmp(&b.position.distance(position)))
+            .map(|(i, _)| *i)
+    }
+    pub const fn is_dragging(&self) -> bool {
+        self.dragged_node.is_some()
+    }
+    /// Counts and problems for the scene panel, see `NetworkStats`.
+    pub fn stats(&self) -> NetworkStats {
+        let count = |state| self.connections.iter().filter(|edge| edge.state == state).count();
+        let dangling_edges: Vec<(usize, usize)> = self.connections.iter().enumerate()
+            .filter_map(|(i, edge)| [edge.a, edge.b].into_iter()
+                .find(|node| !self.nodes.contains_key(node))
+                .map(|node| (i, node)))
+            .collect();
+        let zero_length_edges = self.connections.iter()
+            .filter_map(|edge| Some(self.nodes.get(&edge.a)?.position.distance(self.nodes.get(&edge.b)?.position)))
+            .filter(|length| *length < MIN_EDGE_LENGTH)
+            .count();
+        let segments = self.get_all_connections();
+        NetworkStats {
+            nodes: self.nodes.len(),
+            edges: self.connections.len(),
+            reflective: count(EdgeState::Reflective),
+            absorptive: count(EdgeState::Absorptive),
+            transparent: count(EdgeState::Transparent),
+            traced_segments: segments.len(),
+            merged_segments: merge_collinear_segments(&segments).len(),
+            components: self.components().len(),
+            static_segments: self.static_geometry.segments.len(),
+            zero_length_edges,
+            duplicate_edges: self.connections.len() - dangling_edges.len() - zero_length_edges - segments.len(),
+            dangling_edges,
+        }
+    }
+    /// `get_all_connections` followed by the static geometry, everything the beam collides with.
+    /// The first `get_all_connections().len()` are the edges, in the same order.
+    pub fn traced_segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        self.traced_segments_into(&mut segments);
+        segments
+    }
+    /// `traced_segments` into `out`, replacing what it held but keeping its capacity.
+    pub fn traced_segments_into(&self, out: &mut Vec<Segment>) {
+        self.get_all_connections_into(out);
+        out.extend_from_slice(&self.static_geometry.segments);
+    }
+    /// `get_all_connections` into `out`, replacing what it held but keeping its capacity.
+    pub fn get_all_connections_into(&self, out: &mut Vec<Segment>) {
+        out.clear();
+        out.extend(self.traced_edges().into_iter().map(|i| {
+            let edge = &self.connections[i];
+            Segment(self.nodes[&edge.a].position, self.nodes[&edge.b].position, edge.state)
+        }));
+    }
+    /// Segments of every traced edge, see `traced_edges`.
     pub fn get_all_connections(&self) -> Vec<Segment> {
-        let mut connections = Vec::with_capacity(self.connections.len());
+        let mut segments = Vec::new();
+        self.get_all_connections_into(&mut segments);
+        segments
+    }
+    /// Indices into `connections` of the edges the beam collides with. Edges to missing nodes,
+    /// shorter than `MIN_EDGE_LENGTH` or exactly duplicating an earlier edge are left out, as
+    /// they would only produce NaN normals or double hits, and so are edges of groups that
+    /// don't collide. Parallel to `get_all_connections`, so a segment index maps back to its
+    /// edge as long as the edges haven't changed since.
+    pub fn traced_edges(&self) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        self.connections.iter().enumerate()
+            .filter(|(_, edge)| self.group_flags(edge.group()).collides)
+            .filter_map(|(i, edge)| Some((i, self.nodes.get(&edge.a)?.position, self.nodes.get(&edge.b)?.position, edge.state)))
+            .filter(|(_, a, b, _)| a.distance(*b) >= MIN_EDGE_LENGTH)
+            .filter(|(_, a, b, state)| {
+                let [a, b] = [a, b].map(|position| (position.x.to_bits(), position.y.to_bits()));
+                seen.insert((a.min(b), a.max(b), *state))
+            })
+            .map(|(i, ..)| i)
+            .collect()
+    }
+    /// Drops edges whose nodes no longer exist. Returns how many were removed.
+    pub fn remove_dangling_edges(&mut self) -> usize {
+        let before = self.connections.len();
+        self.observer.retain_edges(&mut self.connections,
+                                   |edge| self.nodes.contains_key(&edge.a) && self.nodes.contains_key(&edge.b));
+        let removed = before - self.connections.len();
+        if removed > 0 { self.generation += 1; }
+        removed
+    }
+    /// Draws the scene, the static geometry and the chain preview `edge_thickness` wide and the
+    /// edges each at its own thickness.
+    pub fn draw(&self, edge_thickness: f32) {
+        if let Some(axis) = self.symmetry {
+            let (start, end) = (axis.point() - axis.direction() * Laser::MAX_DISTANCE,
+                                axis.point() + axis.direction() * Laser::MAX_DISTANCE);
+            draw_line(start.x, start.y, end.x, end.y, 1.0 / self.zoom, SYMMETRY_AXIS_COLOR);
+        }
+        self.static_geometry.draw(edge_thickness);
+        self.draw_fills();
+        for edge in self.connections.iter().filter(|edge| self.group_flags(edge.group()).visible) {
+            edge.draw(self.nodes[&edge.a].position, self.nodes[&edge.b].position, self.zoom);
+        }
+        self.draw_selected_group();
+        self.draw_multi_selection();
+        if let Some(start) = self.chain_last.filter(|_| self.chain_mode).and_then(|node| self.node_position(node)) {
+            let end = vec2tuple(mouse_position());
+            draw_line(start.x, start.y, end.x, end.y, edge_thickness / 2.0, Color::new(1.0, 1.0, 1.0, 0.4));
+        }
+        if let Some(texture) = &self.texture {
+            for node in self.nodes.values().filter(|node| self.group_flags(node.group.as_deref()).visible) {
+                node.draw(texture);
+            }
+        }
+    }
+    /// Bounding box of every node, grown by `margin`.
+    pub fn bounding_rect(&self, margin: f32) -> Option<Rect> {
+        view::bounding_rect(self.nodes.values().map(|node| node.position).chain(self.static_geometry.points()), margin)
+    }
+    /// Smallest and largest corner of the nodes' bounding box, `None` without nodes.
+    pub fn bounding_box(&self) -> Option<(Vec2, Vec2)> {
+        self.bounding_rect(0.0).map(|rect| (rect.point(), rect.point() + rect.size()))
+    }
+    /// Indices into `connections` of the edges touching `node`.
+    pub fn edges_of(&self, node: usize) -> Vec<usize> {
+        self.connections.iter().enumerate()
+            .filter(|(_, edge)| edge.a == node || edge.b == node)
+            .map(|(i, _)| i)
+            .collect()
+    }
+    pub fn degree(&self, node: usize) -> usize {
+        self.connections.iter().filter(|edge| edge.a == node || edge.b == node).count()
+    }
+    /// Node ids grouped by connectivity, each group sorted, groups ordered by their smallest id.
+    /// A node without edges is a component of its own.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        self.components_with(&self.adjacency())
+    }
+    /// Every node's neighbours, one per edge; nodes without edges are left out.
+    fn adjacency(&self) -> HashMap<usize, Vec<usize>> {
+        let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
         for edge in &self.connections {
-            connections.push(Segment(self.nodes[&edge.a].position,
-                                     self.nodes[&edge.b].position,
-                                     edge.state));
+            neighbours.entry(edge.a).or_default().push(edge.b);
+            neighbours.entry(edge.b).or_default().push(edge.a);
         }
-        connections
+        neighbours
     }
-    pub fn draw(&self, edge_thickness: f32) {
+    /// `components` from an `adjacency` built already.
+    fn components_with(&self, neighbours: &HashMap<usize, Vec<usize>>) -> Vec<Vec<usize>> {
+        let mut ids: Vec<usize> = self.nodes.keys().copied().collect();
+        ids.sort_unstable();
+        let mut seen = HashSet::new();
+        let mut components = Vec::new();
+        for id in ids {
+            if !seen.insert(id) { continue; }
+            let mut component = vec![id];
+            let mut stack = vec![id];
+            while let Some(node) = stack.pop() {
+                for &next in neighbours.get(&node).into_iter().flatten() {
+                    if seen.insert(next) {
+                        component.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components
+    }
+    /// The component the selected node belongs to, or without a selected node the selected
+    /// group's nodes. Empty without either.
+    pub fn selected_structure(&self) -> Vec<usize> {
+        let Some(selected) = self.selected_node else {
+            return self.selected_group().map(|group| self.group_members(group)).unwrap_or_default();
+        };
+        self.components().into_iter()
+            .find(|component| component.binary_search(&selected).is_ok())
+            .unwrap_or_default()
+    }
+    /// Whether `component` is exactly one closed polygon: at least three nodes, each with two
+    /// edges, all within the component, forming a single cycle.
+    pub fn is_closed_loop(&self, component: &[usize]) -> bool {
+        if component.len() < 3 { return false; }
+        let members: HashSet<usize> = component.iter().copied().collect();
+        let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
         for edge in &self.connections {
-            edge.draw(self.nodes[&edge.a].position, self.nodes[&edge.b].position, edge_thickness);
+            let (inside_a, inside_b) = (members.contains(&edge.a), members.contains(&edge.b));
+            if inside_a != inside_b { return false; }
+            if inside_a {
+                neighbours.entry(edge.a).or_default().push(edge.b);
+                neighbours.entry(edge.b).or_default().push(edge.a);
+            }
         }
-        for (_, node) in &self.nodes {
-            node.draw(&self.texture);
+        if members.len() != component.len()
+            || members.iter().any(|node| neighbours.get(node).is_none_or(|next| next.len() != 2)) {
+            return false;
         }
+        // every node has degree two, so walking one way round visits the whole cycle it is on
+        let start = component[0];
+        let (mut previous, mut current, mut steps) = (start, neighbours[&start][0], 1);
+        while current != start {
+            let next = &neighbours[&current];
+            let following = if next[0] == previous { next[1] } else { next[0] };
+            (previous, current) = (current, following);
+            steps += 1;
+        }
+        steps == component.len()
     }
-    fn handle_selection(&mut self) {
-        if self.selected_node.is_none() { return; }
-        let mp = vec2tuple(other_mouse_position());
-        let node = &self.nodes[&self.selected_node.unwrap()];
-        let mut new_mp = node.position;
-        new_mp = Self::ctrl_shift(mp, node, &mut new_mp);
-        let (node_x, node_y) = unsafe { world_to_screen((node.position.x, node.position.y)) };
-        draw_line(new_mp.x, new_mp.y, node_x, node_y, 5.0, WHITE);
+    /// For each edge, in `get_all_connections` order, the medium it bounds: closed loops made
+    /// only of transparent edges are numbered as media, everything else is `None`.
+    pub fn segment_media(&self) -> Vec<Option<usize>> {
+        let medium_of = self.media();
+        self.traced_edges().into_iter()
+            .map(|i| medium_of.get(&self.connections[i].a).copied())
+            .collect()
     }
-    fn handle_mouse(&mut self) {
-        if is_mouse_button_pressed(MouseButton::Right) && self.dragged_node.is_none() {
-            let mouse_pos = vec2tuple(mouse_position());
-            let mut selected_index = None;
-
-            // Check if any node is clicked
-            for (i, node) in self.nodes.iter() {
-                if node.contains(mouse_pos) {
-                    selected_index = Some(*i);
-                    break;
+    /// The medium each node of a closed transparent loop bounds, see `segment_media`.
+    fn media(&self) -> HashMap<usize, usize> {
+        let mut medium_of: HashMap<usize, usize> = HashMap::new();
+        for (medium, component) in self.components().into_iter()
+            .filter(|component| self.is_closed_loop(component))
+            .filter(|component| self.connections.iter()
+                .filter(|edge| component.binary_search(&edge.a).is_ok())
+                .all(|edge| edge.state == EdgeState::Transparent))
+            .enumerate() {
+            medium_of.extend(component.into_iter().map(|node| (node, medium)));
+        }
+        medium_of
+    }
+    /// Components that are closed loops, see `is_closed_loop`, as their nodes in order round the
+    /// loop, each starting from its smallest id.
+    pub fn closed_loops(&self) -> Vec<Vec<usize>> {
+        let neighbours = self.adjacency();
+        self.components_with(&neighbours).into_iter()
+            .filter_map(|component| Self::loop_order(&component, &neighbours))
+            .collect()
+    }
+    /// `component`'s nodes in order round it, starting from its first, if it is a closed loop.
+    fn loop_order(component: &[usize], neighbours: &HashMap<usize, Vec<usize>>) -> Option<Vec<usize>> {
+        if component.len() < 3 || component.iter().any(|node| neighbours.get(node).is_none_or(|next| next.len() != 2)) {
+            return None;
+        }
+        let start = component[0];
+        let mut cycle = vec![start];
+        let (mut previous, mut current) = (start, neighbours[&start][0]);
+        while current != start {
+            cycle.push(current);
+            let next = &neighbours[&current];
+            (previous, current) = (current, if next[0] == previous { next[1] } else { next[0] });
+        }
+        (cycle.len() == component.len()).then_some(cycle)
+    }
+    /// Triangulates the closed loops made only of transparent edges again if the scene changed,
+    /// so lenses draw as solids. Loops that cross themselves or have no area are left unfilled.
+    pub fn refresh_fills(&mut self) {
+        let mut fill_loops = std::mem::take(&mut self.fill_loops);
+        if self.fills_generation == Some(self.generation) {
+            // animated nodes move without bumping the generation, only their loops change shape
+            let moving: HashSet<usize> = self.nodes.iter()
+                .filter(|(_, node)| node.animation.is_some())
+                .map(|(index, _)| *index)
+                .collect();
+            if moving.is_empty() {
+                self.fill_loops = fill_loops;
+                return;
+            }
+            let warned = self.unfillable.clone();
+            for (cycle, triangles) in &mut fill_loops {
+                if cycle.iter().any(|node| moving.contains(node)) { *triangles = self.fill_loop(cycle, &warned); }
+            }
+        } else {
+            self.fills_generation = Some(self.generation);
+            let opaque: HashSet<usize> = self.connections.iter()
+                .filter(|edge| edge.state != EdgeState::Transparent)
+                .flat_map(|edge| [edge.a, edge.b])
+                .collect();
+            let cycles: Vec<Vec<usize>> = self.closed_loops().into_iter()
+                .filter(|cycle| cycle.iter()
+                    .all(|node| !opaque.contains(node) && self.group_flags(self.node_group(*node)).visible))
+                .collect();
+            let warned = std::mem::take(&mut self.unfillable);
+            fill_loops = cycles.into_iter()
+                .map(|cycle| {
+                    let triangles = self.fill_loop(&cycle, &warned);
+                    (cycle, triangles)
+                })
+                .collect();
+        }
+        self.fills = fill_loops.iter().flat_map(|(_, triangles)| triangles.iter().copied()).collect();
+        self.fill_loops = fill_loops;
+    }
+    /// Triangles filling `cycle` where its nodes are now, or none if it crosses itself or has
+    /// no area, warning unless `warned` already has it.
+    fn fill_loop(&mut self, cycle: &[usize], warned: &HashSet<Vec<usize>>) -> Vec<[Vec2; 3]> {
+        let corners: Vec<Vec2> = cycle.iter().map(|node| self.nodes[node].position).collect();
+        let Some(triangles) = polygon::triangulate(&corners) else {
+            if !warned.contains(cycle) {
+                warn!("Not filling the loop through nodes {:?}, it crosses itself or has no area", cycle);
+            }
+            self.unfillable.insert(cycle.to_vec());
+            return Vec::new();
+        };
+        self.unfillable.remove(cycle);
+        triangles.into_iter().map(|[a, b, c]| [corners[a], corners[b], corners[c]]).collect()
+    }
+    /// Triangles filling the closed transparent loops as of the last `refresh_fills`.
+    pub fn fills(&self) -> &[[Vec2; 3]] {
+        &self.fills
+    }
+    fn draw_fills(&self) {
+        let color = theme::current().transparent;
+        let color = Color { a: color.a * FILL_ALPHA, ..color };
+        for [a, b, c] in &self.fills {
+            draw_triangle(*a, *b, *c, color);
+        }
+    }
+    /// For each edge, in `get_all_connections` order, its roughness.
+    pub fn segment_roughness(&self) -> Vec<f32> {
+        self.traced_edges().into_iter().map(|i| self.connections[i].roughness).collect()
+    }
+    /// For each edge, in `get_all_connections` order, its thickness.
+    pub fn segment_thickness(&self) -> Vec<f32> {
+        self.traced_edges().into_iter().map(|i| self.connections[i].thickness).collect()
+    }
+    /// For each edge, in `get_all_connections` order, its layer bitmask.
+    pub fn segment_layers(&self) -> Vec<u8> {
+        self.traced_edges().into_iter().map(|i| self.connections[i].layer).collect()
+    }
+    /// For each edge, in `get_all_connections` order, the state of its back face if it differs.
+    pub fn segment_back_states(&self) -> Vec<Option<EdgeState>> {
+        self.traced_edges().into_iter().map(|i| self.connections[i].back_state).collect()
+    }
+    /// For each edge, in `get_all_connections` order, the light it gives off.
+    pub fn segment_emitters(&self) -> Vec<Option<EmitterConfig>> {
+        self.traced_edges().into_iter().map(|i| self.connections[i].emitter).collect()
+    }
+    /// For each edge, in `get_all_connections` order, how it absorbs by the angle of incidence.
+    pub fn segment_absorption(&self) -> Vec<Option<AbsorptionCurve>> {
+        self.traced_edges().into_iter().map(|i| self.connections[i].absorption).collect()
+    }
+    /// Media, roughness, layers, back faces, emitters and absorption of the edges, in
+    /// `get_all_connections` order.
+    pub fn surfaces(&self) -> Surfaces {
+        let mut surfaces = Surfaces::default();
+        self.surfaces_into(&mut surfaces);
+        surfaces
+    }
+    /// `surfaces` into `out`, replacing what it held but keeping its buffers.
+    pub fn surfaces_into(&self, out: &mut Surfaces) {
+        let edges: Vec<&Edge> = self.traced_edges().into_iter().map(|i| &self.connections[i]).collect();
+        let medium_of = self.media();
+        let Surfaces { media, roughness, layers, back_states, emitters, thickness, absorption } = out;
+        fill(media, &edges, |edge| medium_of.get(&edge.a).copied());
+        fill(roughness, &edges, |edge| edge.roughness);
+        fill(layers, &edges, |edge| edge.layer);
+        fill(back_states, &edges, |edge| edge.back_state);
+        fill(emitters, &edges, |edge| edge.emitter);
+        fill(thickness, &edges, |edge| edge.thickness);
+        fill(absorption, &edges, |edge| edge.absorption);
+    }
+    /// Regular polygon of `sides` nodes around `center` with its first corner to the right, edges
+    /// `thickness` wide, in `group` if given.
+    pub fn add_circle(&mut self, center: Vec2, radius: f32, state: EdgeState, sides: usize, thickness: f32,
+                      group: Option<&str>) -> Vec<usize> {
+        let event = || Event::Circle { center, radius, state, sides, thickness, group: group.map(str::to_string) };
+        self.record_as(event, |network| {
+            let first_edge = network.connections.len();
+            let nodes = network.build_circle(center, radius, state, sides);
+            network.thicken(first_edge, thickness);
+            network.grouped(nodes, group)
+        })
+    }
+    fn build_circle(&mut self, center: Vec2, radius: f32, state: EdgeState, sides: usize) -> Vec<usize> {
+        let radius = radius as f64;
+        let node_radius = (8.0 * 20.0 / sides as f32).clamp(1.0, 8.0);
+        let nodes: Vec<usize> = (0..sides)
+            .map(|i| {
+                let angle = i as f64 * 2.0 * std::f64::consts::PI / (sides as f64);
+                let offset = Vec2::new((radius * angle.cos()) as f32, (radius * angle.sin()) as f32);
+                self.add_node_with_radius(center + offset, node_radius)
+            })
+            .collect();
+        // a single node has nothing to connect to, it would only make a loop onto itself
+        let closing = (sides > 2).then(|| (nodes[sides - 1], nodes[0]));
+        for (a, b) in nodes.windows(2).map(|pair| (pair[1], pair[0])).chain(closing) {
+            if self.add_connection(a, b) {
+                self.restate_edge(self.connections.len() - 1, state);
+            }
+        }
+        // dragging one corner would only dent the circle
+        for node in &nodes {
+            if let Some(node) = self.nodes.get_mut(node) { node.pinned = true; }
+        }
+        nodes
+    }
+    /// Like `draw`, but edges are colored cold to hot by the intensity they received, normalized
+    /// to the brightest one. `segment_hits` is parallel to `get_all_connections`.
+    pub fn draw_heatmap(&self, edge_thickness: f32, segment_hits: &[f32]) {
+        let max = segment_hits.iter().copied().fold(0.0, f32::max);
+        let mut hits_of = vec![0.0; self.connections.len()];
+        for (edge, hits) in self.traced_edges().into_iter().zip(segment_hits) { hits_of[edge] = *hits; }
+        self.static_geometry.draw(edge_thickness);
+        self.draw_fills();
+        let shown = self.connections.iter().zip(hits_of).filter(|(edge, _)| self.group_flags(edge.group()).visible);
+        for (edge, hits) in shown {
+            let heat = if max > 0.0 { hits / max } else { 0.0 };
+            let (start, end) = (self.nodes[&edge.a].position, self.nodes[&edge.b].position);
+            draw_line(start.x, start.y, end.x, end.y, edge.thickness, heat_color(heat));
+        }
+        if let Some(texture) = &self.texture {
+            for node in self.nodes.values().filter(|node| self.group_flags(node.group.as_deref()).visible) {
+                node.draw(texture);
+            }
+        }
+    }
+    pub const fn selected_node(&self) -> Option<usize> {
+        self.selected_node
+    }
+    /// Selects `node` as if right clicked, or clears the selection with `None`.
+    pub fn select(&mut self, node: Option<usize>) {
+        self.selected_node = node.filter(|node| self.nodes.contains_key(node));
+    }
+    /// Every group by name, see `assign_group`.
+    pub const fn groups(&self) -> &BTreeMap<String, Group> {
+        &self.groups
+    }
+    /// Flags of `group`; ungrouped items behave as a visible, colliding group.
+    fn group_flags(&self, group: Option<&str>) -> Group {
+        group.and_then(|group| self.groups.get(group)).copied().unwrap_or_default()
+    }
+    pub fn node_group(&self, node: usize) -> Option<&str> {
+        self.nodes.get(&node)?.group.as_deref()
+    }
+    /// Puts `nodes` and the edges among them in `group`, which is created if it doesn't exist
+    /// yet. `None` takes them out of their groups.
+    pub fn assign_group(&mut self, nodes: &[usize], group: Option<&str>) {
+        self.journal.log(|| Event::AssignGroup { nodes: nodes.to_vec(), group: group.map(str::to_string) });
+        if let Some(group) = group { self.groups.entry(group.to_string()).or_default(); }
+        let members: HashSet<usize> = nodes.iter().copied().collect();
+        for (_, node) in self.nodes.iter_mut().filter(|(index, _)| members.contains(index)) {
+            node.group = group.map(str::to_string);
+        }
+        for edge in self.connections.iter_mut().filter(|edge| members.contains(&edge.a) && members.contains(&edge.b)) {
+            edge.group = group.map(str::to_string);
+        }
+        self.generation += 1;
+    }
+    /// Nodes in `group`, sorted.
+    pub fn group_members(&self, group: &str) -> Vec<usize> {
+        let mut members: Vec<usize> = self.nodes.iter()
+            .filter(|(_, node)| node.group.as_deref() == Some(group))
+            .map(|(index, _)| *index)
+            .collect();
+        members.sort_unstable();
+        members
+    }
+    /// `base` if no group is called that yet, otherwise `base` numbered from 2 up.
+    pub fn unused_group_name(&self, base: &str) -> String {
+        std::iter::once(base.to_string())
+            .chain((2..).map(|number| format!("{} {}", base, number)))
+            .find(|name| !self.groups.contains_key(name))
+            .unwrap_or_default()
+    }
+    /// Shows or hides `group`. Hidden nodes and edges are still traced unless the group stops
+    /// colliding too.
+    pub fn set_group_visible(&mut self, group: &str, visible: bool) {
+        let Some(flags) = self.groups.get_mut(group).filter(|flags| flags.visible != visible) else { return; };
+        flags.visible = visible;
+        self.journal.log(|| Event::SetGroupVisible { group: group.to_string(), visible });
+        self.generation += 1;
+    }
+    /// Includes `group`'s edges in the trace, or leaves them out.
+    pub fn set_group_collides(&mut self, group: &str, collides: bool) {
+        let Some(flags) = self.groups.get_mut(group).filter(|flags| flags.collides != collides) else { return; };
+        flags.collides = collides;
+        self.journal.log(|| Event::SetGroupCollides { group: group.to_string(), collides });
+        self.generation += 1;
+    }
+    /// Removes `group` along with every node and edge in it.
+    pub fn delete_group(&mut self, group: &str) {
+        if !self.groups.contains_key(group) { return; }
+        self.record_as(|| Event::DeleteGroup { group: group.to_string() }, |network| {
+            for node in network.group_members(group) {
+                network.remove_node(node);
+            }
+            network.observer.retain_edges(&mut network.connections, |edge| edge.group() != Some(group));
+            network.groups.remove(group);
+            if network.selected_group.as_deref() == Some(group) { network.selected_group = None; }
+            if network.selected_node.is_some_and(|node| !network.nodes.contains_key(&node)) {
+                network.selected_node = None;
+            }
+            network.generation += 1;
+        });
+    }
+    /// Picks `group` for `selected_structure` in place of the selected node, or drops the pick
+    /// with `None`.
+    pub fn select_group(&mut self, group: Option<&str>) {
+        self.selected_group = group.filter(|group| self.groups.contains_key(*group)).map(str::to_string);
+        if self.selected_group.is_some() { self.selected_node = None; }
+    }
+    pub fn selected_group(&self) -> Option<&str> {
+        self.selected_group.as_deref()
+    }
+    /// Outlines the edges of the selected group in the hover color.
+    fn draw_selected_group(&self) {
+        let Some(group) = self.selected_group() else { return; };
+        if !self.group_flags(Some(group)).visible { return; }
+        let color = Color { a: 0.6, ..theme::current().hover };
+        for edge in self.connections.iter().filter(|edge| edge.group() == Some(group)) {
+            let (start, end) = (self.nodes[&edge.a].position, self.nodes[&edge.b].position);
+            draw_line(start.x, start.y, end.x, end.y, edge.thickness + 4.0, color);
+        }
+    }
+    fn draw_multi_selection(&self) {
+        let color = theme::current().hover;
+        for node in self.selection.iter().filter_map(|index| self.nodes.get(index)) {
+            draw_circle_lines(node.position.x, node.position.y, node.radius + 4.0 / self.zoom, 2.0 / self.zoom, color);
+        }
+    }
+    /// Gives every edge from index `first_edge` on, the ones a builder just made, `thickness`.
+    fn thicken(&mut self, first_edge: usize, thickness: f32) {
+        for edge in self.connections.iter_mut().skip(first_edge) { edge.thickness = thickness; }
+    }
+    /// Puts freshly built `nodes` in `group`, if there is one, and hands them back.
+    fn grouped(&mut self, nodes: Vec<usize>, group: Option<&str>) -> Vec<usize> {
+        if group.is_some() { self.assign_group(&nodes, group); }
+        nodes
+    }
+    pub fn node_position(&self, index: usize) -> Option<Vec2> {
+        self.nodes.get(&index).map(|node| node.position)
+    }
+    /// Places a node exactly; unlike dragging this marks the scene changed straight away.
+    pub fn move_node(&mut self, index: usize, position: Vec2) {
+        self.set_node_position(index, position);
+        if let Some((twin, position)) = self.twin_placement(index) {
+            self.set_node_position(twin, position);
+        }
+    }
+    /// Moves every one of `nodes` by `offset` as one edit, their twins along with them.
+    pub fn move_nodes(&mut self, nodes: &[usize], offset: Vec2) {
+        self.journal.log(|| Event::MoveNodes { nodes: nodes.to_vec(), offset });
+        for &index in nodes {
+            let Some(node) = self.nodes.get_mut(&index) else { continue; };
+            let from = node.position;
+            node.position += offset;
+            node.anchor = node.position;
+            self.note_move(index, from);
+        }
+        self.place_twins(nodes);
+        self.report_moves();
+        self.generation += 1;
+    }
+    fn set_node_position(&mut self, index: usize, position: Vec2) {
+        if let Some(node) = self.nodes.get_mut(&index) {
+            self.journal.log(|| Event::MoveNode { node: index, position });
+            let from = node.position;
+            node.position = position;
+            node.anchor = position;
+            self.generation += 1;
+            self.note_move(index, from);
+            self.report_moves();
+        }
+    }
+    /// Attaches `animation` to `node`, or detaches it with `None`. A node that wasn't animated
+    /// yet rests where it is now.
+    pub fn set_animation(&mut self, index: usize, animation: Option<Animation>) {
+        let Some(node) = self.nodes.get_mut(&index) else { return; };
+        self.journal.log(|| Event::Animate { node: index, animation });
+        if node.animation.is_none() { node.anchor = node.position; }
+        node.animation = animation;
+        self.generation += 1;
+    }
+    pub fn animation(&self, index: usize) -> Option<Animation> {
+        self.nodes.get(&index).and_then(|node| node.animation)
+    }
+    /// Whether any node moves on its own, so the scene has to be retraced every frame.
+    pub fn is_animated(&self) -> bool {
+        self.nodes.values().any(|node| node.animation.is_some())
+    }
+    /// `Action::Measure` (M) starts measuring, Escape stops. While measuring, left clicks pick the
+    /// two points instead of editing the scene; a third click starts over. Returns whether the
+    /// tool is active.
+    fn handle_measure(&mut self, input: &InputMap) -> bool {
+        if is_key_pressed(KeyCode::Escape) {
+            self.measure = None;
+        } else if input.is_pressed(Action::Measure) && self.dragged_node.is_none() {
+            self.measure = Some(MeasureTool::default());
+        }
+        let Some(measure) = &mut self.measure else { return false; };
+        if is_mouse_button_pressed(MouseButton::Left) && !root_ui().is_mouse_over(other_mouse_position().into()) {
+            if measure.points.len() == 2 { measure.points.clear(); }
+            measure.points.push(vec2tuple(mouse_position()));
+        }
+        true
+    }
+    /// Quick-connect held (Alt) and a left drag from one node to another joins them on release.
+    /// Returns whether a drag is going, which keeps the mouse from doing anything else.
+    fn handle_quick_connect(&mut self, input: &InputMap) -> bool {
+        let mouse = vec2tuple(mouse_position());
+        if input.is_down(Action::QuickConnect) && is_mouse_button_pressed(MouseButton::Left)
+            && self.dragged_node.is_none() && !root_ui().is_mouse_over(other_mouse_position().into()) {
+            self.begin_quick_connect(mouse);
+        }
+        if self.quick_connect.is_none() { return false; }
+        if is_mouse_button_released(MouseButton::Left) { self.end_quick_connect(mouse); }
+        true
+    }
+    /// Starts a quick-connect drag from the node at `position` (world space). Returns whether
+    /// there was a node to start from.
+    pub fn begin_quick_connect(&mut self, position: Vec2) -> bool {
+        self.quick_connect = match self.pick(position) {
+            Some(PickResult::Node(node)) => Some(node),
+            _ => None,
+        };
+        self.quick_connect.is_some()
+    }
+    /// Ends the quick-connect drag at `position`: over another node the two are connected like
+    /// `add_connection` does, over the starting node or empty space nothing is. Returns whether
+    /// an edge was added.
+    pub fn end_quick_connect(&mut self, position: Vec2) -> bool {
+        let Some(from) = self.quick_connect.take() else { return false; };
+        match self.pick(position) {
+            Some(PickResult::Node(to)) if to != from && self.nodes.contains_key(&from) => self.add_connection(from, to),
+            _ => false,
+        }
+    }
+    /// Multi-select held (Shift), a left click on a node toggles it in the selection and one
+    /// elsewhere clears it; without it a left drag from a selected node moves the whole
+    /// selection, Ctrl locking the move to one axis. Returns whether the mouse was used up.
+    fn handle_multi_select(&mut self, input: &InputMap) -> bool {
+        let mouse = vec2tuple(mouse_position());
+        let pressed = is_mouse_button_pressed(MouseButton::Left) && self.dragged_node.is_none()
+            && !root_ui().is_mouse_over(other_mouse_position().into());
+        if pressed && input.is_down(Action::MultiSelect) {
+            match self.pick(mouse) {
+                Some(PickResult::Node(node)) => self.toggle_selected(node),
+                _ => self.selection.clear(),
+            }
+            return true;
+        }
+        if pressed { self.begin_group_drag(mouse); }
+        if self.group_drag.is_none() { return false; }
+        self.drag_group_to(mouse, self.snapping);
+        if is_mouse_button_released(MouseButton::Left) { self.end_group_drag(); }
+        true
+    }
+    /// Adds `node` to the multi-selection, or takes it out if it is in already.
+    pub fn toggle_selected(&mut self, node: usize) {
+        if !self.selection.remove(&node) && self.nodes.contains_key(&node) { self.selection.insert(node); }
+    }
+    /// The multi-selected nodes that still exist, in index order.
+    pub fn selected_nodes(&self) -> Vec<usize> {
+        self.selection.iter().copied().filter(|node| self.nodes.contains_key(node)).collect()
+    }
+    /// Starts moving the selection if the node at `position` (world space) is in it. Pinned
+    /// nodes stay put. Returns whether a group drag started.
+    pub fn begin_group_drag(&mut self, position: Vec2) -> bool {
+        let Some(PickResult::Node(grabbed)) = self.pick(position) else { return false; };
+        if !self.selection.contains(&grabbed) { return false; }
+        let origins = self.selected_nodes().into_iter()
+            .filter_map(|index| self.nodes.get(&index).filter(|node| !node.pinned).map(|node| (index, node.position)))
+            .collect();
+        self.group_drag = Some(GroupDrag { grab: position, origins });
+        true
+    }
+    /// Moves the dragged selection so it is offset from where it started as far as `cursor` is
+    /// from where the drag started, only along the longer axis of that offset with
+    /// `axis_lock`. Logged once the drag ends.
+    pub fn drag_group_to(&mut self, cursor: Vec2, axis_lock: bool) {
+        let Some(drag) = &self.group_drag else { return; };
+        let mut offset = cursor - drag.grab;
+        if axis_lock {
+            if offset.x.abs() > offset.y.abs() { offset.y = 0.0; } else { offset.x = 0.0; }
+        }
+        let moved: Vec<usize> = drag.origins.iter().map(|&(index, _)| index).collect();
+        for &(index, origin) in &drag.origins {
+            if let Some(node) = self.nodes.get_mut(&index) {
+                node.position = origin + offset;
+                node.anchor = node.position;
+                if self.observer.is_observed() { self.moved_from.entry(index).or_insert(origin); }
+            }
+        }
+        self.place_twins(&moved);
+    }
+    /// Drops the dragged selection where it is, as one `MoveNodes` edit. Returns whether
+    /// anything moved.
+    pub fn end_group_drag(&mut self) -> bool {
+        let Some(drag) = self.group_drag.take() else { return false; };
+        self.report_moves();
+        let Some(&(first, origin)) = drag.origins.first() else { return false; };
+        let Some(offset) = self.node_position(first).map(|position| position - origin) else { return false; };
+        if offset == Vec2::ZERO { return false; }
+        let nodes: Vec<usize> = drag.origins.iter().map(|&(index, _)| index).collect();
+        self.journal.log(|| Event::MoveNodes { nodes, offset });
+        self.generation += 1;
+        true
+    }
+    /// Whether a group drag is going on.
+    pub const fn is_group_dragging(&self) -> bool {
+        self.group_drag.is_some()
+    }
+    /// Puts the twins of `nodes` that aren't among them in mirror of where they are now.
+    fn place_twins(&mut self, nodes: &[usize]) {
+        for &index in nodes {
+            let Some((twin, position)) = self.twin_placement(index) else { continue; };
+            if nodes.contains(&twin) { continue; }
+            if let Some(node) = self.nodes.get_mut(&twin) {
+                let from = node.position;
+                node.position = position;
+                node.anchor = position;
+                self.note_move(twin, from);
+            }
+        }
+    }
+    /// Node the quick-connect drag going on started from.
+    pub const fn quick_connect_origin(&self) -> Option<usize> {
+        self.quick_connect
+    }
+    pub const fn is_measuring(&self) -> bool {
+        self.measure.is_some()
+    }
+    /// Both points picked with the measure tool, once there are two.
+    pub fn measured_points(&self) -> Option<(Vec2, Vec2)> {
+        match self.measure.as_ref()?.points.as_slice() {
+            &[a, b] => Some((a, b)),
+            _ => None,
+        }
+    }
+    /// Hover readouts, the live length of a connection being placed and the measure tool. Call
+    /// with the default camera: text is placed through `world_to_screen` so it stays readable at
+    /// any zoom.
+    pub fn draw_measurements(&self) {
+        let cursor = vec2tuple(other_mouse_position()) + vec2(16.0, -8.0);
+        let to_screen = |world: Vec2| vec2tuple(unsafe { world_to_screen(tuple2vec(world)) });
+        if let Some(measure) = &self.measure {
+            let mut points = measure.points.clone();
+            if points.len() == 1 { points.push(vec2tuple(mouse_position())); }
+            if let [start, end] = points[..] {
+                let (a, b) = (to_screen(start), to_screen(end));
+                draw_line(a.x, a.y, b.x, b.y, 2.0, YELLOW);
+                draw_circle(a.x, a.y, 4.0, YELLOW);
+                draw_circle(b.x, b.y, 4.0, YELLOW);
+                Self::draw_label(&measure::segment_label(start, end), b + vec2(16.0, -8.0));
+            }
+            draw_text("Measuring: click two points, Escape to stop", 20.0, 60.0, 30.0, theme::current().text);
+            return;
+        }
+        if let Some(selected) = self.selected_node {
+            let end = self.placement(vec2tuple(mouse_position()), self.snapping);
+            Self::draw_label(&measure::segment_label(self.nodes[&selected].position, end), cursor);
+        } else if let Some((i, node)) = self.nodes.iter().find(|(_, node)| node.is_hovered) {
+            Self::draw_label(&measure::position_label(*i, node.position), cursor);
+        } else if let Some(edge) = self.connections.iter().find(|edge| edge.is_hovered) {
+            let (a, b) = (self.nodes[&edge.a].position, self.nodes[&edge.b].position);
+            Self::draw_label(&measure::edge_label(a, b), cursor);
+        }
+    }
+    fn draw_label(text: &str, position: Vec2) {
+        let size = measure_text(text, None, 20, 1.0);
+        draw_rectangle(position.x - 2.0, position.y - size.offset_y - 2.0, size.width + 4.0, size.height + 4.0,
+                       Color::new(0.0, 0.0, 0.0, 0.6));
+        draw_text(text, position.x, position.y, 20.0, WHITE);
+    }
+    /// Small window with the selected node's exact position; Enter applies typed coordinates.
+    pub fn inspector_ui(&mut self) {
+        let Some(selected) = self.selected_node else { return; };
+        let Some(position) = self.node_position(selected) else { return; };
+        let inspector = &mut self.inspector;
+        if inspector.node != Some(selected) {
+            *inspector = NodeInspector {
+                node: Some(selected),
+                x: position.x.to_string(),
+                y: position.y.to_string(),
+                invalid: false,
+                offset: inspector.offset.take(),
+            };
+        }
+        let mut apply = None;
+        let animation = self.nodes[&selected].animation;
+        let mut edited = animation;
+        let was_pinned = self.nodes[&selected].pinned;
+        let mut pinned = was_pinned;
+        widgets::Window::new(hash!(), Vec2::new(710., 0.), Vec2::new(220., 250.))
+            .label(&format!("Node {}", selected))
+            .ui(&mut root_ui(), |ui| {
+                ui.input_text(hash!(), "x", &mut inspector.x);
+                ui.input_text(hash!(), "y", &mut inspector.y);
+                if inspector.invalid { ui.label(None, "not a number"); }
+                // while the offset popup is open Enter places the node instead
+                if (is_key_pressed(KeyCode::Enter) && inspector.offset.is_none()) || ui.button(None, "Apply") {
+                    match (inspector.x.trim().parse::<f32>(), inspector.y.trim().parse::<f32>()) {
+                        (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => apply = Some(vec2(x, y)),
+                        _ => inspector.invalid = true,
+                    }
+                }
+                if ui.button(None, "Place connected node") {
+                    inspector.offset.get_or_insert_with(OffsetEntry::default);
+                }
+                ui.checkbox(hash!(), "pinned", &mut pinned);
+                let mut kind = Animation::kind(animation);
+                ui.combo_box(hash!(), "motion", &Animation::LABELS, &mut kind);
+                if kind != Animation::kind(animation) {
+                    edited = match kind {
+                        1 => Some(Animation::orbit_through(position, 50.0, 1.0)),
+                        2 => Some(Animation::Oscillate { axis: Vec2::X, amplitude: 50.0, speed: 1.0 }),
+                        _ => None,
+                    };
+                }
+                match &mut edited {
+                    Some(Animation::Orbit { radius, angular_speed, phase, .. }) => {
+                        ui.slider(hash!(), "radius", 0.0f32..300.0, radius);
+                        ui.slider(hash!(), "angular speed", -5.0f32..5.0, angular_speed);
+                        ui.slider(hash!(), "phase", 0.0f32..std::f32::consts::TAU, phase);
+                    }
+                    Some(Animation::Oscillate { axis, amplitude, speed }) => {
+                        let old_angle = axis.y.atan2(axis.x).to_degrees().rem_euclid(360.0);
+                        let mut angle = old_angle;
+                        ui.slider(hash!(), "axis angle", 0.0f32..360.0, &mut angle);
+                        // rebuilding the axis every frame would drift and count as an edit
+                        if angle != old_angle { *axis = Vec2::from_angle(angle.to_radians()); }
+                        ui.slider(hash!(), "amplitude", 0.0f32..300.0, amplitude);
+                        ui.slider(hash!(), "speed", 0.0f32..10.0, speed);
+                    }
+                    None => {}
+                }
+            });
+        if let Some(position) = apply {
+            self.move_node(selected, position);
+            self.inspector.node = None; // reload the text, normalized
+        }
+        if edited != animation {
+            self.set_animation(selected, edited);
+        }
+        if pinned != was_pinned {
+            self.set_pinned(selected, pinned);
+        }
+        self.offset_ui(selected);
+    }
+    /// Popup placing a node at a typed offset from `selected`, joined to it in `chain_state`.
+    /// The new node is selected, so a polyline can be typed in one offset after another.
+    fn offset_ui(&mut self, selected: usize) {
+        let Some(entry) = &mut self.inspector.offset else { return; };
+        let mut close = is_key_pressed(KeyCode::Escape);
+        let mut place = None;
+        widgets::Window::new(hash!(), Vec2::new(710., 260.), Vec2::new(220., 140.))
+            .label("Place connected node")
+            .ui(&mut root_ui(), |ui| {
+                ui.combo_box(hash!(), "offset", &Offset::LABELS, &mut entry.kind);
+                let [first, second] = if entry.kind == 1 { ["length", "angle"] } else { ["dx", "dy"] };
+                ui.input_text(hash!(), first, &mut entry.first);
+                ui.input_text(hash!(), second, &mut entry.second);
+                if entry.invalid { ui.label(None, "not a number"); }
+                if is_key_pressed(KeyCode::Enter) || ui.button(None, "Place") {
+                    place = Offset::parse(entry.kind, &entry.first, &entry.second);
+                    entry.invalid = place.is_none();
+                }
+                ui.same_line(0.0);
+                close |= ui.button(None, "Close");
+            });
+        if close { self.inspector.offset = None; }
+        if let Some(node) = place.and_then(|offset| self.add_at_offset(selected, offset, self.chain_state)) {
+            self.select(Some(node));
+        }
+    }
+    /// Places a node `offset` away from `from`, joined to it by an edge in `state`, as a chain
+    /// link. `None` if `from` doesn't exist.
+    pub fn add_at_offset(&mut self, from: usize, offset: Offset, state: EdgeState) -> Option<usize> {
+        let position = self.node_position(from)? + offset.vector();
+        Some(self.add_chain_link(from, position, state))
+    }
+    /// Small window with the faces, roughness, absorption, thickness, layers and emitter of the
+    /// last clicked edge.
+    pub fn edge_inspector_ui(&mut self) {
+        let Some((index, ends)) = self.inspected_edge else { return; };
+        let Some(edge) = self.connections.get(index).filter(|edge| edge.endpoints() == ends) else {
+            self.inspected_edge = None;
+            return;
+        };
+        let index_of = |state: EdgeState| EdgeState::ALL.iter().position(|other| *other == state);
+        let (old_front, old_back) = (index_of(edge.state).unwrap_or(0), edge.back_state.and_then(index_of));
+        let labels = EdgeState::ALL.map(EdgeState::label);
+        let back_labels: Vec<&str> = std::iter::once("Same").chain(labels).collect();
+        let (mut front, mut back) = (old_front, old_back.map_or(0, |back| back + 1));
+        let (old_roughness, old_thickness, old_layer) = (edge.roughness, edge.thickness, (edge.layer, edge.visible));
+        let (mut roughness, mut thickness, (mut layer, mut visible)) = (old_roughness, old_thickness, old_layer);
+        let old_emitter = edge.emitter;
+        let (mut emits, mut emitter) = (old_emitter.is_some(), old_emitter.unwrap_or_default());
+        let mut rays = emitter.rays as f32;
+        let old_absorption = edge.absorption;
+        let (mut absorbs, mut absorption) = (old_absorption.is_some(), old_absorption.unwrap_or_default());
+        let (mut flip, mut close) = (false, false);
+        widgets::Window::new(hash!(), Vec2::new(710., 240.), Vec2::new(220., 360.))
+            .label(&format!("Edge {}", index))
+            .ui(&mut root_ui(), |ui| {
+                ui.combo_box(hash!(), "front", &labels, &mut front);
+                ui.combo_box(hash!(), "back", &back_labels, &mut back);
+                flip = ui.button(None, "Flip faces");
+                ui.slider(hash!(), "roughness", 0.0f32..1.0, &mut roughness);
+                ui.checkbox(hash!(), "angle absorption", &mut absorbs);
+                if absorbs {
+                    ui.slider(hash!(), "absorbed head-on", 0.0f32..1.0, &mut absorption.base);
+                    ui.slider(hash!(), "extra at glancing", 0.0f32..1.0, &mut absorption.rise);
+                    ui.slider(hash!(), "steepness power", 0.1f32..8.0, &mut absorption.power);
                 }
+                ui.slider(hash!(), "thickness", Edge::MIN_THICKNESS..Edge::MAX_THICKNESS, &mut thickness);
+                ui.checkbox(hash!(), "visible", &mut visible);
+                layer_checkboxes(ui, "on layer", &mut layer);
+                ui.checkbox(hash!(), "emits light", &mut emits);
+                if emits {
+                    ui.slider(hash!(), "rays", 1.0f32..200.0, &mut rays);
+                    ui.slider(hash!(), "spread", 0.0f32..std::f32::consts::FRAC_PI_2, &mut emitter.spread);
+                    ui.slider(hash!(), "intensity", 0.0f32..1.0, &mut emitter.intensity);
+                    ui.slider(hash!(), "red", 0.0f32..1.0, &mut emitter.color[0]);
+                    ui.slider(hash!(), "green", 0.0f32..1.0, &mut emitter.color[1]);
+                    ui.slider(hash!(), "blue", 0.0f32..1.0, &mut emitter.color[2]);
+                }
+                close = ui.button(None, "Close");
+            });
+        emitter.rays = rays.round() as usize;
+        let emitter = emits.then_some(emitter);
+        let absorption = absorbs.then_some(absorption);
+        if front != old_front { self.set_edge_state(index, EdgeState::ALL[front]); }
+        let back = back.checked_sub(1);
+        if back != old_back { self.set_edge_back_state(index, back.map(|back| EdgeState::ALL[back])); }
+        if flip { self.flip_edge_faces(index); }
+        if roughness != old_roughness { self.set_edge_roughness(index, roughness); }
+        if absorption != old_absorption { self.set_edge_absorption(index, absorption); }
+        if thickness != old_thickness { self.set_edge_thickness(index, thickness); }
+        if (layer, visible) != old_layer { self.set_edge_layer(index, layer, visible); }
+        if emitter != old_emitter { self.set_edge_emitter(index, emitter); }
+        if close { self.inspected_edge = None; }
+    }
+    /// The nudge actions move the selected node by one unit, ten with `NudgeCoarse`, a tenth
+    /// with `NudgeFine`.
+    fn handle_nudge(&mut self, selected: usize, input: &InputMap) {
+        let step = if input.is_down(Action::NudgeCoarse) { 10.0 }
+            else if input.is_down(Action::NudgeFine) { 0.1 }
+            else { 1.0 };
+        let mut nudge = Vec2::ZERO;
+        if input.is_pressed(Action::NudgeLeft) { nudge.x -= step; }
+        if input.is_pressed(Action::NudgeRight) { nudge.x += step; }
+        if input.is_pressed(Action::NudgeUp) { nudge.y -= step; }
+        if input.is_pressed(Action::NudgeDown) { nudge.y += step; }
+        if nudge == Vec2::ZERO { return; }
+        if let Some(position) = self.node_position(selected) {
+            self.move_node(selected, position + nudge);
+            self.inspector.node = None;
+        }
+    }
+    fn handle_selection(&mut self, input: &InputMap) {
+        let Some(selected) = self.selected_node else { return; };
+        self.handle_nudge(selected, input);
+    }
+    /// Rubber band from the selected node to where a right click would put the next one, and
+    /// from the start of a quick-connect drag to the cursor. Drawn in the world-space pass.
+    pub fn draw_selection(&self) {
+        if let Some(start) = self.quick_connect.and_then(|node| self.node_position(node)) {
+            let end = vec2tuple(mouse_position());
+            draw_line(start.x, start.y, end.x, end.y, 2.0, YELLOW);
+        }
+        let Some(start) = self.selected_node.and_then(|selected| self.node_position(selected)) else { return; };
+        let end = self.placement(vec2tuple(mouse_position()), self.snapping);
+        draw_line(start.x, start.y, end.x, end.y, 5.0, WHITE);
+    }
+    fn handle_mouse(&mut self, input: &InputMap) {
+        if input.is_pressed(Action::ChainMode) { self.set_chain_mode(!self.chain_mode); }
+        if self.chain_mode {
+            if is_key_pressed(KeyCode::Escape) { self.chain_last = None; }
+            if input.is_pressed(Action::ConnectSelect) && self.dragged_node.is_none() {
+                self.handle_chain_click(vec2tuple(mouse_position()));
             }
-
-            if let Some(selected_index) = selected_index {
+        } else if input.is_pressed(Action::ConnectSelect) && self.dragged_node.is_none() {
+            let mouse_pos = vec2tuple(mouse_position());
+            if let Some(PickResult::Node(selected_index)) = self.pick(mouse_pos) {
                 if self.selected_node == Some(selected_index) {
                     self.selected_node = None;
                 } else if let Some(prev_selected_index) = self.selected_node {
@@ -622,14 +3201,8 @@ impl NodeNetwork {
                     self.selected_node = Some(selected_index);
                 }
             } else {
-                let mp = vec2tuple(mouse_position());
-                let mut new_mp = mp;
-                if self.selected_node.is_some() {
-                    let node = &self.nodes[&self.selected_node.unwrap()];
-                    new_mp = node.position;
-                    new_mp = Self::ctrl_shift(mp, node, &mut new_mp);
-                }
-                let node_index = self.add_node(new_mp);
+                let position = self.placement(mouse_pos, self.snapping);
+                let node_index = self.place_node(position);
                 if let Some(selected_index) = self.selected_node {
                     debug!("Adding connection from {} to {}", selected_index, node_index);
                     self.add_connection(selected_index, node_index);
@@ -639,77 +3212,463 @@ impl NodeNetwork {
         }
 
 
-        if is_mouse_button_pressed(MouseButton::Middle)
+        if input.is_pressed(Action::DeleteHovered) {
+            self.middle_press = Some(vec2tuple(other_mouse_position()));
+        }
+        let middle_clicked = input.is_released(Action::DeleteHovered)
+            && self.middle_press.take().is_some_and(|pressed|
+            pressed.distance(vec2tuple(other_mouse_position())) <= CLICK_SLOP);
+        if middle_clicked
             && self.dragged_node.is_none()
             && self.selected_node.is_none() {
             // Remove node or connection
-            let mouse_pos = vec2tuple(mouse_position());
-            for (i, node) in self.nodes.iter() {
-                if node.contains(mouse_pos) {
-                    self.remove_node(*i);
-                    return;
-                }
-            }
-            for (i, edge) in &mut self.connections.iter().enumerate() {
-                let pos1 = self.nodes[&edge.a].position;
-                let pos2 = self.nodes[&edge.b].position;
-                if Self::point_line_collision(mouse_pos, pos1, pos2, edge.thickness) {
-                    self.connections.remove(i);
-                    // self.connections.retain(|edge| edge.a != edge.b && edge.a != edge.b);
-                    return;
-                }
+            match self.pick(vec2tuple(mouse_position())) {
+                Some(PickResult::Node(i)) => self.remove_node(i),
+                Some(PickResult::Edge(i)) => self.remove_edge(i),
+                None => {}
             }
         }
     }
 
-    fn ctrl_shift(mp: Vec2, node: &Node, new_mp: &Vec2) -> Vec2 {
-        let mut new_mp = *new_mp;
-        if is_key_down(KeyCode::LeftControl) {
-            let diff = mp - node.position;
-            if diff.x.abs() > diff.y.abs() { new_mp.x = mp.x } else { new_mp.y = mp.y }
-        } else { new_mp = mp; }
-        new_mp
+    /// A right click in chain mode: empty space adds the next link, the chain's own end stops
+    /// it, any other node closes the chain onto it or, with no chain going, starts one there.
+    fn handle_chain_click(&mut self, mouse_pos: Vec2) {
+        // the end of the chain may have been deleted meanwhile
+        let last = self.chain_last.filter(|node| self.nodes.contains_key(node));
+        match (self.pick(mouse_pos), last) {
+            (Some(PickResult::Node(node)), Some(last)) if node == last => self.chain_last = None,
+            (Some(PickResult::Node(node)), Some(last)) => {
+                self.connect_as(last, node, self.chain_state);
+                self.chain_last = None;
+            }
+            (Some(PickResult::Node(node)), None) => self.chain_last = Some(node),
+            (_, last) => {
+                let position = self.placement(mouse_pos, self.snapping);
+                self.chain_last = Some(match last {
+                    Some(last) => self.add_chain_link(last, position, self.chain_state),
+                    None => self.place_node(position),
+                });
+            }
+        }
+    }
+    pub const fn chain_mode(&self) -> bool {
+        self.chain_mode
+    }
+    /// Turns chain mode on or off; either way the chain starts afresh and the selection used
+    /// by the ordinary select-then-connect clicks is dropped.
+    pub fn set_chain_mode(&mut self, on: bool) {
+        self.chain_mode = on;
+        self.chain_last = None;
+        self.selected_node = None;
     }
-    fn point_line_collision(point: Vec2, line_start: Vec2, line_end: Vec2, thickness: f32) -> bool {
-        let distance = point_to_line_distance(point, line_start, line_end);
-        distance <= thickness / 2.0
+    /// Node the next chain link will start from.
+    pub const fn chain_end(&self) -> Option<usize> {
+        self.chain_last
+    }
+    /// Places a node at `position` joined to `from` by an edge in `state`, logged as one edit.
+    /// Returns the new node.
+    pub fn add_chain_link(&mut self, from: usize, position: Vec2, state: EdgeState) -> usize {
+        self.record_as(|| Event::ChainLink { from, position, state }, |network| {
+            let node = network.place_node(position);
+            network.connect_as(from, node, state);
+            node
+        })
+    }
+    /// `add_connection` with the new edge in `state`, logged as one edit.
+    pub fn connect_as(&mut self, a: usize, b: usize, state: EdgeState) -> bool {
+        self.record_as(|| Event::ConnectAs { a, b, state }, |network| {
+            let edge = network.connections.len();
+            let connected = network.add_connection(a, b);
+            if connected { network.set_edge_state(edge, state); }
+            connected
+        })
+    }
+    /// What a click at `position` (world space) lands on. Nodes beat edges; among several nodes
+    /// or several overlapping edges the one closest to `position` wins. Hit areas never shrink
+    /// below the configured on-screen sizes, however far out the camera is zoomed.
+    pub fn pick(&self, position: Vec2) -> Option<PickResult> {
+        let min_half_width = self.min_edge_screen_width / 2.0 / self.zoom;
+        let node = self.nodes.iter()
+            .filter(|(_, node)| self.group_flags(node.group.as_deref()).visible)
+            .filter(|(_, node)| node.contains(position, self.zoom, self.min_node_screen_radius))
+            .min_by(|(_, a), (_, b)| a.position.distance(position).total_cmp(&b.position.distance(position)))
+            .map(|(i, _)| PickResult::Node(*i));
+        node.or_else(|| self.connections.iter().enumerate()
+            .filter(|(_, edge)| self.group_flags(edge.group()).visible)
+            .filter_map(|(i, edge)| {
+                let (start, end) = (self.nodes.get(&edge.a)?.position, self.nodes.get(&edge.b)?.position);
+                let distance = point_to_line_distance(position, start, end);
+                (distance <= (edge.thickness / 2.0).max(min_half_width)).then_some((i, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| PickResult::Edge(i)))
     }
 
-    fn remove_node(&mut self, index: usize) {
-        if let Some(_) = self.nodes.get(&index) {
+    /// Where a right click at `cursor` (world space) puts a new node. With `axis_lock` (Ctrl)
+    /// and a node selected it is moved level with the selected node, horizontally or vertically
+    /// whichever is closer to the cursor.
+    pub fn placement(&self, cursor: Vec2, axis_lock: bool) -> Vec2 {
+        match self.selected_node.and_then(|selected| self.node_position(selected)) {
+            Some(anchor) if axis_lock => {
+                let diff = cursor - anchor;
+                if diff.x.abs() > diff.y.abs() { vec2(cursor.x, anchor.y) } else { vec2(anchor.x, cursor.y) }
+            }
+            _ => cursor,
+        }
+    }
+    pub fn remove_node(&mut self, index: usize) {
+        if let Some(twin) = self.detach_twin(index) {
+            self.remove_node(twin);
+        }
+        if self.nodes.contains_key(&index) {
+            self.journal.log(|| Event::RemoveNode { node: index });
+            self.generation += 1;
             // Remove the node from the connections vector
-            self.connections.retain(|edge| edge.a != index && edge.b != index);
+            self.observer.retain_edges(&mut self.connections, |edge| edge.a != index && edge.b != index);
 
             // Remove the node itself
             self.nodes.remove(&index);
+            self.observer.push(|| NetworkEvent::NodeRemoved { id: index });
         }
         self.nodes.remove(&index);
     }
     pub fn add_node(&mut self, position: Vec2) -> usize {
-        debug!("Added node at {:} keys: {}", position, self.key);
-        self.nodes.insert(self.key, Node::new_default_radius(position));
-        self.key += 1;
-        self.key - 1
+        self.add_node_with_radius(position, Node::DEFAULT_RADIUS)
     }
 
+    /// Adds every segment as an edge keeping its state, after `merge_collinear_segments`.
+    /// Coincident endpoints share a node. Returns the created nodes.
+    pub fn add_segments(&mut self, segments: &[Segment]) -> Vec<usize> {
+        self.add_segments_in(segments, new_edge_thickness(), None)
+    }
+    /// `add_segments` with the new edges `thickness` wide, putting them and their nodes in
+    /// `group`.
+    pub fn add_segments_in(&mut self, segments: &[Segment], thickness: f32, group: Option<&str>) -> Vec<usize> {
+        let event = || Event::Segments { segments: segments.to_vec(), thickness, group: group.map(str::to_string) };
+        self.record_as(event, |network| {
+            let first_edge = network.connections.len();
+            let nodes = network.build_segments(segments);
+            network.thicken(first_edge, thickness);
+            network.grouped(nodes, group)
+        })
+    }
+    fn build_segments(&mut self, segments: &[Segment]) -> Vec<usize> {
+        let mut node_map: HashMap<(u32, u32), usize> = HashMap::new();
+        for segment in merge_collinear_segments(segments) {
+            let [a, b] = [segment.0, segment.1].map(|position| *node_map
+                .entry((position.x.to_bits(), position.y.to_bits()))
+                .or_insert_with(|| self.add_node_with_radius(position, 2.)));
+            if self.add_connection(a, b) {
+                self.restate_edge(self.connections.len() - 1, segment.2);
+            }
+        }
+        // the tiny wall nodes are easy to catch by accident, so they start out pinned
+        for node in node_map.values() {
+            if let Some(node) = self.nodes.get_mut(node) { node.pinned = true; }
+        }
+        node_map.into_values().collect()
+    }
+    /// `add_segments_in` for labyrinth lines moved by `offset`, all in `state`.
+    pub fn add_lines(&mut self, lines: &[labyrinth::Line], offset: Vec2, state: EdgeState, thickness: f32,
+                     group: Option<&str>) -> Vec<usize> {
+        let segments: Vec<Segment> = lines.iter()
+            .map(|&(start, end)| Segment(offset + vec2tuple(start), offset + vec2tuple(end), state))
+            .collect();
+        self.add_segments_in(&segments, thickness, group)
+    }
+    /// Edits walls `add_lines` built in `group` with the same `offset`: the `removed` ones are
+    /// cut out of the edges along them, the `added` ones join the nodes already at their ends,
+    /// in `state` and `thickness` wide. Walls are single cell sides, as
+    /// `Labyrinth::get_as_lines_explicit` gives them.
+    pub fn edit_lines(&mut self, removed: &[labyrinth::Line], added: &[labyrinth::Line], offset: Vec2, state: EdgeState,
+                      thickness: f32, group: &str) {
+        if removed.is_empty() && added.is_empty() { return; }
+        let event = || Event::EditLines { removed: removed.to_vec(), added: added.to_vec(), offset, state, thickness,
+                                          group: group.to_string() };
+        self.record_as(event, |network| {
+            let ends = |&(start, end): &labyrinth::Line| [start, end].map(|point| offset + vec2tuple(point));
+            for line in removed {
+                let [Some(a), Some(b)] = ends(line).map(|position| network.wall_node(position, group))
+                    else { continue; };
+                let edge = network.connections.iter()
+                    .position(|edge| (edge.a, edge.b) == (a, b) || (edge.a, edge.b) == (b, a));
+                if let Some(edge) = edge { network.remove_edge(edge); }
+                for node in [a, b] {
+                    if network.degree(node) == 0 { network.remove_node(node); }
+                }
+            }
+            for line in added {
+                let [a, b] = ends(line).map(|position| network.wall_node(position, group)
+                    .unwrap_or_else(|| network.add_wall_node(position, group)));
+                if network.add_connection(a, b) {
+                    let edge = network.connections.len() - 1;
+                    network.restate_edge(edge, state);
+                    network.connections[edge].thickness = thickness;
+                    network.connections[edge].group = Some(group.to_string());
+                }
+            }
+        });
+    }
+    /// The node of `group` at `position`, splitting the edge of `group` running through it
+    /// if there is none there yet.
+    fn wall_node(&mut self, position: Vec2, group: &str) -> Option<usize> {
+        let in_group = |node: &Node| node.group.as_deref() == Some(group);
+        let at = self.nodes.iter().find(|(_, node)| in_group(node) && node.position.distance(position) < 1e-3);
+        if let Some((&index, _)) = at { return Some(index); }
+        let edge = self.connections.iter().position(|edge| {
+            let (a, b) = (self.nodes[&edge.a].position, self.nodes[&edge.b].position);
+            edge.group() == Some(group) && (b - a).perp_dot(position - a).abs() <= 1e-3 * a.distance(b)
+                && (position - a).dot(b - a) > 0.0 && (position - b).dot(a - b) > 0.0
+        })?;
+        let node = self.add_wall_node(position, group);
+        self.split_edge_at(edge, node);
+        Some(node)
+    }
+    /// A pinned wall node at `position` in `group`, like those `add_lines` builds.
+    fn add_wall_node(&mut self, position: Vec2, group: &str) -> usize {
+        let index = self.add_node_with_radius(position, 2.);
+        if let Some(node) = self.nodes.get_mut(&index) {
+            node.pinned = true;
+            node.group = Some(group.to_string());
+        }
+        index
+    }
+    /// Builds the labyrinth `spec` describes with its top left corner at `offset`, walls in
+    /// `state` and `thickness` wide, in `group` if given. Logged as the spec, so a replay builds
+    /// it again from the seed.
+    pub fn add_labyrinth(&mut self, spec: &LabyrinthSpec, offset: Vec2, state: EdgeState, thickness: f32,
+                         group: Option<&str>) -> Labyrinth {
+        let labyrinth = spec.build();
+        let group_name = group.map(str::to_string);
+        let event = || Event::Labyrinth { spec: spec.clone(), offset, state, thickness, group: group_name };
+        self.record_as(event, |network| network.add_lines(&labyrinth.get_as_lines(), offset, state, thickness, group));
+        labyrinth
+    }
+    pub const fn static_geometry(&self) -> &StaticGeometry {
+        &self.static_geometry
+    }
+    /// Adds `segments` as static geometry: traced and drawn, but without nodes to edit.
+    pub fn add_static_segments(&mut self, segments: &[Segment]) {
+        if segments.is_empty() { return; }
+        self.journal.log(|| Event::StaticSegments { segments: segments.to_vec() });
+        self.static_geometry.segments.extend_from_slice(segments);
+        self.generation += 1;
+    }
+    /// `add_labyrinth` building static geometry rather than nodes.
+    pub fn add_static_labyrinth(&mut self, spec: &LabyrinthSpec, offset: Vec2, state: EdgeState) -> Labyrinth {
+        let labyrinth = spec.build();
+        let segments: Vec<Segment> = labyrinth.get_as_lines().into_iter()
+            .map(|(start, end)| Segment(offset + vec2tuple(start), offset + vec2tuple(end), state))
+            .collect();
+        self.record_as(|| Event::StaticLabyrinth { spec: spec.clone(), offset, state },
+                       |network| network.add_static_segments(&merge_collinear_segments(&segments)));
+        labyrinth
+    }
+    /// Turns all static geometry into editable (pinned) nodes and edges, see `add_segments`.
+    /// Returns the created nodes.
+    pub fn static_to_nodes(&mut self) -> Vec<usize> {
+        if self.static_geometry.is_empty() { return Vec::new(); }
+        self.record_as(|| Event::StaticToNodes, |network| {
+            let segments = std::mem::take(&mut network.static_geometry.segments);
+            network.build_segments(&segments)
+        })
+    }
+    /// Turns `nodes` and the edges among them into static geometry, removing the nodes. Border
+    /// edges stay as they are.
+    pub fn nodes_to_static(&mut self, nodes: &[usize]) {
+        let members: HashSet<usize> = nodes.iter().copied()
+            .filter(|node| self.bounds.is_none_or(|bounds| !bounds.contains(node)))
+            .collect();
+        if members.is_empty() { return; }
+        self.record_as(|| Event::NodesToStatic { nodes: nodes.to_vec() }, |network| {
+            let segments: Vec<Segment> = network.connections.iter()
+                .filter(|edge| members.contains(&edge.a) && members.contains(&edge.b))
+                .filter_map(|edge| Some(Segment(network.node_position(edge.a)?, network.node_position(edge.b)?, edge.state)))
+                .collect();
+            for node in &members { network.remove_node(*node); }
+            network.add_static_segments(&segments);
+        });
+    }
+    /// `nodes` and the edges between them, border edges left out. Unknown and repeated nodes
+    /// are skipped.
+    pub fn extract(&self, nodes: &[usize]) -> NetworkFragment {
+        let mut index_of: HashMap<usize, usize> = HashMap::new();
+        let mut fragment = NetworkFragment::default();
+        for &node in nodes {
+            let Some(found) = self.nodes.get(&node) else { continue; };
+            index_of.entry(node).or_insert_with(|| {
+                fragment.nodes.push((found.position, found.default_radius));
+                fragment.nodes.len() - 1
+            });
+        }
+        fragment.edges = self.connections.iter()
+            .filter(|edge| !edge.is_border)
+            .filter_map(|edge| Some((*index_of.get(&edge.a)?, *index_of.get(&edge.b)?, edge.state)))
+            .collect();
+        fragment
+    }
+    /// Adds `fragment` as new nodes and edges, never sharing a node with the existing network.
+    /// Returns the new nodes in fragment order.
+    pub fn insert(&mut self, fragment: &NetworkFragment) -> Vec<usize> {
+        let nodes: Vec<usize> = fragment.nodes.iter()
+            .map(|&(position, radius)| self.add_node_with_radius(position, radius))
+            .collect();
+        for &(a, b, state) in &fragment.edges {
+            if self.add_connection(nodes[a], nodes[b]) {
+                self.restate_edge(self.connections.len() - 1, state);
+            }
+        }
+        nodes
+    }
+    /// Copies `nodes` and their edges as `layout` describes, logged as a single edit. Returns
+    /// the new nodes, copy by copy.
+    pub fn add_array(&mut self, nodes: &[usize], layout: ArrayLayout) -> Vec<usize> {
+        self.record_as(|| Event::Array { nodes: nodes.to_vec(), layout }, |network| {
+            let fragment = network.extract(nodes);
+            let centroid = fragment.centroid();
+            (1..=layout.copies())
+                .flat_map(|copy| network.insert(&fragment.transformed(|position| layout.place(copy, position, centroid))))
+                .collect()
+        })
+    }
     pub fn add_node_with_radius(&mut self, position: Vec2, radius: f32) -> usize {
         debug!("Added node at {:} keys: {} with radius {}", position, self.key, radius);
+        self.journal.log(|| Event::AddNode { position, radius });
         self.nodes.insert(self.key, Node::new(position, radius));
+        self.observer.push(|| NetworkEvent::NodeAdded { id: self.key, pos: position });
+        self.generation += 1;
         self.key += 1;
         self.key - 1
     }
 
 
-    pub fn add_connection(&mut self, prev_conn: usize, cur_conn: usize) {
+    /// Connects two nodes unless they are the same node or already connected. Returns whether
+    /// an edge was added. With `split_crossings` on, an edge that would cross others is made
+    /// of pieces meeting at a node on each of them instead, see `connect_through_crossings`;
+    /// the edges of compound edits like builders never are.
+    pub fn add_connection(&mut self, prev_conn: usize, cur_conn: usize) -> bool {
+        if prev_conn == cur_conn {
+            debug!("Refusing to connect node {} to itself", prev_conn);
+            return false;
+        }
         if self.connections.iter().any(|edge|
         (edge.a == prev_conn && edge.b == cur_conn) ||
             (edge.a == cur_conn && edge.b == prev_conn)) {
             debug!("Connection already exists");
-            return;
+            return false;
         }
-        self.connections.push(Edge::new(prev_conn, cur_conn));
+        if self.split_crossings && !self.journal.is_muted() {
+            if let Some(connected) = self.connect_through_crossings(prev_conn, cur_conn) { return connected; }
+        }
+        self.push_edge(Edge { thickness: new_edge_thickness(), ..Edge::new(prev_conn, cur_conn) });
+        self.journal.log(|| Event::Connect { a: prev_conn, b: cur_conn });
+        self.generation += 1;
         debug!("Connection created between nodes {} and {}",
                         prev_conn, cur_conn);
+        // the mirrored connection already exists by the time this recurses into it
+        if let Some((a, b)) = self.twin_ends(prev_conn, cur_conn) {
+            self.add_connection(a, b);
+        }
+        true
+    }
+
+    /// Connects `a` to `b` by a chain of edges through every edge the straight connection would
+    /// cross, in order from `a`, splitting each crossed edge with `split_edge` and passing
+    /// through the node where it crosses at an end. `None` if it crosses nothing. Edges it would
+    /// run along are left alone, with a warning.
+    fn connect_through_crossings(&mut self, a: usize, b: usize) -> Option<bool> {
+        let (start, end) = (self.node_position(a)?, self.node_position(b)?);
+        let mut crossings = Vec::new();
+        for edge in self.connections.iter().filter(|edge| ![edge.a, edge.b].iter().any(|end| *end == a || *end == b)) {
+            let (from, to) = (self.nodes.get(&edge.a)?.position, self.nodes.get(&edge.b)?.position);
+            match segment_intersection((start, end), (from, to)) {
+                Some(SegmentIntersection::Point { point, t, u }) if t > 0.0 && t < 1.0 => {
+                    crossings.push((t, edge.endpoints(), point, u));
+                }
+                Some(SegmentIntersection::Overlap) => {
+                    warn!("New edge ({}, {}) runs along edge {:?}, not splitting it", a, b, edge.endpoints());
+                }
+                _ => {}
+            }
+        }
+        if crossings.is_empty() { return None; }
+        crossings.sort_by(|x, y| x.0.total_cmp(&y.0));
+        let mut path = vec![a];
+        for ((from, to), point, u) in crossings.into_iter().map(|(_, ends, point, u)| (ends, point, u)) {
+            let node = if u <= 0.0 { from } else if u >= 1.0 { to } else {
+                // earlier splits shifted the indices, the ends still tell the edge
+                let Some(edge) = self.connections.iter().position(|edge| edge.endpoints() == (from, to)) else {
+                    continue;
+                };
+                self.split_edge(edge, point)
+            };
+            if path.last() != Some(&node) { path.push(node); }
+        }
+        path.push(b);
+        let mut connected = false;
+        for pair in path.windows(2) { connected |= self.add_connection(pair[0], pair[1]); }
+        Some(connected)
+    }
+
+    /// Turns the symmetric build mode on around `axis`, or off with `None`. Changing the axis
+    /// forgets which nodes were twins.
+    pub fn set_symmetry(&mut self, axis: Option<SymmetryAxis>) {
+        if self.symmetry != axis { self.twins.clear(); }
+        self.symmetry = axis;
+    }
+    pub const fn symmetry(&self) -> Option<SymmetryAxis> {
+        self.symmetry
+    }
+    pub fn twin(&self, node: usize) -> Option<usize> {
+        self.twins.get(&node).copied()
+    }
+    /// Adds a node where the user placed it. In symmetric mode its twin is placed across the
+    /// axis too, unless it is close enough to the axis to snap onto it and be its own mirror.
+    /// Returns the placed node.
+    pub fn place_node(&mut self, position: Vec2) -> usize {
+        let Some(axis) = self.symmetry else { return self.add_node(position); };
+        if axis.distance(position) <= MERGE_SNAP_RADIUS / self.zoom {
+            return self.add_node(axis.project(position));
+        }
+        let node = self.add_node(position);
+        let twin = self.add_node(axis.mirror(position));
+        self.link_twins(node, twin);
+        node
+    }
+    /// Breaks `node` and its twin apart so they are edited separately from now on. Returns the
+    /// former twin.
+    pub fn detach_twin(&mut self, node: usize) -> Option<usize> {
+        let twin = self.twins.remove(&node)?;
+        self.twins.remove(&twin);
+        Some(twin)
+    }
+    fn link_twins(&mut self, a: usize, b: usize) {
+        self.twins.insert(a, b);
+        self.twins.insert(b, a);
+    }
+    /// Twin of `node` and where it belongs, mirrored from where `node` is now.
+    fn twin_placement(&self, node: usize) -> Option<(usize, Vec2)> {
+        let axis = self.symmetry?;
+        Some((self.twin(node)?, axis.mirror(self.node_position(node)?)))
+    }
+    /// What `node` maps to in the mirror image: its twin, or itself when it sits on the axis.
+    fn mirror_node(&self, node: usize) -> Option<usize> {
+        let axis = self.symmetry?;
+        self.twin(node).or_else(|| self.node_position(node)
+            .filter(|position| axis.distance(*position) <= MIN_EDGE_LENGTH)
+            .map(|_| node))
+    }
+    /// Ends of the mirror image of the edge between `a` and `b`, `None` if either end has no
+    /// mirror or the edge is its own mirror image.
+    fn twin_ends(&self, a: usize, b: usize) -> Option<(usize, usize)> {
+        let ends = (self.mirror_node(a)?, self.mirror_node(b)?);
+        (ends != (a, b) && ends != (b, a)).then_some(ends)
+    }
+    /// Index of the mirror image of edge `edge_index`, if it exists.
+    fn twin_edge(&self, edge_index: usize) -> Option<usize> {
+        let edge = self.connections.get(edge_index)?;
+        let (a, b) = self.twin_ends(edge.a, edge.b)?;
+        self.connections.iter().position(|edge| (edge.a, edge.b) == (a, b) || (edge.a, edge.b) == (b, a))
     }
 }
 
@@ -723,11 +3682,16 @@ impl Node {
             ..Default::default()
         }
     }
+    pub const DEFAULT_RADIUS: f32 = 8.0;
+
     pub fn new_default_radius(position: Vec2) -> Self {
-        Self::new(position, 8.0)
+        Self::new(position, Self::DEFAULT_RADIUS)
     }
-    pub fn contains(&self, position: Vec2) -> bool {
-        (position - self.position).length_squared() <= self.radius.powi(2)
+    /// Whether `position` is within the node, or within `min_screen_radius` pixels of its center
+    /// at camera `zoom` when the node itself is smaller than that on screen.
+    pub fn contains(&self, position: Vec2, zoom: f32, min_screen_radius: f32) -> bool {
+        let radius = self.radius.max(min_screen_radius / zoom);
+        (position - self.position).length_squared() <= radius.powi(2)
     }
     fn draw(&self, texture2d: &Texture2D) {
         draw_texture_ex(texture2d,
@@ -735,24 +3699,32 @@ impl Node {
                         self.color,
                         DrawTextureParams {
                             dest_size: Some(Vec2::new(self.radius * 2.0, self.radius * 2.0)),
-                            ..core::default::Default::default()
+                            ..Default::default()
                         });
     }
-    fn update(&mut self, delta: f32) {
-        self.handle_drag(delta);
-        self.handle_hover(delta);
+    /// `max_hover_radius` (world units) caps how far hovering grows the node; `snapping` keeps a
+    /// drag to the nearer axis.
+    fn update(&mut self, delta: f32, time: f64, max_hover_radius: f32, snapping: bool) {
+        self.handle_drag(delta, snapping);
+        self.handle_hover(delta, max_hover_radius);
+        if let Some(animation) = self.animation.filter(|_| !self.is_dragged) {
+            self.position = animation.position_at(self.anchor, time);
+        }
     }
-    fn handle_hover(&mut self, delta: f32) {
-        let target_radius: f32 = if
-        self.is_hovered { self.default_radius * 2.0 } else { self.default_radius };
+    fn handle_hover(&mut self, delta: f32, max_hover_radius: f32) {
+        let target_radius: f32 = if self.is_hovered {
+            (self.default_radius * 2.0).min(max_hover_radius).max(self.default_radius)
+        } else { self.default_radius };
 
-        self.radius = lerpf(self.radius, target_radius, delta / 0.10);
+        self.radius = smooth_toward(self.radius, target_radius, theme::current().hover_half_life, delta);
     }
-    fn handle_drag(&mut self, delta: f32) {
+    fn handle_drag(&mut self, delta: f32, snapping: bool) {
+        let theme = theme::current();
+        let tint = if self.pinned { theme.pinned_node } else { theme.node };
         if self.is_dragged {
-            lerp_color_in_place(&mut self.color, WHITE, delta / 0.10);
+            smooth_color_toward(&mut self.color, tint, theme.color_half_life, delta);
             let mouse_pos = vec2tuple(mouse_position());
-            if is_key_down(KeyCode::LeftControl) {
+            if snapping {
                 let diff = mouse_pos - self.dragged_start_pos;
                 if diff.x.abs() > diff.y.abs() {
                     self.position.x = mouse_pos.x;
@@ -760,14 +3732,196 @@ impl Node {
             } else {
                 self.position = mouse_pos;
             }
+            self.anchor = self.position;
         } else {
-            lerp_color_in_place(&mut self.color, WHITE, delta / 0.10);
+            smooth_color_toward(&mut self.color, tint, theme.color_half_life, delta);
             self.dragged_start_pos = self.position;
         };
     }
 }
 
 
+/// How traced lines are colored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum RayColorMode {
+    /// The beam's own color.
+    #[default]
+    Uniform,
+    /// Hue steps around the wheel with every interaction.
+    ByBounceDepth,
+    /// Cold to hot along the path, up to `Laser::MAX_DISTANCE`.
+    ByPathLength,
+    /// Gray levels by remaining intensity.
+    ByIntensity,
+}
+
+impl RayColorMode {
+    pub const ALL: [RayColorMode; 4] = [RayColorMode::Uniform, RayColorMode::ByBounceDepth,
+        RayColorMode::ByPathLength, RayColorMode::ByIntensity];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            RayColorMode::Uniform => "Uniform",
+            RayColorMode::ByBounceDepth => "Bounce depth",
+            RayColorMode::ByPathLength => "Path length",
+            RayColorMode::ByIntensity => "Intensity",
+        }
+    }
+}
+
+/// Whether the region the laser would light as a point light is drawn, see
+/// `core::visibility_polygon`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LightArea {
+    #[default]
+    Off,
+    UnderBeam,
+    InsteadOfBeam,
+}
+
+impl LightArea {
+    pub const ALL: [LightArea; 3] = [LightArea::Off, LightArea::UnderBeam, LightArea::InsteadOfBeam];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            LightArea::Off => "Off",
+            LightArea::UnderBeam => "Under the beam",
+            LightArea::InsteadOfBeam => "Instead of the beam",
+        }
+    }
+}
+
+/// Time-varying emission strength of the laser.
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum Modulation {
+    #[default]
+    Off,
+    /// Smoothly between dark and full, `hz` times a second.
+    Sine { hz: f32 },
+    /// On for the first `duty` fraction of every period, off for the rest.
+    Square { hz: f32, duty: f32 },
+}
+
+impl Modulation {
+    pub const LABELS: [&'static str; 3] = ["Off", "Sine", "Square"];
+
+    /// Emission strength in `[0, 1]` at `time` seconds.
+    pub fn intensity(self, time: f64) -> f32 {
+        match self {
+            Modulation::Off => 1.0,
+            Modulation::Sine { hz } => (0.5 + 0.5 * (std::f64::consts::TAU * hz as f64 * time).sin()) as f32,
+            Modulation::Square { hz, duty } => {
+                let phase = (hz as f64 * time).rem_euclid(1.0);
+                if phase < duty as f64 { 1.0 } else { 0.0 }
+            }
+        }
+    }
+
+    /// Index into `LABELS`.
+    const fn kind(self) -> usize {
+        match self {
+            Modulation::Off => 0,
+            Modulation::Sine { .. } => 1,
+            Modulation::Square { .. } => 2,
+        }
+    }
+
+    /// Frequency and duty cycle, with defaults for what the variant doesn't have.
+    const fn params(self) -> (f32, f32) {
+        match self {
+            Modulation::Off => (1.0, 0.5),
+            Modulation::Sine { hz } => (hz, 0.5),
+            Modulation::Square { hz, duty } => (hz, duty),
+        }
+    }
+
+    const fn from_parts(kind: usize, hz: f32, duty: f32) -> Self {
+        match kind {
+            1 => Modulation::Sine { hz },
+            2 => Modulation::Square { hz, duty },
+            _ => Modulation::Off,
+        }
+    }
+}
+
+/// The beam traced over a fan of angles around the aim and drawn all at once, for caustic
+/// studies, see `Laser::solve_collisions_sweep`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sweep {
+    pub enabled: bool,
+    /// First and last angle off the aim, in degrees.
+    pub start: f32,
+    pub end: f32,
+    /// Angles traced, evenly spaced from `start` to `end`.
+    pub steps: usize,
+    /// Trace one angle per recompute into a buffer kept between frames, so a heavy sweep doesn't
+    /// stall a frame.
+    pub accumulate: bool,
+}
+
+impl Sweep {
+    /// Angle of `step` off the aim, in degrees.
+    pub fn angle(&self, step: usize) -> f32 {
+        if self.steps < 2 { return self.start; }
+        self.start + (self.end - self.start) * step as f32 / (self.steps - 1) as f32
+    }
+
+    /// Color of the lines traced at `step`, going once around the hue wheel over the sweep.
+    pub fn color(&self, step: usize) -> Color {
+        hsl_to_rgb(step as f32 / self.steps.max(1) as f32, 1.0, 0.5)
+    }
+
+    /// Whether the sweep is on and traced a step at a time.
+    pub const fn accumulates(&self) -> bool {
+        self.enabled && self.accumulate
+    }
+
+    fn key(&self) -> Option<(u32, u32, usize)> {
+        self.enabled.then_some((self.start.to_bits(), self.end.to_bits(), self.steps))
+    }
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Self { enabled: false, start: -45.0, end: 45.0, steps: 31, accumulate: false }
+    }
+}
+
+/// An accumulating sweep: the steps traced so far, for the trace key they were traced with.
+#[derive(Clone, Debug, Default)]
+struct SweepAccumulation {
+    key: Option<TraceKey>,
+    traced: usize,
+    result: TraceResult,
+}
+
+/// Bounces it takes the bounce-depth hue to go once around the wheel.
+const BOUNCE_HUE_PERIOD: f32 = 12.0;
+
+/// Color of a line of a beam colored `base`, `bounce` interactions in and `length` along its
+/// path, with `intensity` left. Every mode but `ByIntensity` keeps `intensity` as alpha.
+#[must_use]
+pub fn color_for(mode: RayColorMode, base: Color, bounce: usize, length: f32, intensity: f32) -> Color {
+    let color = match mode {
+        RayColorMode::Uniform => base,
+        RayColorMode::ByBounceDepth => hsl_to_rgb((bounce as f32 / BOUNCE_HUE_PERIOD).fract(), 1.0, 0.5),
+        RayColorMode::ByPathLength => heat_color(length / Laser::MAX_DISTANCE),
+        RayColorMode::ByIntensity => return Color::new(intensity, intensity, intensity, 1.0),
+    };
+    Color { a: intensity, ..color }
+}
+
+/// Replaces what `out` held with `value` of each of `edges`, keeping its capacity.
+fn fill<T>(out: &mut Vec<T>, edges: &[&Edge], value: impl Fn(&Edge) -> T) {
+    out.clear();
+    out.extend(edges.iter().map(|edge| value(edge)));
+}
+
+/// Blue at 0 through green to red at 1.
+pub fn heat_color(t: f32) -> Color {
+    hsl_to_rgb((1.0 - t.clamp(0.0, 1.0)) * 0.66, 1.0, 0.5)
+}
+
 #[inline(always)]
 #[must_use]
 fn lerpf(from: f32, to: f32, t: f32) -> f32 {
@@ -786,12 +3940,27 @@ fn lerp_color(from: Color, to: Color, t: f32) -> Color {
     }
 }
 
+/// Padlock glyph `size` tall centred on `center`, marking a locked edge.
+fn draw_padlock(center: Vec2, size: f32) {
+    let body = vec2(size, size * 0.6);
+    draw_circle_lines(center.x, center.y - size * 0.2, size * 0.3, size * 0.12, LOCK_COLOR);
+    draw_rectangle(center.x - body.x / 2.0, center.y - size * 0.1, body.x, body.y, LOCK_COLOR);
+}
 
-fn lerp_color_in_place(from: &mut Color, to: Color, t: f32) {
-    from.r = lerpf(from.r, to.r, t);
-    from.g = lerpf(from.g, to.g, t);
-    from.b = lerpf(from.b, to.b, t);
-    from.a = lerpf(from.a, to.a, t);
+/// Moves `current` towards `target` so that half the distance is covered every `half_life`
+/// seconds, whatever `dt` the frames take. Never overshoots; a half-life of zero snaps.
+pub fn smooth_toward(current: f32, target: f32, half_life: f32, dt: f32) -> f32 {
+    if half_life <= 0.0 { return target; }
+    let t = (1.0 - 0.5f32.powf(dt.max(0.0) / half_life)).clamp(0.0, 1.0);
+    lerpf(current, target, t)
+}
+
+/// `smooth_toward` for every channel of `color`.
+fn smooth_color_toward(color: &mut Color, target: Color, half_life: f32, dt: f32) {
+    color.r = smooth_toward(color.r, target.r, half_life, dt);
+    color.g = smooth_toward(color.g, target.g, half_life, dt);
+    color.b = smooth_toward(color.b, target.b, half_life, dt);
+    color.a = smooth_toward(color.a, target.a, half_life, dt);
 }
 
 #[inline(always)]
@@ -803,47 +3972,80 @@ pub const fn tuple2vec(vec: Vec2) -> (f32, f32) {
     (vec.x, vec.y)
 }
 
-pub fn rotate(direction: Vec2, angle: f32) -> Vec2 {
-    let cos = angle.cos();
-    let sin = angle.sin();
-    Vec2::new(direction.x * cos - direction.y * sin, direction.x * sin + direction.y * cos)
+/// Corners of the line `start..end` widened to `width`: the left side from start to end, then
+/// the right side back. `None` for a line too short to have a direction.
+pub fn beam_quad(start: Vec2, end: Vec2, width: f32) -> Option<[Vec2; 4]> {
+    let side = (end - start).try_normalize()?.perp() * width / 2.0;
+    Some([start + side, end + side, end - side, start - side])
 }
 
-pub fn reflect(direction: Vec2, normal: Vec2) -> Vec2 {
-    let res = direction - (2.0 * normal * direction.dot(normal));
-    debug_assert!(res.is_normalized(), "res direction not normal: {}, normal is {:?}", res, res.normalize());
-    res.normalize()
+/// Lines per mesh of `line_meshes`, within macroquad's default per-draw-call capacity of 5000
+/// indices.
+pub const LINES_PER_MESH: usize = 800;
+/// Fan triangles per mesh in `fan_meshes`, two vertices and three indices each.
+pub const TRIANGLES_PER_MESH: usize = 1600;
+/// Alpha of the point light's region at the laser, see `Laser::draw_light_area`.
+const LIGHT_AREA_ALPHA: f32 = 0.25;
+/// Fraction of `LIGHT_AREA_ALPHA` left at the edge of the region.
+const LIGHT_AREA_EDGE_FADE: f32 = 0.3;
+
+/// `lines` as solid quads `width` wide, vertex colored, packed into as few meshes as a draw call
+/// takes. Zero-length lines are left out.
+pub fn line_meshes(lines: &[(Vec2, Vec2, Color)], width: f32) -> Vec<Mesh> {
+    let mut meshes: Vec<Mesh> = Vec::new();
+    let quads = lines.iter().filter_map(|&(start, end, color)| Some((beam_quad(start, end, width)?, color)));
+    for (i, (corners, color)) in quads.enumerate() {
+        if i % LINES_PER_MESH == 0 {
+            meshes.push(Mesh { vertices: Vec::with_capacity(LINES_PER_MESH * 4),
+                               indices: Vec::with_capacity(LINES_PER_MESH * 6), texture: None });
+        }
+        let Some(mesh) = meshes.last_mut() else { continue; };
+        let base = mesh.vertices.len() as u16;
+        mesh.vertices.extend(corners.map(|corner|
+            Vertex { position: vec3(corner.x, corner.y, 0.0), uv: Vec2::ZERO, color }));
+        mesh.indices.extend([0, 1, 2, 0, 2, 3].map(|index| base + index));
+    }
+    meshes
 }
 
-pub fn refract(direction: Vec2, normal: Vec2, eta: f32) -> Option<Vec2> {
-    let dot = direction.dot(normal);
-    let k = 1.0 - eta.powi(2) * (1.0 - dot.powi(2));
-    if k < 0.0 { return None; }
-    Some(eta * direction - (eta * dot + k.sqrt()) * normal)
+/// Triangles of a fan from `origin` over the closed `polygon`, colored `color` at the origin
+/// and fading to `LIGHT_AREA_EDGE_FADE` of its alpha at the polygon, packed into as few meshes
+/// as a draw call takes.
+pub fn fan_meshes(origin: Vec2, polygon: &[Vec2], color: Color) -> Vec<Mesh> {
+    let vertex = |point: Vec2, color| Vertex { position: vec3(point.x, point.y, 0.0), uv: Vec2::ZERO, color };
+    let edge_color = Color { a: color.a * LIGHT_AREA_EDGE_FADE, ..color };
+    let mut meshes: Vec<Mesh> = Vec::new();
+    for (i, &corner) in polygon.iter().enumerate() {
+        if i % TRIANGLES_PER_MESH == 0 {
+            meshes.push(Mesh { vertices: vec![vertex(origin, color)],
+                               indices: Vec::with_capacity(TRIANGLES_PER_MESH * 3), texture: None });
+        }
+        let Some(mesh) = meshes.last_mut() else { continue; };
+        let next = polygon[(i + 1) % polygon.len()];
+        let base = mesh.vertices.len() as u16;
+        mesh.vertices.extend([vertex(corner, edge_color), vertex(next, edge_color)]);
+        mesh.indices.extend([0, base, base + 1]);
+    }
+    meshes
 }
 
-pub fn fresnel_reflect_amount(n1: f32, n2: f32, normal: Vec2, incident: Vec2) -> f32
-{
-    // Schlick aproximation
-    let mut r0 = (n1 - n2) / (n1 + n2);
-    r0 *= r0;
-    let mut cos_x = normal.dot(incident);
-    if n1 > n2
-    {
-        let n = n1 / n2;
-        let sin_t2 = n * n * (1.0 - cos_x * cos_x);
-        // Total internal reflection
-        if sin_t2 > 1.0 {
-            return 1.0;
-        }
-        cos_x = (1.0 - sin_t2).sqrt();
+/// One checkbox per bit of the layer bitmask `mask`.
+pub(crate) fn layer_checkboxes(ui: &mut Ui, label: &str, mask: &mut u8) {
+    for bit in 0..8 {
+        let mut on = *mask & (1 << bit) != 0;
+        ui.checkbox(hash!(label, bit), &format!("{} {}", label, bit + 1), &mut on);
+        if on { *mask |= 1 << bit; } else { *mask &= !(1 << bit); }
     }
-    let x = 1.0 - cos_x;
-    let mut ret = r0 + (1.0 - r0) * x * x * x * x * x;
+}
+
+pub fn rotate(direction: Vec2, angle: f32) -> Vec2 {
+    let cos = angle.cos();
+    let sin = angle.sin();
+    Vec2::new(direction.x * cos - direction.y * sin, direction.x * sin + direction.y * cos)
+}
 
-    // adjust reflect multiplier for object reflectivity
-    unsafe { ret = OBJECT_REFLECTIVITY + (1.0 - OBJECT_REFLECTIVITY) * ret; }
-    return ret;
+pub fn fresnel_reflect_amount(n1: f32, n2: f32, normal: Vec2, incident: Vec2) -> f32 {
+    crate::core::fresnel_reflect_amount(n1, n2, normal, incident, unsafe { OBJECT_REFLECTIVITY })
 }
 
 fn point_to_line_distance(point: Vec2, line_start: Vec2, line_end: Vec2) -> f32 {
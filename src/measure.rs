@@ -0,0 +1,42 @@
+// Readouts for the measurement overlay, kept free of any drawing.
+
+use macroquad::math::Vec2;
+
+/// Direction of `to` seen from `from` in degrees, counterclockwise from the positive x axis as it
+/// appears on screen (world y points down), in `[0, 360)`.
+pub fn angle_degrees(from: Vec2, to: Vec2) -> f32 {
+    let diff = to - from;
+    let angle = (-diff.y).atan2(diff.x).to_degrees().rem_euclid(360.0);
+    // rem_euclid can round a tiny negative angle up to exactly 360
+    if angle >= 360.0 { 0.0 } else { angle }
+}
+
+/// Angle between the line through `a` and `b` and the horizontal, in `[0, 180)`. Unlike
+/// `angle_degrees` it doesn't depend on which end comes first.
+pub fn line_angle_degrees(a: Vec2, b: Vec2) -> f32 {
+    let angle = angle_degrees(a, b) % 180.0;
+    if angle >= 180.0 { 0.0 } else { angle }
+}
+
+pub fn format_length(length: f32) -> String {
+    format!("{:.1}", length)
+}
+
+pub fn format_angle(degrees: f32) -> String {
+    // the default font has no degree sign
+    format!("{:.1} deg", degrees)
+}
+
+/// "length @ angle" label for the segment from `from` to `to`.
+pub fn segment_label(from: Vec2, to: Vec2) -> String {
+    format!("{} @ {}", format_length(from.distance(to)), format_angle(angle_degrees(from, to)))
+}
+
+/// Like `segment_label`, for an undirected edge.
+pub fn edge_label(a: Vec2, b: Vec2) -> String {
+    format!("{} @ {}", format_length(a.distance(b)), format_angle(line_angle_degrees(a, b)))
+}
+
+pub fn position_label(id: usize, position: Vec2) -> String {
+    format!("#{} ({:.1}, {:.1})", id, position.x, position.y)
+}
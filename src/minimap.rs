@@ -0,0 +1,110 @@
+// Overview of the whole scene in a corner of the screen with the part the camera shows, for
+// finding the way around a big labyrinth while zoomed in.
+
+use macroquad::color::Color;
+use macroquad::math::{Rect, Vec2};
+use macroquad::shapes::{draw_circle, draw_line, draw_rectangle, draw_rectangle_lines};
+
+use crate::theme;
+use crate::view::{fit_aspect, view_rect};
+use crate::{Laser, NodeNetwork};
+
+/// Side of the square minimap, in screen pixels.
+pub const MINIMAP_SIZE: f32 = 200.0;
+/// Screen pixels between the minimap and the window's edges.
+const MINIMAP_MARGIN: f32 = 10.0;
+/// Room left around the scene, as a fraction of its size.
+const SCENE_MARGIN: f32 = 0.05;
+const VIEWPORT_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.9);
+const LASER_COLOR: Color = Color::new(1.0, 0.3, 0.3, 1.0);
+
+/// Maps world positions into the minimap's rect on screen and back. The world rect is grown
+/// along one axis to the minimap's aspect, so the scene isn't stretched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinimapTransform {
+    world: Rect,
+    map: Rect,
+}
+
+impl MinimapTransform {
+    pub fn new(world: Rect, map: Rect) -> Self {
+        Self { world: fit_aspect(world, map.w / map.h), map }
+    }
+
+    /// The part of the world the minimap shows, `world` as grown by `new`.
+    pub const fn world(&self) -> Rect {
+        self.world
+    }
+
+    pub fn to_map(&self, world: Vec2) -> Vec2 {
+        self.map.point() + (world - self.world.point()) * self.scale()
+    }
+
+    pub fn to_world(&self, map: Vec2) -> Vec2 {
+        self.world.point() + (map - self.map.point()) / self.scale()
+    }
+
+    /// Minimap pixels per world unit.
+    fn scale(&self) -> f32 {
+        self.map.w / self.world.w
+    }
+}
+
+/// The minimap overlay, see `MinimapTransform`.
+#[derive(Debug, Default)]
+pub struct Minimap {
+    pub visible: bool,
+}
+
+impl Minimap {
+    /// Where the minimap sits on a `screen` sized window: the bottom right corner.
+    pub fn rect(screen: Vec2) -> Rect {
+        Rect::new(screen.x - MINIMAP_SIZE - MINIMAP_MARGIN, screen.y - MINIMAP_SIZE - MINIMAP_MARGIN,
+                  MINIMAP_SIZE, MINIMAP_SIZE)
+    }
+
+    /// The mapping for the scene as it is now: the nodes' `bounding_box` and the laser.
+    pub fn transform(network: &NodeNetwork, laser: &Laser, screen: Vec2) -> MinimapTransform {
+        let position = laser.position();
+        let (min, max) = network.bounding_box()
+            .map_or((position, position), |(min, max)| (min.min(position), max.max(position)));
+        let margin = (max - min).max_element() * SCENE_MARGIN;
+        let (min, max) = (min - Vec2::splat(margin), max + Vec2::splat(margin));
+        MinimapTransform::new(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y), Self::rect(screen))
+    }
+
+    /// World position under `mouse` (screen space) if it is over the shown minimap.
+    pub fn world_at(&self, mouse: Vec2, network: &NodeNetwork, laser: &Laser, screen: Vec2) -> Option<Vec2> {
+        if !self.visible || !Self::rect(screen).contains(mouse) { return None; }
+        Some(Self::transform(network, laser, screen).to_world(mouse))
+    }
+
+    /// Draws the edges, the laser and the camera's view of a `screen` sized window. Call in
+    /// screen space.
+    pub fn draw(&self, network: &NodeNetwork, laser: &Laser, camera_target: Vec2, zoom: f32, screen: Vec2) {
+        if !self.visible { return; }
+        let rect = Self::rect(screen);
+        let transform = Self::transform(network, laser, screen);
+        let theme = theme::current();
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color { a: 0.8, ..theme.background });
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, theme.text);
+        let shown = |group: Option<&str>| group.and_then(|group| network.groups().get(group))
+            .is_none_or(|group| group.visible);
+        let edges = network.connections.iter().filter(|edge| shown(edge.group())).filter_map(|edge| {
+            let (a, b) = edge.endpoints();
+            Some((network.node_position(a)?, network.node_position(b)?, edge.state()))
+        });
+        let statics = network.static_geometry().segments.iter().map(|segment| (segment.0, segment.1, segment.2));
+        for (start, end, state) in edges.chain(statics) {
+            let (start, end) = (transform.to_map(start), transform.to_map(end));
+            draw_line(start.x, start.y, end.x, end.y, 1.0, theme.state_color(state));
+        }
+        let dot = transform.to_map(laser.position());
+        draw_circle(dot.x, dot.y, 3.0, LASER_COLOR);
+        let view = view_rect(camera_target, zoom, screen);
+        let (min, max) = (transform.to_map(view.point()), transform.to_map(view.point() + view.size()));
+        if let Some(view) = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y).intersect(rect) {
+            draw_rectangle_lines(view.x, view.y, view.w, view.h, 1.0, VIEWPORT_COLOR);
+        }
+    }
+}
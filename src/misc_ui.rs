@@ -0,0 +1,557 @@
+// The "Misc" window: scene builders, the world border, the background image, the camera and
+// screenshots.
+
+use std::collections::HashSet;
+
+use macroquad::color::Color;
+use macroquad::hash;
+use macroquad::math::{Rect, Vec2, vec2};
+use macroquad::shapes::draw_line;
+use macroquad::ui::{root_ui, widgets};
+use macroquad::window::{screen_height, screen_width};
+
+use crate::background::BackgroundLayer;
+use crate::fragment::ArrayLayout;
+use crate::symmetry::SymmetryAxis;
+use crate::theme::{self, Theme};
+use crate::{labyrinth, layer_checkboxes, new_edge_thickness, presets, replay, tuple2vec, vec2tuple, EdgeState, Laser,
+            NodeNetwork, Segment};
+
+const OPENINGS: [Option<labyrinth::Side>; 5] = [None, Some(labyrinth::Side::Top),
+    Some(labyrinth::Side::Bottom), Some(labyrinth::Side::Left), Some(labyrinth::Side::Right)];
+const OPENING_LABELS: [&str; 5] = ["Closed", "Top", "Bottom", "Left", "Right"];
+const SYMMETRY_LABELS: [&str; 4] = ["Off", "Vertical", "Horizontal", "Measured line"];
+const ARRAY_LABELS: [&str; 2] = ["Linear", "Radial"];
+/// Base name of the group each built labyrinth lands in, numbered when taken.
+const MAZE_GROUP: &str = "maze";
+
+pub(crate) struct MiscUI {
+    lab_position: Vec2,
+    lab_size: Vec2,
+    lab_cell_size: f32,
+    lab_algorithm: usize,
+    lab_entrance: usize,
+    lab_exit: usize,
+    /// Seed for both labyrinth builders, as typed.
+    lab_seed: String,
+    /// Seed of the last labyrinth built, shown so a maze can be rebuilt.
+    lab_last_seed: Option<u64>,
+    circle_position: Vec2,
+    circle_radius: f32,
+    circle_sides: f32,
+    edge_state: EdgeState,
+    edge_combobox: usize,
+    last_labyrinth: Option<(labyrinth::Labyrinth, Vec2)>, // with its world offset
+    lab_solution: Vec<Vec2>,
+    /// Draw the cells of the last labyrinth side by side, see `Labyrinth::debug_draw`.
+    lab_debug: bool,
+    lab_loopiness: f32,
+    lab_animated: bool,
+    /// Build labyrinths as static geometry rather than nodes.
+    lab_static: bool,
+    lab_steps_per_frame: f32,
+    dungeon_rooms: f32,
+    /// Smallest and largest room side in cells.
+    dungeon_room_size: Vec2,
+    lab_animation: Option<LabyrinthAnimation>,
+    polar_rings: f32,
+    polar_sectors: f32,
+    polar_radius: f32,
+    /// Index into `SYMMETRY_LABELS`.
+    symmetry_mode: usize,
+    /// The vertical axis goes through its x, the horizontal one through its y.
+    symmetry_center: Vec2,
+    /// Axis of the "Measured line" mode.
+    symmetry_line: Option<SymmetryAxis>,
+    /// Index into `ARRAY_LABELS`.
+    array_mode: usize,
+    array_copies: f32,
+    array_offset: Vec2,
+    array_center: Vec2,
+    array_rotate: bool,
+    pub(crate) bounded_world: bool,
+    bounds_follow_view: bool,
+    pub(crate) bounds_rect: Rect,
+    bounds_combobox: usize,
+    pub(crate) show_heatmap: bool,
+    pub(crate) screenshot_scale: f32,
+    pub(crate) screenshot_frame: usize,
+    pub(crate) screenshot_requested: bool,
+    pub(crate) camera_command: Option<CameraCommand>,
+    preset_combobox: usize,
+    /// Index into `Theme::PRESETS`. A customized theme is kept until another preset is picked.
+    pub(crate) theme_combobox: usize,
+    pub(crate) absorber_glow: bool,
+    /// Periodic beam recomputes per second.
+    pub(crate) recompute_rate: f32,
+    pub(crate) show_bindings: bool,
+    pub(crate) session_save_requested: bool,
+    pub(crate) session_replay_requested: bool,
+    /// Starts the tutorial, or stops the one running.
+    pub(crate) tutorial_requested: bool,
+    /// File the "Load image" button loads as the background.
+    pub(crate) background_path: String,
+    pub(crate) background_load_requested: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CameraCommand {
+    /// Back to zoom 1 around the original screen centre.
+    Reset,
+    /// Frame every node and the laser.
+    Fit,
+}
+
+/// A labyrinth being carved over several frames, with the walls currently built for it.
+struct LabyrinthAnimation {
+    generator: labyrinth::LabyrinthGenerator,
+    offset: Vec2,
+    edge_state: EdgeState,
+    thickness: f32,
+    loopiness: f32,
+    seed: u64,
+    /// Single cell sides, as `Labyrinth::get_as_lines_explicit` gives them.
+    walls: Vec<labyrinth::Line>,
+    /// Group the walls are built in.
+    group: String,
+}
+
+impl LabyrinthAnimation {
+    /// Brings the built walls to `walls`, touching only those that changed.
+    fn build_walls(&mut self, node_network: &mut NodeNetwork, walls: Vec<labyrinth::Line>) {
+        let (removed, added) = wall_changes(&self.walls, &walls);
+        node_network.edit_lines(&removed, &added, self.offset, self.edge_state, self.thickness, &self.group);
+        self.walls = walls;
+    }
+}
+
+/// Walls in `old` but not in `new`, and in `new` but not in `old`, each once.
+fn wall_changes(old: &[labyrinth::Line], new: &[labyrinth::Line]) -> (Vec<labyrinth::Line>, Vec<labyrinth::Line>) {
+    let key = |&((x1, y1), (x2, y2)): &labyrinth::Line| [x1, y1, x2, y2].map(f32::to_bits);
+    let only_in = |lines: &[labyrinth::Line], other: &[labyrinth::Line]| {
+        let other: HashSet<_> = other.iter().map(key).collect();
+        let mut seen = HashSet::new();
+        lines.iter().filter(|line| !other.contains(&key(line)) && seen.insert(key(line))).copied().collect()
+    };
+    (only_in(old, new), only_in(new, old))
+}
+
+impl MiscUI {
+    pub(crate) fn new() -> Self {
+        Self {
+            lab_position: Vec2::new(0.0, 0.0),
+            lab_size: Vec2::new(10.0, 10.0),
+            lab_cell_size: 50.0,
+            lab_algorithm: 0,
+            lab_entrance: 3,
+            lab_exit: 4,
+            lab_seed: macroquad::rand::rand().to_string(),
+            lab_last_seed: None,
+            circle_position: vec2(screen_width(), screen_height()) / 2.0,
+            circle_radius: 100.0,
+            circle_sides: 20.0,
+            edge_state: EdgeState::Reflective,
+            edge_combobox: 0,
+            last_labyrinth: None,
+            lab_solution: Vec::new(),
+            lab_debug: false,
+            lab_loopiness: 0.0,
+            lab_animated: false,
+            lab_static: false,
+            lab_steps_per_frame: 1.0,
+            dungeon_rooms: 6.0,
+            dungeon_room_size: vec2(2.0, 5.0),
+            lab_animation: None,
+            polar_rings: 6.0,
+            polar_sectors: 16.0,
+            polar_radius: 50.0,
+            symmetry_mode: 0,
+            symmetry_center: vec2(screen_width(), screen_height()) / 2.0,
+            symmetry_line: None,
+            array_mode: 0,
+            array_copies: 3.0,
+            array_offset: vec2(100.0, 0.0),
+            array_center: vec2(screen_width(), screen_height()) / 2.0,
+            array_rotate: true,
+            bounded_world: true,
+            bounds_follow_view: false,
+            bounds_rect: Rect::new(0.0, 0.0, screen_width(), screen_height()),
+            bounds_combobox: 1,
+            show_heatmap: false,
+            screenshot_scale: 1.0,
+            screenshot_frame: 0,
+            screenshot_requested: false,
+            camera_command: None,
+            preset_combobox: 0,
+            theme_combobox: 0,
+            absorber_glow: true,
+            recompute_rate: 60.0,
+            show_bindings: false,
+            session_save_requested: false,
+            session_replay_requested: false,
+            tutorial_requested: false,
+            background_path: String::new(),
+            background_load_requested: false,
+        }
+    }
+    pub(crate) fn ui(&mut self, node_network: &mut NodeNetwork, laser: &mut Laser,
+                     background: &mut Option<BackgroundLayer>) {
+        widgets::Window::new(hash!(), Vec2::new(400., 0.), Vec2::new(300., 400.))
+            .label("Misc")
+            .ui(&mut root_ui(), |ui| {
+                // the builders make their walls as thick as hand-drawn edges
+                let thickness = new_edge_thickness();
+                let preset_names: Vec<&str> = presets::all().iter().map(|(name, _)| *name).collect();
+                ui.combo_box(hash!(), "Preset", &preset_names, &mut self.preset_combobox);
+                if ui.button(None, "Load preset") {
+                    self.load_preset(node_network, laser);
+                }
+                ui.separator();
+                ui.label(None, "Labyrinth (pos in top left)");
+                ui.slider(hash!(), "lab x",
+                          0.0f32..screen_width(), &mut self.lab_position.x);
+                ui.slider(hash!(), "lab y",
+                          0.0f32..screen_height(), &mut self.lab_position.y);
+                ui.slider(hash!(), "width in cells",
+                          1.0f32..screen_height(), &mut self.lab_size.x);
+                ui.slider(hash!(), "height in cells",
+                          1.0f32..screen_height(), &mut self.lab_size.y);
+                ui.slider(hash!(), "cell size", 0.0f32..100.0, &mut self.lab_cell_size);
+                let algorithms = labyrinth::Algorithm::ALL.map(labyrinth::Algorithm::label);
+                ui.combo_box(hash!(), "Algorithm", &algorithms, &mut self.lab_algorithm);
+                ui.combo_box(hash!(), "Entrance", &OPENING_LABELS, &mut self.lab_entrance);
+                ui.combo_box(hash!(), "Exit", &OPENING_LABELS, &mut self.lab_exit);
+                ui.slider(hash!(), "loopiness", 0.0f32..1.0, &mut self.lab_loopiness);
+                ui.checkbox(hash!(), "animated build", &mut self.lab_animated);
+                ui.checkbox(hash!(), "as static geometry", &mut self.lab_static);
+                ui.slider(hash!(), "steps per frame", 1.0f32..50.0, &mut self.lab_steps_per_frame);
+                self.lab_steps_per_frame = self.lab_steps_per_frame.round();
+                self.lab_size = self.lab_size.round().max(Vec2::ONE);
+                ui.input_text(hash!(), "seed", &mut self.lab_seed);
+                if ui.button(None, "Randomize seed") {
+                    self.lab_seed = macroquad::rand::rand().to_string();
+                }
+                let seed = self.lab_seed.trim().parse::<u64>().ok();
+                match (seed, self.lab_last_seed) {
+                    (None, _) => ui.label(None, "seed must be a whole number"),
+                    (Some(_), Some(last)) => ui.label(None, &format!("last built with seed {}", last)),
+                    (Some(_), None) => {}
+                }
+                if let (true, Some(seed)) = (ui.button(None, "Build Labyrinth"), seed) {
+                    let spec = labyrinth::LabyrinthSpec {
+                        cell_size: self.lab_cell_size,
+                        size: (self.lab_size.x as usize, self.lab_size.y as usize),
+                        algorithm: labyrinth::Algorithm::ALL[self.lab_algorithm],
+                        entrance: OPENINGS[self.lab_entrance],
+                        exit: OPENINGS[self.lab_exit],
+                        loopiness: self.lab_loopiness,
+                        seed,
+                    };
+                    self.lab_last_seed = Some(seed);
+                    self.lab_solution.clear();
+                    if self.lab_static {
+                        let labyrinth = node_network.add_static_labyrinth(&spec, self.lab_position, self.edge_state);
+                        self.last_labyrinth = Some((labyrinth, self.lab_position));
+                    } else if self.lab_animated && spec.algorithm == labyrinth::Algorithm::DepthFirst {
+                        self.finish_animation(node_network);
+                        let generator = spec.layout().generator_depth_first(seed);
+                        let group = node_network.unused_group_name(MAZE_GROUP);
+                        node_network.add_lines(&generator.labyrinth().get_as_lines(), self.lab_position,
+                                               self.edge_state, thickness, Some(&group));
+                        let walls = generator.labyrinth().get_as_lines_explicit();
+                        self.lab_animation = Some(LabyrinthAnimation {
+                            generator,
+                            offset: self.lab_position,
+                            edge_state: self.edge_state,
+                            thickness,
+                            loopiness: self.lab_loopiness,
+                            seed,
+                            walls,
+                            group,
+                        });
+                    } else {
+                        let group = node_network.unused_group_name(MAZE_GROUP);
+                        let labyrinth = node_network.add_labyrinth(&spec, self.lab_position, self.edge_state,
+                                                                   thickness, Some(&group));
+                        self.last_labyrinth = Some((labyrinth, self.lab_position));
+                    }
+                };
+                ui.label(None, "Dungeon (lab position, size and seed)");
+                ui.slider(hash!(), "rooms", 1.0f32..40.0, &mut self.dungeon_rooms);
+                ui.slider(hash!(), "room min", 1.0f32..20.0, &mut self.dungeon_room_size.x);
+                ui.slider(hash!(), "room max", 1.0f32..20.0, &mut self.dungeon_room_size.y);
+                self.dungeon_rooms = self.dungeon_rooms.round();
+                self.dungeon_room_size = self.dungeon_room_size.round();
+                self.dungeon_room_size.y = self.dungeon_room_size.y.max(self.dungeon_room_size.x);
+                if let (true, Some(seed)) = (ui.button(None, "Build Dungeon"), seed) {
+                    let mut labyrinth = labyrinth::Labyrinth::new(
+                        self.lab_cell_size, (self.lab_size.x as usize, self.lab_size.y as usize));
+                    labyrinth.carve_rooms(self.dungeon_rooms as usize, self.dungeon_room_size.x as usize,
+                                          self.dungeon_room_size.y as usize, seed);
+                    if self.lab_static {
+                        let segments: Vec<Segment> = labyrinth.get_as_lines().into_iter()
+                            .map(|(start, end)| Segment(self.lab_position + vec2tuple(start),
+                                                        self.lab_position + vec2tuple(end), self.edge_state))
+                            .collect();
+                        node_network.add_static_segments(&segments);
+                    } else {
+                        let group = node_network.unused_group_name(MAZE_GROUP);
+                        node_network.add_lines(&labyrinth.get_as_lines(), self.lab_position, self.edge_state,
+                                               thickness, Some(&group));
+                    }
+                    self.lab_last_seed = Some(seed);
+                    self.lab_solution.clear();
+                    self.last_labyrinth = Some((labyrinth, self.lab_position));
+                }
+                ui.label(None, "Polar labyrinth (pos in center)");
+                ui.slider(hash!(), "rings", 1.0f32..30.0, &mut self.polar_rings);
+                ui.slider(hash!(), "sectors", 1.0f32..64.0, &mut self.polar_sectors);
+                // at least a cell across, or the first ring's walls pile up on the centre
+                let min_radius = self.lab_cell_size.max(1.0);
+                ui.slider(hash!(), "inner radius", min_radius..500.0, &mut self.polar_radius);
+                self.polar_radius = self.polar_radius.max(min_radius);
+                self.polar_rings = self.polar_rings.round();
+                self.polar_sectors = self.polar_sectors.round();
+                if let (true, Some(seed)) = (ui.button(None, "Build Polar Labyrinth"), seed) {
+                    let sectors = self.polar_sectors as usize;
+                    let mut labyrinth = labyrinth::PolarLabyrinth::new(
+                        self.lab_cell_size, self.polar_rings as usize, sectors);
+                    labyrinth.generate_depth_first(seed);
+                    self.lab_last_seed = Some(seed);
+                    labyrinth.open_center(0);
+                    labyrinth.open_rim(sectors / 2);
+                    let group = node_network.unused_group_name(MAZE_GROUP);
+                    node_network.add_lines(&labyrinth.get_as_lines(tuple2vec(self.lab_position), self.polar_radius),
+                                   Vec2::ZERO, self.edge_state, thickness, Some(&group));
+                }
+                if ui.button(None, "Static geometry to nodes") {
+                    node_network.static_to_nodes();
+                }
+                if ui.button(None, "Selected structure to static") {
+                    node_network.nodes_to_static(&node_network.selected_structure());
+                }
+                if self.last_labyrinth.is_some() {
+                    ui.checkbox(hash!(), "debug draw cells", &mut self.lab_debug);
+                }
+                if ui.button(None, "Solve") {
+                    self.solve_last_labyrinth();
+                }
+                if ui.button(None, "Aim laser at entrance") {
+                    if self.lab_solution.is_empty() { self.solve_last_labyrinth(); }
+                    if let Some(entrance) = self.lab_solution.first() {
+                        laser.look_at(*entrance);
+                    }
+                }
+                ui.separator();
+                ui.label(None, "Circle (pos in center)");
+                ui.slider(hash!(), "circle x", 0.0f32..screen_width(), &mut self.circle_position.x);
+                ui.slider(hash!(), "circle y", 0.0f32..screen_height(), &mut self.circle_position.y);
+                ui.slider(hash!(), "circle radius", 0.0f32..screen_height(), &mut self.circle_radius);
+                ui.slider(hash!(), "circle sides", 1.0f32..1000.0f32, &mut self.circle_sides);
+                self.circle_sides = self.circle_sides.round();
+                if ui.button(None, "Draw Circle") {
+                    node_network.add_circle(self.circle_position, self.circle_radius, self.edge_state,
+                                            self.circle_sides as usize, thickness, None);
+                };
+                ui.separator();
+                ui.combo_box(hash!(), "Symmetry", &SYMMETRY_LABELS, &mut self.symmetry_mode);
+                ui.slider(hash!(), "axis x", 0.0f32..screen_width(), &mut self.symmetry_center.x);
+                ui.slider(hash!(), "axis y", 0.0f32..screen_height(), &mut self.symmetry_center.y);
+                if ui.button(None, "Axis through measured points") {
+                    if let Some((a, b)) = node_network.measured_points() {
+                        self.symmetry_line = SymmetryAxis::through(a, b);
+                        self.symmetry_mode = 3;
+                    }
+                }
+                ui.separator();
+                ui.label(None, "Array (copies the selected structure)");
+                ui.combo_box(hash!(), "Array", &ARRAY_LABELS, &mut self.array_mode);
+                ui.slider(hash!(), "copies", 1.0f32..60.0, &mut self.array_copies);
+                self.array_copies = self.array_copies.round();
+                ui.slider(hash!(), "offset x", -500.0f32..500.0, &mut self.array_offset.x);
+                ui.slider(hash!(), "offset y", -500.0f32..500.0, &mut self.array_offset.y);
+                ui.slider(hash!(), "center x", 0.0f32..screen_width(), &mut self.array_center.x);
+                ui.slider(hash!(), "center y", 0.0f32..screen_height(), &mut self.array_center.y);
+                ui.checkbox(hash!(), "rotate to face center", &mut self.array_rotate);
+                if ui.button(None, "Array selected structure") {
+                    let copies = self.array_copies as usize;
+                    let layout = match self.array_mode {
+                        0 => ArrayLayout::Linear { offset: self.array_offset, copies },
+                        _ => ArrayLayout::Radial { center: self.array_center, copies, rotate: self.array_rotate },
+                    };
+                    node_network.add_array(&node_network.selected_structure(), layout);
+                }
+                ui.separator();
+                ui.label(None, "Background image");
+                ui.input_text(hash!(), "image file", &mut self.background_path);
+                if ui.button(None, "Load image") {
+                    self.background_load_requested = true;
+                }
+                let mut remove_background = false;
+                if let Some(background) = background.as_mut() {
+                    let transform = &mut background.transform;
+                    ui.drag(hash!(), "image x", None, &mut transform.position.x);
+                    ui.drag(hash!(), "image y", None, &mut transform.position.y);
+                    ui.slider(hash!(), "image scale", 0.05f32..10.0, &mut transform.scale);
+                    let mut degrees = transform.rotation.to_degrees();
+                    ui.slider(hash!(), "image rotation", -180.0f32..180.0, &mut degrees);
+                    transform.rotation = degrees.to_radians();
+                    ui.slider(hash!(), "image opacity", 0.0f32..1.0, &mut background.opacity);
+                    ui.checkbox(hash!(), "lock image", &mut background.locked);
+                    remove_background = ui.button(None, "Remove image");
+                }
+                if remove_background { *background = None; }
+                ui.separator();
+                ui.label(None, "Layers (edges set theirs in the edge inspector)");
+                layer_checkboxes(ui, "beam hits layer", &mut laser.collision_mask);
+                ui.separator();
+                ui.checkbox(hash!(), "Bounded world", &mut self.bounded_world);
+                ui.checkbox(hash!(), "border follows view", &mut self.bounds_follow_view);
+                ui.slider(hash!(), "border x", -screen_width()..screen_width(), &mut self.bounds_rect.x);
+                ui.slider(hash!(), "border y", -screen_height()..screen_height(), &mut self.bounds_rect.y);
+                ui.slider(hash!(), "border w", 1.0f32..screen_width() * 4.0, &mut self.bounds_rect.w);
+                ui.slider(hash!(), "border h", 1.0f32..screen_height() * 4.0, &mut self.bounds_rect.h);
+                ui.combo_box(hash!(), "Border type", &EdgeState::ALL.map(EdgeState::label), &mut self.bounds_combobox);
+                ui.checkbox(hash!(), "illumination heatmap", &mut self.show_heatmap);
+                ui.checkbox(hash!(), "absorber glow", &mut self.absorber_glow);
+                let theme_names: Vec<&str> = Theme::PRESETS.iter().map(|(name, _)| *name).collect();
+                let picked = self.theme_combobox;
+                ui.combo_box(hash!(), "Theme", &theme_names, &mut self.theme_combobox);
+                if self.theme_combobox != picked { theme::set(Theme::PRESETS[self.theme_combobox].1); }
+                let mut theme = theme::current();
+                ui.checkbox(hash!(), "edge patterns", &mut theme.edge_patterns);
+                if theme.edge_patterns { ui.slider(hash!(), "dash length", 2.0f32..50.0, &mut theme.dash_length); }
+                if theme != theme::current() { theme::set(theme); }
+                ui.checkbox(hash!(), "key bindings", &mut self.show_bindings);
+                ui.slider(hash!(), "recompute rate (/s)", 1.0f32..240.0, &mut self.recompute_rate);
+                ui.slider(hash!(), "screenshot scale", 1.0f32..4.0, &mut self.screenshot_scale);
+                ui.combo_box(hash!(), "Frame", &["Current view", "All nodes"], &mut self.screenshot_frame);
+                if ui.button(None, "Screenshot (F12)") {
+                    self.screenshot_requested = true;
+                }
+                if ui.button(None, "Reset camera (Home)") {
+                    self.camera_command = Some(CameraCommand::Reset);
+                }
+                if ui.button(None, "Fit to scene") {
+                    self.camera_command = Some(CameraCommand::Fit);
+                }
+                if ui.button(None, "Save session") {
+                    self.session_save_requested = true;
+                }
+                if ui.button(None, "Replay session") {
+                    self.session_replay_requested = true;
+                }
+                if ui.button(None, "Start/stop tutorial") {
+                    self.tutorial_requested = true;
+                }
+                if ui.button(None, "Delete all nodes") {
+                    node_network.clean(self.bounded_world);
+                };
+                if ui.button(None, "Pin all nodes") {
+                    node_network.pin_all(true);
+                }
+                if ui.button(None, "Unpin all nodes") {
+                    node_network.pin_all(false);
+                }
+                ui.combo_box(hash!(), "Edge type", &EdgeState::ALL.map(EdgeState::label), &mut self.edge_combobox);
+                self.edge_state = EdgeState::ALL[self.edge_combobox];
+                node_network.chain_state = self.edge_state;
+                let mut chain_mode = node_network.chain_mode();
+                ui.checkbox(hash!(), "chain mode (walls in edge type)", &mut chain_mode);
+                if chain_mode != node_network.chain_mode() { node_network.set_chain_mode(chain_mode); }
+                ui.checkbox(hash!(), "split edges at crossings", &mut node_network.split_crossings);
+                if ui.button(None, format!("Set all edges to {}", self.edge_state.label()).as_str()) {
+                    node_network.set_all_states(self.edge_state);
+                }
+            });
+    }
+
+    /// Replaces the scene with the selected preset and frames it.
+    fn load_preset(&mut self, node_network: &mut NodeNetwork, laser: &mut Laser) {
+        self.forget_scene();
+        node_network.clean(self.bounded_world);
+        let (name, _) = presets::all()[self.preset_combobox];
+        replay::load_preset(name, node_network, laser);
+        self.camera_command = Some(CameraCommand::Fit);
+    }
+
+    /// Drops everything kept about the labyrinths built so far, for when the scene is replaced.
+    pub(crate) fn forget_scene(&mut self) {
+        self.lab_animation = None;
+        self.last_labyrinth = None;
+        self.lab_solution.clear();
+    }
+
+    /// Keeps the world border and the symmetry axis in sync with the settings; `view` is the
+    /// world rect on screen.
+    pub(crate) fn update(&mut self, node_network: &mut NodeNetwork, view: Rect) {
+        if self.bounded_world {
+            let rect = if self.bounds_follow_view { view } else { self.bounds_rect };
+            node_network.add_bounds(rect, EdgeState::ALL[self.bounds_combobox]);
+        } else {
+            node_network.remove_bounds();
+        }
+        node_network.set_symmetry(match self.symmetry_mode {
+            1 => Some(SymmetryAxis::vertical(self.symmetry_center.x)),
+            2 => Some(SymmetryAxis::horizontal(self.symmetry_center.y)),
+            3 => self.symmetry_line,
+            _ => None,
+        });
+        self.update_animation(node_network);
+    }
+
+    /// Advances an animated labyrinth build and edits the walls it carved or added since the
+    /// last step.
+    fn update_animation(&mut self, node_network: &mut NodeNetwork) {
+        let Some(animation) = &mut self.lab_animation else { return; };
+        let mut finished = animation.generator.is_finished();
+        for _ in 0..self.lab_steps_per_frame as usize {
+            finished = animation.generator.step();
+            if finished { break; }
+        }
+        if !finished {
+            animation.build_walls(node_network, animation.generator.labyrinth().get_as_lines_explicit());
+            return;
+        }
+        let Some(LabyrinthAnimation { generator, offset, edge_state, thickness, loopiness, seed, walls, group })
+            = self.lab_animation.take() else { return; };
+        let mut labyrinth = generator.into_labyrinth();
+        labyrinth.braid(loopiness, seed);
+        let (removed, added) = wall_changes(&walls, &labyrinth.get_as_lines_explicit());
+        node_network.edit_lines(&removed, &added, offset, edge_state, thickness, &group);
+        self.last_labyrinth = Some((labyrinth, offset));
+    }
+
+    /// Completes a running animated build immediately.
+    fn finish_animation(&mut self, node_network: &mut NodeNetwork) {
+        if let Some(animation) = &mut self.lab_animation {
+            while !animation.generator.step() {}
+            self.update_animation(node_network);
+        }
+    }
+
+    fn solve_last_labyrinth(&mut self) {
+        self.lab_solution.clear();
+        let Some((labyrinth, offset)) = &self.last_labyrinth else { return; };
+        let (width, height) = labyrinth.size;
+        let start = labyrinth.entrance.unwrap_or((0, 0));
+        let end = labyrinth.exit.unwrap_or((width - 1, height - 1));
+        if let Some(path) = labyrinth.solve(start, end) {
+            self.lab_solution = labyrinth::Labyrinth::path_as_polyline(&path, labyrinth.cell_size, tuple2vec(*offset))
+                .into_iter().map(vec2tuple).collect();
+        }
+    }
+
+    /// World-space overlays, drawn under the active camera.
+    pub(crate) fn draw(&self) {
+        for pair in self.lab_solution.windows(2) {
+            draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, 3.0, SOLUTION_COLOR);
+        }
+        if let Some((labyrinth, offset)) = self.last_labyrinth.as_ref().filter(|_| self.lab_debug) {
+            let mouse = crate::mouse_position();
+            labyrinth.debug_draw(tuple2vec(*offset), labyrinth.cell_at((mouse.0 - offset.x, mouse.1 - offset.y)));
+        }
+    }
+}
+
+const SOLUTION_COLOR: Color = Color::new(1.0, 0.8, 0.0, 0.8);
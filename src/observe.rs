@@ -0,0 +1,80 @@
+// Changes to a network's nodes and edges, queued for an app embedding the sandbox to react to,
+// say by syncing them to a server. Lower level than the replay events: a built labyrinth comes
+// out as every node and edge it adds.
+
+use macroquad::math::Vec2;
+
+use crate::core::EdgeState;
+use crate::Edge;
+
+/// One change to a `NodeNetwork`. Edge indices are into `connections` as it was right then: an
+/// added edge shifts the ones from its index on up by one, a removed edge those after it down.
+/// New edges start out in `EdgeState::default()`; any other state follows as an
+/// `EdgeStateChanged`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetworkEvent {
+    NodeAdded { id: usize, pos: Vec2 },
+    /// A drag is one move, reported when the node is dropped. Animations aren't reported.
+    NodeMoved { id: usize, from: Vec2, to: Vec2 },
+    /// Comes after the removal of the node's edges.
+    NodeRemoved { id: usize },
+    EdgeAdded { index: usize, a: usize, b: usize },
+    /// The state of the edge's front face.
+    EdgeStateChanged { index: usize, old: EdgeState, new: EdgeState },
+    EdgeRemoved { index: usize },
+}
+
+/// `NetworkEvent`s queued while the network is observed, until they are taken.
+#[derive(Debug, Default)]
+pub(crate) struct EventQueue {
+    events: Option<Vec<NetworkEvent>>,
+}
+
+impl EventQueue {
+    pub(crate) fn set_observed(&mut self, observed: bool) {
+        if observed != self.events.is_some() {
+            self.events = observed.then(Vec::new);
+        }
+    }
+
+    pub(crate) const fn is_observed(&self) -> bool {
+        self.events.is_some()
+    }
+
+    /// `event` is only built when it is going to be kept.
+    pub(crate) fn push(&mut self, event: impl FnOnce() -> NetworkEvent) {
+        if let Some(events) = &mut self.events { events.push(event()); }
+    }
+
+    /// Reports `edge` as added at `index`, followed by its state unless that is the default.
+    pub(crate) fn edge_added(&mut self, index: usize, edge: &Edge) {
+        let (a, b, state) = (edge.a, edge.b, edge.state);
+        self.push(|| NetworkEvent::EdgeAdded { index, a, b });
+        if state != EdgeState::default() {
+            self.push(|| NetworkEvent::EdgeStateChanged { index, old: EdgeState::default(), new: state });
+        }
+    }
+
+    /// Reports a state change of the edge at `index`, if it is one.
+    pub(crate) fn edge_restated(&mut self, index: usize, old: EdgeState, new: EdgeState) {
+        if old != new { self.push(|| NetworkEvent::EdgeStateChanged { index, old, new }); }
+    }
+
+    /// `Vec::retain` on `edges`, reporting the removed ones last to first so every index is
+    /// still right when its event is applied in order.
+    pub(crate) fn retain_edges(&mut self, edges: &mut Vec<Edge>, mut keep: impl FnMut(&Edge) -> bool) {
+        let mut index = 0;
+        let mut removed = Vec::new();
+        edges.retain(|edge| {
+            let kept = keep(edge);
+            if !kept { removed.push(index); }
+            index += 1;
+            kept
+        });
+        for index in removed.into_iter().rev() { self.push(|| NetworkEvent::EdgeRemoved { index }); }
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<NetworkEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
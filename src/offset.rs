@@ -0,0 +1,36 @@
+// Offsets typed in for placing a node connected to the selected one, instead of clicking.
+
+use macroquad::math::{vec2, Vec2};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Offset {
+    Cartesian { dx: f32, dy: f32 },
+    /// `angle` in degrees from the positive x axis, clockwise on screen like the laser's
+    /// rotation.
+    Polar { length: f32, angle: f32 },
+}
+
+impl Offset {
+    /// How the two fields are read, in the order `parse` takes `kind`.
+    pub const LABELS: [&'static str; 2] = ["dx, dy", "length, angle"];
+
+    /// The offset typed as `first` and `second` in the `kind` of `LABELS`. `None` unless both
+    /// are finite numbers.
+    pub fn parse(kind: usize, first: &str, second: &str) -> Option<Self> {
+        let [first, second] = [first, second]
+            .map(|text| text.trim().parse::<f32>().ok().filter(|value| value.is_finite()));
+        let (first, second) = (first?, second?);
+        Some(match kind {
+            1 => Offset::Polar { length: first, angle: second },
+            _ => Offset::Cartesian { dx: first, dy: second },
+        })
+    }
+
+    /// The offset in world units.
+    pub fn vector(self) -> Vec2 {
+        match self {
+            Offset::Cartesian { dx, dy } => vec2(dx, dy),
+            Offset::Polar { length, angle } => Vec2::from_angle(angle.to_radians()) * length,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+// The F5 outliner: every named group of the scene, shown or hidden, traced or not, selected or
+// deleted from one list.
+
+use macroquad::hash;
+use macroquad::math::Vec2;
+use macroquad::ui::{root_ui, widgets};
+
+use crate::NodeNetwork;
+
+/// What a click in the outliner asked for, applied once the window is done drawing.
+enum GroupAction {
+    Visible(String, bool),
+    Collides(String, bool),
+    Select(String),
+    Delete(String),
+    /// Puts the selected structure in the named group.
+    Assign(String),
+}
+
+/// Window listing the network's groups, see `NodeNetwork::assign_group`.
+#[derive(Default)]
+pub struct Outliner {
+    pub visible: bool,
+    /// Group the selected structure goes into.
+    name: String,
+}
+
+impl Outliner {
+    pub fn ui(&mut self, network: &mut NodeNetwork) {
+        if !self.visible { return; }
+        let mut actions = Vec::new();
+        widgets::Window::new(hash!(), Vec2::new(940., 310.), Vec2::new(280., 300.))
+            .label("Outliner")
+            .ui(&mut root_ui(), |ui| {
+                if network.groups().is_empty() { ui.label(None, "no groups"); }
+                for (name, group) in network.groups() {
+                    let selected = network.selected_group() == Some(name.as_str());
+                    let members = network.group_members(name).len();
+                    ui.label(None, &format!("{}{} ({} nodes)", if selected { "> " } else { "" }, name, members));
+                    let (mut visible, mut collides) = (group.visible, group.collides);
+                    ui.checkbox(hash!(name, "visible"), "visible", &mut visible);
+                    ui.same_line(0.0);
+                    ui.checkbox(hash!(name, "collides"), "collides", &mut collides);
+                    if visible != group.visible { actions.push(GroupAction::Visible(name.clone(), visible)); }
+                    if collides != group.collides { actions.push(GroupAction::Collides(name.clone(), collides)); }
+                    if ui.button(None, format!("Select {}", name)) { actions.push(GroupAction::Select(name.clone())); }
+                    ui.same_line(0.0);
+                    if ui.button(None, format!("Delete {}", name)) { actions.push(GroupAction::Delete(name.clone())); }
+                    ui.separator();
+                }
+                ui.input_text(hash!(), "group", &mut self.name);
+                if ui.button(None, "Group selected structure") && !self.name.trim().is_empty() {
+                    actions.push(GroupAction::Assign(self.name.trim().to_string()));
+                }
+            });
+        for action in actions {
+            match action {
+                GroupAction::Visible(group, visible) => network.set_group_visible(&group, visible),
+                GroupAction::Collides(group, collides) => network.set_group_collides(&group, collides),
+                GroupAction::Select(group) => network.select_group(Some(&group)),
+                GroupAction::Delete(group) => network.delete_group(&group),
+                GroupAction::Assign(group) => network.assign_group(&network.selected_structure(), Some(&group)),
+            }
+        }
+    }
+}
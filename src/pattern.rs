@@ -0,0 +1,38 @@
+// Dash and hatch patterns laid over edges, so edge states tell apart by more than their color.
+// Only the geometry lives here; `Edge::draw` draws the pieces.
+
+use macroquad::math::Vec2;
+
+/// Most pieces one pattern is cut into, however long the edge or short the pattern.
+const MAX_PIECES: usize = 1000;
+
+/// The dashes of `start..end` dashed with `dash` long dashes `gap` apart. Both ends land on a
+/// dash: the dashes and gaps are stretched or squeezed a little so a whole number fits. An edge
+/// no longer than one dash, or a pattern with no dash or gap, is a single dash.
+pub fn dashes(start: Vec2, end: Vec2, dash: f32, gap: f32) -> Vec<(Vec2, Vec2)> {
+    let length = start.distance(end);
+    if length <= dash || dash <= 0.0 || gap <= 0.0 { return vec![(start, end)]; }
+    let count = (((length + gap) / (dash + gap)).round() as usize).clamp(1, MAX_PIECES);
+    let scale = length / (count as f32 * dash + (count - 1) as f32 * gap);
+    let (dash, period) = (dash * scale, (dash + gap) * scale);
+    let direction = (end - start) / length;
+    (0..count).map(|i| {
+        let from = i as f32 * period;
+        (start + direction * from, start + direction * (from + dash).min(length))
+    }).collect()
+}
+
+/// Short diagonal strokes across `start..end`, `width` long and about `spacing` apart, spread
+/// evenly with half a spacing left at either end. At least one, in the middle.
+pub fn hatches(start: Vec2, end: Vec2, spacing: f32, width: f32) -> Vec<(Vec2, Vec2)> {
+    let length = start.distance(end);
+    let Some(direction) = (end - start).try_normalize() else { return Vec::new(); };
+    let count = if spacing > 0.0 { ((length / spacing) as usize).clamp(1, MAX_PIECES) } else { 1 };
+    let step = length / count as f32;
+    // leaning 45 degrees along the edge
+    let stroke = (direction + direction.perp()).normalize() * width / 2.0;
+    (0..count).map(|i| {
+        let center = start + direction * step * (i as f32 + 0.5);
+        (center - stroke, center + stroke)
+    }).collect()
+}
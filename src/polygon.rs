@@ -0,0 +1,109 @@
+// Simple polygons given as their corners in order: containment, area and triangulation, for
+// filling the closed loops of a network.
+
+use macroquad::math::Vec2;
+
+/// Whether `p` is inside `polygon`, by the even-odd rule. Points on the outline may go either
+/// way.
+pub fn point_in_polygon(p: Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    let mut previous = match polygon.last() { Some(&last) => last, None => return false };
+    for &corner in polygon {
+        // edges straddling the horizontal through `p`, crossed to the right of it
+        if (corner.y > p.y) != (previous.y > p.y)
+            && p.x < corner.x + (p.y - corner.y) * (previous.x - corner.x) / (previous.y - corner.y) {
+            inside = !inside;
+        }
+        previous = corner;
+    }
+    inside
+}
+
+/// Area of `polygon`, positive when its corners go clockwise on screen (y down).
+pub fn signed_area(polygon: &[Vec2]) -> f32 {
+    let Some(&last) = polygon.last() else { return 0.0; };
+    let mut previous = last;
+    let mut twice = 0.0;
+    for &corner in polygon {
+        twice += previous.perp_dot(corner);
+        previous = corner;
+    }
+    twice / 2.0
+}
+
+/// Whether no two edges of `polygon` cross or touch, other than neighbours at their shared
+/// corner.
+pub fn is_simple(polygon: &[Vec2]) -> bool {
+    let n = polygon.len();
+    if n < 3 { return false; }
+    let edge = |i: usize| (polygon[i], polygon[(i + 1) % n]);
+    for i in 0..n {
+        let (a, b) = edge(i);
+        if a == b { return false; }
+        // the neighbours only share a corner, unless one doubles back along the other
+        let (_, after) = edge((i + 1) % n);
+        if (b - a).perp_dot(after - b) == 0.0 && (b - a).dot(after - b) < 0.0 { return false; }
+        for j in i + 2..n {
+            if i == 0 && j == n - 1 { continue; }
+            let (c, d) = edge(j);
+            if segments_touch(a, b, c, d) { return false; }
+        }
+    }
+    true
+}
+
+/// Triangles covering `polygon`, as indices into it wound the same way, by ear clipping. `None`
+/// for polygons with no area or whose edges cross.
+pub fn triangulate(polygon: &[Vec2]) -> Option<Vec<[usize; 3]>> {
+    let area = signed_area(polygon);
+    if area.abs() <= f32::EPSILON * bounds_area(polygon) || !is_simple(polygon) { return None; }
+    // clipped clockwise, and turned back afterwards
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    if area < 0.0 { remaining.reverse(); }
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find_map(|i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let turn = (polygon[b] - polygon[a]).perp_dot(polygon[c] - polygon[b]);
+            // a corner on a straight line is dropped without a triangle, it covers nothing
+            if turn == 0.0 { return Some((i, None)); }
+            let blocked = turn < 0.0 || remaining.iter()
+                .filter(|&&other| other != a && other != b && other != c)
+                .any(|&other| in_triangle(polygon[other], polygon[a], polygon[b], polygon[c]));
+            (!blocked).then_some((i, Some([a, b, c])))
+        })?;
+        triangles.extend(ear.1);
+        remaining.remove(ear.0);
+    }
+    let (a, b, c) = (remaining[0], remaining[1], remaining[2]);
+    if (polygon[b] - polygon[a]).perp_dot(polygon[c] - polygon[b]) != 0.0 { triangles.push([a, b, c]); }
+    if area < 0.0 {
+        for triangle in &mut triangles { triangle.reverse(); }
+    }
+    Some(triangles)
+}
+
+/// Area of the axis-aligned box around `polygon`, the scale a negligible area is judged at.
+fn bounds_area(polygon: &[Vec2]) -> f32 {
+    let (min, max) = polygon.iter().fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                                         |(min, max), &corner| (min.min(corner), max.max(corner)));
+    ((max - min).x * (max - min).y).max(0.0)
+}
+
+/// Whether `p` is inside the clockwise triangle `a b c` or on its edges.
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    (b - a).perp_dot(p - a) >= 0.0 && (c - b).perp_dot(p - b) >= 0.0 && (a - c).perp_dot(p - c) >= 0.0
+}
+
+/// Whether segments `a b` and `c d` share any point.
+fn segments_touch(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let within = |p: Vec2, q: Vec2, r: Vec2| r.cmpge(p.min(q)).all() && r.cmple(p.max(q)).all();
+    let (d1, d2, d3, d4) = (side(c, d, a), side(c, d, b), side(a, b, c), side(a, b, d));
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0)) {
+        return true;
+    }
+    (d1 == 0.0 && within(c, d, a)) || (d2 == 0.0 && within(c, d, b))
+        || (d3 == 0.0 && within(a, b, c)) || (d4 == 0.0 && within(a, b, d))
+}
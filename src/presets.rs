@@ -0,0 +1,108 @@
+// Built-in example scenes, constructed in code so they never go stale against the geometry API.
+
+use macroquad::math::{vec2, Vec2};
+
+use crate::core::{EdgeState, Segment};
+use crate::labyrinth::{Algorithm, Labyrinth, Side};
+use crate::{Animation, Laser, NodeNetwork};
+
+/// Adds a preset's geometry to the network and places the laser. Expects an empty network.
+pub type BuildFn = fn(&mut NodeNetwork, &mut Laser);
+
+/// Middle of the default window, presets are laid out around it.
+const CENTER: Vec2 = vec2(480.0, 270.0);
+
+static PRESETS: [(&str, BuildFn); 6] = [
+    ("Mirror box", mirror_box),
+    ("Corner reflector", corner_reflector),
+    ("Lens circle", lens_circle),
+    ("Parabolic mirror", parabolic_mirror),
+    ("Small maze", small_maze),
+    ("Spinning mirror", spinning_mirror),
+];
+
+/// Every preset with its display name, in menu order.
+pub fn all() -> &'static [(&'static str, BuildFn)] {
+    &PRESETS
+}
+
+/// Joins consecutive points with edges in `state`, and the last back to the first if `closed`.
+fn polyline(network: &mut NodeNetwork, points: &[Vec2], state: EdgeState, closed: bool) {
+    let nodes: Vec<usize> = points.iter().map(|point| network.add_node(*point)).collect();
+    let pairs = nodes.windows(2).map(|pair| (pair[0], pair[1]))
+        .chain(closed.then(|| (nodes[nodes.len() - 1], nodes[0])));
+    for (a, b) in pairs {
+        if network.add_connection(a, b) {
+            network.set_edge_state(network.connections.len() - 1, state);
+        }
+    }
+}
+
+/// A closed square of mirrors with the beam bouncing around inside.
+pub fn mirror_box(network: &mut NodeNetwork, laser: &mut Laser) {
+    let half = 150.0;
+    let corners = [vec2(-half, -half), vec2(half, -half), vec2(half, half), vec2(-half, half)];
+    polyline(network, &corners.map(|corner| CENTER + corner), EdgeState::Reflective, true);
+    laser.place(CENTER, CENTER + vec2(100.0, 37.0));
+}
+
+/// Two mirrors at a right angle send the beam back parallel to where it came from.
+pub fn corner_reflector(network: &mut NodeNetwork, laser: &mut Laser) {
+    let corner = CENTER + vec2(150.0, 150.0);
+    polyline(network, &[corner - vec2(0.0, 250.0), corner, corner - vec2(250.0, 0.0)],
+             EdgeState::Reflective, false);
+    laser.place(CENTER - vec2(250.0, 100.0), corner - vec2(0.0, 120.0));
+}
+
+/// A transparent disc bending a beam that passes off its axis.
+pub fn lens_circle(network: &mut NodeNetwork, laser: &mut Laser) {
+    let (radius, sides) = (100.0, 32);
+    let points: Vec<Vec2> = (0..sides)
+        .map(|i| CENTER + Vec2::from_angle(i as f32 * std::f32::consts::TAU / sides as f32) * radius)
+        .collect();
+    polyline(network, &points, EdgeState::Transparent, true);
+    laser.place(CENTER - vec2(300.0, 40.0), CENTER + vec2(0.0, -40.0));
+}
+
+/// A parabola made of short mirrors; beams parallel to its axis all pass through the focus.
+pub fn parabolic_mirror(network: &mut NodeNetwork, laser: &mut Laser) {
+    let (focal_length, half_height, segments) = (100.0, 150.0, 16);
+    let vertex = CENTER + vec2(200.0, 0.0);
+    // opens towards -x: x = vertex.x - y^2 / 4f
+    let points: Vec<Vec2> = (0..=segments)
+        .map(|i| {
+            let y = -half_height + 2.0 * half_height * i as f32 / segments as f32;
+            vertex + vec2(-y * y / (4.0 * focal_length), y)
+        })
+        .collect();
+    polyline(network, &points, EdgeState::Reflective, false);
+    let start = CENTER + vec2(-250.0, 60.0);
+    laser.place(start, start + Vec2::X);
+}
+
+/// A labyrinth with its entrance on the left, the beam aimed straight in.
+pub fn small_maze(network: &mut NodeNetwork, laser: &mut Laser) {
+    let cell_size = 40.0;
+    let mut labyrinth = Labyrinth::new(cell_size, (6, 6));
+    labyrinth.open_entrance(Side::Left, 0);
+    labyrinth.generate(Algorithm::DepthFirst, 7);
+    let offset = CENTER - Vec2::splat(3.0 * cell_size);
+    let segments: Vec<Segment> = labyrinth.get_as_lines().iter()
+        .map(|&((x1, y1), (x2, y2))| Segment(offset + vec2(x1, y1), offset + vec2(x2, y2), EdgeState::Reflective))
+        .collect();
+    network.add_segments(&segments);
+    let start = offset + vec2(-100.0, cell_size / 2.0);
+    laser.place(start, start + Vec2::X);
+}
+
+/// A mirror turning about its middle, sweeping the beam around the room.
+pub fn spinning_mirror(network: &mut NodeNetwork, laser: &mut Laser) {
+    let (radius, angular_speed) = (60.0, 0.5);
+    let ends = [0.0, std::f32::consts::PI].map(|phase| {
+        let node = network.add_node(CENTER + Vec2::from_angle(phase) * radius);
+        network.set_animation(node, Some(Animation::Orbit { center: CENTER, radius, angular_speed, phase }));
+        node
+    });
+    network.add_connection(ends[0], ends[1]);
+    laser.place(CENTER + vec2(0.0, 200.0), CENTER);
+}
@@ -0,0 +1,137 @@
+// The bounce inspector: one bounce of the beam picked with a click and annotated with the
+// surface normal and the angles the light arrives and leaves at, for teaching reflection and
+// refraction.
+
+use macroquad::color::{Color, SKYBLUE, WHITE, YELLOW};
+use macroquad::math::Vec2;
+use macroquad::shapes::{draw_line, draw_rectangle, draw_triangle};
+use macroquad::text::{draw_text, measure_text};
+
+use crate::core::TraceResult;
+use crate::measure::format_angle;
+use crate::view::world_to_screen;
+
+/// Screen pixels from a bounce a click still picks it at.
+pub const PICK_RADIUS: f32 = 12.0;
+/// Length of the normal and the rays drawn at the bounce, in screen pixels.
+const RAY_LENGTH: f32 = 90.0;
+const ARC_RADIUS: f32 = 36.0;
+const ARC_STEPS: usize = 16;
+const NORMAL_COLOR: Color = WHITE;
+const INCIDENT_COLOR: Color = YELLOW;
+const REFLECTED_COLOR: Color = Color::new(1.0, 0.6, 0.2, 1.0);
+const TRANSMITTED_COLOR: Color = SKYBLUE;
+
+/// The light at one bounce. Angles are in radians from the normal, on the side each ray is on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BounceAngles {
+    pub position: Vec2,
+    /// Normal of the surface, facing the side the light came from.
+    pub normal: Vec2,
+    /// Direction the light arrived along.
+    pub incoming: Vec2,
+    pub incidence: f32,
+    /// Direction and angle of the light sent back, unless the surface kept all of it.
+    pub reflected: Option<(Vec2, f32)>,
+    /// Direction and angle of the light let through a transparent surface.
+    pub transmitted: Option<(Vec2, f32)>,
+}
+
+impl BounceAngles {
+    /// The bounce at the end of the line at `index` of `trace`, `None` unless the line ended on
+    /// a segment. Of several reflected rays off a rough surface the first one counts.
+    pub fn at(trace: &TraceResult, index: usize) -> Option<Self> {
+        let hit = trace.hits.get(index).filter(|hit| hit.segment.is_some())?;
+        let mut angles = Self {
+            position: hit.position,
+            normal: hit.normal,
+            incoming: (hit.position - hit.origin).normalize_or_zero(),
+            incidence: hit.incidence,
+            reflected: None,
+            transmitted: None,
+        };
+        for child in trace.children(index) {
+            let direction = (child.position - child.origin).normalize_or_zero();
+            let along = direction.dot(hit.normal).clamp(-1.0, 1.0);
+            if along >= 0.0 {
+                angles.reflected.get_or_insert((direction, along.acos()));
+            } else {
+                angles.transmitted.get_or_insert((direction, (-along).acos()));
+            }
+        }
+        Some(angles)
+    }
+}
+
+/// Inspection mode: while on, a click picks the bounce nearest to it, see `BounceAngles`.
+#[derive(Debug, Default)]
+pub struct Protractor {
+    pub active: bool,
+    /// Where the picked bounce was. Retraced beams are searched for the bounce nearest to it,
+    /// so the pick survives small changes to the scene.
+    picked: Option<Vec2>,
+}
+
+impl Protractor {
+    /// Picks the bounce of `trace` within `radius` of `point` (world space), or drops the pick
+    /// if there is none.
+    pub fn pick(&mut self, trace: &TraceResult, point: Vec2, radius: f32) {
+        self.picked = trace.nearest_bounce(point, radius).map(|i| trace.hits[i].position);
+    }
+
+    /// The picked bounce as found in `trace`.
+    pub fn selected(&self, trace: &TraceResult) -> Option<BounceAngles> {
+        BounceAngles::at(trace, trace.nearest_bounce(self.picked?, f32::INFINITY)?)
+    }
+
+    /// Draws the normal, the rays and their angles at the picked bounce. Call in screen space.
+    pub fn draw(&self, trace: &TraceResult, camera_target: Vec2, zoom: f32, screen: Vec2) {
+        if !self.active { return; }
+        let Some(angles) = self.selected(trace) else { return; };
+        // the camera only scales and moves the world, so directions and angles carry over
+        let at = world_to_screen(angles.position, screen, camera_target, zoom);
+        let normal = angles.normal;
+        draw_arrow(at, at + normal * RAY_LENGTH, NORMAL_COLOR);
+        let back = -angles.incoming;
+        draw_line(at.x, at.y, at.x + back.x * RAY_LENGTH, at.y + back.y * RAY_LENGTH, 2.0, INCIDENT_COLOR);
+        draw_angle(at, normal, back, ARC_RADIUS, &format!("in {}", format_angle(angles.incidence.to_degrees())),
+                   INCIDENT_COLOR);
+        if let Some((direction, angle)) = angles.reflected {
+            draw_arrow(at, at + direction * RAY_LENGTH, REFLECTED_COLOR);
+            draw_angle(at, normal, direction, ARC_RADIUS + 10.0,
+                       &format!("out {}", format_angle(angle.to_degrees())), REFLECTED_COLOR);
+        }
+        if let Some((direction, angle)) = angles.transmitted {
+            let behind = at - normal * RAY_LENGTH;
+            draw_line(at.x, at.y, behind.x, behind.y, 1.0, NORMAL_COLOR);
+            draw_arrow(at, at + direction * RAY_LENGTH, TRANSMITTED_COLOR);
+            draw_angle(at, -normal, direction, ARC_RADIUS, &format!("through {}", format_angle(angle.to_degrees())),
+                       TRANSMITTED_COLOR);
+        }
+    }
+}
+
+fn draw_arrow(from: Vec2, to: Vec2, color: Color) {
+    let Some(direction) = (to - from).try_normalize() else { return; };
+    let base = to - direction * 10.0;
+    draw_line(from.x, from.y, base.x, base.y, 2.0, color);
+    let side = direction.perp() * 5.0;
+    draw_triangle(to, base + side, base - side, color);
+}
+
+/// Arc of `radius` around `center` from direction `from` to direction `to` the short way round,
+/// labelled past its middle.
+fn draw_angle(center: Vec2, from: Vec2, to: Vec2, radius: f32, label: &str, color: Color) {
+    let (start, sweep) = (from.y.atan2(from.x), from.perp_dot(to).atan2(from.dot(to)));
+    let point = |t: f32| center + Vec2::from_angle(start + sweep * t) * radius;
+    for step in 0..ARC_STEPS {
+        let (a, b) = (point(step as f32 / ARC_STEPS as f32), point((step + 1) as f32 / ARC_STEPS as f32));
+        draw_line(a.x, a.y, b.x, b.y, 1.5, color);
+    }
+    let position = center + Vec2::from_angle(start + sweep / 2.0) * (radius + 14.0);
+    let size = measure_text(label, None, 18, 1.0);
+    let corner = position - Vec2::new(size.width / 2.0, -size.offset_y / 2.0);
+    draw_rectangle(corner.x - 2.0, corner.y - size.offset_y - 2.0, size.width + 4.0, size.height + 4.0,
+                   Color::new(0.0, 0.0, 0.0, 0.6));
+    draw_text(label, corner.x, corner.y, 18.0, color);
+}
@@ -0,0 +1,264 @@
+// Light accumulation, tone mapping and screenshots.
+
+use log::info;
+use macroquad::color::{BLANK, WHITE};
+use macroquad::material::{gl_use_default_material, gl_use_material, load_material, Material, MaterialParams};
+use macroquad::math::{Rect, vec2};
+use macroquad::models::draw_mesh;
+use macroquad::miniquad::{BlendFactor, BlendState, BlendValue, Equation, ShaderSource, TextureFormat,
+                          TextureParams, UniformType};
+use macroquad::prelude::{Camera2D, clear_background, get_internal_gl, Image, PipelineParams, render_target,
+                         RenderTarget, set_camera, set_default_camera};
+use macroquad::texture::{draw_texture_ex, DrawTextureParams, Texture2D};
+use macroquad::window::{screen_height, screen_width};
+
+use crate::core::visibility_polygon;
+use crate::{line_meshes, theme, Laser, LightArea, NodeNetwork, TraceResult};
+
+/// Material the rays are drawn with: additive, with a `brightness` uniform for beams brighter
+/// than vertex colors can express.
+fn light_material() -> Material {
+    load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX_SHADER,
+            fragment: FRAGMENT_SHADER,
+        },
+        MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::One,
+                )),
+                alpha_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::One,
+                    BlendFactor::One,
+                )
+                ),
+                ..Default::default()
+            },
+            uniforms: vec![("brightness".to_owned(), UniformType::Float1)],
+            ..Default::default()
+        },
+    ).unwrap()
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+    attribute vec3 position;
+    attribute vec2 texcoord;
+    attribute vec4 color0;
+
+    varying lowp vec2 uv;
+    varying lowp vec4 color;
+
+    uniform mat4 Model;
+    uniform mat4 Projection;
+
+    void main() {
+        gl_Position = Projection * Model * vec4(position, 1);
+        color = color0 / 255.0;
+        uv = texcoord;
+    }"#;
+const FRAGMENT_SHADER: &str = r#"
+#version 100
+precision mediump float;
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+// vertex colors are 8 bit, anything brighter than 1 has to come in through here
+uniform float brightness;
+
+void main() {
+    vec4 light = color * texture2D(Texture, uv);
+    gl_FragColor = vec4(light.rgb * brightness, light.a);
+}"#;
+
+const TONEMAP_SHADER: &str = r#"
+#version 100
+precision mediump float;
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+uniform float exposure;
+
+void main() {
+    vec3 light = texture2D(Texture, uv).rgb;
+    gl_FragColor = vec4(vec3(1.0) - exp(-light * exposure), 1.0) * color;
+}"#;
+
+/// `TONEMAP_SHADER` with the light as premultiplied color over the background, as opaque as
+/// its brightest channel, so it darkens a light background towards its own color.
+const TONEMAP_OVER_SHADER: &str = r#"
+#version 100
+precision mediump float;
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+uniform float exposure;
+
+void main() {
+    vec3 light = vec3(1.0) - exp(-texture2D(Texture, uv).rgb * exposure);
+    gl_FragColor = vec4(light, max(light.r, max(light.g, light.b))) * color;
+}"#;
+
+/// Offscreen target the rays are summed into, then composited through `TONEMAP_SHADER` so
+/// dense overlaps stay readable instead of clipping to white.
+pub(crate) struct LightBuffer {
+    pub(crate) target: RenderTarget,
+    /// What the rays are drawn into `target` with, see `light_material`.
+    pub(crate) light: Material,
+    size: (u32, u32),
+    tonemap: Material,
+    /// `tonemap` for themes without additive light, see `Theme::additive_light`.
+    tonemap_over: Material,
+}
+
+impl LightBuffer {
+    pub(crate) fn new() -> Self {
+        Self::with_size((screen_width() as u32, screen_height() as u32))
+    }
+
+    fn with_size(size: (u32, u32)) -> Self {
+        let tonemap = Self::tonemap_material(TONEMAP_SHADER, BlendFactor::One);
+        let tonemap_over = Self::tonemap_material(TONEMAP_OVER_SHADER,
+                                                  BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        Self { target: Self::create_target(size), light: light_material(), size, tonemap, tonemap_over }
+    }
+
+    /// Material drawing the target through `fragment`, keeping `destination` of what is under it.
+    fn tonemap_material(fragment: &str, destination: BlendFactor) -> Material {
+        load_material(
+            ShaderSource::Glsl {
+                vertex: VERTEX_SHADER,
+                fragment,
+            },
+            MaterialParams {
+                pipeline_params: PipelineParams {
+                    color_blend: Some(BlendState::new(
+                        Equation::Add,
+                        BlendFactor::One,
+                        destination,
+                    )),
+                    ..Default::default()
+                },
+                uniforms: vec![("exposure".to_owned(), UniformType::Float1)],
+                ..Default::default()
+            },
+        ).unwrap()
+    }
+
+    fn create_target((width, height): (u32, u32)) -> RenderTarget {
+        // WebGL 1 can't render into float textures
+        #[cfg(not(target_family = "wasm"))]
+        let format = TextureFormat::RGBA16F;
+        #[cfg(target_family = "wasm")]
+        let format = TextureFormat::RGBA8;
+        let context = unsafe { get_internal_gl() }.quad_context;
+        let texture = context.new_render_texture(TextureParams {
+            width: width.max(1),
+            height: height.max(1),
+            format,
+            ..Default::default()
+        });
+        let render_pass = context.new_render_pass(texture, None);
+        RenderTarget { texture: Texture2D::from_miniquad_texture(texture), render_pass }
+    }
+
+    /// Recreates the target after the window was resized.
+    pub(crate) fn fit_to_screen(&mut self) {
+        self.resize((screen_width() as u32, screen_height() as u32));
+    }
+
+    /// Recreates the target at `size` pixels, keeping the materials.
+    fn resize(&mut self, size: (u32, u32)) {
+        if size == self.size { return; }
+        self.target.delete();
+        self.target = Self::create_target(size);
+        self.size = size;
+    }
+
+    /// Adds the tone-mapped light onto the current target, or lays it over it if the theme has
+    /// no additive light; call with a camera mapping one unit to one pixel, e.g. the default
+    /// camera.
+    pub(crate) fn composite(&self, exposure: f32) {
+        let tonemap = if theme::current().additive_light { &self.tonemap } else { &self.tonemap_over };
+        tonemap.set_uniform("exposure", exposure);
+        gl_use_material(tonemap);
+        draw_texture_ex(&self.target.texture, 0.0, 0.0, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(self.size.0 as f32, self.size.1 as f32)),
+            ..Default::default()
+        });
+        gl_use_default_material();
+    }
+
+}
+
+/// Camera showing `rect` in world space, drawing into `render_target`.
+fn framing_camera(rect: Rect, render_target: &RenderTarget) -> Camera2D {
+    Camera2D {
+        zoom: vec2(2.0 / rect.w, 2.0 / rect.h),
+        target: rect.center(),
+        render_target: Some(render_target.clone()),
+        ..Default::default()
+    }
+}
+
+/// Renders the scene framing `rect` at `scale` times the window resolution, without any UI,
+/// and saves it as a PNG in the working directory. `light` is borrowed at that size and fitted
+/// back to the screen after.
+pub(crate) fn take_screenshot(rect: Rect, scale: f32, network: &NodeNetwork, laser: &Laser,
+                              collisions: &TraceResult, light: &mut LightBuffer, absorber_glow: bool) {
+    let size = ((screen_width() * scale) as u32, (screen_height() * scale) as u32);
+    let target = render_target(size.0, size.1);
+    light.resize(size);
+
+    set_camera(&framing_camera(rect, &light.target));
+    clear_background(BLANK);
+    light.light.set_uniform("brightness", laser.brightness());
+    gl_use_material(&light.light);
+    if laser.light_area != LightArea::Off {
+        laser.draw_light_area(&visibility_polygon(laser.position(), &network.traced_segments()), 1.0);
+    }
+    if laser.light_area != LightArea::InsteadOfBeam {
+        if laser.soft_beam {
+            laser.draw_soft_lines(collisions, rect.w / size.0 as f32);
+        } else if laser.meshed_beam {
+            line_meshes(collisions.lines(), laser.thickness).iter().for_each(draw_mesh);
+        } else {
+            laser.draw_lines(collisions.lines());
+        }
+        if absorber_glow { laser.draw_absorber_glow(collisions); }
+    }
+    gl_use_default_material();
+    let pixels = Rect::new(0.0, 0.0, size.0 as f32, size.1 as f32);
+    set_camera(&framing_camera(pixels, &target));
+    clear_background(theme::current().background);
+    light.composite(laser.exposure());
+    set_camera(&framing_camera(rect, &target));
+    network.draw(laser.thickness);
+    laser.draw_laser_texture();
+    set_default_camera();
+
+    unsafe { get_internal_gl().flush(); }
+    save_png(&target.texture.get_texture_data());
+    target.delete();
+    light.fit_to_screen();
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn save_png(image: &Image) {
+    let seconds = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = format!("screenshot-{}.png", seconds);
+    image.export_png(&path);
+    info!("Saved {}", path);
+}
+
+#[cfg(target_family = "wasm")]
+fn save_png(_image: &Image) {
+    info!("Screenshots are not supported on the web yet");
+}
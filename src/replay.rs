@@ -0,0 +1,348 @@
+// Recording an editing session and playing it back, for reproducible bug reports.
+
+use std::path::{Path, PathBuf};
+
+use macroquad::math::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{AbsorptionCurve, EdgeState, EmitterConfig, Segment, DEFAULT_THICKNESS};
+use crate::fragment::ArrayLayout;
+use crate::labyrinth::{LabyrinthSpec, Line};
+use crate::settings::{LaserSettings, SimulationSettings};
+use crate::{presets, Animation, Laser, LockKind, NodeNetwork};
+
+/// One edit, at the level the user made it: a built labyrinth is one event, not the hundreds of
+/// nodes and edges it consists of. Node and edge indices are the ones the network handed out,
+/// which replaying from the same starting scene reproduces.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    AddNode { position: Vec2, radius: f32 },
+    RemoveNode { node: usize },
+    MoveNode { node: usize, position: Vec2 },
+    /// Nodes moved together by `offset`, see `NodeNetwork::move_nodes`.
+    MoveNodes { nodes: Vec<usize>, offset: Vec2 },
+    Animate { node: usize, animation: Option<Animation> },
+    PinNode { node: usize, pinned: bool },
+    PinAll { pinned: bool },
+    Connect { a: usize, b: usize },
+    /// A connection made straight in `state`, see `NodeNetwork::connect_as`.
+    ConnectAs { a: usize, b: usize, state: EdgeState },
+    ChainLink { from: usize, position: Vec2, state: EdgeState },
+    /// Index into `connections`.
+    RemoveEdge { edge: usize },
+    SetEdgeState { edge: usize, state: EdgeState },
+    SetEdgeBackState { edge: usize, state: Option<EdgeState> },
+    FlipEdgeFaces { edge: usize },
+    SetAllStates { state: EdgeState },
+    SetEdgeRoughness { edge: usize, roughness: f32 },
+    SetEdgeThickness { edge: usize, thickness: f32 },
+    SetEdgeLayer { edge: usize, layer: u8, visible: bool },
+    SetEdgeEmitter { edge: usize, emitter: Option<EmitterConfig> },
+    SetEdgeAbsorption { edge: usize, curve: Option<AbsorptionCurve> },
+    LockEdge { edge: usize, kind: Option<LockKind> },
+    SplitEdge { edge: usize, position: Vec2 },
+    MergeNodes { a: usize, b: usize },
+    Clean { keep_bounds: bool },
+    Bounds { position: Vec2, size: Vec2, state: EdgeState },
+    RemoveBounds,
+    Circle {
+        center: Vec2,
+        radius: f32,
+        state: EdgeState,
+        sides: usize,
+        /// Sessions from before edges had a thickness get the one they were all drawn at.
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+        #[serde(default)]
+        group: Option<String>,
+    },
+    Segments {
+        segments: Vec<Segment>,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+        #[serde(default)]
+        group: Option<String>,
+    },
+    Labyrinth {
+        spec: LabyrinthSpec,
+        offset: Vec2,
+        state: EdgeState,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+        #[serde(default)]
+        group: Option<String>,
+    },
+    /// Walls of an animated labyrinth build carved or added, see `NodeNetwork::edit_lines`.
+    EditLines {
+        removed: Vec<Line>,
+        added: Vec<Line>,
+        offset: Vec2,
+        state: EdgeState,
+        thickness: f32,
+        group: String,
+    },
+    StaticSegments { segments: Vec<Segment> },
+    StaticLabyrinth { spec: LabyrinthSpec, offset: Vec2, state: EdgeState },
+    StaticToNodes,
+    NodesToStatic { nodes: Vec<usize> },
+    Array { nodes: Vec<usize>, layout: ArrayLayout },
+    AssignGroup { nodes: Vec<usize>, group: Option<String> },
+    SetGroupVisible { group: String, visible: bool },
+    SetGroupCollides { group: String, collides: bool },
+    /// Removes the group with its nodes and edges.
+    DeleteGroup { group: String },
+    /// One of `presets::all`, by name.
+    Preset { name: String },
+    Laser { position: Vec2, direction: Vec2 },
+    /// The node the laser sits on and the neighbour it is aligned towards, see
+    /// `Laser::follow_mount`.
+    LaserMount { node: Option<usize>, align: Option<usize> },
+    LaserSettings(LaserSettings),
+    Config(SimulationSettings),
+}
+
+const fn default_thickness() -> f32 {
+    DEFAULT_THICKNESS
+}
+
+/// Edits a `NodeNetwork` logged while recording, until `Recorder::collect` picks them up.
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    events: Option<Vec<Event>>,
+    /// Compound edits being logged as one event; their parts are not logged.
+    muted: usize,
+}
+
+impl Journal {
+    pub(crate) fn set_recording(&mut self, recording: bool) {
+        if recording != self.events.is_some() {
+            self.events = recording.then(Vec::new);
+        }
+    }
+
+    pub(crate) const fn is_recording(&self) -> bool {
+        self.events.is_some()
+    }
+
+    /// `event` is only built when it is going to be kept.
+    pub(crate) fn log(&mut self, event: impl FnOnce() -> Event) {
+        if self.muted > 0 { return; }
+        if let Some(events) = &mut self.events { events.push(event()); }
+    }
+
+    pub(crate) fn mute(&mut self) {
+        self.muted += 1;
+    }
+
+    /// Whether a compound edit is going on.
+    pub(crate) const fn is_muted(&self) -> bool {
+        self.muted > 0
+    }
+
+    pub(crate) fn unmute(&mut self) {
+        self.muted -= 1;
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<Event> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
+
+/// Recorded events with the time, in seconds, they happened at.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub events: Vec<(f64, Event)>,
+}
+
+impl Session {
+    /// File name used next to the executable on native builds.
+    pub const FILE_NAME: &'static str = "session.json";
+
+    pub fn to_json(&self) -> String {
+        // compact, a session can hold a lot of geometry
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|error| error.to_string())
+    }
+
+    /// Where sessions are saved on native builds: next to the executable.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(std::env::current_exe().ok()?.parent()?.join(Self::FILE_NAME))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Self::from_json(&std::fs::read_to_string(path).map_err(|error| error.to_string())?)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+/// Builds a `Session` from a network's logged edits, plus the laser and the simulation settings,
+/// which are compared against their last recorded state instead of logged.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    session: Session,
+    laser: Option<(Vec2, Vec2)>,
+    laser_mount: (Option<usize>, Option<usize>),
+    laser_settings: Option<LaserSettings>,
+    config: Option<SimulationSettings>,
+}
+
+impl Recorder {
+    /// Starts recording `network`. A recording made from an empty network replays into one.
+    pub fn start(network: &mut NodeNetwork) -> Self {
+        network.set_recording(true);
+        Self::default()
+    }
+
+    pub fn record(&mut self, time: f64, event: Event) {
+        self.session.events.push((time, event));
+    }
+
+    /// Call every frame: takes the edits the network logged and notes whatever changed about the
+    /// laser or the settings, all at `time`.
+    pub fn collect(&mut self, time: f64, network: &mut NodeNetwork, laser: &Laser) {
+        for event in network.take_events() {
+            self.record(time, event);
+        }
+        let pose = (laser.position(), laser.aim().1);
+        if self.laser != Some(pose) {
+            self.laser = Some(pose);
+            self.record(time, Event::Laser { position: pose.0, direction: pose.1 });
+        }
+        let mount = (laser.mount(), laser.mount_align());
+        if self.laser_mount != mount {
+            self.laser_mount = mount;
+            self.record(time, Event::LaserMount { node: mount.0, align: mount.1 });
+        }
+        let laser_settings = LaserSettings::of(laser);
+        if self.laser_settings.as_ref() != Some(&laser_settings) {
+            self.laser_settings = Some(laser_settings.clone());
+            self.record(time, Event::LaserSettings(laser_settings));
+        }
+        let config = SimulationSettings::current();
+        if self.config.as_ref() != Some(&config) {
+            self.config = Some(config.clone());
+            self.record(time, Event::Config(config));
+        }
+    }
+
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+}
+
+/// Feeds a `Session` back through the `NodeNetwork` and `Laser` APIs, either at the recorded
+/// times with `advance` or all at once with `run_to_end`.
+#[derive(Clone, Debug)]
+pub struct Replayer {
+    session: Session,
+    next: usize,
+}
+
+impl Replayer {
+    pub const fn new(session: Session) -> Self {
+        Self { session, next: 0 }
+    }
+
+    /// Applies every event recorded up to `time` that hasn't been applied yet.
+    pub fn advance(&mut self, time: f64, network: &mut NodeNetwork, laser: &mut Laser) {
+        while let Some((at, event)) = self.session.events.get(self.next) {
+            if *at > time { break; }
+            apply(event, network, laser);
+            self.next += 1;
+        }
+    }
+
+    /// Applies everything left, as fast as possible.
+    pub fn run_to_end(&mut self, network: &mut NodeNetwork, laser: &mut Laser) {
+        self.advance(f64::INFINITY, network, laser);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.session.events.len()
+    }
+}
+
+/// Repeats `event` on `network` and `laser`. Settings events change the global sliders.
+pub fn apply(event: &Event, network: &mut NodeNetwork, laser: &mut Laser) {
+    match event {
+        Event::AddNode { position, radius } => { network.add_node_with_radius(*position, *radius); }
+        Event::RemoveNode { node } => network.remove_node(*node),
+        Event::MoveNode { node, position } => network.move_node(*node, *position),
+        Event::MoveNodes { nodes, offset } => network.move_nodes(nodes, *offset),
+        Event::Animate { node, animation } => network.set_animation(*node, *animation),
+        Event::Connect { a, b } => { network.add_connection(*a, *b); }
+        Event::ConnectAs { a, b, state } => { network.connect_as(*a, *b, *state); }
+        Event::ChainLink { from, position, state } => { network.add_chain_link(*from, *position, *state); }
+        Event::RemoveEdge { edge } => network.remove_edge(*edge),
+        Event::SetEdgeState { edge, state } => network.set_edge_state(*edge, *state),
+        Event::SetEdgeBackState { edge, state } => network.set_edge_back_state(*edge, *state),
+        Event::FlipEdgeFaces { edge } => network.flip_edge_faces(*edge),
+        Event::SetAllStates { state } => network.set_all_states(*state),
+        Event::PinNode { node, pinned } => network.set_pinned(*node, *pinned),
+        Event::PinAll { pinned } => network.pin_all(*pinned),
+        Event::SetEdgeRoughness { edge, roughness } => network.set_edge_roughness(*edge, *roughness),
+        Event::SetEdgeThickness { edge, thickness } => network.set_edge_thickness(*edge, *thickness),
+        Event::SetEdgeLayer { edge, layer, visible } => network.set_edge_layer(*edge, *layer, *visible),
+        Event::SetEdgeEmitter { edge, emitter } => network.set_edge_emitter(*edge, *emitter),
+        Event::SetEdgeAbsorption { edge, curve } => network.set_edge_absorption(*edge, *curve),
+        Event::LockEdge { edge, kind } => { network.lock_edge(*edge, *kind); }
+        Event::SplitEdge { edge, position } => { network.split_edge(*edge, *position); }
+        Event::MergeNodes { a, b } => network.merge_nodes(*a, *b),
+        Event::Clean { keep_bounds } => network.clean(*keep_bounds),
+        Event::Bounds { position, size, state } =>
+            network.add_bounds(Rect::new(position.x, position.y, size.x, size.y), *state),
+        Event::RemoveBounds => network.remove_bounds(),
+        Event::Circle { center, radius, state, sides, thickness, group } => {
+            network.add_circle(*center, *radius, *state, *sides, *thickness, group.as_deref());
+        }
+        Event::Segments { segments, thickness, group } => {
+            network.add_segments_in(segments, *thickness, group.as_deref());
+        }
+        Event::Labyrinth { spec, offset, state, thickness, group } => {
+            network.add_labyrinth(spec, *offset, *state, *thickness, group.as_deref());
+        }
+        Event::EditLines { removed, added, offset, state, thickness, group } => {
+            network.edit_lines(removed, added, *offset, *state, *thickness, group);
+        }
+        Event::StaticSegments { segments } => network.add_static_segments(segments),
+        Event::StaticLabyrinth { spec, offset, state } => { network.add_static_labyrinth(spec, *offset, *state); }
+        Event::StaticToNodes => { network.static_to_nodes(); }
+        Event::NodesToStatic { nodes } => network.nodes_to_static(nodes),
+        Event::Array { nodes, layout } => { network.add_array(nodes, *layout); }
+        Event::AssignGroup { nodes, group } => network.assign_group(nodes, group.as_deref()),
+        Event::SetGroupVisible { group, visible } => network.set_group_visible(group, *visible),
+        Event::SetGroupCollides { group, collides } => network.set_group_collides(group, *collides),
+        Event::DeleteGroup { group } => network.delete_group(group),
+        Event::Preset { name } => load_preset(name, network, laser),
+        Event::Laser { position, direction } => laser.set_pose(*position, *direction),
+        Event::LaserMount { node, align } => {
+            match node {
+                Some(node) => laser.attach_to_node(*node),
+                None => laser.detach(),
+            }
+            laser.align_along(*align);
+            laser.follow_mount(network);
+        }
+        Event::LaserSettings(settings) => settings.apply(laser),
+        Event::Config(settings) => settings.apply(),
+    }
+}
+
+/// Builds the preset called `name` into the network, logged as a single event.
+pub(crate) fn load_preset(name: &str, network: &mut NodeNetwork, laser: &mut Laser) {
+    let Some((name, build)) = presets::all().iter().find(|(preset, _)| *preset == name) else {
+        log::error!("Unknown preset {:?} in session", name);
+        return;
+    };
+    network.record_as(|| Event::Preset { name: name.to_string() }, |network| build(network, laser));
+}
@@ -0,0 +1,674 @@
+// The whole interactive sandbox behind one type, for embedding it in any macroquad program.
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use macroquad::color::{BLANK, Color, GREEN, ORANGE, RED};
+use macroquad::input::{is_mouse_button_down, is_mouse_button_pressed, mouse_position, mouse_wheel, MouseButton};
+use macroquad::material::{gl_use_default_material, gl_use_material};
+use macroquad::math::{Rect, Vec2, vec2};
+use macroquad::models::Mesh;
+use macroquad::prelude::{Camera2D, clear_background, set_camera, set_default_camera};
+use macroquad::shapes::{draw_circle, draw_line};
+use macroquad::text::{draw_text, measure_text};
+use macroquad::time::get_time;
+use macroquad::ui::root_ui;
+use macroquad::window::{screen_height, screen_width};
+
+use crate::assets::Textures;
+use crate::background::BackgroundLayer;
+use crate::cache::TraceCache;
+use crate::hud::{FrameTimings, PerfHud, Stopwatch};
+use crate::input::{Action, BindingsPanel, InputMap};
+use crate::misc_ui::{CameraCommand, MiscUI};
+use crate::minimap::Minimap;
+use crate::scale_bar::ScaleBar;
+use crate::outliner::Outliner;
+use crate::protractor::{self, Protractor};
+use crate::render::{self, LightBuffer};
+use crate::replay::{Recorder, Replayer, Session};
+use crate::schedule::SimScheduler;
+use crate::settings::{AutoSave, BackgroundSettings, CameraSettings, LaserSettings, Settings, SimulationSettings};
+use crate::stats;
+use crate::theme;
+use crate::tutorial::{self, Tutorial};
+use crate::view::{apply_wheel, fit_aspect, fit_camera, screen_to_world, view_rect, WheelMode};
+use crate::core::{visibility_polygon, Surfaces, TraceScratch};
+use crate::{first_obstruction, line_meshes, EdgeState, Laser, LightArea, NodeNetwork, PickResult, RayColorMode, Segment,
+            TraceResult};
+
+const SCREENSHOT_MARGIN: f32 = 50.0;
+/// Brightness the beam is dimmed to while collisions are off, so a frozen beam doesn't pass for
+/// a live one.
+const FROZEN_BEAM_DIM: f32 = 0.35;
+/// How long problems found at startup stay on screen.
+const WARNING_SECONDS: f64 = 10.0;
+
+/// Scene, laser, camera, settings and the last trace, driven by calling `update`, `draw` and
+/// `ui` once per frame in that order.
+pub struct Sandbox {
+    pub network: NodeNetwork,
+    pub laser: Laser,
+    light_buffer: LightBuffer,
+    misc_ui: MiscUI,
+    show_ui: bool,
+    time_delta: f32,
+    collisions: TraceResult,
+    /// Bumped whenever `collisions` is replaced.
+    traces: u64,
+    /// The beam as `line_meshes`, with the trace, intensity, thickness and coloring it was
+    /// built for.
+    beam_meshes: Vec<Mesh>,
+    beam_mesh_key: Option<(u64, u32, u32, RayColorMode)>,
+    traced_aim: (Vec2, Vec2),
+    /// Region lit by the laser as a point light, with the generation and laser position it is
+    /// for, see `Laser::light_area`.
+    light_area: Vec<Vec2>,
+    light_area_key: Option<(u64, [u32; 2])>,
+    /// Restarts the propagation animation whenever the scene changes.
+    scene_generation: u64,
+    propagation_time: f32,
+    /// Emission strength this frame, see `Laser::current_intensity`.
+    intensity: f32,
+    zoom: f32,
+    zoom_step: f32,
+    home_target: Vec2,
+    camera_target: Vec2,
+    pan_mouse: Option<Vec2>,
+    scheduler: SimScheduler,
+    /// Pause is tapped, but the same key held with a drag pans.
+    pause_tap: bool,
+    perf_hud: PerfHud,
+    /// How long `draw` took to issue the beam, handed to `perf_hud` by the next `update`.
+    beam_draw_micros: Cell<Option<f32>>,
+    show_stats: bool,
+    outliner: Outliner,
+    minimap: Minimap,
+    scale_bar: ScaleBar,
+    protractor: Protractor,
+    trace_cache: TraceCache,
+    /// Buffers kept from one trace to the next, so retracing doesn't allocate them again.
+    segments: Vec<Segment>,
+    surfaces: Surfaces,
+    trace_scratch: TraceScratch,
+    input: InputMap,
+    bindings_panel: BindingsPanel,
+    /// Only native builds have a settings file, on the web the defaults are used.
+    settings_path: Option<PathBuf>,
+    autosave: AutoSave,
+    /// Every edit since the scene was last replaced, for "Save session".
+    recorder: Recorder,
+    recording_started: f64,
+    /// A saved session being played back, with when playback started.
+    replayer: Option<(Replayer, f64)>,
+    /// Reference image drawn under the scene.
+    pub background: Option<BackgroundLayer>,
+    /// Where the sprites came from, kept for the settings file.
+    art: Option<PathBuf>,
+    /// First steps shown to new users, `None` when not running.
+    tutorial: Option<Tutorial>,
+    tutorial_done: bool,
+    /// Problems loading the sprites, shown until `warnings_until`.
+    warnings: Vec<String>,
+    warnings_until: f64,
+}
+
+impl Sandbox {
+    /// An empty bounded world the size of the window with the laser in the middle, set up from
+    /// the saved settings.
+    pub async fn new() -> Self {
+        let settings_path = Settings::default_path();
+        let settings = settings_path.as_deref().map_or_else(Settings::default, Settings::load_or_default);
+        let textures = Textures::load(settings.art.as_deref()).await;
+        let mut network = NodeNetwork::with_texture(textures.node);
+        let recorder = Recorder::start(&mut network);
+        let mut laser = Laser::with_textures(vec2(screen_width() / 2.0, screen_height() / 2.0), vec2(1.0, 0.0),
+                                             textures.laser);
+        // default border at the corners of the screen, kept up to date by MiscUI
+        network.add_bounds(Rect::new(0.0, 0.0, screen_width(), screen_height()), EdgeState::Absorptive);
+
+        settings.simulation.apply();
+        theme::set(settings.theme);
+        settings.laser.apply(&mut laser);
+        let mut misc_ui = MiscUI::new();
+        misc_ui.show_heatmap = settings.show_heatmap;
+        misc_ui.absorber_glow = settings.absorber_glow;
+        misc_ui.theme_combobox = settings.theme.preset().unwrap_or(0);
+        misc_ui.recompute_rate = settings.recompute_rate;
+        let background = settings.background.as_ref().and_then(|background| match background.load() {
+            Ok(layer) => Some(layer),
+            Err(e) => {
+                error!("Could not load background image {}: {}", background.path.display(), e);
+                None
+            }
+        });
+        if let Some(background) = &settings.background {
+            misc_ui.background_path = background.path.display().to_string();
+        }
+        let home_target = vec2(screen_width() / 2.0, screen_height() / 2.0);
+        let art = settings.art.clone();
+        let tutorial_done = settings.tutorial_done;
+        let tutorial = (!tutorial_done).then(|| Tutorial::new(&tutorial::STEPS, &network, settings.show_ui));
+
+        Self {
+            scene_generation: network.generation(),
+            traced_aim: laser.aim(),
+            network,
+            laser,
+            light_buffer: LightBuffer::new(),
+            show_ui: settings.show_ui,
+            time_delta: 0.0,
+            collisions: TraceResult::default(),
+            traces: 0,
+            beam_meshes: Vec::new(),
+            beam_mesh_key: None,
+            light_area: Vec::new(),
+            light_area_key: None,
+            propagation_time: 0.0,
+            intensity: 1.0,
+            zoom: settings.camera.zoom,
+            zoom_step: settings.camera.zoom_step,
+            home_target,
+            camera_target: home_target,
+            pan_mouse: None,
+            scheduler: SimScheduler::new(misc_ui.recompute_rate),
+            misc_ui,
+            pause_tap: false,
+            perf_hud: PerfHud::new(60),
+            beam_draw_micros: Cell::new(None),
+            show_stats: false,
+            outliner: Outliner::default(),
+            minimap: Minimap { visible: settings.show_minimap },
+            scale_bar: ScaleBar { visible: settings.show_scale_bar },
+            protractor: Protractor::default(),
+            trace_cache: TraceCache::new(8),
+            segments: Vec::new(),
+            surfaces: Surfaces::default(),
+            trace_scratch: TraceScratch::default(),
+            input: settings.input.clone(),
+            bindings_panel: BindingsPanel::default(),
+            settings_path,
+            autosave: AutoSave::new(settings, 1.0),
+            recorder,
+            recording_started: get_time(),
+            replayer: None,
+            background,
+            art,
+            tutorial,
+            tutorial_done,
+            warnings: textures.warnings,
+            warnings_until: get_time() + WARNING_SECONDS,
+        }
+    }
+
+    /// Builds a scene into the network, e.g. one of `presets::all`.
+    pub fn with_scene(mut self, build: impl FnOnce(&mut NodeNetwork, &mut Laser)) -> Self {
+        build(&mut self.network, &mut self.laser);
+        self
+    }
+
+    /// Puts `background` under the scene, e.g. one made with `BackgroundLayer::from_bytes`.
+    pub fn with_background(mut self, background: BackgroundLayer) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn with_laser(mut self, laser: Laser) -> Self {
+        self.laser = laser;
+        self
+    }
+
+    /// Borders the world with `rect`, or leaves it open with `None`.
+    pub fn with_bounds(mut self, rect: Option<Rect>) -> Self {
+        self.misc_ui.bounded_world = rect.is_some();
+        if let Some(rect) = rect { self.misc_ui.bounds_rect = rect; }
+        let view = self.view();
+        self.misc_ui.update(&mut self.network, view);
+        self
+    }
+
+    /// Handles input, edits the scene, retraces when due and moves the camera. `delta` is the
+    /// frame time in seconds.
+    pub fn update(&mut self, delta: f32) {
+        self.time_delta = delta;
+        if let Some(micros) = self.beam_draw_micros.take() { self.perf_hud.record_draw(micros); }
+        // a key pressed to rebind something shouldn't also trigger what it is currently bound to
+        let hotkeys = !self.bindings_panel.is_capturing();
+        if hotkeys && self.input.is_pressed(Action::Screenshot) { self.misc_ui.screenshot_requested = true; }
+        if std::mem::take(&mut self.misc_ui.screenshot_requested) { self.take_screenshot(); }
+        if std::mem::take(&mut self.misc_ui.session_save_requested) { self.save_session(); }
+        if std::mem::take(&mut self.misc_ui.session_replay_requested) { self.start_replay(); }
+        if std::mem::take(&mut self.misc_ui.background_load_requested) { self.load_background(); }
+        if std::mem::take(&mut self.misc_ui.tutorial_requested) {
+            self.tutorial = match self.tutorial {
+                Some(_) => None,
+                None => Some(Tutorial::new(&tutorial::STEPS, &self.network, self.show_ui)),
+            };
+        }
+        if let Some((replayer, started)) = &mut self.replayer {
+            replayer.advance(get_time() - *started, &mut self.network, &mut self.laser);
+            if replayer.is_finished() { self.replayer = None; }
+        }
+        if hotkeys { self.handle_hotkeys(); }
+
+        unsafe { self.network.update_camera(self.camera_target, self.zoom); }
+        // the laser gets the mouse first, it sits on top of everything, unless the measure tool is
+        // picking points; the pan modifier + drag pans instead
+        let mut mouse_free = false;
+        let screen = vec2(screen_width(), screen_height());
+        let minimap_click = is_mouse_button_pressed(MouseButton::Left)
+            .then(|| self.minimap.world_at(mouse_position().into(), &self.network, &self.laser, screen))
+            .flatten();
+        if let Some(world) = minimap_click {
+            // a click on the minimap only moves the camera, the scene under it stays untouched
+            self.camera_target = world;
+        } else if hotkeys && self.input.is_down(Action::TargetLock) && is_mouse_button_pressed(MouseButton::Left) {
+            // the click only picks the target, it doesn't also grab the node or cycle an edge
+            let mouse = screen_to_world(mouse_position().into(), vec2(screen_width(), screen_height()),
+                                        self.camera_target, self.zoom);
+            let target = match self.network.pick(mouse) {
+                Some(PickResult::Node(node)) => Some(node),
+                _ => None,
+            };
+            self.laser.set_target(target);
+        } else if hotkeys && self.protractor.active && is_mouse_button_pressed(MouseButton::Left)
+            && !root_ui().is_mouse_over(mouse_position().into()) {
+            // likewise a click only picks a bounce
+            let mouse = screen_to_world(mouse_position().into(), screen, self.camera_target, self.zoom);
+            self.protractor.pick(&self.collisions, mouse, protractor::PICK_RADIUS / self.zoom);
+        } else if hotkeys && !self.input.is_down(Action::PanModifier)
+            && (self.network.is_measuring() || !self.laser.update(delta, &self.input)) {
+            self.network.update(delta, &self.input);
+            mouse_free = !self.network.is_measuring() && !self.network.is_dragging();
+        }
+        if let Some(background) = &mut self.background {
+            let mouse = screen_to_world(mouse_position().into(), vec2(screen_width(), screen_height()),
+                                        self.camera_target, self.zoom);
+            let can_grab = mouse_free && self.network.pick(mouse).is_none()
+                && !root_ui().is_mouse_over(mouse_position().into());
+            background.handle_drag(mouse, can_grab);
+        }
+        self.laser.follow_mouse(root_ui().is_mouse_over(mouse_position().into()) || self.network.is_dragging()
+            || self.network.is_measuring());
+        self.laser.follow_target(&self.network);
+        // a moved mount node moves the muzzle, which makes the beam stale
+        self.laser.follow_mount(&self.network);
+        let view = self.view();
+        self.misc_ui.update(&mut self.network, view);
+        self.network.refresh_fills();
+        if let Some(tutorial) = &mut self.tutorial {
+            tutorial.update(&self.network, &self.laser, self.show_ui);
+            if tutorial.is_finished() { (self.tutorial, self.tutorial_done) = (None, true); }
+        }
+        if self.network.generation() != self.scene_generation {
+            self.scene_generation = self.network.generation();
+            self.propagation_time = 0.0;
+            self.scheduler.mark_changed();
+        } else { self.propagation_time += delta; }
+        self.retrace();
+
+        self.handle_mouse_wheel();
+        self.handle_pan();
+        match self.misc_ui.camera_command.take() {
+            Some(CameraCommand::Reset) => (self.camera_target, self.zoom) = (self.home_target, 1.0),
+            Some(CameraCommand::Fit) => {
+                let laser_rect = Rect::new(self.laser.position().x, self.laser.position().y, 0.0, 0.0);
+                let scene = self.network.bounding_rect(0.0).map_or(laser_rect, |rect| rect.combine_with(laser_rect));
+                (self.camera_target, self.zoom) = fit_camera(scene, vec2(screen_width(), screen_height()), 0.1);
+            }
+            None => {}
+        }
+        self.intensity = self.laser.current_intensity(get_time());
+        self.laser.modulate(&mut self.collisions, self.intensity);
+        self.rebuild_beam_meshes();
+        self.refresh_light_area();
+        self.light_buffer.fit_to_screen();
+        self.autosave();
+        self.recorder.collect(get_time() - self.recording_started, &mut self.network, &self.laser);
+    }
+
+    /// Turns the beam into meshes again if the lines or how they are drawn changed.
+    fn rebuild_beam_meshes(&mut self) {
+        if !self.laser.meshed_beam {
+            (self.beam_meshes, self.beam_mesh_key) = (Vec::new(), None);
+            return;
+        }
+        let key = (self.traces, self.intensity.to_bits(), self.laser.thickness.to_bits(), self.laser.color_mode);
+        if self.beam_mesh_key == Some(key) { return; }
+        self.beam_meshes = line_meshes(self.collisions.lines(), self.laser.thickness);
+        self.beam_mesh_key = Some(key);
+    }
+
+    /// Finds the region the laser lights as a point light again if it or the scene moved.
+    fn refresh_light_area(&mut self) {
+        if self.laser.light_area == LightArea::Off {
+            (self.light_area, self.light_area_key) = (Vec::new(), None);
+            return;
+        }
+        let key = (self.network.generation(), self.laser.position().to_array().map(f32::to_bits));
+        // animated nodes move without bumping the generation
+        // frozen along with the beam while collisions are off
+        if (self.light_area_key == Some(key) && !self.network.is_animated()) || !self.laser.collisions { return; }
+        self.light_area = visibility_polygon(self.laser.position(), &self.network.traced_segments());
+        self.light_area_key = Some(key);
+    }
+
+    /// Draws the light, the scene and the overlays to the screen.
+    pub fn draw(&self) {
+        clear_background(theme::current().background);
+        let camera = self.camera();
+        if let Some(background) = &self.background {
+            set_camera(&camera);
+            background.draw();
+        }
+        // same transform as the scene, so the light stays glued to world space
+        set_camera(&Camera2D { render_target: Some(self.light_buffer.target.clone()), ..camera });
+        clear_background(BLANK);
+        let dim = if self.laser.collisions { 1.0 } else { FROZEN_BEAM_DIM };
+        self.light_buffer.light.set_uniform("brightness", self.laser.brightness() * dim);
+        gl_use_material(&self.light_buffer.light);
+        let stopwatch = Stopwatch::start();
+        // a dark pulse draws nothing at all
+        if self.intensity > 0.0 {
+            self.laser.draw_light_area(&self.light_area, self.intensity);
+        }
+        if self.intensity > 0.0 && self.laser.light_area != LightArea::InsteadOfBeam {
+            if self.laser.animates_propagation() {
+                self.laser.draw_rays_animated(&self.collisions, self.propagation_time);
+            } else if self.laser.soft_beam {
+                self.laser.draw_rays_soft(&self.collisions, 1.0 / self.zoom);
+            } else if self.laser.meshed_beam {
+                self.laser.draw_rays_meshed(self.collisions.lines().len(), &self.beam_meshes);
+            } else {
+                self.laser.draw_rays_explicit(self.collisions.lines());
+            }
+            if self.misc_ui.absorber_glow { self.laser.draw_absorber_glow(&self.collisions); }
+        }
+        self.beam_draw_micros.set(Some(stopwatch.micros()));
+        gl_use_default_material();
+        set_default_camera();
+        self.light_buffer.composite(self.laser.exposure());
+
+        set_camera(&camera);
+        if self.misc_ui.show_heatmap {
+            self.network.draw_heatmap(self.laser.thickness, self.collisions.segment_hits());
+        } else {
+            self.network.draw(self.laser.thickness);
+        }
+        self.network.draw_selection();
+        self.laser.draw_bounce_numbers(&self.collisions);
+        self.laser.draw_truncated_ends(&self.collisions);
+        self.misc_ui.draw();
+        if self.input.is_down(Action::SightLine) {
+            let mouse = screen_to_world(mouse_position().into(), vec2(screen_width(), screen_height()),
+                                        self.camera_target, self.zoom);
+            draw_sight_line(&self.network, self.laser.muzzle(), mouse);
+        }
+        self.laser.draw_target(&self.network);
+        self.laser.draw_laser_texture();
+        set_default_camera();
+
+        self.laser.draw_budget_warning(&self.collisions);
+        self.laser.draw_analytics_summary(&self.collisions);
+        self.network.draw_measurements();
+        if self.protractor.active {
+            draw_text(&format!("Inspecting: click a bounce, {} to stop", self.input.binding(Action::Inspect)),
+                      20.0, 60.0, 30.0, theme::current().text);
+        }
+        self.protractor.draw(&self.collisions, self.camera_target, self.zoom, vec2(screen_width(), screen_height()));
+        if let Some(tutorial) = &self.tutorial {
+            tutorial.draw(&self.network, &self.laser, self.camera_target, self.zoom);
+        }
+        draw_text(format!("Frame time: {}", self.time_delta).as_str(), 20.0, 20.0, 30.0, theme::current().text);
+        draw_text(&format!("{} for options, {} for disable collisions", self.input.binding(Action::ToggleUi),
+                           self.input.binding(Action::ToggleCollisions)), 20.0, 40.0, 30.0, theme::current().text);
+        let lines_drawn = if self.intensity > 0.0 { self.collisions.lines().len() } else { 0 };
+        self.perf_hud.draw(lines_drawn, screen_height());
+        self.minimap.draw(&self.network, &self.laser, self.camera_target, self.zoom,
+                          vec2(screen_width(), screen_height()));
+        // the bar sits in the bottom right corner, above the minimap when that is there
+        let bottom = if self.minimap.visible { Minimap::rect(vec2(screen_width(), screen_height())).y }
+        else { screen_height() };
+        self.scale_bar.draw(self.zoom, vec2(screen_width() - 10.0, bottom - 10.0));
+        if get_time() < self.warnings_until {
+            for (i, warning) in self.warnings.iter().enumerate() {
+                draw_text(warning, 20.0, 110.0 + 20.0 * i as f32, 20.0, ORANGE);
+            }
+        }
+        if !self.laser.collisions {
+            let banner = format!("COLLISIONS PAUSED, {} to resume", self.input.binding(Action::ToggleCollisions));
+            let size = measure_text(&banner, None, 40, 1.0);
+            draw_text(&banner, (screen_width() - size.width) / 2.0, screen_height() - 40.0, 40.0, ORANGE);
+        }
+        if self.scheduler.is_paused() {
+            let status = if self.scheduler.is_stale() { "Paused (stale beam)" } else { "Paused" };
+            draw_text(&format!("{}, {} to step", status, self.input.binding(Action::Step)), 20.0, 80.0, 30.0,
+                      if self.scheduler.is_stale() { ORANGE } else { theme::current().text });
+        }
+    }
+
+    /// Windows: the options (when toggled on), key bindings, scene statistics, the outliner and
+    /// the node and edge inspectors.
+    pub fn ui(&mut self) {
+        if self.show_ui {
+            self.misc_ui.ui(&mut self.network, &mut self.laser, &mut self.background);
+            self.laser.ui(self.network.selected_node(), &self.collisions);
+        }
+        self.bindings_panel.visible = self.show_ui && self.misc_ui.show_bindings;
+        self.bindings_panel.ui(&mut self.input);
+        if self.show_stats { stats::stats_ui(&self.network.stats(), &self.collisions.stats); }
+        self.outliner.ui(&mut self.network);
+        self.network.inspector_ui();
+        self.network.edge_inspector_ui();
+    }
+
+    fn handle_hotkeys(&mut self) {
+        let input = &self.input;
+        if input.is_pressed(Action::ToggleUi) { self.show_ui = !self.show_ui; }
+        if input.is_pressed(Action::ToggleCollisions) { self.laser.collisions = !self.laser.collisions; }
+        if input.is_pressed(Action::FollowMouse) { self.laser.toggle_follow_mouse(); }
+        if input.is_pressed(Action::Pause) { self.pause_tap = true; }
+        if is_mouse_button_down(MouseButton::Left) { self.pause_tap = false; }
+        if input.is_released(Action::Pause) && std::mem::take(&mut self.pause_tap) { self.scheduler.toggle_pause(); }
+        if input.is_pressed(Action::Step) { self.scheduler.step(); }
+        if input.is_pressed(Action::PerfHud) { self.perf_hud.visible = !self.perf_hud.visible; }
+        if input.is_pressed(Action::SceneStats) { self.show_stats = !self.show_stats; }
+        if input.is_pressed(Action::Outliner) { self.outliner.visible = !self.outliner.visible; }
+        if input.is_pressed(Action::Minimap) { self.minimap.visible = !self.minimap.visible; }
+        if input.is_pressed(Action::ScaleBar) { self.scale_bar.visible = !self.scale_bar.visible; }
+        if input.is_pressed(Action::Inspect) { self.protractor.active = !self.protractor.active; }
+        if input.is_pressed(Action::ResetCamera) { self.misc_ui.camera_command = Some(CameraCommand::Reset); }
+    }
+
+    /// Traces the beam again when the scheduler says so, reusing cached traces where possible.
+    fn retrace(&mut self) {
+        self.scheduler.rate = self.misc_ui.recompute_rate;
+        // a moved beam is retraced right away so it doesn't trail behind the cursor
+        // and so is a scene that moves by itself
+        let urgent = self.laser.aim() != self.traced_aim || self.network.is_animated();
+        // animated nodes move without bumping the generation, so their traces can't be reused
+        if self.network.is_animated() { self.trace_cache.invalidate(); }
+        self.scheduler.set_collisions_enabled(self.laser.collisions);
+        if !self.scheduler.tick(self.time_delta, urgent) { return; }
+        if self.laser.sweep.accumulates() {
+            if self.laser.sweep_complete(self.network.generation()) { return; }
+            let segments = self.network.traced_segments();
+            let surfaces = self.network.surfaces();
+            if !self.laser.accumulate_sweep(self.network.generation(), &segments, &surfaces) { return; }
+            self.collisions = self.laser.accumulated_sweep().clone();
+            self.collisions.forget_segments_from(segments.len() - self.network.static_geometry().segments.len());
+            self.traces += 1;
+            self.traced_aim = self.laser.aim();
+            return;
+        }
+        let key = self.laser.trace_key(self.network.generation());
+        if let Some(cached) = self.trace_cache.get(key) {
+            self.collisions = cached.clone();
+        } else {
+            let stopwatch = Stopwatch::start();
+            self.network.traced_segments_into(&mut self.segments);
+            self.network.surfaces_into(&mut self.surfaces);
+            let rebuild_micros = stopwatch.micros();
+            if self.laser.sweep.enabled {
+                self.collisions = self.laser.solve_collisions_sweep(&self.segments, &self.surfaces);
+            } else {
+                self.laser.solve_collisions_into(&self.segments, &self.surfaces, &mut self.trace_scratch,
+                                                 &mut self.collisions);
+            }
+            // only the edges have a heat to show
+            self.collisions.forget_segments_from(self.segments.len() - self.network.static_geometry().segments.len());
+            #[cfg(not(target_family = "wasm"))]
+            let trace_micros = self.collisions.stats.trace_micros;
+            // spans within a frame read as zero on the web, so what the frame took beyond a
+            // vsync interval stands in for the trace time
+            #[cfg(target_family = "wasm")]
+            let trace_micros = (self.time_delta - 1.0 / 60.0).max(0.0) * 1e6;
+            self.laser.tune_ray_budget(trace_micros);
+            self.laser.calibrate_watchdog(&self.collisions.stats, trace_micros);
+            self.trace_cache.insert(key, self.collisions.clone());
+            self.perf_hud.record(FrameTimings {
+                rebuild_micros,
+                trace_micros: self.collisions.stats.trace_micros,
+                lines: self.collisions.stats.lines,
+                segments: self.collisions.stats.segments,
+            });
+        }
+        self.traces += 1;
+        self.traced_aim = self.laser.aim();
+    }
+
+    /// Middle-drag, or Space + left-drag, moves the camera along with the cursor.
+    fn handle_pan(&mut self) {
+        let panning = self.input.is_down(Action::PanButton)
+            || (self.input.is_down(Action::PanModifier) && is_mouse_button_down(MouseButton::Left));
+        if !panning {
+            self.pan_mouse = None;
+            return;
+        }
+        let mouse = Vec2::from(mouse_position());
+        if let Some(last) = self.pan_mouse {
+            self.camera_target -= (mouse - last) / self.zoom;
+        }
+        self.pan_mouse = Some(mouse);
+    }
+
+    fn handle_mouse_wheel(&mut self) {
+        let wheel = mouse_wheel().1;
+        if wheel == 0.0 { return; }
+        let mode = if self.input.is_down(Action::WheelZoom) {
+            WheelMode::Zoom
+        } else if self.input.is_down(Action::WheelHorizontal) {
+            WheelMode::ScrollHorizontal
+        } else {
+            WheelMode::ScrollVertical
+        };
+        (self.camera_target, self.zoom) = apply_wheel(mode, wheel, mouse_position().into(),
+                                                      vec2(screen_width(), screen_height()),
+                                                      self.camera_target, self.zoom, self.zoom_step);
+    }
+
+    fn camera(&self) -> Camera2D {
+        Camera2D {
+            zoom: vec2(2.0 / screen_width(), 2.0 / screen_height()) * self.zoom,
+            target: self.camera_target,
+            ..Default::default()
+        }
+    }
+
+    /// The part of the world currently on screen.
+    fn view(&self) -> Rect {
+        view_rect(self.camera_target, self.zoom, vec2(screen_width(), screen_height()))
+    }
+
+    fn take_screenshot(&mut self) {
+        let view = self.view();
+        let rect = match self.misc_ui.screenshot_frame {
+            1 => self.network.bounding_rect(SCREENSHOT_MARGIN)
+                .map_or(view, |rect| fit_aspect(rect, screen_width() / screen_height())),
+            _ => view,
+        };
+        render::take_screenshot(rect, self.misc_ui.screenshot_scale, &self.network, &self.laser, &self.collisions,
+                                &mut self.light_buffer, self.misc_ui.absorber_glow);
+    }
+
+    fn save_session(&self) {
+        let Some(path) = Session::default_path() else { return; };
+        match self.recorder.session().save(&path) {
+            Ok(()) => info!("Saved session to {}", path.display()),
+            Err(e) => error!("Could not save session to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Replaces the scene with the saved session, played back at its recorded pace. The playback
+    /// is recorded like any other editing.
+    fn start_replay(&mut self) {
+        let Some(path) = Session::default_path() else { return; };
+        let session = match Session::load(&path) {
+            Ok(session) => session,
+            Err(e) => {
+                error!("Could not load session from {}: {}", path.display(), e);
+                return;
+            }
+        };
+        self.misc_ui.forget_scene();
+        self.network.clean(false);
+        self.network.take_events();
+        self.recorder = Recorder::start(&mut self.network);
+        self.recording_started = get_time();
+        self.replayer = Some((Replayer::new(session), get_time()));
+    }
+
+    /// Replaces the background with the image file named in the Misc window.
+    fn load_background(&mut self) {
+        let path = Path::new(self.misc_ui.background_path.trim());
+        match BackgroundLayer::load(path) {
+            Ok(mut layer) => {
+                match &self.background {
+                    // a replaced image keeps its place
+                    Some(old) => (layer.transform, layer.opacity, layer.locked) = (old.transform, old.opacity, old.locked),
+                    None => layer.transform.position = self.view().center(),
+                }
+                self.background = Some(layer);
+            }
+            Err(e) => error!("Could not load background image {}: {}", path.display(), e),
+        }
+    }
+
+    /// Writes the settings once they have settled after a change.
+    fn autosave(&mut self) {
+        let settings = Settings {
+            simulation: SimulationSettings::current(),
+            laser: LaserSettings::of(&self.laser),
+            camera: CameraSettings { zoom_step: self.zoom_step, zoom: self.zoom },
+            show_ui: self.show_ui,
+            show_heatmap: self.misc_ui.show_heatmap,
+            show_minimap: self.minimap.visible,
+            show_scale_bar: self.scale_bar.visible,
+            absorber_glow: self.misc_ui.absorber_glow,
+            recompute_rate: self.misc_ui.recompute_rate,
+            input: self.input.clone(),
+            background: self.background.as_ref().and_then(BackgroundSettings::of),
+            art: self.art.clone(),
+            theme: theme::current(),
+            tutorial_done: self.tutorial_done,
+        };
+        if let (Some(settings), Some(path)) = (self.autosave.update(&settings, get_time()), &self.settings_path) {
+            if let Err(e) = settings.save(path) { error!("Could not save settings to {}: {}", path.display(), e); }
+        }
+    }
+}
+
+/// Line from `from` to `to`, green when nothing but transparent edges is in between, otherwise
+/// red up to the first obstruction and dim after it.
+fn draw_sight_line(network: &NodeNetwork, from: Vec2, to: Vec2) {
+    match first_obstruction(from, to, &network.traced_segments()) {
+        None => draw_line(from.x, from.y, to.x, to.y, 2.0, GREEN),
+        Some((collision, _)) => {
+            let hit = collision.position;
+            draw_line(from.x, from.y, hit.x, hit.y, 2.0, RED);
+            draw_line(hit.x, hit.y, to.x, to.y, 1.0, Color { a: 0.3, ..RED });
+            draw_circle(hit.x, hit.y, 4.0, RED);
+        }
+    }
+}
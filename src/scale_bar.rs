@@ -0,0 +1,58 @@
+// Map style scale bar and cursor coordinates in a corner of the screen, for a sense of world
+// lengths at any zoom.
+
+use macroquad::math::Vec2;
+use macroquad::shapes::draw_line;
+use macroquad::text::{draw_text, measure_text};
+
+use crate::{mouse_position, theme, vec2tuple};
+
+/// Screen pixels the bar is at most long.
+pub const SCALE_BAR_PIXELS: f32 = 100.0;
+const FONT_SIZE: f32 = 20.0;
+
+/// The longest of 1, 5, 10, 50, 100, ... world units (or 0.5, 0.1, ... below one) that is at
+/// most `SCALE_BAR_PIXELS` on screen at `zoom`, in screen pixels per world unit.
+pub fn nice_scale_length(zoom: f32) -> f32 {
+    let fitting = SCALE_BAR_PIXELS / zoom;
+    if !(fitting.is_finite() && fitting > 0.0) { return 1.0; }
+    let mut power = 10f32.powf(fitting.log10().floor());
+    // log10 rounds, a power right at a boundary can land a step off either way
+    if power > fitting { power /= 10.0; }
+    if power * 10.0 <= fitting { power *= 10.0; }
+    if power * 5.0 <= fitting { power * 5.0 } else { power }
+}
+
+/// Decimals to print world positions with, so they resolve a tenth of `length`.
+fn decimals(length: f32) -> usize {
+    (1.0 - length.log10().floor()).max(0.0) as usize
+}
+
+/// The scale bar and cursor readout, see `nice_scale_length`.
+#[derive(Debug, Default)]
+pub struct ScaleBar {
+    pub visible: bool,
+}
+
+impl ScaleBar {
+    /// Draws the bar with its length and the world position under the mouse above it, right
+    /// aligned to `corner`. Call in screen space.
+    pub fn draw(&self, zoom: f32, corner: Vec2) {
+        if !self.visible { return; }
+        let length = nice_scale_length(zoom);
+        let color = theme::current().text;
+        let (right, bottom) = (corner.x, corner.y);
+        let left = right - length * zoom;
+        draw_line(left, bottom, right, bottom, 2.0, color);
+        for x in [left, right] { draw_line(x, bottom, x, bottom - 8.0, 2.0, color); }
+        let label = format!("{}", length);
+        let size = measure_text(&label, None, FONT_SIZE as u16, 1.0);
+        draw_text(&label, (left + right - size.width) / 2.0, bottom - 6.0, FONT_SIZE, color);
+
+        let mouse = vec2tuple(mouse_position());
+        let precision = decimals(length);
+        let readout = format!("x {:.*}  y {:.*}", precision, mouse.x, precision, mouse.y);
+        let size = measure_text(&readout, None, FONT_SIZE as u16, 1.0);
+        draw_text(&readout, right - size.width, bottom - FONT_SIZE - 8.0, FONT_SIZE, color);
+    }
+}
@@ -0,0 +1,118 @@
+// When to retrace: pausing, single steps, disabled collisions and the periodic recompute rate.
+
+/// Decides each frame whether the beam is recomputed. Unpaused it retraces `rate` times a
+/// second, or right away for urgent changes; paused it only retraces on `step`, and remembers
+/// that the shown beam no longer matches the scene. With collisions disabled it never retraces,
+/// not even on `step`.
+#[derive(Clone, Debug)]
+pub struct SimScheduler {
+    /// Periodic recomputes per second.
+    pub rate: f32,
+    elapsed: f32,
+    paused: bool,
+    step_requested: bool,
+    stale: bool,
+    collisions_enabled: bool,
+    /// Collisions were just enabled again, the next `tick` retraces without waiting.
+    resumed: bool,
+}
+
+impl SimScheduler {
+    pub const fn new(rate: f32) -> Self {
+        Self { rate, elapsed: 0.0, paused: false, step_requested: false, stale: false, collisions_enabled: true,
+            resumed: false }
+    }
+
+    pub const fn collisions_enabled(&self) -> bool {
+        self.collisions_enabled
+    }
+
+    /// Freezes the beam as last traced, or lets it follow the scene again, starting right away.
+    pub fn set_collisions_enabled(&mut self, enabled: bool) {
+        if enabled && !self.collisions_enabled { self.resumed = true; }
+        self.collisions_enabled = enabled;
+    }
+
+    pub const fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_requested = false;
+    }
+
+    /// Recompute exactly once on the next `tick`, even while paused.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// The scene changed; while paused that leaves the beam stale until the next step.
+    pub fn mark_changed(&mut self) {
+        if self.paused { self.stale = true; }
+    }
+
+    /// Whether the beam on screen is out of date because of changes made while paused.
+    pub const fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Advances by `delta` seconds and returns whether to recompute this frame. `urgent`
+    /// skips the wait, for changes that would visibly lag, like the laser moving.
+    pub fn tick(&mut self, delta: f32, urgent: bool) -> bool {
+        self.elapsed += delta;
+        if !self.collisions_enabled { return false; }
+        // the beam may be far behind by now
+        let urgent = urgent || std::mem::take(&mut self.resumed);
+        if self.step_requested {
+            self.step_requested = false;
+        } else if self.paused {
+            if urgent { self.stale = true; }
+            return false;
+        } else if !urgent && self.elapsed < 1.0 / self.rate {
+            return false;
+        }
+        self.elapsed = 0.0;
+        self.stale = false;
+        true
+    }
+}
+
+/// Ray budget that follows how long the traces take: a proportional controller nudging the
+/// budget towards whatever would have taken `target_micros`, within `min..=max`.
+#[derive(Clone, Debug)]
+pub struct BudgetController {
+    pub target_micros: f32,
+    pub min: u32,
+    pub max: u32,
+    /// Fraction of the way to the ideal budget each update goes, damping the jitter of timings.
+    pub gain: f32,
+    budget: u32,
+}
+
+impl BudgetController {
+    /// Most one update may scale the budget up or down by.
+    const MAX_STEP: f32 = 2.0;
+
+    pub fn new(budget: u32, target_micros: f32) -> Self {
+        Self { target_micros, min: 100, max: 100_000, gain: 0.5, budget }
+    }
+
+    pub const fn budget(&self) -> u32 {
+        self.budget
+    }
+
+    /// Adjusts the budget after a trace with it took `last_micros`, and returns the new one.
+    /// Trace time is about proportional to the rays traced, so the budget is scaled by how far
+    /// off the target the last trace was.
+    pub fn update(&mut self, last_micros: f32) -> u32 {
+        let scale = if last_micros > 0.0 {
+            (1.0 + self.gain * (self.target_micros / last_micros - 1.0)).clamp(1.0 / Self::MAX_STEP, Self::MAX_STEP)
+        } else {
+            Self::MAX_STEP
+        };
+        let budget = (self.budget as f32 * scale).round() as u32;
+        self.budget = budget.clamp(self.min, self.max.max(self.min));
+        self.budget
+    }
+}
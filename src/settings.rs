@@ -0,0 +1,350 @@
+// Preferences kept between runs: simulation sliders, the laser, the camera, key bindings, the
+// background image and the color theme.
+
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use macroquad::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::background::{BackgroundLayer, BackgroundTransform};
+use crate::core::Attenuation;
+use crate::input::InputMap;
+use crate::theme::Theme;
+use crate::{Laser, LightArea, Modulation, RayColorMode, Sweep};
+
+/// Everything remembered between runs. Missing fields, e.g. from an older file, take their
+/// defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub simulation: SimulationSettings,
+    pub laser: LaserSettings,
+    pub camera: CameraSettings,
+    pub show_ui: bool,
+    pub show_heatmap: bool,
+    pub show_minimap: bool,
+    pub show_scale_bar: bool,
+    pub absorber_glow: bool,
+    /// Periodic beam recomputes per second.
+    pub recompute_rate: f32,
+    pub input: InputMap,
+    pub background: Option<BackgroundSettings>,
+    /// Folder with replacement sprites, see `assets::Textures::load`.
+    pub art: Option<PathBuf>,
+    pub theme: Theme,
+    /// The tutorial has been gone through once, so it no longer starts by itself.
+    pub tutorial_done: bool,
+}
+
+/// The global simulation sliders of the laser window.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationSettings {
+    pub max_rays: f32,
+    pub max_bounces: f32,
+    pub auto_ray_budget: bool,
+    pub target_trace_ms: f32,
+    /// Traces running longer are cut short, see `core::Watchdog`.
+    pub trace_time_limit_ms: f32,
+    pub thick_edges: bool,
+    /// Thickness new edges start out with.
+    pub edge_thickness: f32,
+    pub double_precision: bool,
+    pub refractive_index: f32,
+    /// In degrees.
+    pub max_gloss_angle: f32,
+    pub gloss_samples: f32,
+    pub gloss_seed: f32,
+    pub exposure: f32,
+    pub show_analytics: bool,
+    pub animate_propagation: bool,
+    pub propagation_speed: f32,
+    pub loop_propagation: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaserSettings {
+    /// Beam color as RGBA.
+    pub color: [f32; 4],
+    pub thickness: f32,
+    pub brightness: f32,
+    pub color_mode: RayColorMode,
+    pub modulation: Modulation,
+    pub attenuation: Attenuation,
+    pub soft_beam: bool,
+    pub taper_beam: bool,
+    pub meshed_beam: bool,
+    pub occluder: bool,
+    pub beam_on: bool,
+    pub collision_mask: u8,
+    pub sweep: Sweep,
+    pub light_area: LightArea,
+    pub muzzle_offset: f32,
+    pub sprite_size: f32,
+}
+
+/// A background image loaded from a file and where it was put.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackgroundSettings {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub transform: BackgroundTransform,
+    #[serde(default)]
+    pub opacity: f32,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Zoom change per unit of Ctrl + mouse wheel.
+    pub zoom_step: f32,
+    pub zoom: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            simulation: SimulationSettings::default(),
+            laser: LaserSettings::default(),
+            camera: CameraSettings::default(),
+            show_ui: false,
+            show_heatmap: false,
+            show_minimap: false,
+            show_scale_bar: false,
+            absorber_glow: true,
+            recompute_rate: 60.0,
+            input: InputMap::default(),
+            background: None,
+            art: None,
+            theme: Theme::default(),
+            tutorial_done: false,
+        }
+    }
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            max_rays: 1000.0,
+            max_bounces: 1000.0,
+            auto_ray_budget: false,
+            target_trace_ms: 4.0,
+            trace_time_limit_ms: 250.0,
+            thick_edges: false,
+            edge_thickness: crate::core::DEFAULT_THICKNESS,
+            double_precision: false,
+            refractive_index: 1.5,
+            max_gloss_angle: 30.0,
+            gloss_samples: 1.0,
+            gloss_seed: 0.0,
+            exposure: 2.0,
+            show_analytics: false,
+            animate_propagation: false,
+            propagation_speed: 1000.0,
+            loop_propagation: true,
+        }
+    }
+}
+
+impl Default for LaserSettings {
+    fn default() -> Self {
+        Self::of(&Laser::headless(Default::default(), macroquad::math::Vec2::X))
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self { zoom_step: 0.001, zoom: 1.0 }
+    }
+}
+
+impl Settings {
+    /// File name used next to the executable on native builds.
+    pub const FILE_NAME: &'static str = "settings.json";
+
+    pub fn to_json(&self) -> String {
+        // plain data with string keys, serializing can't fail
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|error| error.to_string())
+    }
+
+    /// Where settings are kept on native builds: next to the executable.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(std::env::current_exe().ok()?.parent()?.join(Self::FILE_NAME))
+    }
+
+    /// Settings from `path`, or the defaults if it is missing or can't be parsed.
+    pub fn load_or_default(path: &Path) -> Self {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(error) => {
+                info!("No settings loaded from {}: {}", path.display(), error);
+                return Self::default();
+            }
+        };
+        Self::from_json(&json).unwrap_or_else(|error| {
+            error!("Ignoring corrupt settings in {}: {}", path.display(), error);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+impl SimulationSettings {
+    /// The values the sliders currently hold.
+    pub fn current() -> Self {
+        unsafe {
+            Self {
+                max_rays: crate::MAX_RAYS,
+                max_bounces: crate::MAX_BOUNCES,
+                auto_ray_budget: crate::AUTO_RAY_BUDGET,
+                target_trace_ms: crate::TARGET_TRACE_MS,
+                trace_time_limit_ms: crate::TRACE_TIME_LIMIT_MS,
+                thick_edges: crate::THICK_EDGES,
+                edge_thickness: crate::EDGE_THICKNESS,
+                double_precision: crate::DOUBLE_PRECISION,
+                refractive_index: crate::REFRACTIVE_INDEX,
+                max_gloss_angle: crate::MAX_GLOSS_ANGLE,
+                gloss_samples: crate::GLOSS_SAMPLES,
+                gloss_seed: crate::GLOSS_SEED,
+                exposure: crate::EXPOSURE,
+                show_analytics: crate::SHOW_ANALYTICS,
+                animate_propagation: crate::ANIMATE_PROPAGATION,
+                propagation_speed: crate::PROPAGATION_SPEED,
+                loop_propagation: crate::LOOP_PROPAGATION,
+            }
+        }
+    }
+
+    /// Moves the sliders to these values.
+    pub fn apply(&self) {
+        unsafe {
+            crate::MAX_RAYS = self.max_rays;
+            crate::MAX_BOUNCES = self.max_bounces;
+            crate::AUTO_RAY_BUDGET = self.auto_ray_budget;
+            crate::TARGET_TRACE_MS = self.target_trace_ms;
+            crate::TRACE_TIME_LIMIT_MS = self.trace_time_limit_ms;
+            crate::THICK_EDGES = self.thick_edges;
+            crate::EDGE_THICKNESS = self.edge_thickness;
+            crate::DOUBLE_PRECISION = self.double_precision;
+            crate::REFRACTIVE_INDEX = self.refractive_index;
+            crate::MAX_GLOSS_ANGLE = self.max_gloss_angle;
+            crate::GLOSS_SAMPLES = self.gloss_samples;
+            crate::GLOSS_SEED = self.gloss_seed;
+            crate::EXPOSURE = self.exposure;
+            crate::SHOW_ANALYTICS = self.show_analytics;
+            crate::ANIMATE_PROPAGATION = self.animate_propagation;
+            crate::PROPAGATION_SPEED = self.propagation_speed;
+            crate::LOOP_PROPAGATION = self.loop_propagation;
+        }
+    }
+}
+
+impl LaserSettings {
+    pub fn of(laser: &Laser) -> Self {
+        let color = laser.ray.color;
+        Self {
+            color: [color.r, color.g, color.b, color.a],
+            thickness: laser.thickness,
+            brightness: laser.brightness,
+            color_mode: laser.color_mode,
+            modulation: laser.modulation,
+            attenuation: laser.attenuation,
+            soft_beam: laser.soft_beam,
+            taper_beam: laser.taper_beam,
+            meshed_beam: laser.meshed_beam,
+            occluder: laser.occluder,
+            beam_on: laser.beam_on,
+            collision_mask: laser.collision_mask,
+            sweep: laser.sweep,
+            light_area: laser.light_area,
+            muzzle_offset: laser.muzzle_offset(),
+            sprite_size: laser.sprite_size(),
+        }
+    }
+
+    pub fn apply(&self, laser: &mut Laser) {
+        let [r, g, b, a] = self.color;
+        laser.ray.color = Color::new(r, g, b, a);
+        laser.thickness = self.thickness;
+        laser.brightness = self.brightness;
+        laser.color_mode = self.color_mode;
+        laser.modulation = self.modulation;
+        laser.attenuation = self.attenuation;
+        laser.soft_beam = self.soft_beam;
+        laser.taper_beam = self.taper_beam;
+        laser.meshed_beam = self.meshed_beam;
+        laser.occluder = self.occluder;
+        laser.beam_on = self.beam_on;
+        laser.collision_mask = self.collision_mask;
+        laser.sweep = self.sweep;
+        laser.light_area = self.light_area;
+        laser.set_muzzle_offset(self.muzzle_offset);
+        laser.set_sprite_size(self.sprite_size);
+    }
+}
+
+impl BackgroundSettings {
+    /// `None` for an image that wasn't loaded from a file, it can't be loaded again.
+    pub fn of(layer: &BackgroundLayer) -> Option<Self> {
+        Some(Self {
+            path: layer.source.clone()?,
+            transform: layer.transform,
+            opacity: layer.opacity,
+            locked: layer.locked,
+        })
+    }
+
+    /// Loads the image again and puts it back where it was.
+    pub fn load(&self) -> Result<BackgroundLayer, String> {
+        let mut layer = BackgroundLayer::load(&self.path)?;
+        layer.transform = self.transform;
+        layer.opacity = self.opacity;
+        layer.locked = self.locked;
+        Ok(layer)
+    }
+}
+
+/// Writes settings once they have stopped changing for `delay` seconds, so dragging a slider
+/// saves once rather than every frame.
+#[derive(Clone, Debug)]
+pub struct AutoSave {
+    saved: Settings,
+    pending: Option<(Settings, f64)>,
+    delay: f64,
+}
+
+impl AutoSave {
+    /// `saved` is what is already on disk.
+    pub const fn new(saved: Settings, delay: f64) -> Self {
+        Self { saved, pending: None, delay }
+    }
+
+    /// Call every frame with the current settings and time; returns the settings to write when
+    /// they are due.
+    pub fn update(&mut self, current: &Settings, now: f64) -> Option<&Settings> {
+        if *current == self.saved {
+            self.pending = None;
+            return None;
+        }
+        match &self.pending {
+            Some((pending, _)) if pending == current => {}
+            _ => self.pending = Some((current.clone(), now)),
+        }
+        let (_, changed_at) = self.pending.as_ref()?;
+        if now - changed_at < self.delay { return None; }
+        let (settings, _) = self.pending.take()?;
+        self.saved = settings;
+        Some(&self.saved)
+    }
+}
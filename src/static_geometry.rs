@@ -0,0 +1,37 @@
+// Walls that are traced and drawn but never edited, for scenes too big to keep as nodes.
+
+use macroquad::math::Vec2;
+use macroquad::shapes::draw_line;
+
+use crate::core::{EdgeState, Segment};
+use crate::{state_color, theme};
+
+/// Segments the beam collides with that have no nodes to hover, drag or pick. Kept by
+/// `NodeNetwork` and traced after its edges, see `NodeNetwork::traced_segments`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StaticGeometry {
+    pub segments: Vec<Segment>,
+}
+
+impl StaticGeometry {
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Every endpoint, for bounding boxes.
+    pub fn points(&self) -> impl Iterator<Item=Vec2> + '_ {
+        self.segments.iter().flat_map(|segment| [segment.0, segment.1])
+    }
+
+    /// One plain line per segment in the color of its state, absorptive ones outlined if the
+    /// theme says so.
+    pub fn draw(&self, thickness: f32) {
+        let outline = theme::current().absorptive_outline;
+        for Segment(start, end, state) in &self.segments {
+            if outline.a > 0.0 && *state == EdgeState::Absorptive {
+                draw_line(start.x, start.y, end.x, end.y, thickness + 2.0, outline);
+            }
+            draw_line(start.x, start.y, end.x, end.y, thickness, state_color(*state));
+        }
+    }
+}
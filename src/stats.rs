@@ -0,0 +1,74 @@
+// Scene statistics and validation warnings, for the F4 panel and for headless logging.
+
+use macroquad::hash;
+use macroquad::math::Vec2;
+use macroquad::ui::{root_ui, widgets};
+
+use crate::core::TraceStats;
+
+/// What `NodeNetwork::stats` found in a network.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkStats {
+    pub nodes: usize,
+    pub edges: usize,
+    pub reflective: usize,
+    pub absorptive: usize,
+    pub transparent: usize,
+    /// Segments handed to the trace, see `NodeNetwork::get_all_connections`.
+    pub traced_segments: usize,
+    /// What `traced_segments` would be after `merge_collinear_segments`.
+    pub merged_segments: usize,
+    /// Connected components, counting lone nodes.
+    pub components: usize,
+    /// Segments of the static geometry, traced after the edges.
+    pub static_segments: usize,
+    /// Edges left out of the trace for being shorter than `MIN_EDGE_LENGTH`.
+    pub zero_length_edges: usize,
+    /// Edges left out of the trace for exactly repeating an earlier one.
+    pub duplicate_edges: usize,
+    /// Edges and the end node they point at that doesn't exist.
+    pub dangling_edges: Vec<(usize, usize)>,
+}
+
+impl NetworkStats {
+    /// Problems worth telling the user about, one line each. `trace` is the last trace, if any.
+    pub fn warnings(&self, trace: Option<&TraceStats>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.zero_length_edges > 0 {
+            warnings.push(format!("{} zero-length edges removed", self.zero_length_edges));
+        }
+        if self.duplicate_edges > 0 {
+            warnings.push(format!("{} duplicate edges removed", self.duplicate_edges));
+        }
+        for (edge, node) in &self.dangling_edges {
+            warnings.push(format!("edge {} points at missing node {}", edge, node));
+        }
+        if trace.is_some_and(|trace| trace.budget_exhausted) {
+            warnings.push("MAX_RAYS budget exhausted last trace".to_string());
+        }
+        if trace.is_some_and(|trace| trace.aborted) {
+            warnings.push("last trace aborted, scene too complex".to_string());
+        }
+        warnings
+    }
+}
+
+/// Window with `network` and `trace` summarized.
+pub fn stats_ui(network: &NetworkStats, trace: &TraceStats) {
+    widgets::Window::new(hash!(), Vec2::new(940., 0.), Vec2::new(280., 300.))
+        .label("Scene")
+        .ui(&mut root_ui(), |ui| {
+            ui.label(None, &format!("nodes {}  edges {}", network.nodes, network.edges));
+            ui.label(None, &format!("reflective {}  absorptive {}  transparent {}",
+                                    network.reflective, network.absorptive, network.transparent));
+            ui.label(None, &format!("segments {}  merged {}", network.traced_segments, network.merged_segments));
+            ui.label(None, &format!("components {}  static segments {}", network.components, network.static_segments));
+            ui.label(None, &format!("lines {}  buffers {:.1} KiB", trace.lines, trace.buffer_bytes as f32 / 1024.0));
+            ui.separator();
+            let warnings = network.warnings(Some(trace));
+            if warnings.is_empty() { ui.label(None, "no warnings"); }
+            for warning in warnings {
+                ui.label(None, &warning);
+            }
+        });
+}
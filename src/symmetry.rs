@@ -0,0 +1,53 @@
+// Mirror axes for the symmetric build mode.
+
+use macroquad::math::{vec2, Vec2};
+
+use crate::core::mirror;
+
+/// Line that nodes placed in symmetric mode are mirrored across, see `NodeNetwork::set_symmetry`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SymmetryAxis {
+    point: Vec2,
+    /// Unit normal of the line.
+    normal: Vec2,
+}
+
+impl SymmetryAxis {
+    /// The line through `a` and `b`, `None` if they coincide.
+    pub fn through(a: Vec2, b: Vec2) -> Option<Self> {
+        let direction = (b - a).try_normalize()?;
+        Some(Self { point: a, normal: direction.perp() })
+    }
+
+    /// The vertical line at `x`, mirroring left and right.
+    pub const fn vertical(x: f32) -> Self {
+        Self { point: vec2(x, 0.0), normal: Vec2::X }
+    }
+
+    /// The horizontal line at `y`, mirroring top and bottom.
+    pub const fn horizontal(y: f32) -> Self {
+        Self { point: vec2(0.0, y), normal: Vec2::Y }
+    }
+
+    pub const fn point(&self) -> Vec2 {
+        self.point
+    }
+
+    pub fn direction(&self) -> Vec2 {
+        -self.normal.perp()
+    }
+
+    /// `position` reflected across the axis.
+    pub fn mirror(&self, position: Vec2) -> Vec2 {
+        self.point + mirror(position - self.point, self.normal)
+    }
+
+    pub fn distance(&self, position: Vec2) -> f32 {
+        (position - self.point).dot(self.normal).abs()
+    }
+
+    /// Closest point on the axis to `position`.
+    pub fn project(&self, position: Vec2) -> Vec2 {
+        position - self.normal * (position - self.point).dot(self.normal)
+    }
+}
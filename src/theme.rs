@@ -0,0 +1,133 @@
+// Colors of everything that isn't light: the background, edges, nodes, hover highlights and
+// text, and how quickly hovering fades between them. The beam keeps its own colors.
+
+use macroquad::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::core::EdgeState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(with = "rgba")]
+    pub background: Color,
+    #[serde(with = "rgba")]
+    pub reflective: Color,
+    #[serde(with = "rgba")]
+    pub absorptive: Color,
+    #[serde(with = "rgba")]
+    pub transparent: Color,
+    /// Drawn around absorptive edges, for backgrounds they'd blend into. Fully transparent
+    /// draws nothing.
+    #[serde(with = "rgba")]
+    pub absorptive_outline: Color,
+    /// Edges and the laser under the cursor.
+    #[serde(with = "rgba")]
+    pub hover: Color,
+    /// Tint of the node sprite.
+    #[serde(with = "rgba")]
+    pub node: Color,
+    #[serde(with = "rgba")]
+    pub pinned_node: Color,
+    /// Counters, hints and other text drawn over the scene.
+    #[serde(with = "rgba")]
+    pub text: Color,
+    /// Light adds onto the background, as on a dark one. Off, it is laid over it, so a beam still
+    /// shows on a light background.
+    pub additive_light: bool,
+    /// Seconds a hovered node takes to grow or shrink halfway to its new size, see
+    /// `smooth_toward`.
+    pub hover_half_life: f32,
+    /// Seconds edge, node and laser colors take to fade halfway to a new color.
+    pub color_half_life: f32,
+    /// Transparent edges are drawn dashed and absorptive ones hatched, see `pattern`, so the
+    /// states don't tell apart by color alone.
+    pub edge_patterns: bool,
+    /// Length of a dash, and spacing of the hatches, in world units. Zoomed far out they grow
+    /// so the pattern stays visible.
+    pub dash_length: f32,
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        background: Color::new(0.15686275, 0.16470589, 0.21176471, 1.0),
+        reflective: Color::new(1.0, 1.0, 1.0, 1.0),
+        absorptive: Color::new(0.0, 0.0, 0.0, 1.0),
+        transparent: Color::new(1.0, 1.0, 1.0, 0.5),
+        absorptive_outline: Color::new(0.0, 0.0, 0.0, 0.0),
+        hover: Color::new(0.4, 0.75, 1.0, 1.0),
+        node: Color::new(1.0, 1.0, 1.0, 1.0),
+        pinned_node: Color::new(0.65, 0.65, 0.7, 1.0),
+        text: Color::new(0.31, 0.31, 0.31, 1.0),
+        additive_light: true,
+        hover_half_life: 0.06,
+        color_half_life: 0.06,
+        edge_patterns: false,
+        dash_length: 12.0,
+    };
+
+    pub const LIGHT: Theme = Theme {
+        background: Color::new(0.93, 0.93, 0.9, 1.0),
+        reflective: Color::new(0.35, 0.45, 0.6, 1.0),
+        absorptive: Color::new(0.2, 0.2, 0.2, 1.0),
+        transparent: Color::new(0.35, 0.55, 0.75, 0.45),
+        absorptive_outline: Color::new(0.0, 0.0, 0.0, 1.0),
+        hover: Color::new(0.1, 0.5, 0.9, 1.0),
+        node: Color::new(0.55, 0.55, 0.6, 1.0),
+        pinned_node: Color::new(0.35, 0.35, 0.45, 1.0),
+        text: Color::new(0.2, 0.2, 0.2, 1.0),
+        additive_light: false,
+        hover_half_life: 0.06,
+        color_half_life: 0.06,
+        edge_patterns: true,
+        dash_length: 12.0,
+    };
+
+    pub const PRESETS: [(&'static str, Theme); 2] = [("Dark", Theme::DARK), ("Light", Theme::LIGHT)];
+
+    /// Color of an edge, or an edge face, in `state`.
+    pub const fn state_color(&self, state: EdgeState) -> Color {
+        match state {
+            EdgeState::Reflective => self.reflective,
+            EdgeState::Absorptive => self.absorptive,
+            EdgeState::Transparent => self.transparent,
+        }
+    }
+
+    /// Index into `PRESETS` of the preset this is, `None` once customized.
+    pub fn preset(&self) -> Option<usize> {
+        Self::PRESETS.iter().position(|(_, preset)| preset == self)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
+
+static mut THEME: Theme = Theme::DARK;
+
+/// The theme everything is drawn with.
+pub fn current() -> Theme {
+    unsafe { THEME }
+}
+
+pub fn set(theme: Theme) {
+    unsafe { THEME = theme; }
+}
+
+/// Colors as `[r, g, b, a]` in the settings file.
+mod rgba {
+    use macroquad::color::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.r, color.g, color.b, color.a].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let [r, g, b, a] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(Color::new(r, g, b, a))
+    }
+}
@@ -0,0 +1,152 @@
+// First steps for new users: a prompt at a time, each moving on only once the user has done
+// what it asks.
+
+use std::collections::HashMap;
+
+use macroquad::color::Color;
+use macroquad::math::{vec2, Vec2};
+use macroquad::prelude::{draw_text, measure_text};
+use macroquad::shapes::{draw_line, draw_rectangle, draw_triangle};
+use macroquad::window::{screen_height, screen_width};
+
+use crate::core::EdgeState;
+use crate::theme;
+use crate::view::world_to_screen;
+use crate::{Laser, NodeNetwork};
+
+const PROMPT_SIZE: f32 = 30.0;
+const ARROW_COLOR: Color = Color::new(1.0, 0.8, 0.2, 1.0);
+/// Screen pixels the arrow stops short of what it points at.
+const ARROW_GAP: f32 = 16.0;
+const ARROW_LENGTH: f32 = 80.0;
+
+/// What a step can't read off the network or the laser.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UiState {
+    pub show_ui: bool,
+    /// The scene as it was when the current step began.
+    pub step_start: Snapshot,
+}
+
+/// The parts of the scene steps compare against.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub nodes: usize,
+    pub connections: usize,
+    /// State of every edge by its ends.
+    pub edge_states: HashMap<(usize, usize), EdgeState>,
+    pub show_ui: bool,
+}
+
+impl Snapshot {
+    pub fn of(network: &NodeNetwork, show_ui: bool) -> Self {
+        Self {
+            nodes: network.nodes.len(),
+            connections: network.connections.len(),
+            edge_states: network.connections.iter().map(|edge| (edge.endpoints(), edge.state())).collect(),
+            show_ui,
+        }
+    }
+}
+
+pub struct Step {
+    pub prompt: &'static str,
+    /// Whether the user has done what `prompt` asks.
+    pub predicate: fn(&NodeNetwork, &Laser, &UiState) -> bool,
+    /// World position the arrow points at, if there is anything to point at.
+    pub target: fn(&NodeNetwork, &Laser) -> Option<Vec2>,
+}
+
+/// The steps a first run goes through.
+pub const STEPS: [Step; 4] = [
+    Step { prompt: "Right-click to place a node", predicate: placed_node, target: |_, _| None },
+    Step {
+        prompt: "Right-click a node, then another, to connect them",
+        predicate: connected_nodes,
+        target: newest_node,
+    },
+    Step { prompt: "Click an edge to change its type", predicate: changed_edge_state, target: newest_edge },
+    Step { prompt: "Press Tab for options", predicate: toggled_ui, target: |_, _| None },
+];
+
+pub fn placed_node(network: &NodeNetwork, _: &Laser, ui: &UiState) -> bool {
+    network.nodes.len() > ui.step_start.nodes
+}
+
+pub fn connected_nodes(network: &NodeNetwork, _: &Laser, ui: &UiState) -> bool {
+    network.connections.len() > ui.step_start.connections
+}
+
+pub fn changed_edge_state(network: &NodeNetwork, _: &Laser, ui: &UiState) -> bool {
+    network.connections.iter()
+        .any(|edge| ui.step_start.edge_states.get(&edge.endpoints()).is_some_and(|&state| state != edge.state()))
+}
+
+pub fn toggled_ui(_: &NodeNetwork, _: &Laser, ui: &UiState) -> bool {
+    ui.show_ui != ui.step_start.show_ui
+}
+
+fn newest_node(network: &NodeNetwork, _: &Laser) -> Option<Vec2> {
+    network.nodes.keys().max().and_then(|&node| network.node_position(node))
+}
+
+fn newest_edge(network: &NodeNetwork, _: &Laser) -> Option<Vec2> {
+    let (a, b) = network.connections.last()?.endpoints();
+    Some((network.node_position(a)? + network.node_position(b)?) / 2.0)
+}
+
+/// Walks through `steps` in order, see `update`.
+pub struct Tutorial {
+    steps: &'static [Step],
+    current: usize,
+    ui: UiState,
+}
+
+impl Tutorial {
+    /// Starts at the first of `steps`, judged from the scene as it is now.
+    pub fn new(steps: &'static [Step], network: &NodeNetwork, show_ui: bool) -> Self {
+        Self { steps, current: 0, ui: UiState { show_ui, step_start: Snapshot::of(network, show_ui) } }
+    }
+    /// Moves on to the next step if the current one is done. Returns whether it did.
+    pub fn update(&mut self, network: &NodeNetwork, laser: &Laser, show_ui: bool) -> bool {
+        let Some(step) = self.steps.get(self.current) else { return false; };
+        self.ui.show_ui = show_ui;
+        if !(step.predicate)(network, laser, &self.ui) { return false; }
+        self.current += 1;
+        self.ui.step_start = Snapshot::of(network, show_ui);
+        true
+    }
+    /// The step waiting on the user, `None` once all are done.
+    pub fn step(&self) -> Option<&Step> {
+        self.steps.get(self.current)
+    }
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.steps.len()
+    }
+    /// Draws the prompt at the top of the screen, and an arrow at its target. Call in screen
+    /// space.
+    pub fn draw(&self, network: &NodeNetwork, laser: &Laser, camera_target: Vec2, zoom: f32) {
+        let Some(step) = self.step() else { return; };
+        let text = format!("{} ({}/{})", step.prompt, self.current + 1, self.steps.len());
+        let size = measure_text(&text, None, PROMPT_SIZE as u16, 1.0);
+        let corner = vec2((screen_width() - size.width) / 2.0, 60.0);
+        let background = theme::current().background;
+        draw_rectangle(corner.x - 10.0, corner.y - size.offset_y - 6.0, size.width + 20.0, size.height + 12.0,
+                       Color { a: 0.8, ..background });
+        draw_text(&text, corner.x, corner.y, PROMPT_SIZE, theme::current().text);
+        if let Some(target) = (step.target)(network, laser) {
+            let screen = vec2(screen_width(), screen_height());
+            draw_arrow(world_to_screen(target, screen, camera_target, zoom), screen / 2.0);
+        }
+    }
+}
+
+/// Arrow pointing at `target`, coming from the side of `from`.
+fn draw_arrow(target: Vec2, from: Vec2) {
+    let direction = (target - from).try_normalize().unwrap_or(Vec2::Y);
+    let tip = target - direction * ARROW_GAP;
+    let tail = tip - direction * ARROW_LENGTH;
+    draw_line(tail.x, tail.y, tip.x, tip.y, 4.0, ARROW_COLOR);
+    let side = direction.perp() * 10.0;
+    draw_triangle(tip + direction * 6.0, tip - direction * 14.0 + side, tip - direction * 14.0 - side, ARROW_COLOR);
+}
@@ -0,0 +1,80 @@
+// Framing math for cameras and offscreen renders, kept free of any drawing.
+
+use macroquad::math::{Rect, Vec2};
+
+// All screen positions are in logical pixels, the space macroquad's `mouse_position` and
+// `screen_width`/`screen_height` already use on high-dpi windows.
+
+/// World position shown at `screen` by a camera centred on `camera_target`.
+pub fn screen_to_world(screen: Vec2, screen_size: Vec2, camera_target: Vec2, zoom: f32) -> Vec2 {
+    (screen - screen_size / 2.0) / zoom + camera_target
+}
+
+pub fn world_to_screen(world: Vec2, screen_size: Vec2, camera_target: Vec2, zoom: f32) -> Vec2 {
+    (world - camera_target) * zoom + screen_size / 2.0
+}
+
+/// Camera target after changing `zoom` to `new_zoom` that keeps the world point under `cursor`
+/// where it is on screen.
+pub fn zoom_around(cursor: Vec2, screen_size: Vec2, camera_target: Vec2, zoom: f32, new_zoom: f32) -> Vec2 {
+    let anchor = screen_to_world(cursor, screen_size, camera_target, zoom);
+    anchor - (cursor - screen_size / 2.0) / new_zoom
+}
+
+/// Smallest rect containing all `points`, grown by `margin` on every side. `None` without points.
+pub fn bounding_rect(points: impl IntoIterator<Item=Vec2>, margin: f32) -> Option<Rect> {
+    let mut points = points.into_iter();
+    let first = points.next()?;
+    let (min, max) = points.fold((first, first), |(min, max), point| (min.min(point), max.max(point)));
+    let (min, max) = (min - Vec2::splat(margin), max + Vec2::splat(margin));
+    Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+}
+
+/// `rect` grown around its centre along one axis until `width / height == aspect`, so
+/// rendering it into a target of that aspect doesn't stretch anything.
+pub fn fit_aspect(rect: Rect, aspect: f32) -> Rect {
+    let center = rect.center();
+    let (mut width, mut height) = (rect.w.max(f32::EPSILON), rect.h.max(f32::EPSILON));
+    if width / height < aspect { width = height * aspect; } else { height = width / aspect; }
+    Rect::new(center.x - width / 2.0, center.y - height / 2.0, width, height)
+}
+
+/// Camera target and zoom showing all of `rect` on a `screen` sized window, with `margin` (a
+/// fraction of the rect size) of space on every side.
+pub fn fit_camera(rect: Rect, screen: Vec2, margin: f32) -> (Vec2, f32) {
+    let size = rect.size() * (1.0 + 2.0 * margin);
+    let zoom = (screen.x / size.x).min(screen.y / size.y);
+    // a single point (or nothing at all) has no scale to fit
+    (rect.center(), if zoom.is_finite() { zoom } else { 1.0 })
+}
+
+/// The part of the world on a `screen_size` window with the camera at `camera_target` and `zoom`.
+pub fn view_rect(camera_target: Vec2, zoom: f32, screen_size: Vec2) -> Rect {
+    let min = screen_to_world(Vec2::ZERO, screen_size, camera_target, zoom);
+    let size = screen_to_world(screen_size, screen_size, camera_target, zoom) - min;
+    Rect::new(min.x, min.y, size.x, size.y)
+}
+
+/// What turning the mouse wheel does to the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WheelMode {
+    /// Zooms around the cursor (`Action::WheelZoom`).
+    Zoom,
+    /// Scrolls sideways (`Action::WheelHorizontal`).
+    ScrollHorizontal,
+    ScrollVertical,
+}
+
+/// Camera target and zoom after the wheel turned by `wheel` in `mode`. Zooming changes the zoom
+/// by a factor of `exp(zoom_step * wheel)` and keeps the world point under `cursor` in place.
+pub fn apply_wheel(mode: WheelMode, wheel: f32, cursor: Vec2, screen_size: Vec2, camera_target: Vec2,
+                   zoom: f32, zoom_step: f32) -> (Vec2, f32) {
+    match mode {
+        WheelMode::Zoom => {
+            let new_zoom = zoom * (zoom_step * wheel).exp();
+            (zoom_around(cursor, screen_size, camera_target, zoom, new_zoom), new_zoom)
+        }
+        WheelMode::ScrollHorizontal => (camera_target - Vec2::X * wheel / zoom * 0.25, zoom),
+        WheelMode::ScrollVertical => (camera_target - Vec2::Y * wheel / zoom * 0.25, zoom),
+    }
+}
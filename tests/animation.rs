@@ -0,0 +1,50 @@
+use std::f32::consts::PI;
+
+use macroquad::math::{vec2, Vec2};
+use ray_cast::{Animation, NodeNetwork};
+
+fn close(a: Vec2, b: Vec2) -> bool {
+    a.distance(b) < 1e-3
+}
+
+#[test]
+fn orbit_circles_its_center() {
+    let orbit = Animation::Orbit { center: vec2(10.0, 10.0), radius: 5.0, angular_speed: PI, phase: 0.0 };
+
+    assert!(close(orbit.position_at(Vec2::ZERO, 0.0), vec2(15.0, 10.0)));
+    assert!(close(orbit.position_at(Vec2::ZERO, 0.5), vec2(10.0, 15.0)));
+    assert!(close(orbit.position_at(Vec2::ZERO, 1.0), vec2(5.0, 10.0)));
+    assert!(close(orbit.position_at(Vec2::ZERO, 2.0), vec2(15.0, 10.0)));
+}
+
+#[test]
+fn orbit_through_starts_at_the_given_point() {
+    let position = vec2(3.0, -4.0);
+
+    assert!(close(Animation::orbit_through(position, 20.0, 1.0).position_at(Vec2::ZERO, 0.0), position));
+}
+
+#[test]
+fn oscillation_swings_around_the_anchor() {
+    let swing = Animation::Oscillate { axis: vec2(0.0, 2.0), amplitude: 10.0, speed: PI };
+    let anchor = vec2(100.0, 100.0);
+
+    assert!(close(swing.position_at(anchor, 0.0), anchor));
+    assert!(close(swing.position_at(anchor, 0.5), vec2(100.0, 110.0)));
+    assert!(close(swing.position_at(anchor, 1.5), vec2(100.0, 90.0)));
+}
+
+#[test]
+fn attaching_and_detaching_marks_the_network_animated() {
+    let mut network = NodeNetwork::headless();
+    let node = network.add_node(vec2(1.0, 2.0));
+    assert!(!network.is_animated());
+
+    let swing = Animation::Oscillate { axis: Vec2::X, amplitude: 1.0, speed: 1.0 };
+    network.set_animation(node, Some(swing));
+    assert!(network.is_animated());
+    assert_eq!(network.animation(node), Some(swing));
+
+    network.set_animation(node, None);
+    assert!(!network.is_animated());
+}
@@ -0,0 +1,24 @@
+use macroquad::color::Color;
+use ray_cast::assets::{decode_image, placeholder_image};
+
+#[test]
+fn the_embedded_art_decodes_and_garbage_does_not() {
+    let laser = decode_image(include_bytes!("../assets/laser.png")).unwrap();
+    assert!(laser.width > 0 && laser.height > 0);
+    assert!(decode_image(include_bytes!("../assets/node2.png")).is_ok());
+
+    assert!(decode_image(b"not an image").is_err());
+    assert!(decode_image(&[]).is_err());
+}
+
+#[test]
+fn placeholders_are_a_solid_circle() {
+    const RED: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+    let image = placeholder_image(16, RED);
+
+    assert_eq!((image.width, image.height), (16, 16));
+    assert_eq!(image.get_pixel(8, 8), RED);
+    assert_eq!(image.get_pixel(0, 8), RED);
+    assert_eq!(image.get_pixel(0, 0), Color::new(0.0, 0.0, 0.0, 0.0));
+    assert_eq!(image.get_pixel(15, 15), Color::new(0.0, 0.0, 0.0, 0.0));
+}
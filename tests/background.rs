@@ -0,0 +1,45 @@
+use std::f32::consts::FRAC_PI_2;
+
+use macroquad::math::{vec2, Vec2};
+use ray_cast::background::BackgroundTransform;
+use ray_cast::settings::{BackgroundSettings, Settings};
+
+#[test]
+fn transform_round_trips_between_world_and_image() {
+    let transform = BackgroundTransform { position: vec2(100.0, 50.0), scale: 2.0, rotation: FRAC_PI_2 };
+
+    // a quarter turn takes the image's x axis onto the world's y axis
+    assert!(transform.to_world(vec2(10.0, 0.0)).abs_diff_eq(vec2(100.0, 70.0), 1e-4));
+    assert!(transform.to_image(vec2(100.0, 70.0)).abs_diff_eq(vec2(10.0, 0.0), 1e-4));
+    let pixel = vec2(-13.0, 27.5);
+    assert!(transform.to_image(transform.to_world(pixel)).abs_diff_eq(pixel, 1e-4));
+    assert_eq!(BackgroundTransform::default().to_world(pixel), pixel);
+}
+
+#[test]
+fn containment_follows_scale_and_rotation() {
+    let size = vec2(100.0, 20.0);
+    let transform = BackgroundTransform { position: Vec2::ZERO, scale: 0.5, rotation: FRAC_PI_2 };
+
+    // 50 x 10 in the world, standing upright
+    assert!(transform.contains(size, vec2(0.0, 24.0)));
+    assert!(transform.contains(size, vec2(4.0, -24.0)));
+    assert!(!transform.contains(size, vec2(24.0, 0.0)));
+    assert!(!transform.contains(size, vec2(0.0, 26.0)));
+}
+
+#[test]
+fn background_placement_is_saved_with_the_settings() {
+    let settings = Settings {
+        background: Some(BackgroundSettings {
+            path: "diagram.png".into(),
+            transform: BackgroundTransform { position: vec2(3.0, 4.0), scale: 0.25, rotation: 1.0 },
+            opacity: 0.4,
+            locked: true,
+        }),
+        ..Settings::default()
+    };
+
+    assert_eq!(Settings::from_json(&settings.to_json()).unwrap(), settings);
+    assert_eq!(Settings::from_json("{}").unwrap().background, None);
+}
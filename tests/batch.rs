@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use macroquad::math::{vec2, Rect, Vec2};
+use ray_cast::batch::{self, BatchArgs, BatchError, Command};
+use ray_cast::core::DEFAULT_THICKNESS;
+use ray_cast::replay::Recorder;
+use ray_cast::{EdgeState, Laser, NodeNetwork};
+
+fn args(line: &str) -> Result<Command, BatchError> {
+    Command::parse(line.split_whitespace().map(str::to_string))
+}
+
+/// A file in the temp directory, unique to this test binary and `name`.
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ray_cast_batch_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn the_command_line_picks_the_mode() {
+    assert_eq!(args(""), Ok(Command::Window));
+    assert_eq!(args("--help"), Ok(Command::Help));
+    assert_eq!(args("--scene s.json --trace-out t.csv --svg-out r.svg --max-rays 50000 --time-limit-ms 900"),
+               Ok(Command::Batch(BatchArgs {
+                   scene: PathBuf::from("s.json"),
+                   trace_out: Some(PathBuf::from("t.csv")),
+                   svg_out: Some(PathBuf::from("r.svg")),
+                   max_rays: Some(50_000),
+                   time_limit_ms: Some(900.0),
+               })));
+    for bad in ["--trace-out t.csv", "--scene", "--scene s.json --max-rays many", "--frobnicate",
+        "--scene s.json --png-out r.png"] {
+        let error = args(bad).unwrap_err();
+        assert!(matches!(error, BatchError::Usage(_)), "{}", bad);
+        assert_eq!(error.exit_code(), 2);
+    }
+}
+
+#[test]
+fn a_saved_session_is_traced_into_csv_and_svg() {
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(vec2(20.0, 20.0), Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    network.add_bounds(Rect::new(0.0, 0.0, 400.0, 300.0), EdgeState::Reflective);
+    network.set_edge_thickness(0, 12.5);
+    laser.place(vec2(50.0, 150.0), vec2(300.0, 97.0));
+    recorder.collect(0.0, &mut network, &laser);
+    let scene = temp_file("scene.json");
+    recorder.into_session().save(&scene).unwrap();
+
+    let (trace_out, svg_out) = (temp_file("trace.csv"), temp_file("render.svg"));
+    let run = BatchArgs {
+        scene: scene.clone(),
+        trace_out: Some(trace_out.clone()),
+        svg_out: Some(svg_out.clone()),
+        max_rays: Some(4),
+        time_limit_ms: Some(10_000.0),
+    };
+    let stats = batch::run(&run).unwrap();
+    assert_eq!(stats.lines, 4);
+    assert!(stats.budget_exhausted);
+    let csv = std::fs::read_to_string(&trace_out).unwrap();
+    assert_eq!(csv.lines().count(), 1 + 4);
+    let svg = std::fs::read_to_string(&svg_out).unwrap();
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<line").count(), 4 + 4);
+    assert_eq!(svg.matches(r#"stroke-width="12.5""#).count(), 1);
+    assert_eq!(svg.matches(&format!(r#"stroke-width="{}""#, DEFAULT_THICKNESS)).count(), 3);
+
+    let missing = batch::run(&BatchArgs { scene: temp_file("missing.json"), ..run.clone() }).unwrap_err();
+    assert_eq!(missing.exit_code(), 3);
+    let unwritable = BatchArgs { trace_out: Some(temp_file("no/such/dir/trace.csv")), ..run };
+    assert_eq!(batch::run(&unwritable).unwrap_err().exit_code(), 5);
+    for file in [scene, trace_out, svg_out] { std::fs::remove_file(file).unwrap(); }
+}
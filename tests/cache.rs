@@ -0,0 +1,114 @@
+use macroquad::color::{BLUE, RED};
+use macroquad::math::{vec2, Vec2};
+use ray_cast::cache::{TraceCache, TraceKey};
+use ray_cast::core::{Precision, SimConfig, Watchdog};
+use ray_cast::{Laser, Ray, RayColorMode, TraceResult};
+
+fn key(ray: Ray, generation: u64, config: &SimConfig) -> TraceKey {
+    TraceKey::new(ray, RayColorMode::Uniform, generation, config)
+}
+
+fn cached(cache: &mut TraceCache, key: TraceKey) -> bool {
+    let hit = cache.get(key).is_some();
+    if !hit { cache.insert(key, TraceResult::default()); }
+    hit
+}
+
+#[test]
+fn repeated_key_hits() {
+    let mut cache = TraceCache::new(4);
+    let ray = Ray::new(Vec2::ZERO, Vec2::X, RED);
+    let config = SimConfig::default();
+
+    assert!(!cached(&mut cache, key(ray, 0, &config)));
+    assert!(cached(&mut cache, key(ray, 0, &config)));
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+}
+
+#[test]
+fn each_kind_of_change_misses() {
+    let mut cache = TraceCache::new(16);
+    let ray = Ray::new(Vec2::ZERO, Vec2::X, RED);
+    let config = SimConfig::default();
+    assert!(!cached(&mut cache, key(ray, 0, &config)));
+
+    let moved = Ray { origin: vec2(1.0, 0.0), ..ray };
+    let rotated = Ray { direction: Vec2::Y, ..ray };
+    let recolored = Ray { color: BLUE, ..ray };
+    assert!(!cached(&mut cache, key(moved, 0, &config)));
+    assert!(!cached(&mut cache, key(rotated, 0, &config)));
+    assert!(!cached(&mut cache, key(recolored, 0, &config)));
+    assert!(!cached(&mut cache, key(ray, 1, &config)));
+    assert!(!cached(&mut cache, key(ray, 0, &SimConfig { max_rays: 10, ..config })));
+    assert!(!cached(&mut cache, key(ray, 0, &SimConfig { edge_radius: Some(2.0), ..config })));
+    assert!(!cached(&mut cache, key(ray, 0, &SimConfig { refractive_index: 1.3, ..config })));
+    assert!(!cached(&mut cache, key(ray, 0, &SimConfig { precision: Precision::Double, ..config })));
+    assert!(!cached(&mut cache, key(ray, 0, &SimConfig { watchdog: Watchdog::Micros(5000.0), ..config })));
+    assert!(!cached(&mut cache, key(ray, 0, &SimConfig { watchdog: Watchdog::Micros(9000.0), ..config })));
+    assert!(!cached(&mut cache, TraceKey::new(ray, RayColorMode::ByBounceDepth, 0, &config)));
+    assert_eq!(cache.hits(), 0);
+}
+
+#[test]
+fn wiggling_back_and_forth_hits() {
+    let mut cache = TraceCache::new(4);
+    let config = SimConfig::default();
+    let poses = [Vec2::X, vec2(1.0, 0.1).normalize(), vec2(1.0, 0.2).normalize()];
+    for direction in poses { cached(&mut cache, key(Ray::new(Vec2::ZERO, direction, RED), 0, &config)); }
+
+    assert!(poses.iter().rev().all(|&direction| cached(&mut cache, key(Ray::new(Vec2::ZERO, direction, RED), 0, &config))));
+}
+
+#[test]
+fn least_recently_used_is_evicted() {
+    let mut cache = TraceCache::new(2);
+    let config = SimConfig::default();
+    let keys: Vec<_> = (0..3).map(|generation| key(Ray::new(Vec2::ZERO, Vec2::X, RED), generation, &config)).collect();
+    cached(&mut cache, keys[0]);
+    cached(&mut cache, keys[1]);
+    assert!(cached(&mut cache, keys[0]));
+    cached(&mut cache, keys[2]);
+
+    assert_eq!(cache.len(), 2);
+    assert!(cached(&mut cache, keys[0]));
+    assert!(!cached(&mut cache, keys[1]));
+}
+
+#[test]
+fn traces_cut_short_are_not_stored() {
+    let mut cache = TraceCache::new(4);
+    let key = key(Ray::new(Vec2::ZERO, Vec2::X, RED), 0, &SimConfig::default());
+    let mut aborted = TraceResult::default();
+    aborted.stats.aborted = true;
+
+    cache.insert(key, aborted);
+
+    assert!(cache.is_empty());
+    assert!(cache.get(key).is_none());
+}
+
+#[test]
+fn invalidate_forgets_everything() {
+    let mut cache = TraceCache::new(4);
+    let laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    cached(&mut cache, laser.trace_key(3));
+
+    cache.invalidate();
+
+    assert!(cache.is_empty());
+    assert!(!cached(&mut cache, laser.trace_key(3)));
+}
+
+#[test]
+fn laser_key_follows_pose_and_color_mode() {
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let before = laser.trace_key(0);
+
+    assert_eq!(laser.trace_key(0), before);
+    assert_ne!(laser.trace_key(1), before);
+    laser.color_mode = RayColorMode::ByPathLength;
+    assert_ne!(laser.trace_key(0), before);
+    laser.color_mode = RayColorMode::Uniform;
+    laser.place(vec2(5.0, 5.0), vec2(10.0, 5.0));
+    assert_ne!(laser.trace_key(0), before);
+}
@@ -0,0 +1,43 @@
+use macroquad::color::{Color, RED};
+use ray_cast::{color_for, Laser, RayColorMode};
+
+#[test]
+fn uniform_keeps_the_base_color_at_the_given_intensity() {
+    let color = color_for(RayColorMode::Uniform, RED, 5, 100.0, 0.25);
+
+    assert_eq!(color, Color { a: 0.25, ..RED });
+}
+
+#[test]
+fn bounce_depth_changes_hue_per_bounce_and_wraps() {
+    let at = |bounce| color_for(RayColorMode::ByBounceDepth, RED, bounce, 0.0, 1.0);
+
+    assert_ne!(at(0), at(1));
+    assert_ne!(at(1), at(2));
+    assert_eq!(at(0), at(12));
+}
+
+#[test]
+fn path_length_runs_cold_to_hot_up_to_max_distance() {
+    let at = |length| color_for(RayColorMode::ByPathLength, RED, 0, length, 0.5);
+    let (start, end) = (at(0.0), at(Laser::MAX_DISTANCE));
+
+    assert!(start.b > start.r);
+    assert!(end.r > end.b);
+    assert_eq!(at(Laser::MAX_DISTANCE * 2.0), end);
+    assert_eq!(start.a, 0.5);
+}
+
+#[test]
+fn intensity_maps_to_brightness() {
+    assert_eq!(color_for(RayColorMode::ByIntensity, RED, 3, 10.0, 0.5), Color::new(0.5, 0.5, 0.5, 1.0));
+}
+
+#[test]
+fn labels_are_unique() {
+    let mut labels = RayColorMode::ALL.map(RayColorMode::label).to_vec();
+    labels.sort_unstable();
+    labels.dedup();
+
+    assert_eq!(labels.len(), RayColorMode::ALL.len());
+}
@@ -0,0 +1,1163 @@
+use std::collections::{BTreeSet, HashMap};
+
+use macroquad::color::Color;
+use macroquad::math::{dvec2, vec2, DVec2, Vec2};
+use ray_cast::core::{find_closest_segment_new, merge_collinear_segments, scatter, trace, trace_lights,
+                     trace_lights_into, trace_with_media, trace_with_surfaces, visibility_polygon, AbsorptionCurve,
+                     Attenuation, ClosestHit, CollisionInfo, EdgeState, EmitterConfig, Interaction, Precision, Ray,
+                     Ray64, Segment, segment_intersection, SegmentIntersection, SimConfig, Surfaces, Termination,
+                     TraceResult, TraceScratch, Watchdog};
+use ray_cast::labyrinth::{Algorithm, Labyrinth};
+
+const RED: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+
+fn mirror_box(min: Vec2, max: Vec2, state: EdgeState) -> Vec<Segment> {
+    vec![
+        Segment(vec2(min.x, min.y), vec2(max.x, min.y), state),
+        Segment(vec2(max.x, min.y), vec2(max.x, max.y), state),
+        Segment(vec2(max.x, max.y), vec2(min.x, max.y), state),
+        Segment(vec2(min.x, max.y), vec2(min.x, min.y), state),
+    ]
+}
+
+#[test]
+fn mirror_box_bounces_until_budget() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+    let config = SimConfig { max_rays: 25, ..Default::default() };
+
+    let hits = trace(ray, &segments, &config).hits;
+
+    assert_eq!(hits.len(), 25);
+    assert!(hits.iter().all(|hit| hit.interaction == Interaction::Reflected));
+    assert!(hits.iter().all(|hit| hit.segment.is_some()));
+    for pair in hits.windows(2) {
+        assert_eq!(pair[0].position, pair[1].origin);
+        assert_ne!(pair[0].segment, pair[1].segment);
+    }
+}
+
+#[test]
+fn mirror_box_first_hit_is_right_wall() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), vec2(1.0, 0.0), RED);
+
+    let hits = trace(ray, &segments, &SimConfig { max_rays: 3, ..Default::default() }).hits;
+
+    assert_eq!(hits[0].segment, Some(1));
+    assert!(hits[0].position.abs_diff_eq(vec2(100.0, 50.0), 1e-4));
+    assert!(hits[1].position.abs_diff_eq(vec2(0.0, 50.0), 1e-4));
+    assert_eq!(hits[1].segment, Some(3));
+}
+
+#[test]
+fn absorber_terminates_trace() {
+    let segments = [
+        Segment(vec2(100.0, -50.0), vec2(100.0, 50.0), EdgeState::Absorptive),
+        Segment(vec2(-100.0, -50.0), vec2(-100.0, 50.0), EdgeState::Reflective),
+    ];
+    let ray = Ray::new(Vec2::ZERO, vec2(1.0, 0.0), RED);
+
+    let hits = trace(ray, &segments, &SimConfig::default()).hits;
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].interaction, Interaction::Absorbed);
+    assert_eq!(hits[0].segment, Some(0));
+    assert!(hits[0].position.abs_diff_eq(vec2(100.0, 0.0), 1e-4));
+}
+
+#[test]
+fn parallel_ray_misses() {
+    let segments = [Segment(vec2(0.0, 10.0), vec2(100.0, 10.0), EdgeState::Reflective)];
+    let ray = Ray::new(Vec2::ZERO, vec2(1.0, 0.0), RED);
+    let config = SimConfig::default();
+
+    let hits = trace(ray, &segments, &config).hits;
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].interaction, Interaction::Escaped);
+    assert_eq!(hits[0].segment, None);
+    assert!(hits[0].position.abs_diff_eq(vec2(config.max_distance, 0.0), 1e-2));
+}
+
+#[test]
+fn ray_stays_inside_sealed_labyrinth() {
+    let mut labyrinth = Labyrinth::new(50.0, (6, 6));
+    labyrinth.generate_depth_first(7);
+    let segments: Vec<Segment> = labyrinth.get_as_lines().iter()
+        .map(|&((x1, y1), (x2, y2))| Segment(vec2(x1, y1), vec2(x2, y2), EdgeState::Reflective))
+        .collect();
+    let ray = Ray::new(vec2(25.0, 25.0), Vec2::from_angle(0.3), RED);
+
+    let hits = trace(ray, &segments, &SimConfig { max_rays: 200, ..Default::default() }).hits;
+
+    assert_eq!(hits.len(), 200);
+    for hit in &hits {
+        assert_eq!(hit.interaction, Interaction::Reflected);
+        assert!((-1e-3..=300.001).contains(&hit.position.x), "{:?}", hit);
+        assert!((-1e-3..=300.001).contains(&hit.position.y), "{:?}", hit);
+    }
+}
+
+#[test]
+fn box_corner_retro_reflects() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    for (direction, corner) in [
+        (vec2(1.0, 1.0), vec2(100.0, 100.0)),
+        (vec2(-1.0, 1.0), vec2(0.0, 100.0)),
+        (vec2(-1.0, -1.0), vec2(0.0, 0.0)),
+        (vec2(1.0, -1.0), vec2(100.0, 0.0)),
+    ] {
+        let ray = Ray::new(vec2(50.0, 50.0), direction.normalize(), RED);
+
+        let hits = trace(ray, &segments, &SimConfig { max_rays: 10, ..Default::default() }).hits;
+
+        assert_eq!(hits.len(), 10);
+        assert!(hits[0].position.abs_diff_eq(corner, 1e-3), "{:?}", hits[0]);
+        // bounces back through the centre into the opposite corner
+        assert!(hits[1].position.abs_diff_eq(vec2(100.0, 100.0) - corner, 1e-3), "{:?}", hits[1]);
+        assert!(hits.iter().all(|hit| hit.interaction == Interaction::Reflected));
+    }
+}
+
+#[test]
+fn shared_node_between_collinear_edges_reflects() {
+    let segments = [
+        Segment(vec2(100.0, 0.0), vec2(100.0, 50.0), EdgeState::Reflective),
+        Segment(vec2(100.0, 50.0), vec2(100.0, 100.0), EdgeState::Reflective),
+    ];
+    let ray = Ray::new(vec2(50.0, 50.0), vec2(1.0, 0.0), RED);
+
+    let hits = trace(ray, &segments, &SimConfig { max_rays: 3, ..Default::default() }).hits;
+
+    assert_eq!(hits[0].interaction, Interaction::Reflected);
+    assert!(hits[0].position.abs_diff_eq(vec2(100.0, 50.0), 1e-4));
+    assert_eq!(hits[1].interaction, Interaction::Escaped);
+    assert!(hits[1].position.x < 0.0);
+}
+
+#[test]
+fn a_t_junction_reflects_off_the_wall_and_not_the_stem_behind_it() {
+    let wall = Segment(vec2(0.0, 100.0), vec2(200.0, 100.0), EdgeState::Reflective);
+    let stem = Segment(vec2(100.0, 100.0), vec2(100.0, 200.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), vec2(1.0, 1.0).normalize(), RED);
+    for segments in [[wall, stem], [stem, wall]] {
+        for precision in [Precision::Single, Precision::Double] {
+            let config = SimConfig { max_rays: 3, precision, ..Default::default() };
+
+            let hits = trace(ray, &segments, &config).hits;
+
+            assert!(hits[0].position.abs_diff_eq(vec2(100.0, 100.0), 1e-3), "{:?}", hits[0]);
+            assert_eq!(hits[1].interaction, Interaction::Escaped);
+            assert!(hits[1].position.x > 100.0 && hits[1].position.y < 100.0, "{:?} {:?}", precision, hits[1]);
+        }
+    }
+}
+
+#[test]
+fn a_corner_of_a_short_and_a_long_wall_catches_a_ray_just_off_it() {
+    // bouncing through a maze left this ray a rounding error off the corner: it meets the long
+    // wall a little inside its end and misses the short one by as little
+    let segments = [
+        Segment(vec2(0.0, 0.0), vec2(100.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(0.0, 0.0), vec2(0.0, 50.0), EdgeState::Reflective),
+    ];
+    let origin = vec2(100.000694, 100.0);
+    let ray = Ray::new(origin, (vec2(0.000679, 7.6e-6) - origin).normalize(), RED);
+
+    let hits = trace(ray, &segments, &SimConfig { max_rays: 3, ..Default::default() }).hits;
+
+    assert_eq!(hits[0].interaction, Interaction::Reflected);
+    assert!(hits[1].position.x > 50.0 && hits[1].position.y > 50.0, "{:?}", hits[1]);
+}
+
+#[test]
+fn ray_along_labyrinth_grid_lines_stays_inside() {
+    for seed in 0..8 {
+        let mut labyrinth = Labyrinth::new(50.0, (6, 6));
+        labyrinth.generate_depth_first(seed);
+        let segments: Vec<Segment> = labyrinth.get_as_lines().iter()
+            .map(|&((x1, y1), (x2, y2))| Segment(vec2(x1, y1), vec2(x2, y2), EdgeState::Reflective))
+            .collect();
+        // starts on a cell centre and travels through wall junctions on the diagonal
+        let ray = Ray::new(vec2(25.0, 25.0), vec2(1.0, 1.0).normalize(), RED);
+
+        let hits = trace(ray, &segments, &SimConfig { max_rays: 200, ..Default::default() }).hits;
+
+        for hit in &hits {
+            assert_ne!(hit.interaction, Interaction::Escaped, "seed {}: {:?}", seed, hit);
+            assert!((-1e-3..=300.001).contains(&hit.position.x), "seed {}: {:?}", seed, hit);
+            assert!((-1e-3..=300.001).contains(&hit.position.y), "seed {}: {:?}", seed, hit);
+        }
+    }
+}
+
+#[test]
+fn normal_faces_ray_on_horizontal_segment() {
+    let segment = (vec2(0.0, 0.0), vec2(100.0, 0.0));
+
+    let from_above = Ray::new(vec2(25.0, -10.0), vec2(0.0, 1.0), RED);
+    let (position, normal, t) = from_above.collides_with(segment).unwrap();
+    assert!(position.abs_diff_eq(vec2(25.0, 0.0), 1e-5));
+    assert!(normal.abs_diff_eq(vec2(0.0, -1.0), 1e-6));
+    assert!((t - 0.25).abs() < 1e-6);
+
+    let from_below = Ray::new(vec2(75.0, 10.0), vec2(0.0, -1.0), RED);
+    let (position, normal, t) = from_below.collides_with(segment).unwrap();
+    assert!(position.abs_diff_eq(vec2(75.0, 0.0), 1e-5));
+    assert!(normal.abs_diff_eq(vec2(0.0, 1.0), 1e-6));
+    assert!((t - 0.75).abs() < 1e-6);
+}
+
+#[test]
+fn normal_faces_ray_on_diagonal_segment() {
+    let segment = (vec2(0.0, 0.0), vec2(100.0, 100.0));
+    let expected = vec2(1.0, -1.0).normalize();
+
+    for (origin, direction, normal_sign) in [
+        (vec2(50.0, 0.0), vec2(-1.0, 1.0).normalize(), 1.0),
+        (vec2(0.0, 50.0), vec2(1.0, -1.0).normalize(), -1.0),
+        (vec2(60.0, 0.0), vec2(0.0, 1.0), 1.0),
+        (vec2(0.0, 60.0), vec2(1.0, 0.0), -1.0),
+    ] {
+        let ray = Ray::new(origin, direction, RED);
+        let (_, normal, t) = ray.collides_with(segment).unwrap();
+        assert!(normal.abs_diff_eq(expected * normal_sign, 1e-6), "{:?} {:?}", origin, normal);
+        assert!(normal.dot(direction) < 0.0);
+        assert!((0.0..=1.0).contains(&t));
+    }
+}
+
+#[test]
+fn normal_does_not_depend_on_hit_position() {
+    let segment = (vec2(10.0, 20.0), vec2(70.0, 50.0));
+    let normals: Vec<Vec2> = [0.05f32, 0.5, 0.95].iter()
+        .map(|t| segment.0.lerp(segment.1, *t) + vec2(0.0, -30.0))
+        .map(|origin| Ray::new(origin, vec2(0.0, 1.0), RED).collides_with(segment).unwrap().1)
+        .collect();
+
+    assert!(normals[0].abs_diff_eq(normals[1], 1e-6));
+    assert!(normals[1].abs_diff_eq(normals[2], 1e-6));
+}
+
+#[test]
+fn capsule_side_and_cap_hits() {
+    let (start, end) = (vec2(0.0, 0.0), vec2(100.0, 0.0));
+
+    let side = Ray::new(vec2(50.0, -20.0), vec2(0.0, 1.0), RED);
+    let (position, normal) = side.collides_with_capsule(start, end, 2.5).unwrap();
+    assert!(position.abs_diff_eq(vec2(50.0, -2.5), 1e-4));
+    assert!(normal.abs_diff_eq(vec2(0.0, -1.0), 1e-6));
+
+    let cap = Ray::new(vec2(-20.0, 0.0), vec2(1.0, 0.0), RED);
+    let (position, normal) = cap.collides_with_capsule(start, end, 2.5).unwrap();
+    assert!(position.abs_diff_eq(vec2(-2.5, 0.0), 1e-4));
+    assert!(normal.abs_diff_eq(vec2(-1.0, 0.0), 1e-6));
+
+    let inside = Ray::new(vec2(50.0, 1.0), vec2(0.0, 1.0), RED);
+    assert!(inside.collides_with_capsule(start, end, 2.5).is_none());
+}
+
+#[test]
+fn segment_hits_report_both_parameters() {
+    let ray = Ray::new(vec2(0.0, 0.0), vec2(2.0, 0.0), RED);
+
+    let hit = ray.intersect_segment(vec2(30.0, -10.0), vec2(30.0, 30.0)).unwrap();
+    assert_eq!(hit.point, vec2(30.0, 0.0));
+    assert_eq!(hit.normal, vec2(-1.0, 0.0));
+    assert!((hit.t_ray - 30.0).abs() < 1e-4);
+    assert!((hit.t_seg - 0.25).abs() < 1e-6);
+    assert_eq!(ray.collides_with((vec2(30.0, -10.0), vec2(30.0, 30.0))), Some((hit.point, hit.normal, hit.t_seg)));
+
+    // behind the origin, parallel, and just past an end
+    assert_eq!(ray.intersect_segment(vec2(-30.0, -10.0), vec2(-30.0, 30.0)), None);
+    assert_eq!(ray.intersect_segment(vec2(0.0, 5.0), vec2(50.0, 5.0)), None);
+    assert_eq!(ray.intersect_segment(vec2(30.0, 1.0), vec2(30.0, 30.0)), None);
+    let end = ray.intersect_segment(vec2(30.0, 30.0), vec2(30.0, 0.0)).unwrap();
+    assert_eq!(end.t_seg, 1.0);
+}
+
+#[test]
+fn aabb_hits_give_entry_and_exit() {
+    let (min, max) = (vec2(10.0, -5.0), vec2(20.0, 5.0));
+
+    let (enter, exit) = Ray::new(vec2(0.0, 0.0), vec2(1.0, 0.0), RED).intersect_aabb(min, max).unwrap();
+    assert_eq!((enter, exit), (10.0, 20.0));
+    let diagonal = Ray::new(vec2(0.0, -15.0), vec2(1.0, 1.0), RED).intersect_aabb(min, max).unwrap();
+    assert!((diagonal.0 - 200f32.sqrt()).abs() < 1e-4 && (diagonal.1 - 800f32.sqrt()).abs() < 1e-4);
+
+    // starting inside enters behind the origin
+    let (enter, exit) = Ray::new(vec2(15.0, 0.0), vec2(0.0, -3.0), RED).intersect_aabb(min, max).unwrap();
+    assert_eq!((enter, exit), (-5.0, 5.0));
+    // grazing along a face still touches it
+    assert_eq!(Ray::new(vec2(0.0, 5.0), vec2(1.0, 0.0), RED).intersect_aabb(min, max), Some((10.0, 20.0)));
+
+    assert_eq!(Ray::new(vec2(30.0, 0.0), vec2(1.0, 0.0), RED).intersect_aabb(min, max), None);
+    assert_eq!(Ray::new(vec2(0.0, 6.0), vec2(1.0, 0.0), RED).intersect_aabb(min, max), None);
+    assert_eq!(Ray::new(vec2(0.0, 0.0), vec2(1.0, 2.0), RED).intersect_aabb(min, max), None);
+}
+
+#[test]
+fn circle_hits_enter_touch_or_leave() {
+    let center = vec2(10.0, 0.0);
+    let ray = Ray::new(Vec2::ZERO, vec2(1.0, 0.0), RED);
+
+    let hit = ray.intersect_circle(center, 4.0).unwrap();
+    assert_eq!((hit.point, hit.normal, hit.t, hit.from_inside), (vec2(6.0, 0.0), vec2(-1.0, 0.0), 6.0, false));
+    assert_eq!(ray.collides_with_circle(center, 4.0), Some(hit.point));
+
+    let tangent = ray.intersect_circle(vec2(5.0, 1.0), 1.0).unwrap();
+    assert_eq!((tangent.point, tangent.normal), (vec2(5.0, 0.0), vec2(0.0, -1.0)));
+
+    let inside = Ray::new(vec2(11.0, 0.0), vec2(1.0, 0.0), RED).intersect_circle(center, 4.0).unwrap();
+    assert_eq!((inside.point, inside.normal, inside.t, inside.from_inside), (vec2(14.0, 0.0), vec2(1.0, 0.0), 3.0, true));
+    assert_eq!(Ray::new(vec2(11.0, 0.0), vec2(1.0, 0.0), RED).collides_with_circle(center, 4.0), None);
+
+    // behind the origin, and passing by
+    assert_eq!(Ray::new(vec2(20.0, 0.0), vec2(1.0, 0.0), RED).intersect_circle(center, 4.0), None);
+    assert_eq!(ray.intersect_circle(vec2(10.0, 5.0), 4.0), None);
+}
+
+#[test]
+fn thick_edges_catch_rays_slipping_past_the_end() {
+    let segments = [Segment(vec2(100.0, -50.0), vec2(100.0, 50.0), EdgeState::Absorptive)];
+    let ray = Ray::new(vec2(0.0, 51.0), vec2(1.0, 0.0), RED);
+
+    let thin = trace(ray, &segments, &SimConfig::default()).hits;
+    assert_eq!(thin[0].interaction, Interaction::Escaped);
+
+    let thick = trace(ray, &segments, &SimConfig { edge_radius: Some(2.5), ..Default::default() }).hits;
+    assert_eq!(thick[0].interaction, Interaction::Absorbed);
+    assert!(thick[0].position.x < 100.0);
+}
+
+#[test]
+fn thick_edges_collide_at_their_own_thickness() {
+    let segments = [
+        Segment(vec2(100.0, -50.0), vec2(100.0, 50.0), EdgeState::Absorptive),
+        Segment(vec2(200.0, -50.0), vec2(200.0, 50.0), EdgeState::Absorptive),
+    ];
+    let config = SimConfig { edge_radius: Some(2.5), ..Default::default() };
+    let ray = Ray::new(vec2(0.0, 0.0), vec2(1.0, 0.0), RED);
+    let closest = |surfaces: &Surfaces| find_closest_segment_new(ray, &segments, surfaces, &config).unwrap();
+
+    assert_eq!(closest(&Surfaces::default()).info.position, vec2(97.5, 0.0));
+    let surfaces = Surfaces { thickness: vec![20.0], ..Default::default() };
+    assert_eq!(closest(&surfaces).info.position, vec2(90.0, 0.0));
+    // a ray just past the first wall's end only clips it at its own width
+    let wide = Ray::new(vec2(0.0, 58.0), vec2(1.0, 0.0), RED);
+    let hit = find_closest_segment_new(wide, &segments, &surfaces, &config).unwrap();
+    assert_eq!(hit.segment_index, 0);
+    assert!(find_closest_segment_new(wide, &segments, &Surfaces::default(), &config).is_none());
+}
+
+#[test]
+fn thick_edges_keep_ray_inside_mirror_box() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.7), RED);
+
+    let hits = trace(ray, &segments, &SimConfig { max_rays: 100, edge_radius: Some(3.0), ..Default::default() }).hits;
+
+    assert_eq!(hits.len(), 100);
+    for hit in &hits {
+        assert_eq!(hit.interaction, Interaction::Reflected);
+        assert!((3.0 - 1e-3..=97.0 + 1e-3).contains(&hit.position.x), "{:?}", hit);
+        assert!((3.0 - 1e-3..=97.0 + 1e-3).contains(&hit.position.y), "{:?}", hit);
+    }
+}
+
+fn unit_walls(segments: &[Segment], cell_size: f32) -> BTreeSet<(i32, i32, i32, i32)> {
+    let mut walls = BTreeSet::new();
+    for segment in segments {
+        let (start, end) = (segment.0 / cell_size, segment.1 / cell_size);
+        let steps = start.distance(end).round() as i32;
+        let step = (end - start) / steps as f32;
+        for i in 0..steps {
+            let (a, b) = ((start + step * i as f32).round(), (start + step * (i + 1) as f32).round());
+            let (a, b) = if (a.x, a.y) <= (b.x, b.y) { (a, b) } else { (b, a) };
+            walls.insert((a.x as i32, a.y as i32, b.x as i32, b.y as i32));
+        }
+    }
+    walls
+}
+
+/// Number of distinct walls meeting at each node.
+fn degrees(segments: &[Segment]) -> HashMap<(u32, u32), usize> {
+    let key = |point: Vec2| (point.x.to_bits(), point.y.to_bits());
+    let mut neighbours: HashMap<(u32, u32), BTreeSet<(u32, u32)>> = HashMap::new();
+    for segment in segments {
+        neighbours.entry(key(segment.0)).or_default().insert(key(segment.1));
+        neighbours.entry(key(segment.1)).or_default().insert(key(segment.0));
+    }
+    neighbours.into_iter().map(|(node, others)| (node, others.len())).collect()
+}
+
+#[test]
+fn merging_labyrinth_walls_keeps_geometry_and_junctions() {
+    let mut labyrinth = Labyrinth::new(10.0, (50, 50));
+    labyrinth.generate(Algorithm::Kruskal, 3);
+    let segments: Vec<Segment> = labyrinth.get_as_lines_explicit().iter()
+        .map(|&((x1, y1), (x2, y2))| Segment(vec2(x1, y1), vec2(x2, y2), EdgeState::Reflective))
+        .collect();
+
+    let merged = merge_collinear_segments(&segments);
+
+    assert!(merged.len() < segments.len() / 2, "{} -> {}", segments.len(), merged.len());
+    assert_eq!(unit_walls(&merged, 10.0), unit_walls(&segments, 10.0));
+    let kept = degrees(&merged);
+    for (node, degree) in degrees(&segments) {
+        if degree != 2 {
+            assert_eq!(kept.get(&node), Some(&degree), "junction or end {:?} must survive", node);
+        }
+    }
+}
+
+#[test]
+fn merging_stops_at_corners_junctions_and_state_changes() {
+    let runs = [
+        Segment(vec2(0.0, 0.0), vec2(1.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(1.0, 0.0), vec2(2.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(2.0, 0.0), vec2(3.0, 0.0), EdgeState::Absorptive),
+        Segment(vec2(3.0, 0.0), vec2(3.0, 1.0), EdgeState::Absorptive),
+        Segment(vec2(3.0, 1.0), vec2(3.0, 2.0), EdgeState::Absorptive),
+        Segment(vec2(3.0, 1.0), vec2(4.0, 1.0), EdgeState::Absorptive),
+    ];
+
+    let merged = merge_collinear_segments(&runs);
+
+    assert_eq!(merged, vec![
+        Segment(vec2(0.0, 0.0), vec2(2.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(2.0, 0.0), vec2(3.0, 0.0), EdgeState::Absorptive),
+        Segment(vec2(3.0, 0.0), vec2(3.0, 1.0), EdgeState::Absorptive),
+        Segment(vec2(3.0, 1.0), vec2(3.0, 2.0), EdgeState::Absorptive),
+        Segment(vec2(3.0, 1.0), vec2(4.0, 1.0), EdgeState::Absorptive),
+    ]);
+}
+
+#[test]
+fn trace_records_bounces_lengths_and_terminations() {
+    let mut segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    segments[1].2 = EdgeState::Absorptive; // right wall
+    let ray = Ray::new(vec2(50.0, 50.0), vec2(-1.0, 0.0), RED);
+
+    let result = trace(ray, &segments, &SimConfig::default());
+
+    let bounces: Vec<usize> = result.hits.iter().map(|hit| hit.bounce).collect();
+    assert_eq!(bounces, vec![0, 1]);
+    assert!((result.hits[0].path_length - 50.0).abs() < 1e-3);
+    assert!((result.hits[1].path_length - 150.0).abs() < 1e-3);
+    assert!((result.total_path_length() - 150.0).abs() < 1e-3);
+    assert_eq!(result.deepest_bounce(), Some(1));
+    assert_eq!(result.terminations(Termination::Absorbed), 1);
+    assert_eq!(result.ends.len(), 1);
+    assert_eq!(result.lines().len(), 2);
+}
+
+#[test]
+fn trace_reports_budget_and_escape_terminations() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.4), RED);
+    let budget = trace(ray, &segments, &SimConfig { max_rays: 7, ..Default::default() });
+    assert_eq!(budget.terminations(Termination::Budget), 1);
+    assert_eq!(budget.hits.last().unwrap().bounce, 6);
+
+    let escaped = trace(ray, &[], &SimConfig::default());
+    assert_eq!(escaped.terminations(Termination::MaxDistance), 1);
+
+    let dim = Ray::new(vec2(50.0, 50.0), vec2(1.0, 0.0), Color::new(1.0, 0.0, 0.0, 0.05));
+    let cut = trace(dim, &segments, &SimConfig::default());
+    assert!(cut.hits.is_empty());
+    assert_eq!(cut.terminations(Termination::IntensityCutoff), 1);
+}
+
+#[test]
+fn combined_results_keep_every_line_and_sum_segment_hits() {
+    let segments = [
+        Segment(vec2(100.0, -50.0), vec2(100.0, 50.0), EdgeState::Absorptive),
+        Segment(vec2(-100.0, -50.0), vec2(-100.0, 50.0), EdgeState::Absorptive),
+    ];
+    let right = trace(Ray::new(vec2(0.0, 0.0), vec2(1.0, 0.0), RED), &segments, &SimConfig::default());
+    let left = trace(Ray::new(vec2(0.0, 0.0), vec2(-1.0, 0.0), RED), &segments, &SimConfig::default());
+    let up = trace(Ray::new(vec2(0.0, 0.0), vec2(0.0, -1.0), RED), &segments[..1], &SimConfig::default());
+
+    let combined = TraceResult::combine([right.clone(), left.clone(), up.clone()]);
+
+    assert_eq!(combined.lines(), [right.lines(), left.lines(), up.lines()].concat());
+    assert_eq!(combined.ends.len(), 3);
+    assert_eq!(combined.stats.absorbed, 2);
+    assert_eq!(combined.stats.max_distance, 1);
+    assert_eq!(combined.segment_hits(), [right.segment_hits()[0], left.segment_hits()[1]]);
+}
+
+#[test]
+fn segment_hits_accumulate_received_intensity() {
+    let segments = [
+        Segment(vec2(10.0, -50.0), vec2(10.0, 50.0), EdgeState::Transparent),
+        Segment(vec2(100.0, -50.0), vec2(100.0, 50.0), EdgeState::Absorptive),
+        Segment(vec2(0.0, 200.0), vec2(10.0, 200.0), EdgeState::Reflective),
+    ];
+    let ray = Ray::new(vec2(0.0, 0.0), vec2(1.0, 0.0), RED);
+
+    let result = trace(ray, &segments, &SimConfig::default());
+    let received = result.segment_hits();
+
+    assert_eq!(received.len(), 3);
+    // a pane at normal incidence passes almost everything on to the absorber behind it
+    assert!(received[0] < 0.05, "{:?}", received);
+    assert!(received[1] > 0.9, "{:?}", received);
+    assert_eq!(received[2], 0.0);
+}
+
+#[test]
+fn recolor_rewrites_line_colors_only() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+    let mut result = trace(ray, &segments, &SimConfig { max_rays: 5, ..Default::default() });
+
+    result.recolor(|hit| Color::new(0.0, 0.0, hit.bounce as f32, 1.0));
+
+    assert!(result.hits.iter().all(|hit| hit.color == RED));
+    for (line, hit) in result.lines().iter().zip(&result.hits) {
+        assert_eq!(line.2.b, hit.bounce as f32);
+    }
+}
+
+#[test]
+fn transparent_split_keeps_beam_color_and_splits_intensity() {
+    let segments = [Segment(vec2(10.0, -50.0), vec2(10.0, 50.0), EdgeState::Transparent)];
+    let teal = Color::new(0.2, 0.8, 0.6, 1.0);
+    let ray = Ray::new(vec2(0.0, 0.0), Vec2::from_angle(0.4), teal);
+
+    let hits = trace(ray, &segments, &SimConfig { min_intensity: 0.0, ..Default::default() }).hits;
+    let children: Vec<Color> = hits[1..].iter().map(|hit| hit.color).collect();
+
+    assert_eq!(children.len(), 2);
+    for color in &children {
+        assert_eq!((color.r, color.g, color.b), (teal.r, teal.g, teal.b));
+    }
+    assert!((children[0].a + children[1].a - 1.0).abs() < 1e-5);
+}
+
+/// Closed polygon approximating a circle, as consecutive segments.
+fn circle(center: Vec2, radius: f32, sides: usize, state: EdgeState) -> Vec<Segment> {
+    let corner = |i: usize| center + Vec2::from_angle(i as f32 * std::f32::consts::TAU / sides as f32) * radius;
+    (0..sides).map(|i| Segment(corner(i), corner(i + 1), state)).collect()
+}
+
+#[test]
+fn transparent_circle_medium_focuses_parallel_rays() {
+    let radius = 100.0;
+    // fine facets, coarse ones tilt the normals enough to smear the focus
+    let segments = circle(Vec2::ZERO, radius, 2048, EdgeState::Transparent);
+    let media = vec![Some(0); segments.len()];
+    let config = SimConfig { refractive_index: 1.5, ..Default::default() };
+
+    let focus_crossings: Vec<f32> = [-12.0, -6.0, 6.0, 12.0].into_iter().map(|height| {
+        let ray = Ray::new(vec2(-300.0, height), vec2(1.0, 0.0), RED);
+        let hits = trace_with_media(ray, &segments, &media, &config).hits;
+        // entry, then the refracted line through the glass, then the line leaving it
+        let through = hits.iter().find(|hit| hit.bounce == 1 && hit.position.length() > radius - 1.0
+            && hit.origin.x < 0.0 && hit.position.x > 0.0).expect("no line through the lens");
+        let out = hits.iter().find(|hit| hit.bounce == 2 && hit.origin == through.position
+            && hit.position.length() > radius + 1.0).expect("no line leaving the lens");
+        assert_eq!(out.interaction, Interaction::Escaped);
+        let direction = (out.position - out.origin).normalize();
+        // where the outgoing line crosses the axis
+        out.origin.x - out.origin.y * direction.x / direction.y
+    }).collect();
+
+    // ball lens: focal length n R / 2 (n - 1) from the centre, 150 here
+    for x in &focus_crossings {
+        assert!((x - 150.0).abs() < 10.0, "{:?}", focus_crossings);
+    }
+    let spread = focus_crossings.iter().fold(f32::MIN, |a, b| a.max(*b))
+        - focus_crossings.iter().fold(f32::MAX, |a, b| a.min(*b));
+    assert!(spread < 5.0, "{:?}", focus_crossings);
+}
+
+#[test]
+fn untagged_transparent_segments_do_not_bend() {
+    let segments = circle(Vec2::ZERO, 100.0, 64, EdgeState::Transparent);
+    let ray = Ray::new(vec2(-300.0, 30.0), vec2(1.0, 0.0), RED);
+
+    let hits = trace(ray, &segments, &SimConfig::default()).hits;
+    let through = hits.iter().filter(|hit| hit.bounce == 1).find(|hit| hit.position.x > 0.0).unwrap();
+
+    assert!((through.position.y - 30.0).abs() < 1e-3);
+}
+
+#[test]
+fn total_internal_reflection_keeps_the_ray_inside() {
+    let segments = circle(Vec2::ZERO, 100.0, 128, EdgeState::Transparent);
+    let media = vec![Some(0); segments.len()];
+    // entering near the rim, the refracted ray meets the far wall at a grazing angle
+    let ray = Ray::new(vec2(-300.0, 99.0), vec2(1.0, 0.0), RED);
+    let config = SimConfig { refractive_index: 3.0, min_intensity: 0.0, max_rays: 200, ..Default::default() };
+
+    let result = trace_with_media(ray, &segments, &media, &config);
+
+    // everything entered has to leave eventually, nothing gets lost in the glass
+    assert!(result.hits.iter().all(|hit| hit.color.a <= 1.0 + 1e-5));
+    assert!(result.hits.iter().any(|hit| hit.bounce >= 2));
+}
+
+#[test]
+fn absorbed_lists_hits_ending_on_absorbers() {
+    let mut segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    segments[1].2 = EdgeState::Absorptive;
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+
+    let result = trace(ray, &segments, &SimConfig::default());
+    let absorbed: Vec<_> = result.absorbed().collect();
+
+    assert!(!absorbed.is_empty());
+    for (hit, color) in absorbed {
+        assert_eq!(hit.segment, Some(1));
+        assert!((hit.position.x - 100.0).abs() < 1e-3);
+        assert_eq!(color, hit.color);
+    }
+}
+
+/// Hit `k` of a ray leaving (0, 50) along (1, 1000) between mirrors at y = 0 and y = 100.
+fn parallel_mirror_hit(k: usize) -> Vec2 {
+    vec2((50.0 + 100.0 * k as f64) as f32 / 1000.0, if k % 2 == 0 { 100.0 } else { 0.0 })
+}
+
+#[test]
+fn double_precision_stays_on_track_between_parallel_mirrors() {
+    let segments = [
+        Segment(vec2(-10.0, 0.0), vec2(2000.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(-10.0, 100.0), vec2(2000.0, 100.0), EdgeState::Reflective),
+    ];
+    let ray = Ray::new(vec2(0.0, 50.0), vec2(1.0, 1000.0).normalize(), RED);
+    let config = SimConfig { max_rays: 10_000, precision: Precision::Double, ..Default::default() };
+
+    let hits = trace(ray, &segments, &config).hits;
+
+    assert_eq!(hits.len(), 10_000);
+    for (k, hit) in hits.iter().enumerate() {
+        assert_eq!(hit.interaction, Interaction::Reflected);
+        assert!(hit.position.abs_diff_eq(parallel_mirror_hit(k), 1e-3), "bounce {k}: {:?}", hit.position);
+    }
+}
+
+#[test]
+fn double_precision_collides_like_single() {
+    let ray = Ray::new(vec2(0.0, 0.0), vec2(1.0, 0.5).normalize(), RED);
+
+    let (position, normal, t) = ray.collides_with((vec2(100.0, -100.0), vec2(100.0, 100.0))).unwrap();
+    let (position64, normal64, t64) = Ray64::from(ray)
+        .collides_with((dvec2(100.0, -100.0), dvec2(100.0, 100.0))).unwrap();
+
+    assert!(position64.as_vec2().abs_diff_eq(position, 1e-4));
+    assert_eq!(normal64.as_vec2(), normal);
+    assert!((t64 as f32 - t).abs() < 1e-6);
+
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+    let single = trace(ray, &segments, &SimConfig { max_rays: 20, ..Default::default() }).hits;
+    let double = trace(ray, &segments, &SimConfig { max_rays: 20, precision: Precision::Double, ..Default::default() }).hits;
+    for (a, b) in single.iter().zip(&double) {
+        assert_eq!(a.segment, b.segment);
+        assert!(a.position.abs_diff_eq(b.position, 1e-2), "{:?} {:?}", a, b);
+    }
+}
+
+#[test]
+fn rough_reflections_repeat_for_the_same_seed() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let surfaces = Surfaces { roughness: vec![0.5, 1.0, 0.0, 0.25], ..Default::default() };
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+    let config = SimConfig { max_rays: 200, min_intensity: 0.0, seed: 7, gloss_samples: 3, ..Default::default() };
+
+    let first = trace_with_surfaces(ray, &segments, &surfaces, &config).hits;
+    let second = trace_with_surfaces(ray, &segments, &surfaces, &config).hits;
+    assert_eq!(first, second);
+    // each glossy bounce splits the intensity between its samples
+    assert_eq!(first.len(), 200);
+    assert!((first[1].color.a - 1.0 / 3.0).abs() < 1e-6);
+
+    let reseeded = trace_with_surfaces(ray, &segments, &surfaces, &SimConfig { seed: 8, ..config }).hits;
+    assert_ne!(first, reseeded);
+    let smooth = trace_with_surfaces(ray, &segments, &Surfaces::default(), &config).hits;
+    assert_eq!(smooth, trace(ray, &segments, &config).hits);
+}
+
+#[test]
+fn scattered_rays_never_point_into_the_surface() {
+    let (direction, normal) = (DVec2::new(1.0, 1.0).normalize(), DVec2::Y);
+
+    let turned = scatter(direction, normal, 0.1);
+    assert!(turned.abs_diff_eq(DVec2::from_angle(std::f64::consts::FRAC_PI_4 + 0.1), 1e-9));
+    for angle in [-3.0, -1.5, -0.8, 0.8, 1.5, 3.0] {
+        let turned = scatter(direction, normal, angle);
+        assert!((turned.length() - 1.0).abs() < 1e-9);
+        assert!(turned.dot(normal) > 0.0, "{angle}: {turned}");
+    }
+}
+
+#[test]
+fn rays_pass_through_walls_on_other_layers() {
+    // the same wall twice, the first on layer 2, the second on the default layer
+    let wall = |x| Segment(vec2(x, -50.0), vec2(x, 50.0), EdgeState::Absorptive);
+    let segments = [wall(100.0), wall(100.0)];
+    let surfaces = Surfaces { layers: vec![0b10], ..Default::default() };
+    let ray = Ray::new(vec2(0.0, 0.0), Vec2::X, RED);
+    let hit = |mask| trace_with_surfaces(ray, &segments, &surfaces, &SimConfig { collision_mask: mask, ..Default::default() })
+        .hits[0].segment;
+
+    assert_eq!(hit(0b01), Some(1));
+    assert_eq!(hit(0b10), Some(0));
+    assert_eq!(hit(0b100), None);
+    assert_eq!(trace_with_surfaces(ray, &segments, &surfaces,
+                                   &SimConfig { collision_mask: 0b01, precision: Precision::Double, ..Default::default() })
+                   .hits[0].segment, Some(1));
+}
+
+#[test]
+fn exponential_attenuation_accumulates_over_the_path() {
+    let segments = [
+        Segment(vec2(0.0, -10.0), vec2(0.0, 10.0), EdgeState::Reflective),
+        Segment(vec2(100.0, -10.0), vec2(100.0, 10.0), EdgeState::Reflective),
+    ];
+    let ray = Ray::new(vec2(50.0, 0.0), Vec2::X, RED);
+    let config = SimConfig { max_rays: 3, min_intensity: 0.0, attenuation: Attenuation::Exponential { per_unit: 0.01 },
+        ..Default::default() };
+
+    let hits = trace(ray, &segments, &config).hits;
+
+    let alphas: Vec<_> = hits.iter().map(|hit| hit.color.a).collect();
+    for (alpha, distance) in alphas.iter().zip([50.0f32, 150.0, 250.0]) {
+        assert!((alpha - (-0.01 * distance).exp()).abs() < 1e-4, "{:?}", alphas);
+    }
+}
+
+#[test]
+fn inverse_square_attenuation_follows_the_distance_from_the_laser() {
+    let segments = [
+        Segment(vec2(100.0, -10.0), vec2(100.0, 10.0), EdgeState::Reflective),
+        Segment(vec2(-100.0, -10.0), vec2(-100.0, 10.0), EdgeState::Absorptive),
+    ];
+    let ray = Ray::new(Vec2::ZERO, Vec2::X, RED);
+    let config = SimConfig { min_intensity: 0.01, attenuation: Attenuation::InverseSquareApprox { scale: 100.0 },
+        ..Default::default() };
+
+    let result = trace(ray, &segments, &config);
+
+    assert_eq!(result.hits.len(), 2);
+    assert!((result.hits[0].color.a - 0.5).abs() < 1e-4);
+    assert!((result.hits[1].color.a - 0.1).abs() < 1e-4);
+    assert!((result.segment_hits()[1] - 0.1).abs() < 1e-4);
+}
+
+#[test]
+fn faded_rays_stop_where_they_die() {
+    let segments = [Segment(vec2(1000.0, -10.0), vec2(1000.0, 10.0), EdgeState::Absorptive)];
+    let ray = Ray::new(Vec2::ZERO, Vec2::X, RED);
+    let config = SimConfig { min_intensity: 0.1, attenuation: Attenuation::Exponential { per_unit: 0.01 },
+        ..Default::default() };
+
+    let result = trace(ray, &segments, &config);
+
+    assert_eq!(result.hits.len(), 1);
+    let hit = result.hits[0];
+    assert_eq!(hit.interaction, Interaction::Faded);
+    assert!((hit.position.x - 10.0f32.ln() * 100.0).abs() < 1e-2, "{:?}", hit);
+    assert!((hit.color.a - 0.1).abs() < 1e-4);
+    assert_eq!(result.terminations(Termination::IntensityCutoff), 1);
+    assert_eq!(result.segment_hits()[0], 0.0);
+}
+
+#[test]
+fn two_sided_segments_act_by_the_face_hit() {
+    // front faces up (y < 0), where `(end - start).perp()` points
+    let segments = [Segment(vec2(100.0, 0.0), vec2(-100.0, 0.0), EdgeState::Reflective)];
+    let surfaces = Surfaces { back_states: vec![Some(EdgeState::Absorptive)], ..Default::default() };
+    let config = SimConfig { max_rays: 2, ..Default::default() };
+
+    let from_front = trace_with_surfaces(Ray::new(vec2(0.0, -50.0), Vec2::Y, RED), &segments, &surfaces, &config);
+    let from_back = trace_with_surfaces(Ray::new(vec2(0.0, 50.0), -Vec2::Y, RED), &segments, &surfaces, &config);
+
+    assert_eq!(from_front.hits[0].interaction, Interaction::Reflected);
+    assert_eq!(from_back.hits[0].interaction, Interaction::Absorbed);
+    let one_sided = trace_with_surfaces(Ray::new(vec2(0.0, 50.0), -Vec2::Y, RED), &segments, &Surfaces::default(),
+                                        &config);
+    assert_eq!(one_sided.hits[0].interaction, Interaction::Reflected);
+}
+
+#[test]
+fn emitters_spread_rays_along_their_front_face() {
+    let segment = Segment(vec2(0.0, 0.0), vec2(100.0, 0.0), EdgeState::Reflective);
+    let emitter = EmitterConfig { rays: 10, spread: 0.4, color: [0.0, 1.0, 0.0], intensity: 0.8 };
+
+    let rays = emitter.rays(&segment);
+
+    assert_eq!(rays.len(), 10);
+    for (i, ray) in rays.iter().enumerate() {
+        assert!(ray.origin.abs_diff_eq(vec2(i as f32 * 10.0 + 5.0, 0.0), 1e-4), "{:?}", ray.origin);
+        assert!(ray.direction.angle_between(Vec2::Y) <= 0.4 + 1e-5, "{:?}", ray.direction);
+        assert_eq!(ray.color, Color::new(0.0, 1.0, 0.0, 0.8));
+    }
+    let point = Segment(Vec2::ZERO, Vec2::ZERO, EdgeState::Reflective);
+    assert!(emitter.rays(&point).is_empty());
+}
+
+#[test]
+fn room_lit_only_by_a_ceiling_strip() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(200.0, 100.0), EdgeState::Reflective);
+    let emitter = EmitterConfig { rays: 8, ..Default::default() };
+    let surfaces = Surfaces { emitters: vec![Some(emitter)], ..Default::default() };
+    let config = SimConfig { max_rays: 80, ..Default::default() };
+
+    let result = trace_lights(None, &segments, &surfaces, &config);
+
+    assert_eq!(result.hits.len(), 80);
+    let first: Vec<_> = result.hits.iter().filter(|hit| hit.bounce == 0).collect();
+    assert_eq!(first.len(), 8);
+    assert!(first.iter().all(|hit| hit.origin.y == 0.0));
+    for hit in &result.hits {
+        assert_eq!(hit.interaction, Interaction::Reflected);
+        assert!((-1e-2..=200.01).contains(&hit.position.x) && (-1e-2..=100.01).contains(&hit.position.y));
+    }
+    assert!(trace_lights(None, &segments, &Surfaces::default(), &config).hits.is_empty());
+}
+
+#[test]
+fn emitting_segments_still_collide() {
+    let segments = [Segment(vec2(-100.0, 0.0), vec2(100.0, 0.0), EdgeState::Absorptive)];
+    let emitter = EmitterConfig { rays: 4, spread: 0.0, ..Default::default() };
+    let surfaces = Surfaces { emitters: vec![Some(emitter)], ..Default::default() };
+
+    let result = trace_with_surfaces(Ray::new(vec2(0.0, 50.0), -Vec2::Y, RED), &segments, &surfaces,
+                                     &SimConfig::default());
+
+    assert_eq!(result.hits[0].interaction, Interaction::Absorbed);
+    assert_eq!(result.hits[0].position, Vec2::ZERO);
+    // the strip's own rays leave straight up and away
+    assert_eq!(result.terminations(Termination::MaxDistance), 4);
+}
+
+#[test]
+fn ray_budget_is_shared_by_rays_per_source() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(200.0, 100.0), EdgeState::Reflective);
+    let emitter = EmitterConfig { rays: 9, ..Default::default() };
+    let surfaces = Surfaces { emitters: vec![None, None, Some(emitter)], ..Default::default() };
+    let config = SimConfig { max_rays: 100, ..Default::default() };
+
+    let result = trace_lights(Some(Ray::new(vec2(100.0, 50.0), vec2(1.0, 0.3).normalize(), RED)),
+                              &segments, &surfaces, &config);
+
+    let laser_lines = result.hits.iter().filter(|hit| hit.color == RED).count();
+    assert_eq!(laser_lines, 10);
+    assert_eq!(result.hits.len(), 100);
+    assert!(result.stats.budget_exhausted);
+}
+
+#[test]
+fn retracing_into_the_same_buffers_keeps_them() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(200.0, 100.0), EdgeState::Reflective);
+    let emitter = EmitterConfig { rays: 9, ..Default::default() };
+    let surfaces = Surfaces { emitters: vec![None, None, Some(emitter)], ..Default::default() };
+    let config = SimConfig { max_rays: 100, ..Default::default() };
+    let laser = Some(Ray::new(vec2(100.0, 50.0), vec2(1.0, 0.3).normalize(), RED));
+    let (mut scratch, mut out) = (TraceScratch::default(), TraceResult::default());
+
+    trace_lights_into(laser, &segments, &surfaces, &config, &mut scratch, &mut out);
+    let expected = trace_lights(laser, &segments, &surfaces, &config);
+    assert_eq!(out.hits, expected.hits);
+    assert_eq!(out.segment_hits(), expected.segment_hits());
+    assert_eq!(out.lines(), expected.lines());
+    let (hits, capacity, queue) = (out.hits.as_ptr(), out.hits.capacity(), scratch.capacity());
+
+    for _ in 0..3 {
+        trace_lights_into(laser, &segments, &surfaces, &config, &mut scratch, &mut out);
+        assert_eq!(out.hits, expected.hits);
+        assert_eq!((out.hits.as_ptr(), out.hits.capacity(), scratch.capacity()), (hits, capacity, queue));
+        assert_eq!(out.ends, expected.ends);
+    }
+}
+
+/// The walls of the axis-aligned square from `-half` to `half`.
+fn square_room(half: f32) -> Vec<Segment> {
+    let corners = [vec2(-half, -half), vec2(half, -half), vec2(half, half), vec2(-half, half)];
+    (0..4).map(|i| Segment(corners[i], corners[(i + 1) % 4], EdgeState::Reflective)).collect()
+}
+
+fn assert_polygon_near(polygon: &[Vec2], expected: &[Vec2]) {
+    assert_eq!(polygon.len(), expected.len(), "{:?}", polygon);
+    for (corner, expected) in polygon.iter().zip(expected) {
+        assert!(corner.distance(*expected) < 1e-2, "{:?} isn't {:?}", polygon, expected);
+    }
+}
+
+#[test]
+fn a_square_room_is_lit_up_to_its_corners() {
+    let polygon = visibility_polygon(vec2(0.0, 0.0), &square_room(100.0));
+
+    assert_polygon_near(&polygon, &[vec2(-100.0, -100.0), vec2(100.0, -100.0), vec2(100.0, 100.0), vec2(-100.0, 100.0)]);
+
+    // off centre the corners are still all there is
+    let polygon = visibility_polygon(vec2(60.0, -20.0), &square_room(100.0));
+    assert_polygon_near(&polygon, &[vec2(-100.0, -100.0), vec2(100.0, -100.0), vec2(100.0, 100.0), vec2(-100.0, 100.0)]);
+}
+
+#[test]
+fn an_inner_wall_casts_a_shadow() {
+    let mut segments = square_room(100.0);
+    segments.push(Segment(vec2(50.0, -25.0), vec2(50.0, 25.0), EdgeState::Absorptive));
+
+    let polygon = visibility_polygon(vec2(0.0, 0.0), &segments);
+
+    // the shadow's edges run from the wall's ends straight away from the light
+    assert_polygon_near(&polygon, &[vec2(-100.0, -100.0), vec2(100.0, -100.0), vec2(100.0, -50.0), vec2(50.0, -25.0),
+        vec2(50.0, 25.0), vec2(100.0, 50.0), vec2(100.0, 100.0), vec2(-100.0, 100.0)]);
+}
+
+#[test]
+fn walls_in_line_with_the_light_or_sharing_corners_add_no_corners() {
+    let mut segments = square_room(100.0);
+    // edge-on to the light, and split in two at a shared corner
+    segments.push(Segment(vec2(20.0, 0.0), vec2(80.0, 0.0), EdgeState::Reflective));
+    segments.push(Segment(vec2(-100.0, -100.0), vec2(0.0, -100.0), EdgeState::Reflective));
+    segments.push(Segment(vec2(0.0, -100.0), vec2(100.0, -100.0), EdgeState::Reflective));
+    // glass doesn't block the light
+    segments.push(Segment(vec2(-50.0, -10.0), vec2(-50.0, 10.0), EdgeState::Transparent));
+
+    let polygon = visibility_polygon(vec2(0.0, 0.0), &segments);
+
+    assert_polygon_near(&polygon, &[vec2(-100.0, -100.0), vec2(100.0, -100.0), vec2(100.0, 100.0), vec2(-100.0, 100.0)]);
+}
+
+#[test]
+fn open_scenes_are_lit_out_to_the_reach() {
+    let polygon = visibility_polygon(vec2(5.0, 5.0), &[]);
+
+    assert!(polygon.len() >= 3);
+    for corner in &polygon {
+        assert!((corner.distance(vec2(5.0, 5.0)) - ray_cast::core::VISIBILITY_REACH).abs() < 1.0);
+    }
+}
+
+#[test]
+fn overlapping_walls_do_not_catch_their_own_bounce() {
+    // two mirrors along the same slanted line, overlapping where the beam lands, and an absorber
+    // the bounce should reach in one step
+    let direction = vec2(100.0, 37.0).normalize();
+    let segments = [Segment(vec2(0.0, 0.0), vec2(100.0, 37.0), EdgeState::Reflective),
+        Segment(vec2(50.0, 18.5), vec2(150.0, 55.5), EdgeState::Reflective),
+        Segment(vec2(-200.0, -300.0), vec2(400.0, -300.0), EdgeState::Absorptive)];
+    let landing = direction * 75.0;
+    let ray = Ray::new(landing - direction.perp() * 100.0, direction.perp(), RED);
+
+    for precision in [Precision::Single, Precision::Double] {
+        let config = SimConfig { precision, ..Default::default() };
+        let result = trace(ray, &segments, &config);
+
+        assert_eq!(result.hits.len(), 2, "{:?}", precision);
+        assert_eq!(result.hits[1].interaction, Interaction::Absorbed, "{:?}", precision);
+        assert!(result.lines().iter().all(|(start, end, _)| start.distance(*end) > config.min_hit_distance),
+                "{:?}", precision);
+        assert!(!result.stats.budget_exhausted, "{:?}", precision);
+    }
+}
+
+#[test]
+fn hits_closer_than_the_minimum_distance_are_skipped() {
+    let segments = [Segment(vec2(0.5, -10.0), vec2(0.5, 10.0), EdgeState::Absorptive),
+        Segment(vec2(100.0, -10.0), vec2(100.0, 10.0), EdgeState::Absorptive)];
+    let ray = Ray::new(vec2(0.0, 0.0), Vec2::X, RED);
+
+    let near = trace(ray, &segments, &SimConfig::default());
+    assert_eq!(near.hits[0].position, vec2(0.5, 0.0));
+    let far = trace(ray, &segments, &SimConfig { min_hit_distance: 1.0, ..Default::default() });
+    assert_eq!(far.hits[0].position, vec2(100.0, 0.0));
+}
+
+#[test]
+fn a_trace_exported_as_csv_parses_back_row_per_line() {
+    let mut segments = mirror_box(vec2(0.0, 0.0), vec2(200.0, 100.0), EdgeState::Reflective);
+    segments[1].2 = EdgeState::Transparent;
+    segments[3].2 = EdgeState::Absorptive;
+    let emitter = EmitterConfig { rays: 3, ..Default::default() };
+    let surfaces = Surfaces { emitters: vec![None, None, Some(emitter)], ..Default::default() };
+    let config = SimConfig { max_rays: 60, ..Default::default() };
+    let result = trace_lights(Some(Ray::new(vec2(100.0, 50.0), vec2(1.0, 0.3).normalize(), RED)),
+                              &segments, &surfaces, &config);
+
+    let mut csv = Vec::new();
+    result.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut rows = csv.lines().map(|line| line.split(',').collect::<Vec<_>>());
+    let header = rows.next().unwrap();
+    assert_eq!(header[..2], ["ray", "bounce"]);
+    let rows: Vec<_> = rows.collect();
+    assert_eq!(rows.len(), result.hits.len());
+    assert!(rows.iter().all(|row| row.len() == header.len()));
+
+    let mut last_bounce: HashMap<usize, usize> = HashMap::new();
+    for (row, hit) in rows.iter().zip(&result.hits) {
+        let (ray, bounce) = (row[0].parse::<usize>().unwrap(), row[1].parse::<usize>().unwrap());
+        assert_eq!((ray, bounce), (hit.ray, hit.bounce));
+        assert!(last_bounce.insert(ray, bounce).is_none_or(|last| last <= bounce), "ray {}", ray);
+        assert_eq!(row[4].parse::<f32>().unwrap(), hit.position.x);
+        assert_eq!(row[6].is_empty(), hit.segment.is_none());
+    }
+    // the laser and each emitted ray start a ray of their own
+    assert_eq!(last_bounce.len(), 4);
+    assert!(rows.iter().any(|row| row[7] == "transparent" && row[8] == "Split"));
+    let absorbed = rows.iter().find(|row| row[8] == "Absorbed").unwrap();
+    assert_eq!(absorbed[11].parse::<f32>().unwrap(), 0.0);
+}
+
+/// A mirror box with `clutter` short walls far outside it, which every ray is still tested
+/// against.
+fn cluttered_mirror_box(clutter: usize) -> Vec<Segment> {
+    let mut segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    segments.extend((0..clutter).map(|i| {
+        let x = 1000.0 + i as f32;
+        Segment(vec2(x, 1000.0), vec2(x, 1001.0), EdgeState::Absorptive)
+    }));
+    segments
+}
+
+#[test]
+fn the_watchdog_cuts_a_slow_trace_short() {
+    let segments = cluttered_mirror_box(2000);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+    let limit = 1_000_000;
+    let config = SimConfig { max_rays: 2000, watchdog: Watchdog::Tests(limit), ..Default::default() };
+
+    let result = trace(ray, &segments, &config);
+    assert!(result.stats.aborted);
+    assert!(!result.hits.is_empty() && result.hits.len() < config.max_rays);
+    // only checked every so often, so it may overrun by that many rays at most
+    let slack = (Watchdog::CHECK_EVERY * segments.len()) as u64;
+    assert!(result.stats.segment_tests <= limit + slack, "{}", result.stats.segment_tests);
+    assert!(result.lines().len() == result.hits.len());
+
+    let unlimited = trace(ray, &segments, &SimConfig { watchdog: Watchdog::Off, ..config });
+    assert!(!unlimited.stats.aborted);
+    assert_eq!(unlimited.hits.len(), config.max_rays);
+    assert_eq!(unlimited.hits[..result.hits.len()], result.hits[..]);
+}
+
+#[test]
+fn the_watchdog_reads_the_clock_on_native() {
+    let segments = cluttered_mirror_box(100);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.3), RED);
+    let config = SimConfig { max_rays: 10_000, ..Default::default() };
+
+    let expired = trace(ray, &segments, &SimConfig { watchdog: Watchdog::Micros(0.0), ..config });
+    assert!(expired.stats.aborted);
+    assert_eq!(expired.hits.len(), Watchdog::CHECK_EVERY - 1);
+    let generous = trace(ray, &segments, &SimConfig { watchdog: Watchdog::Micros(60e6), ..config });
+    assert!(!generous.stats.aborted);
+}
+
+#[test]
+fn edge_states_round_trip_through_their_names() {
+    for state in EdgeState::ALL {
+        assert_eq!(state.to_string(), state.name());
+        assert_eq!(state.name().parse::<EdgeState>(), Ok(state));
+        assert_eq!(state.label().parse::<EdgeState>(), Ok(state));
+    }
+    assert_eq!(" Absorptive ".parse::<EdgeState>(), Ok(EdgeState::Absorptive));
+    assert!("mirror".parse::<EdgeState>().is_err());
+}
+
+#[test]
+fn cycling_an_edge_state_visits_every_state_once() {
+    let mut visited = vec![EdgeState::default()];
+    while visited.len() <= EdgeState::ALL.len() {
+        visited.push(visited[visited.len() - 1].next());
+    }
+    assert_eq!(visited[..EdgeState::ALL.len()], EdgeState::ALL);
+    assert_eq!(visited[EdgeState::ALL.len()], visited[0]);
+}
+
+#[test]
+fn the_absorption_curve_rises_towards_glancing_hits() {
+    let curve = AbsorptionCurve { base: 0.1, rise: 0.4, power: 2.0 };
+    assert_eq!(curve.at(1.0), 0.1);
+    assert!((curve.at(0.5) - (0.1 + 0.4 * 0.25)).abs() < 1e-6);
+    assert!((curve.at(0.0) - 0.5).abs() < 1e-6);
+    assert_eq!(AbsorptionCurve { base: 0.8, rise: 1.0, power: 1.0 }.at(0.0), 1.0);
+    assert_eq!(AbsorptionCurve::default().at(0.3), 0.0);
+}
+
+#[test]
+fn a_lossless_curve_changes_nothing_and_a_lossy_one_dims_reflections() {
+    let segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    let ray = Ray::new(vec2(50.0, 50.0), Vec2::from_angle(0.7), RED);
+    let config = SimConfig { max_rays: 20, ..Default::default() };
+    let plain = trace_with_surfaces(ray, &segments, &Surfaces::default(), &config);
+    let lossless = Surfaces { absorption: vec![Some(AbsorptionCurve::default()); 4], ..Default::default() };
+    assert_eq!(trace_with_surfaces(ray, &segments, &lossless, &config).hits, plain.hits);
+
+    let curve = AbsorptionCurve { base: 0.1, rise: 0.5, power: 1.0 };
+    let lossy = Surfaces { absorption: vec![Some(curve); 4], ..Default::default() };
+    let dimmed = trace_with_surfaces(ray, &segments, &lossy, &config).hits;
+    let (first, second) = (&dimmed[0], &dimmed[1]);
+    let kept = 1.0 - curve.at((-(first.position - first.origin).normalize()).dot(first.normal));
+    assert!((second.color.a - first.color.a * kept).abs() < 1e-5, "{} {}", second.color.a, kept);
+    assert!(dimmed.iter().zip(&plain.hits).skip(1).all(|(dim, full)| dim.color.a < full.color.a));
+}
+
+#[test]
+fn every_ray_of_a_fan_is_followed_for_the_same_number_of_bounces() {
+    let mut segments = mirror_box(vec2(0.0, 0.0), vec2(100.0, 100.0), EdgeState::Reflective);
+    segments.push(Segment(vec2(40.0, 50.0), vec2(60.0, 50.0), EdgeState::Reflective));
+    // the emitter itself is on no layer, so the fan never lands back on it
+    let surfaces = Surfaces {
+        emitters: vec![None, None, None, None, Some(EmitterConfig { rays: 8, ..Default::default() })],
+        layers: vec![u8::MAX, u8::MAX, u8::MAX, u8::MAX, 0],
+        ..Default::default()
+    };
+    let config = SimConfig { max_rays: 10_000, max_bounces_per_ray: 5, ..Default::default() };
+
+    let result = trace_lights(None, &segments, &surfaces, &config);
+    let mut lines = HashMap::new();
+    for hit in &result.hits { *lines.entry(hit.ray).or_insert(0) += 1; }
+    assert_eq!(lines.len(), 8);
+    assert!(lines.values().all(|&count| count == 5), "{:?}", lines);
+    assert_eq!(result.stats.terminations(Termination::MaxBounces), 8);
+    assert!(!result.stats.budget_exhausted);
+
+    // the total budget still caps the trace
+    let capped = trace_lights(None, &segments, &surfaces, &SimConfig { max_rays: 12, ..config });
+    assert_eq!(capped.hits.len(), 12);
+    assert!(capped.stats.budget_exhausted);
+}
+
+#[test]
+fn segments_meet_at_a_point_or_along_a_stretch() {
+    let horizontal = (vec2(0.0, 0.0), vec2(100.0, 0.0));
+    assert_eq!(segment_intersection(horizontal, (vec2(25.0, -50.0), vec2(25.0, 50.0))),
+               Some(SegmentIntersection::Point { point: vec2(25.0, 0.0), t: 0.25, u: 0.5 }));
+    assert_eq!(segment_intersection(horizontal, (vec2(100.0, 0.0), vec2(100.0, 20.0))),
+               Some(SegmentIntersection::Point { point: vec2(100.0, 0.0), t: 1.0, u: 0.0 }));
+    assert_eq!(segment_intersection(horizontal, (vec2(50.0, 10.0), vec2(50.0, 20.0))), None);
+    assert_eq!(segment_intersection(horizontal, (vec2(0.0, 10.0), vec2(100.0, 10.0))), None);
+    assert_eq!(segment_intersection(horizontal, (vec2(150.0, 0.0), vec2(50.0, 0.0))),
+               Some(SegmentIntersection::Overlap));
+    assert_eq!(segment_intersection(horizontal, (vec2(150.0, 0.0), vec2(120.0, 0.0))), None);
+}
+
+#[test]
+fn the_closest_hit_names_its_segment_and_where_along_it() {
+    let segments = [
+        Segment(vec2(200.0, -50.0), vec2(200.0, 50.0), EdgeState::Reflective),
+        Segment(vec2(100.0, -100.0), vec2(100.0, 100.0), EdgeState::Absorptive),
+    ];
+    let ray = Ray::new(vec2(0.0, 50.0), Vec2::X, RED);
+    let hit = find_closest_segment_new(ray, &segments, &Surfaces::default(), &SimConfig::default());
+    assert_eq!(hit, Some(ClosestHit {
+        info: CollisionInfo { position: vec2(100.0, 50.0), normal: -Vec2::X },
+        segment_index: 1,
+        t_along_segment: 0.75,
+    }));
+
+    let capsules = SimConfig { edge_radius: Some(5.0), ..SimConfig::default() };
+    let hit = find_closest_segment_new(ray, &segments, &Surfaces::default(), &capsules).unwrap();
+    assert_eq!((hit.segment_index, hit.t_along_segment, hit.info.position), (1, 0.75, vec2(95.0, 50.0)));
+}
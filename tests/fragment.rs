@@ -0,0 +1,100 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use macroquad::math::{vec2, Vec2};
+use ray_cast::fragment::{rotate_about, ArrayLayout};
+use ray_cast::replay::{Event, Recorder, Replayer};
+use ray_cast::{EdgeState, Laser, NodeNetwork};
+
+/// A bent mirror a-b-c with a reflective and an absorptive edge, and `d` hanging off `c`.
+fn bent_mirror(network: &mut NodeNetwork) -> [usize; 4] {
+    let nodes = [vec2(200.0, 100.0), vec2(220.0, 130.0), vec2(260.0, 130.0), vec2(300.0, 300.0)]
+        .map(|position| network.add_node(position));
+    network.add_connection(nodes[0], nodes[1]);
+    network.add_connection(nodes[1], nodes[2]);
+    network.set_edge_state(1, EdgeState::Absorptive);
+    network.add_connection(nodes[2], nodes[3]);
+    nodes
+}
+
+fn edge_states(network: &NodeNetwork, nodes: &[usize]) -> Vec<EdgeState> {
+    network.connections.iter()
+        .filter(|edge| nodes.contains(&edge.endpoints().0) && nodes.contains(&edge.endpoints().1))
+        .map(|edge| edge.state())
+        .collect()
+}
+
+#[test]
+fn rotation_about_an_arbitrary_center() {
+    let center = vec2(10.0, 5.0);
+
+    assert!(rotate_about(vec2(13.0, 7.0), center, FRAC_PI_2).abs_diff_eq(vec2(8.0, 8.0), 1e-5));
+    assert!(rotate_about(vec2(13.0, 7.0), center, PI).abs_diff_eq(vec2(7.0, 3.0), 1e-5));
+    assert_eq!(rotate_about(center, center, 1.0), center);
+
+    // 3 copies and the original make a quarter turn each
+    let rotating = ArrayLayout::Radial { center, copies: 3, rotate: true };
+    let point = vec2(14.0, 5.0);
+    for copy in 1..=3 {
+        let placed = rotating.place(copy, point, point);
+        assert!((placed.distance(center) - 4.0).abs() < 1e-5);
+        assert!((rotating.place(copy, vec2(15.0, 5.0), point).distance(placed) - 1.0).abs() < 1e-5);
+    }
+    assert!(rotating.place(1, point, point).abs_diff_eq(vec2(10.0, 9.0), 1e-5));
+
+    // without rotating the copy is only moved: its shape keeps its orientation
+    let moving = ArrayLayout::Radial { center, copies: 3, rotate: false };
+    let centroid = vec2(14.0, 5.0);
+    let placed = moving.place(1, vec2(16.0, 5.0), centroid);
+    assert!(placed.abs_diff_eq(vec2(12.0, 9.0), 1e-5));
+
+    let linear = ArrayLayout::Linear { offset: vec2(30.0, -10.0), copies: 4 };
+    assert_eq!(linear.place(3, Vec2::ONE, Vec2::ZERO), vec2(91.0, -29.0));
+}
+
+#[test]
+fn copies_get_fresh_keys_even_where_they_overlap() {
+    let mut network = NodeNetwork::headless();
+    let [a, b, c, d] = bent_mirror(&mut network);
+    let before = network.connections.len();
+
+    // repeated nodes, and a zero offset landing every copy right on the original
+    let copies = network.add_array(&[c, a, b, b, 99], ArrayLayout::Linear { offset: Vec2::ZERO, copies: 2 });
+
+    assert_eq!(copies.len(), 2 * 3);
+    assert_eq!(network.nodes.len(), 4 + 6);
+    for copy in copies.chunks(3) {
+        // fragment order follows the selection: c, a, b
+        assert_eq!(network.node_position(copy[0]), network.node_position(c));
+        assert_eq!(network.node_position(copy[1]), network.node_position(a));
+        assert!(copy.iter().all(|node| ![a, b, c, d].contains(node)));
+        assert_eq!(network.degree(copy[2]), 2);
+        // the edge out to d was not selected and is not copied
+        assert_eq!(network.degree(copy[0]), 1);
+        assert_eq!(edge_states(&network, copy), vec![EdgeState::Reflective, EdgeState::Absorptive]);
+    }
+    assert_eq!(network.connections.len(), before + 2 * 2);
+    assert_eq!(network.degree(c), 2);
+}
+
+#[test]
+fn an_array_is_one_event_and_replays() {
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    let [a, ..] = bent_mirror(&mut network);
+    network.select(Some(a));
+    let structure = network.selected_structure();
+    assert_eq!(structure.len(), 4);
+    recorder.collect(0.0, &mut network, &laser);
+
+    let layout = ArrayLayout::Radial { center: vec2(400.0, 300.0), copies: 11, rotate: true };
+    network.add_array(&structure, layout);
+    let events = network.take_events();
+    assert_eq!(events, vec![Event::Array { nodes: structure, layout }]);
+    recorder.record(1.0, events[0].clone());
+    assert_eq!(network.nodes.len(), 4 * 12);
+
+    let mut replayed = NodeNetwork::headless();
+    Replayer::new(recorder.into_session()).run_to_end(&mut replayed, &mut laser);
+    assert_eq!(replayed.get_all_connections(), network.get_all_connections());
+}
@@ -0,0 +1,38 @@
+use ray_cast::hud::{FrameTimings, PerfHud};
+
+fn sample(trace_micros: f32, lines: usize) -> FrameTimings {
+    FrameTimings { rebuild_micros: 1.0, trace_micros, lines, segments: 4 }
+}
+
+#[test]
+fn empty_hud_has_no_average() {
+    let hud = PerfHud::new(4);
+
+    assert_eq!(hud.average(), None);
+    assert_eq!(hud.latest(), None);
+}
+
+#[test]
+fn average_covers_only_the_window() {
+    let mut hud = PerfHud::new(3);
+    for (micros, lines) in [(100.0, 10), (10.0, 1), (20.0, 2), (30.0, 3)] {
+        hud.record(sample(micros, lines));
+    }
+
+    let average = hud.average().unwrap();
+
+    assert_eq!(average.trace_micros, 20.0);
+    assert_eq!(average.lines, 2);
+    assert_eq!(average.segments, 4);
+    assert_eq!(hud.latest(), Some(sample(30.0, 3)));
+}
+
+#[test]
+fn draw_times_are_kept_apart_from_recomputes() {
+    let mut hud = PerfHud::new(2);
+    assert_eq!(hud.draw_micros(), None);
+    for micros in [100.0, 200.0, 400.0] { hud.record_draw(micros); }
+
+    assert_eq!(hud.draw_micros(), Some((400.0, 300.0)));
+    assert_eq!(hud.latest(), None);
+}
@@ -0,0 +1,67 @@
+use macroquad::input::{KeyCode, MouseButton};
+use ray_cast::input::{Action, Binding, InputMap};
+
+#[test]
+fn defaults_have_no_conflicts() {
+    let input = InputMap::default();
+
+    assert_eq!(input.binding(Action::ToggleUi), Binding::Key(KeyCode::Tab));
+    assert_eq!(input.binding(Action::DeleteHovered), Binding::Mouse(MouseButton::Middle));
+    // Pause and the pan modifier share Space on purpose, tap versus hold
+    assert_eq!(input.binding(Action::Pause), input.binding(Action::PanModifier));
+    assert!(input.conflicts().is_empty());
+}
+
+#[test]
+fn rebinding_onto_a_used_key_conflicts() {
+    let mut input = InputMap::default();
+
+    input.bind(Action::ToggleCollisions, Binding::Key(KeyCode::F));
+
+    assert_eq!(input.binding(Action::ToggleCollisions), Binding::Key(KeyCode::F));
+    assert_eq!(input.conflicts(), vec![(Action::ToggleCollisions, Action::FollowMouse)]);
+}
+
+#[test]
+fn binding_back_to_the_default_forgets_the_override() {
+    let mut input = InputMap::default();
+    input.bind(Action::Step, Binding::Key(KeyCode::Period));
+
+    input.bind(Action::Step, Binding::Key(KeyCode::N));
+
+    assert_eq!(input, InputMap::default());
+}
+
+#[test]
+fn bindings_round_trip_through_their_names() {
+    for binding in [Binding::Key(KeyCode::CapsLock), Binding::Key(KeyCode::Key1), Binding::Key(KeyCode::F12),
+        Binding::Mouse(MouseButton::Right)] {
+        assert_eq!(Binding::try_from(String::from(binding)), Ok(binding));
+    }
+    assert_eq!(String::from(Binding::Mouse(MouseButton::Middle)), "MouseMiddle");
+    assert!(Binding::try_from("Hyper".to_owned()).is_err());
+}
+
+#[test]
+fn edge_state_keys_and_nudging_are_rebindable() {
+    let mut input = InputMap::default();
+    // the nudge step modifiers share Shift and Alt with multi-select and quick connect
+    assert_eq!(input.binding(Action::NudgeCoarse), input.binding(Action::MultiSelect));
+    assert_eq!(input.binding(Action::NudgeFine), input.binding(Action::QuickConnect));
+
+    input.bind(Action::EdgeState1, Binding::Key(KeyCode::R));
+
+    assert_eq!(input.conflicts(), vec![(Action::EdgeState1, Action::RotateLaser)]);
+}
+
+#[test]
+fn snap_and_wheel_modifiers_are_rebindable() {
+    let mut input = InputMap::default();
+    assert_eq!(input.binding(Action::Snap), Binding::Key(KeyCode::LeftControl));
+    assert_eq!(input.binding(Action::WheelHorizontal), input.binding(Action::MultiSelect));
+
+    // either Control counts as the same key, so this still clashes with the snap modifier
+    input.bind(Action::TargetLock, Binding::Key(KeyCode::RightControl));
+
+    assert_eq!(input.conflicts(), vec![(Action::TargetLock, Action::Snap), (Action::TargetLock, Action::WheelZoom)]);
+}
@@ -0,0 +1,491 @@
+use std::collections::{HashMap, VecDeque};
+
+use ray_cast::labyrinth::{Algorithm, Cell, Labyrinth, PolarLabyrinth, Room, Side};
+
+fn open_internal_walls(labyrinth: &Labyrinth) -> usize {
+    let (width, height) = labyrinth.size;
+    let mut count = 0;
+    for (y, row) in labyrinth.get_cells().iter().enumerate().take(height) {
+        for (x, cell) in row.iter().enumerate().take(width) {
+            if x + 1 < width && cell.is_open(Side::Right) { count += 1; }
+            if y + 1 < height && cell.is_open(Side::Bottom) { count += 1; }
+        }
+    }
+    count
+}
+
+fn reachable_from_origin(labyrinth: &Labyrinth) -> usize {
+    let (width, height) = labyrinth.size;
+    let cells = labyrinth.get_cells();
+    let mut visited = vec![vec![false; width]; height];
+    let mut queue = VecDeque::from([(0usize, 0usize)]);
+    visited[0][0] = true;
+    let mut count = 0;
+    while let Some((x, y)) = queue.pop_front() {
+        count += 1;
+        let cell = cells[y][x];
+        let mut next = Vec::new();
+        if y > 0 && cell.is_open(Side::Top) { next.push((x, y - 1)); }
+        if y + 1 < height && cell.is_open(Side::Bottom) { next.push((x, y + 1)); }
+        if x > 0 && cell.is_open(Side::Left) { next.push((x - 1, y)); }
+        if x + 1 < width && cell.is_open(Side::Right) { next.push((x + 1, y)); }
+        for (nx, ny) in next {
+            if !visited[ny][nx] {
+                visited[ny][nx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    count
+}
+
+fn boundary_is_closed(labyrinth: &Labyrinth) -> bool {
+    let (width, height) = labyrinth.size;
+    let cells = labyrinth.get_cells();
+    (0..width).all(|x| cells[0][x].is_closed(Side::Top) && cells[height - 1][x].is_closed(Side::Bottom))
+        && (0..height).all(|y| cells[y][0].is_closed(Side::Left) && cells[y][width - 1].is_closed(Side::Right))
+}
+
+#[test]
+fn seeded_generators_produce_spanning_trees() {
+    for algorithm in Algorithm::ALL {
+        for seed in 0..10 {
+            for size in [(1, 1), (2, 2), (7, 7), (12, 12)] {
+                let mut labyrinth = Labyrinth::new(10.0, size);
+                labyrinth.generate(algorithm, seed);
+
+                let cell_count = size.0 * size.1;
+                assert_eq!(open_internal_walls(&labyrinth), cell_count - 1, "{:?} seed {}", algorithm, seed);
+                assert_eq!(reachable_from_origin(&labyrinth), cell_count, "{:?} seed {}", algorithm, seed);
+                assert!(boundary_is_closed(&labyrinth), "{:?} seed {}", algorithm, seed);
+            }
+        }
+    }
+}
+
+#[test]
+fn seeded_generators_are_reproducible() {
+    for algorithm in Algorithm::ALL {
+        let mut first = Labyrinth::new(10.0, (9, 9));
+        let mut second = Labyrinth::new(10.0, (9, 9));
+        first.generate(algorithm, 42);
+        second.generate(algorithm, 42);
+        assert_eq!(first.get_cells(), second.get_cells(), "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn depth_first_generator_follows_its_seed() {
+    let build = |seed| {
+        let mut labyrinth = Labyrinth::new(10.0, (8, 7));
+        labyrinth.generate_depth_first(seed);
+        labyrinth
+    };
+    assert_eq!(build(3).get_cells(), build(3).get_cells());
+    assert_ne!(build(3).get_cells(), build(4).get_cells());
+    let mut generated = Labyrinth::new(10.0, (8, 7));
+    generated.generate(Algorithm::DepthFirst, 3);
+    assert_eq!(build(3).get_cells(), generated.get_cells());
+}
+
+#[test]
+fn polar_generator_follows_its_seed() {
+    let build = |seed| {
+        let mut labyrinth = PolarLabyrinth::new(20.0, 5, 12);
+        labyrinth.generate_depth_first(seed);
+        labyrinth
+    };
+    assert_eq!(build(9).get_cells(), build(9).get_cells());
+    assert_ne!(build(9).get_cells(), build(10).get_cells());
+}
+
+/// Splits axis-aligned lines into unit walls in cell coordinates so line sets can be compared
+/// regardless of how runs were merged.
+fn unit_walls(lines: &[ray_cast::labyrinth::Line], cell_size: f32) -> std::collections::BTreeSet<((i32, i32), (i32, i32))> {
+    let mut walls = std::collections::BTreeSet::new();
+    for &((x1, y1), (x2, y2)) in lines {
+        let (x1, y1) = ((x1 / cell_size).round() as i32, (y1 / cell_size).round() as i32);
+        let (x2, y2) = ((x2 / cell_size).round() as i32, (y2 / cell_size).round() as i32);
+        assert!(x1 == x2 || y1 == y2, "line is not axis aligned");
+        let ((x1, y1), (x2, y2)) = if (x1, y1) <= (x2, y2) { ((x1, y1), (x2, y2)) } else { ((x2, y2), (x1, y1)) };
+        if x1 == x2 {
+            for y in y1..y2 { walls.insert(((x1, y), (x1, y + 1))); }
+        } else {
+            for x in x1..x2 { walls.insert(((x, y1), (x + 1, y1))); }
+        }
+    }
+    walls
+}
+
+#[test]
+fn non_square_labyrinths_are_spanning_trees() {
+    for algorithm in Algorithm::ALL {
+        for size in [(3, 8), (11, 4), (1, 6), (6, 1)] {
+            let mut labyrinth = Labyrinth::new(10.0, size);
+            labyrinth.generate(algorithm, 5);
+            assert_eq!(open_internal_walls(&labyrinth), size.0 * size.1 - 1, "{:?} {:?}", algorithm, size);
+            assert_eq!(labyrinth.get_cells().len(), size.1);
+            assert!(labyrinth.get_cells().iter().all(|row| row.len() == size.0));
+            assert_eq!(unit_walls(&labyrinth.get_as_lines(), 10.0),
+                       unit_walls(&labyrinth.get_as_lines_explicit(), 10.0), "{:?} {:?}", algorithm, size);
+        }
+    }
+}
+
+#[test]
+fn entrance_and_exit_leave_gaps_in_merged_lines() {
+    for seed in 0..10 {
+        let mut labyrinth = Labyrinth::new(10.0, (6, 4));
+        labyrinth.generate(Algorithm::Kruskal, seed);
+        labyrinth.open_entrance(Side::Left, 0);
+        labyrinth.open_exit(Side::Right, usize::MAX);
+
+        assert_eq!(labyrinth.entrance, Some((0, 0)));
+        assert_eq!(labyrinth.exit, Some((5, 3)));
+        let merged = unit_walls(&labyrinth.get_as_lines(), 10.0);
+        assert!(!merged.contains(&((0, 0), (0, 1))));
+        assert!(!merged.contains(&((6, 3), (6, 4))));
+        assert!(merged.contains(&((0, 1), (0, 2))));
+        assert!(merged.contains(&((6, 2), (6, 3))));
+        assert_eq!(merged, unit_walls(&labyrinth.get_as_lines_explicit(), 10.0));
+    }
+}
+
+#[test]
+fn openings_in_the_middle_of_a_side_split_the_run() {
+    for side in [Side::Top, Side::Bottom, Side::Left, Side::Right] {
+        let mut labyrinth = Labyrinth::new(10.0, (5, 5));
+        labyrinth.generate(Algorithm::Prim, 3);
+        labyrinth.open_entrance(side, 2);
+
+        assert_eq!(unit_walls(&labyrinth.get_as_lines(), 10.0),
+                   unit_walls(&labyrinth.get_as_lines_explicit(), 10.0), "{:?}", side);
+    }
+}
+
+#[test]
+fn perfect_mazes_are_solvable_between_opposite_corners() {
+    for algorithm in Algorithm::ALL {
+        for seed in 0..10 {
+            for size in [(1, 1), (5, 5), (8, 3), (13, 13)] {
+                let mut labyrinth = Labyrinth::new(10.0, size);
+                labyrinth.generate(algorithm, seed);
+                let end = (size.0 - 1, size.1 - 1);
+
+                let path = labyrinth.solve((0, 0), end).unwrap_or_else(|| panic!("{:?} {} {:?}", algorithm, seed, size));
+
+                assert!(path.len() <= size.0 * size.1);
+                assert_eq!(path.first(), Some(&(0, 0)));
+                assert_eq!(path.last(), Some(&end));
+                for pair in path.windows(2) {
+                    let (a, b) = (pair[0], pair[1]);
+                    assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "path must step between neighbours");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn solve_fails_on_sealed_cells() {
+    let labyrinth = Labyrinth::new(10.0, (3, 3));
+    assert_eq!(labyrinth.solve((0, 0), (0, 0)), Some(vec![(0, 0)]));
+    assert_eq!(labyrinth.solve((0, 0), (2, 2)), None);
+    assert_eq!(labyrinth.solve((0, 0), (3, 0)), None);
+}
+
+#[test]
+fn polyline_goes_through_cell_centres() {
+    let points = Labyrinth::path_as_polyline(&[(0, 0), (1, 0), (1, 1)], 10.0, (100.0, 200.0));
+    assert_eq!(points, vec![(105.0, 205.0), (115.0, 205.0), (115.0, 215.0)]);
+}
+
+const SIDES: [Side; 4] = [Side::Top, Side::Bottom, Side::Left, Side::Right];
+
+#[test]
+fn new_cell_is_closed_on_every_side() {
+    let cell = Cell::new();
+    assert_eq!(cell.get_sides(), 0b0000_1111);
+    assert_eq!(Cell::default(), cell);
+    for side in SIDES {
+        assert!(cell.is_closed(side), "{:?}", side);
+        assert!(!cell.is_open(side), "{:?}", side);
+    }
+}
+
+#[test]
+fn empty_cell_is_open_on_every_side() {
+    let cell = Cell::new_empty();
+    assert_eq!(cell.get_sides(), 0);
+    for side in SIDES {
+        assert!(cell.is_open(side), "{:?}", side);
+        assert!(!cell.is_closed(side), "{:?}", side);
+    }
+}
+
+#[test]
+fn opening_a_side_only_affects_that_side() {
+    for side in SIDES {
+        let mut cell = Cell::new();
+        cell.open(side);
+        assert!(cell.is_open(side), "{:?}", side);
+        for other in SIDES.into_iter().filter(|other| *other != side) {
+            assert!(cell.is_closed(other), "opening {:?} opened {:?}", side, other);
+        }
+        assert_eq!(cell.get_sides().count_ones(), 3);
+
+        let before = cell;
+        cell.open(side);
+        assert_eq!(cell, before, "opening twice must be idempotent");
+    }
+}
+
+#[test]
+fn sides_stay_within_low_nibble() {
+    let mut cell = Cell::new();
+    for side in SIDES {
+        cell.open(side);
+        assert_eq!(cell.get_sides() & 0b1111_0000, 0);
+    }
+    assert_eq!(cell.get_sides(), Cell::new_empty().get_sides());
+}
+
+#[test]
+fn opposite_sides_pair_up() {
+    for side in SIDES {
+        assert_ne!(side.opposite(), side);
+        assert_eq!(side.opposite().opposite(), side);
+    }
+}
+
+#[test]
+fn merged_and_explicit_lines_cover_the_same_walls() {
+    let mut rng_state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move |high: usize| {
+        rng_state ^= rng_state << 13;
+        rng_state ^= rng_state >> 7;
+        rng_state ^= rng_state << 17;
+        (rng_state % high as u64) as usize
+    };
+    for seed in 0..60 {
+        let size = (1 + next(14), 1 + next(14));
+        let algorithm = Algorithm::ALL[next(Algorithm::ALL.len())];
+        let mut labyrinth = Labyrinth::new(7.0, size);
+        labyrinth.generate(algorithm, seed);
+        for _ in 0..next(4) {
+            labyrinth.open_entrance(SIDES[next(4)], next(size.0.max(size.1)));
+        }
+
+        assert_eq!(unit_walls(&labyrinth.get_as_lines(), 7.0),
+                   unit_walls(&labyrinth.get_as_lines_explicit(), 7.0),
+                   "{:?} seed {} size {:?}", algorithm, seed, size);
+    }
+}
+
+#[test]
+fn stepwise_generation_matches_instant_generation() {
+    for seed in 0..10 {
+        for size in [(1, 1), (4, 7), (10, 10)] {
+            let mut instant = Labyrinth::new(10.0, size);
+            instant.generate(Algorithm::DepthFirst, seed);
+
+            let mut generator = Labyrinth::new(10.0, size).generator_depth_first(seed);
+            let mut steps = 0;
+            while !generator.step() {
+                steps += 1;
+                // every intermediate state is a tree over the carved cells
+                assert_eq!(open_internal_walls(generator.labyrinth()), steps);
+            }
+            assert!(generator.is_finished());
+            assert!(generator.step(), "stepping a finished generator stays finished");
+            assert_eq!(steps, size.0 * size.1 - 1);
+            assert_eq!(generator.into_labyrinth().get_cells(), instant.get_cells(), "seed {} size {:?}", seed, size);
+        }
+    }
+}
+
+#[test]
+fn full_braid_removes_every_dead_end() {
+    for algorithm in Algorithm::ALL {
+        for seed in 0..10 {
+            let mut labyrinth = Labyrinth::new(10.0, (9, 6));
+            labyrinth.generate(algorithm, seed);
+            assert!(!labyrinth.dead_ends().is_empty());
+
+            labyrinth.braid(1.0, seed);
+
+            assert!(labyrinth.dead_ends().is_empty(), "{:?} seed {}", algorithm, seed);
+            assert!(boundary_is_closed(&labyrinth), "{:?} seed {}", algorithm, seed);
+            assert!(open_internal_walls(&labyrinth) > 9 * 6 - 1, "braiding must add loops");
+        }
+    }
+}
+
+#[test]
+fn zero_braid_leaves_maze_untouched() {
+    let mut labyrinth = Labyrinth::new(10.0, (9, 9));
+    labyrinth.generate(Algorithm::Wilson, 4);
+    let before = labyrinth.get_cells().clone();
+
+    labyrinth.braid(0.0, 4);
+
+    assert_eq!(labyrinth.get_cells(), &before);
+}
+
+#[test]
+fn partial_braid_keeps_boundary_and_openings() {
+    for seed in 0..10 {
+        let mut labyrinth = Labyrinth::new(10.0, (8, 8));
+        labyrinth.generate(Algorithm::Prim, seed);
+        labyrinth.open_entrance(Side::Left, 0);
+        let dead_ends = labyrinth.dead_ends().len();
+
+        labyrinth.braid(0.5, seed);
+
+        assert!(labyrinth.dead_ends().len() < dead_ends);
+        assert!(labyrinth.get_cells()[0][0].is_open(Side::Left));
+        let cells = labyrinth.get_cells();
+        assert!((0..8).all(|x| cells[0][x].is_closed(Side::Top) && cells[7][x].is_closed(Side::Bottom)));
+        assert!((1..8).all(|y| cells[y][0].is_closed(Side::Left)));
+        assert!((0..8).all(|y| cells[y][7].is_closed(Side::Right)));
+    }
+}
+
+#[test]
+fn polar_depth_first_is_spanning_tree() {
+    for seed in 0..10 {
+        let mut labyrinth = PolarLabyrinth::new(20.0, 5, 12);
+        labyrinth.generate_depth_first(seed);
+
+        let cells = labyrinth.get_cells();
+        let mut visited = vec![vec![false; 12]; 5];
+        visited[0][0] = true;
+        let mut stack = vec![(0, 0)];
+        let mut passages = 0;
+        while let Some(cell) = stack.pop() {
+            for (next, side) in labyrinth.neighbours(cell) {
+                if cells[cell.0][cell.1].is_open(side) {
+                    assert!(cells[next.0][next.1].is_open(side.opposite()), "walls must agree");
+                    passages += 1;
+                    if !visited[next.0][next.1] {
+                        visited[next.0][next.1] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        assert!(visited.iter().flatten().all(|v| *v), "seed {}", seed);
+        assert_eq!(passages / 2, 5 * 12 - 1, "seed {}", seed);
+        assert!(cells[0].iter().all(|cell| cell.is_closed(Side::Top)));
+        assert!(cells[4].iter().all(|cell| cell.is_closed(Side::Bottom)));
+    }
+}
+
+#[test]
+fn polar_lines_share_endpoints_exactly() {
+    let mut labyrinth = PolarLabyrinth::new(20.0, 3, 8);
+    labyrinth.subdivisions = 5;
+    let lines = labyrinth.get_as_lines((100.0, 100.0), 30.0);
+
+    // fully closed: 4 circles of 8 * 5 chords, plus 8 radial walls per ring
+    assert_eq!(lines.len(), 4 * 8 * 5 + 3 * 8);
+    let mut endpoints: HashMap<(u32, u32), usize> = HashMap::new();
+    for (a, b) in &lines {
+        for (x, y) in [a, b] {
+            *endpoints.entry((x.to_bits(), y.to_bits())).or_default() += 1;
+        }
+    }
+    assert_eq!(endpoints.len(), 4 * 8 * 5);
+    assert!(endpoints.values().all(|count| *count >= 2), "every chord end must be shared bit-for-bit");
+}
+
+#[test]
+fn braided_and_opened_labyrinths_have_no_line_diff() {
+    for &algorithm in Algorithm::ALL.iter() {
+        for seed in 0..6 {
+            let mut labyrinth = Labyrinth::new(5.0, (3 + seed as usize, 9 - seed as usize));
+            labyrinth.generate(algorithm, seed);
+            labyrinth.braid(0.5, seed);
+            labyrinth.open_entrance(Side::Left, 0);
+            labyrinth.open_entrance(Side::Bottom, 1);
+            assert_eq!(labyrinth.diff_lines(), (vec![], vec![]), "{:?} seed {}", algorithm, seed);
+        }
+    }
+}
+
+#[test]
+fn cell_at_maps_points_to_grid_cells() {
+    let labyrinth = Labyrinth::new(10.0, (4, 3));
+    assert_eq!(labyrinth.cell_at((0.0, 0.0)), Some((0, 0)));
+    assert_eq!(labyrinth.cell_at((35.0, 12.0)), Some((3, 1)));
+    assert_eq!(labyrinth.cell_at((40.0, 5.0)), None);
+    assert_eq!(labyrinth.cell_at((5.0, -1.0)), None);
+}
+
+#[test]
+fn rooms_are_open_inside_and_apart_from_each_other() {
+    for seed in 0..20 {
+        let mut labyrinth = Labyrinth::new(10.0, (24, 18));
+        let rooms = labyrinth.carve_rooms(6, 2, 5, seed);
+        assert!(!rooms.is_empty());
+        for (i, room) in rooms.iter().enumerate() {
+            assert!(room.x + room.width <= 24 && room.y + room.height <= 18, "{:?}", room);
+            for other in &rooms[i + 1..] {
+                assert!(room.x + room.width < other.x || other.x + other.width < room.x
+                            || room.y + room.height < other.y || other.y + other.height < room.y,
+                        "seed {} {:?} touches {:?}", seed, room, other);
+            }
+            for (y, row) in labyrinth.get_cells().iter().enumerate() {
+                for (x, cell) in row.iter().enumerate().filter(|(x, _)| room.contains((*x, y))) {
+                    if room.contains((x + 1, y)) { assert!(cell.is_open(Side::Right)); }
+                    if room.contains((x, y + 1)) { assert!(cell.is_open(Side::Bottom)); }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn corridors_join_every_room() {
+    for seed in 0..20 {
+        let mut labyrinth = Labyrinth::new(10.0, (30, 20));
+        let rooms = labyrinth.carve_rooms(8, 2, 4, seed);
+        for room in &rooms[1..] {
+            assert!(labyrinth.solve(rooms[0].center(), room.center()).is_some(), "seed {} {:?}", seed, room);
+        }
+        assert!(boundary_is_closed(&labyrinth));
+    }
+}
+
+#[test]
+fn rooms_follow_their_seed() {
+    let build = |seed| {
+        let mut labyrinth = Labyrinth::new(10.0, (20, 20));
+        let rooms = labyrinth.carve_rooms(5, 2, 6, seed);
+        (rooms, labyrinth.get_cells().clone())
+    };
+    assert_eq!(build(1), build(1));
+    assert_ne!(build(1), build(2));
+}
+
+#[test]
+fn room_filling_the_grid_leaves_only_the_boundary() {
+    let mut labyrinth = Labyrinth::new(10.0, (5, 4));
+    let rooms = labyrinth.carve_rooms(3, 8, 8, 0);
+    assert_eq!(rooms, vec![Room { x: 0, y: 0, width: 5, height: 4 }]);
+    let mut lines = labyrinth.get_as_lines();
+    lines.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(lines, vec![((0.0, 0.0), (0.0, 40.0)), ((0.0, 0.0), (50.0, 0.0)),
+                           ((0.0, 40.0), (50.0, 40.0)), ((50.0, 0.0), (50.0, 40.0))]);
+}
+
+#[test]
+fn dungeon_lines_match_the_cells() {
+    for seed in 0..40 {
+        let size = (5 + seed as usize % 17, 4 + seed as usize % 11);
+        let mut labyrinth = Labyrinth::new(10.0, size);
+        labyrinth.carve_rooms(1 + seed as usize % 7, 1, 6, seed);
+        labyrinth.open_entrance(Side::Top, seed as usize % size.0);
+        assert_eq!(labyrinth.diff_lines(), (vec![], vec![]), "seed {} size {:?}", seed, size);
+    }
+}
@@ -0,0 +1,369 @@
+use macroquad::color::Color;
+use macroquad::math::{vec2, Vec2};
+use ray_cast::settings::LaserSettings;
+use ray_cast::core::{EmitterConfig, Termination, TraceResult, TraceScratch};
+use ray_cast::{beam_quad, fan_meshes, line_meshes, EdgeState, Laser, NodeNetwork, Segment, Sweep, LINES_PER_MESH,
+               TRIANGLES_PER_MESH};
+
+#[test]
+fn beam_starts_at_the_muzzle_whichever_way_the_laser_moves() {
+    let mut laser = Laser::headless(vec2(100.0, 100.0), Vec2::X);
+    let offset = Laser::DEFAULT_MUZZLE_OFFSET;
+    assert_eq!(laser.muzzle(), vec2(100.0 + offset, 100.0));
+
+    laser.look_at(vec2(100.0, 300.0));
+    assert_eq!(laser.aim(), (vec2(100.0, 100.0 + offset), Vec2::Y));
+
+    laser.place(vec2(0.0, 0.0), vec2(-50.0, 0.0));
+    assert_eq!(laser.muzzle(), vec2(-offset, 0.0));
+
+    laser.set_pose(vec2(10.0, 10.0), Vec2::Y);
+    laser.set_muzzle_offset(0.0);
+    assert_eq!(laser.muzzle(), laser.position());
+}
+
+#[test]
+fn a_wall_touching_the_housing_is_not_lit_from_inside() {
+    let laser = Laser::headless(vec2(0.0, 0.0), Vec2::X);
+    // across the sprite, between the laser's centre and its muzzle
+    let segments = [Segment(vec2(10.0, -50.0), vec2(10.0, 50.0), EdgeState::Reflective),
+        Segment(vec2(200.0, -50.0), vec2(200.0, 50.0), EdgeState::Absorptive)];
+
+    let result = laser.solve_collisions(&segments);
+
+    let (start, end, _) = result.lines()[0];
+    assert_eq!(start, laser.muzzle());
+    assert_eq!(end, vec2(200.0, 0.0));
+}
+
+#[test]
+fn muzzle_and_sprite_size_are_laser_settings() {
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let settings = LaserSettings { muzzle_offset: 12.0, sprite_size: 40.0, ..LaserSettings::of(&laser) };
+
+    settings.apply(&mut laser);
+
+    assert_eq!(laser.muzzle(), vec2(12.0, 0.0));
+    assert_eq!(laser.sprite_size(), 40.0);
+    assert_eq!(LaserSettings::of(&laser), settings);
+}
+
+#[test]
+fn beam_quads_are_offset_perpendicular_to_the_line() {
+    let quad = beam_quad(vec2(0.0, 0.0), vec2(10.0, 0.0), 4.0).unwrap();
+    assert_eq!(quad, [vec2(0.0, 2.0), vec2(10.0, 2.0), vec2(10.0, -2.0), vec2(0.0, -2.0)]);
+
+    let (start, end) = (vec2(3.0, -1.0), vec2(-5.0, 7.0));
+    let quad = beam_quad(start, end, 6.0).unwrap();
+    let along = (end - start).normalize();
+    for (corner, base) in quad.iter().zip([start, end, end, start]) {
+        let offset = *corner - base;
+        assert!(offset.dot(along).abs() < 1e-5);
+        assert!((offset.length() - 3.0).abs() < 1e-5);
+    }
+    // the sides are on opposite sides of the line
+    assert!((quad[0] - start).dot(quad[3] - start) < 0.0);
+    assert_eq!(beam_quad(start, start, 6.0), None);
+}
+
+#[test]
+fn housing_follows_the_laser_and_absorbs_returning_light() {
+    let mut laser = Laser::headless(vec2(0.0, 0.0), Vec2::X);
+    laser.set_sprite_size(40.0);
+    let housing = laser.housing().unwrap();
+    assert_eq!(housing[0], Segment(vec2(-20.0, 20.0), vec2(-20.0, -20.0), EdgeState::Absorptive));
+
+    laser.look_at(vec2(0.0, 100.0));
+    let back = laser.housing().unwrap()[0];
+    assert!(back.0.abs_diff_eq(vec2(-20.0, -20.0), 1e-5) && back.1.abs_diff_eq(vec2(20.0, -20.0), 1e-5));
+
+    // a mirror straight ahead sends the beam back into the laser; a wall behind it would
+    // otherwise catch it
+    laser.look_at(vec2(100.0, 0.0));
+    let segments = [Segment(vec2(100.0, -50.0), vec2(100.0, 50.0), EdgeState::Reflective),
+        Segment(vec2(-100.0, -50.0), vec2(-100.0, 50.0), EdgeState::Absorptive)];
+    let blocked = laser.solve_collisions(&segments);
+    assert_eq!(blocked.lines().last().unwrap().1, vec2(-20.0, 0.0));
+    assert_eq!(blocked.segment_hits().len(), segments.len());
+    assert_eq!(blocked.segment_hits()[1], 0.0);
+    let key = laser.trace_key(0);
+
+    laser.occluder = false;
+    assert_eq!(laser.housing(), None);
+    let escaped = laser.solve_collisions(&segments).lines().last().unwrap().1;
+    assert!(escaped.abs_diff_eq(vec2(-100.0, 0.0), 1e-3), "{:?}", escaped);
+    assert_ne!(laser.trace_key(0), key);
+}
+
+#[test]
+fn the_beam_only_hits_edges_on_its_layers() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(100.0, -50.0));
+    let b = network.add_node(vec2(100.0, 50.0));
+    network.add_connection(a, b);
+    network.set_edge_layer(0, 0b100, false);
+    assert!(!network.connections[0].is_visible());
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.occluder = false;
+    let segments = network.get_all_connections();
+
+    laser.collision_mask = 0b011;
+    let through = laser.solve_collisions_with_surfaces(&segments, &network.surfaces());
+    assert_eq!(through.hits[0].segment, None);
+
+    laser.collision_mask = 0b110;
+    let blocked = laser.solve_collisions_with_surfaces(&segments, &network.surfaces());
+    assert_eq!(blocked.hits[0].segment, Some(0));
+    assert_eq!(LaserSettings::of(&laser).collision_mask, 0b110);
+}
+
+#[test]
+fn trace_stats_count_why_the_beam_stopped() {
+    let mut laser = Laser::headless(vec2(0.0, 0.0), Vec2::from_angle(0.3));
+    laser.occluder = false;
+    let closed = [
+        Segment(vec2(-200.0, -200.0), vec2(200.0, -200.0), EdgeState::Reflective),
+        Segment(vec2(200.0, -200.0), vec2(200.0, 200.0), EdgeState::Reflective),
+        Segment(vec2(200.0, 200.0), vec2(-200.0, 200.0), EdgeState::Reflective),
+        Segment(vec2(-200.0, 200.0), vec2(-200.0, -200.0), EdgeState::Reflective),
+    ];
+
+    let trapped = laser.solve_collisions(&closed);
+    assert!(trapped.stats.budget_exhausted);
+    assert_eq!(trapped.stats.terminations(Termination::Budget), 1);
+    assert_eq!(trapped.truncated_ends().count(), 1);
+    assert_eq!(trapped.truncated_ends().next(), trapped.lines().last().map(|line| line.1));
+
+    let absorbed = laser.solve_collisions(&[Segment(vec2(200.0, -200.0), vec2(200.0, 200.0), EdgeState::Absorptive)]);
+    assert!(!absorbed.stats.budget_exhausted);
+    assert_eq!((absorbed.stats.absorbed, absorbed.stats.budget, absorbed.stats.max_distance), (1, 0, 0));
+    assert_eq!(laser.solve_collisions(&[]).stats.terminations(Termination::MaxDistance), 1);
+}
+
+#[test]
+fn emitting_edges_light_the_scene_with_the_beam_off() {
+    let mut network = NodeNetwork::headless();
+    network.add_segments(&[Segment(vec2(-100.0, 100.0), vec2(100.0, 100.0), EdgeState::Reflective)]);
+    network.set_edge_emitter(0, Some(EmitterConfig { rays: 5, ..Default::default() }));
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let (segments, surfaces) = (network.get_all_connections(), network.surfaces());
+
+    let both = laser.solve_collisions_with_surfaces(&segments, &surfaces);
+    let key = laser.trace_key(network.generation());
+    laser.beam_on = false;
+    let emitted = laser.solve_collisions_with_surfaces(&segments, &surfaces);
+
+    assert_eq!(both.hits.iter().filter(|hit| hit.bounce == 0).count(), 6);
+    assert_eq!(emitted.hits.iter().filter(|hit| hit.bounce == 0).count(), 5);
+    assert!(emitted.hits.iter().all(|hit| hit.bounce > 0 || hit.origin.y == 100.0));
+    assert_ne!(laser.trace_key(network.generation()), key);
+    assert!(!LaserSettings::of(&laser).beam_on);
+}
+
+#[test]
+fn line_meshes_hold_one_quad_per_line_in_its_color() {
+    let color = Color::new(0.2, 0.4, 0.6, 0.8);
+    let lines: Vec<_> = (0..LINES_PER_MESH + 5)
+        .map(|i| (vec2(i as f32, 0.0), vec2(i as f32, 10.0), color))
+        .chain([(vec2(3.0, 3.0), vec2(3.0, 3.0), color)])
+        .collect();
+
+    let meshes = line_meshes(&lines, 2.0);
+
+    assert_eq!(meshes.len(), 2);
+    assert_eq!((meshes[0].vertices.len(), meshes[0].indices.len()), (LINES_PER_MESH * 4, LINES_PER_MESH * 6));
+    // the zero-length line is left out
+    assert_eq!((meshes[1].vertices.len(), meshes[1].indices.len()), (20, 30));
+    assert!(meshes.iter().all(|mesh| mesh.indices.iter().all(|&i| (i as usize) < mesh.vertices.len())));
+    let corners: Vec<_> = meshes[0].vertices[..4].iter().map(|vertex| vertex.position.truncate()).collect();
+    assert_eq!(Some(corners.try_into().unwrap()), beam_quad(vec2(0.0, 0.0), vec2(0.0, 10.0), 2.0));
+    assert_eq!(meshes[1].vertices[0].color, color);
+}
+
+fn sweep(start: f32, end: f32, steps: usize) -> Sweep {
+    Sweep { enabled: true, start, end, steps, accumulate: false }
+}
+
+#[test]
+fn sweep_angles_are_evenly_spaced() {
+    let sweep = sweep(-45.0, 45.0, 7);
+
+    assert_eq!((0..7).map(|step| sweep.angle(step)).collect::<Vec<_>>(), [-45.0, -30.0, -15.0, 0.0, 15.0, 30.0, 45.0]);
+    assert_eq!(self::sweep(10.0, 20.0, 1).angle(0), 10.0);
+    assert_ne!(sweep.color(0), sweep.color(3));
+}
+
+#[test]
+fn a_sweep_traces_every_angle_in_its_own_color() {
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.sweep = sweep(-90.0, 90.0, 3);
+
+    let result = laser.solve_collisions_sweep(&[], &Default::default());
+
+    assert_eq!(result.lines().len(), 3);
+    for ((start, end, color), direction) in result.lines().iter().zip([-Vec2::Y, Vec2::X, Vec2::Y]) {
+        assert!(((*end - *start).normalize() - direction).length() < 1e-5);
+        assert!((*start - direction * Laser::DEFAULT_MUZZLE_OFFSET).length() < 1e-4);
+        assert_eq!(color.a, 1.0);
+    }
+    assert_eq!(result.lines()[0].2, Color { a: 1.0, ..laser.sweep.color(0) });
+    assert_ne!(result.lines()[0].2, result.lines()[1].2);
+}
+
+#[test]
+fn a_sweep_shares_the_ray_budget() {
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.occluder = false;
+    // a mirror box the beam never leaves
+    let corners = [vec2(-100.0, -100.0), vec2(100.0, -100.0), vec2(100.0, 100.0), vec2(-100.0, 100.0)];
+    let segments: Vec<Segment> = (0..4)
+        .map(|i| Segment(corners[i], corners[(i + 1) % 4], EdgeState::Reflective))
+        .collect();
+    let single = laser.solve_collisions(&segments).lines().len();
+
+    laser.sweep = sweep(-30.0, 30.0, 4);
+    let swept = laser.solve_collisions_sweep(&segments, &Default::default());
+
+    assert_eq!(swept.lines().len(), single / 4 * 4);
+    assert!(swept.stats.budget_exhausted);
+}
+
+#[test]
+fn an_accumulating_sweep_adds_an_angle_per_call_until_the_scene_changes() {
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.sweep = Sweep { accumulate: true, ..sweep(-10.0, 10.0, 3) };
+    assert!(laser.sweep.accumulates());
+    let surfaces = Default::default();
+
+    for traced in 1..=3 {
+        assert!(!laser.sweep_complete(0));
+        assert!(laser.accumulate_sweep(0, &[], &surfaces));
+        assert_eq!(laser.accumulated_sweep().lines().len(), traced);
+    }
+    assert!(laser.sweep_complete(0));
+    assert!(!laser.accumulate_sweep(0, &[], &surfaces));
+    assert_eq!(laser.accumulated_sweep().lines(), laser.solve_collisions_sweep(&[], &surfaces).lines());
+
+    // a new generation starts over
+    assert!(laser.accumulate_sweep(1, &[], &surfaces));
+    assert_eq!(laser.accumulated_sweep().lines().len(), 1);
+    laser.clear_sweep_accumulation();
+    assert!(laser.accumulated_sweep().lines().is_empty());
+    assert!(!laser.sweep_complete(1));
+}
+
+#[test]
+fn fan_meshes_hold_a_triangle_per_corner_fading_outwards() {
+    let square = [vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)];
+    let color = Color::new(1.0, 0.5, 0.0, 0.8);
+
+    let meshes = fan_meshes(Vec2::ZERO, &square, color);
+
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(meshes[0].indices.len(), 12);
+    assert_eq!(meshes[0].vertices[0].color, color);
+    assert!(meshes[0].vertices[1..].iter().all(|vertex| vertex.color.a < color.a));
+    // the last triangle closes the fan back onto the first corner
+    let last = &meshes[0].indices[9..];
+    assert_eq!(meshes[0].vertices[last[2] as usize].position.truncate(), square[0]);
+
+    let circle: Vec<Vec2> = (0..TRIANGLES_PER_MESH + 1).map(|i| Vec2::from_angle(i as f32)).collect();
+    let meshes = fan_meshes(Vec2::ZERO, &circle, color);
+    assert_eq!(meshes.len(), 2);
+    assert_eq!(meshes[1].indices.len(), 3);
+}
+
+#[test]
+fn a_locked_laser_keeps_aiming_at_its_target_node() {
+    let mut network = NodeNetwork::headless();
+    let target = network.add_node(vec2(100.0, 0.0));
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.set_target(Some(target));
+
+    network.move_node(target, vec2(0.0, 100.0));
+    laser.follow_target(&network);
+    assert_eq!(laser.aim().1, Vec2::Y);
+
+    network.move_node(target, vec2(-100.0, 0.0));
+    laser.follow_target(&network);
+    assert_eq!(laser.aim().1, -Vec2::X);
+}
+
+#[test]
+fn deleting_the_target_node_releases_the_lock() {
+    let mut network = NodeNetwork::headless();
+    let target = network.add_node(vec2(0.0, 100.0));
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.set_target(Some(target));
+    laser.follow_target(&network);
+    let aim = laser.aim();
+
+    network.remove_node(target);
+    laser.follow_target(&network);
+    assert_eq!(laser.target(), None);
+    assert_eq!(laser.aim(), aim);
+}
+
+#[test]
+fn retracing_into_kept_buffers_matches_a_fresh_trace() {
+    let mut network = NodeNetwork::headless();
+    network.add_segments(&[Segment(vec2(-100.0, 100.0), vec2(100.0, 100.0), EdgeState::Reflective),
+                           Segment(vec2(200.0, -100.0), vec2(200.0, 100.0), EdgeState::Reflective)]);
+    network.set_edge_emitter(0, Some(EmitterConfig { rays: 5, ..Default::default() }));
+    let laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut segments = Vec::new();
+    network.traced_segments_into(&mut segments);
+    assert_eq!(segments, network.traced_segments());
+    let surfaces = network.surfaces();
+    let (mut scratch, mut out) = (TraceScratch::default(), TraceResult::default());
+
+    let fresh = laser.solve_collisions_with_surfaces(&segments, &surfaces);
+    for _ in 0..2 {
+        laser.solve_collisions_into(&segments, &surfaces, &mut scratch, &mut out);
+        assert_eq!(out.hits, fresh.hits);
+        assert_eq!(out.lines(), fresh.lines());
+    }
+    let capacity = segments.capacity();
+    network.traced_segments_into(&mut segments);
+    assert_eq!(segments.capacity(), capacity);
+}
+
+#[test]
+fn a_mounted_laser_moves_with_its_node_and_turns_along_its_edge() {
+    let mut network = NodeNetwork::headless();
+    let host = network.add_node(vec2(50.0, 50.0));
+    let neighbour = network.add_node(vec2(50.0, 150.0));
+    network.add_connection(host, neighbour);
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.attach_to_node(host);
+    laser.follow_mount(&network);
+    assert_eq!(laser.position(), vec2(50.0, 50.0));
+    assert_eq!(laser.aim().1, Vec2::X);
+
+    network.move_node(host, vec2(250.0, 150.0));
+    laser.follow_mount(&network);
+    assert_eq!(laser.position(), vec2(250.0, 150.0));
+    assert_eq!(laser.aim().0, vec2(250.0, 150.0) + Vec2::X * laser.muzzle_offset());
+
+    laser.align_along(Some(neighbour));
+    laser.follow_mount(&network);
+    assert_eq!(laser.aim().1, -Vec2::X);
+
+    network.remove_edge(0);
+    laser.follow_mount(&network);
+    assert_eq!((laser.mount(), laser.mount_align()), (Some(host), None));
+}
+
+#[test]
+fn deleting_the_mount_node_detaches_the_laser_where_it_was() {
+    let mut network = NodeNetwork::headless();
+    let host = network.add_node(vec2(50.0, 50.0));
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    laser.attach_to_node(host);
+    laser.follow_mount(&network);
+
+    network.remove_node(host);
+    laser.follow_mount(&network);
+    assert_eq!(laser.mount(), None);
+    assert_eq!(laser.position(), vec2(50.0, 50.0));
+}
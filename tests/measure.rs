@@ -0,0 +1,42 @@
+use macroquad::math::vec2;
+use ray_cast::measure::{angle_degrees, edge_label, format_angle, format_length, line_angle_degrees, position_label, segment_label};
+
+#[test]
+fn angles_are_counterclockwise_on_screen() {
+    let origin = vec2(0.0, 0.0);
+
+    assert_eq!(angle_degrees(origin, vec2(1.0, 0.0)), 0.0);
+    // world y points down, so up on screen is negative y
+    assert!((angle_degrees(origin, vec2(0.0, -1.0)) - 90.0).abs() < 1e-4);
+    assert!((angle_degrees(origin, vec2(-1.0, 0.0)) - 180.0).abs() < 1e-4);
+    assert!((angle_degrees(origin, vec2(0.0, 1.0)) - 270.0).abs() < 1e-4);
+}
+
+#[test]
+fn line_angle_ignores_direction() {
+    let (a, b) = (vec2(0.0, 0.0), vec2(1.0, -1.0));
+
+    assert!((line_angle_degrees(a, b) - 45.0).abs() < 1e-4);
+    assert!((line_angle_degrees(b, a) - 45.0).abs() < 1e-4);
+    assert_eq!(line_angle_degrees(vec2(3.0, 0.0), vec2(0.0, 0.0)), 0.0);
+}
+
+#[test]
+fn tiny_negative_angle_wraps_to_zero() {
+    let angle = angle_degrees(vec2(0.0, 0.0), vec2(1.0, 1e-9));
+
+    assert!((0.0..360.0).contains(&angle));
+}
+
+#[test]
+fn formatting_rounds_to_one_decimal() {
+    assert_eq!(format_length(12.345), "12.3");
+    assert_eq!(format_angle(44.96), "45.0 deg");
+    assert_eq!(position_label(7, vec2(1.26, -3.0)), "#7 (1.3, -3.0)");
+}
+
+#[test]
+fn labels_combine_length_and_angle() {
+    assert_eq!(segment_label(vec2(0.0, 0.0), vec2(0.0, 10.0)), "10.0 @ 270.0 deg");
+    assert_eq!(edge_label(vec2(0.0, 0.0), vec2(0.0, 10.0)), "10.0 @ 90.0 deg");
+}
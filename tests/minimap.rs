@@ -0,0 +1,44 @@
+use macroquad::math::{vec2, Rect, Vec2};
+use ray_cast::minimap::{Minimap, MinimapTransform, MINIMAP_SIZE};
+use ray_cast::{Laser, NodeNetwork};
+
+#[test]
+fn world_and_minimap_points_map_back_and_forth() {
+    let transform = MinimapTransform::new(Rect::new(-100.0, 0.0, 400.0, 400.0), Rect::new(600.0, 400.0, 200.0, 200.0));
+
+    assert_eq!(transform.to_map(vec2(-100.0, 0.0)), vec2(600.0, 400.0));
+    assert_eq!(transform.to_map(vec2(300.0, 400.0)), vec2(800.0, 600.0));
+    assert_eq!(transform.to_world(vec2(700.0, 500.0)), vec2(100.0, 200.0));
+    for point in [vec2(0.0, 0.0), vec2(-37.5, 250.0), vec2(1000.0, -20.0)] {
+        assert!(transform.to_world(transform.to_map(point)).abs_diff_eq(point, 1e-3), "{:?}", point);
+    }
+}
+
+#[test]
+fn a_wide_scene_is_centred_without_stretching() {
+    let transform = MinimapTransform::new(Rect::new(0.0, 0.0, 1000.0, 100.0), Rect::new(0.0, 0.0, 200.0, 200.0));
+
+    assert_eq!(transform.world(), Rect::new(0.0, -450.0, 1000.0, 1000.0));
+    assert_eq!(transform.to_map(vec2(500.0, 50.0)), vec2(100.0, 100.0));
+    let (left, right) = (transform.to_map(vec2(0.0, 0.0)), transform.to_map(vec2(1000.0, 0.0)));
+    assert_eq!(right - left, vec2(200.0, 0.0));
+}
+
+#[test]
+fn clicks_only_land_on_a_shown_minimap() {
+    let mut network = NodeNetwork::headless();
+    network.add_node(vec2(0.0, 0.0));
+    network.add_node(vec2(1000.0, 1000.0));
+    let laser = Laser::headless(vec2(500.0, 500.0), Vec2::X);
+    let screen = vec2(800.0, 600.0);
+    let rect = Minimap::rect(screen);
+    assert_eq!(rect.size(), Vec2::splat(MINIMAP_SIZE));
+    assert!(rect.right() <= screen.x && rect.bottom() <= screen.y);
+
+    let mut minimap = Minimap::default();
+    assert_eq!(minimap.world_at(rect.center(), &network, &laser, screen), None);
+    minimap.visible = true;
+    let center = minimap.world_at(rect.center(), &network, &laser, screen).unwrap();
+    assert!(center.abs_diff_eq(vec2(500.0, 500.0), 1e-3), "{:?}", center);
+    assert_eq!(minimap.world_at(vec2(10.0, 10.0), &network, &laser, screen), None);
+}
@@ -0,0 +1,54 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::{EdgeState, Laser, Modulation, Segment};
+
+#[test]
+fn off_is_always_full() {
+    for time in [0.0, 0.3, 12.7] {
+        assert_eq!(Modulation::Off.intensity(time), 1.0);
+    }
+}
+
+#[test]
+fn sine_swings_between_dark_and_full() {
+    let sine = Modulation::Sine { hz: 2.0 };
+
+    assert!((sine.intensity(0.0) - 0.5).abs() < 1e-6);
+    // a quarter period in: the peak, three quarters: the trough
+    assert!((sine.intensity(0.125) - 1.0).abs() < 1e-6);
+    assert!(sine.intensity(0.375).abs() < 1e-6);
+    assert!((sine.intensity(0.125) - sine.intensity(0.625)).abs() < 1e-6);
+}
+
+#[test]
+fn square_is_on_for_the_duty_fraction() {
+    let square = Modulation::Square { hz: 1.0, duty: 0.25 };
+
+    assert_eq!(square.intensity(0.0), 1.0);
+    assert_eq!(square.intensity(0.2), 1.0);
+    assert_eq!(square.intensity(0.3), 0.0);
+    assert_eq!(square.intensity(0.9), 0.0);
+    assert_eq!(square.intensity(3.1), 1.0);
+}
+
+#[test]
+fn square_duty_extremes() {
+    assert_eq!(Modulation::Square { hz: 3.0, duty: 0.0 }.intensity(0.1), 0.0);
+    assert_eq!(Modulation::Square { hz: 3.0, duty: 1.0 }.intensity(0.1), 1.0);
+}
+
+#[test]
+fn modulate_retints_without_touching_geometry() {
+    let laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let segments = [Segment(vec2(50.0, -10.0), vec2(50.0, 10.0), EdgeState::Absorptive)];
+    let mut result = laser.solve_collisions(&segments);
+    let full = result.lines()[0];
+
+    laser.modulate(&mut result, 0.25);
+    let dim = result.lines()[0];
+    laser.modulate(&mut result, 0.5);
+
+    assert_eq!((dim.0, dim.1), (full.0, full.1));
+    assert_eq!(dim.2.a, full.2.a * 0.25);
+    // recolored from the hits each time, not compounded
+    assert_eq!(result.lines()[0].2.a, full.2.a * 0.5);
+}
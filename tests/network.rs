@@ -0,0 +1,904 @@
+use macroquad::math::{vec2, Rect, Vec2};
+use ray_cast::core::{EdgeState, EmitterConfig, Segment, Surfaces, DEFAULT_THICKNESS};
+use ray_cast::labyrinth::{Algorithm, Labyrinth};
+use ray_cast::replay::{apply, Event};
+use ray_cast::{polygon, Animation, Edge, Laser, LockKind, Node, NodeNetwork, PickResult};
+
+/// Endpoints of every edge, in `connections` order.
+fn endpoints(network: &NodeNetwork) -> Vec<(usize, usize)> {
+    network.connections.iter().map(|edge| edge.endpoints()).collect()
+}
+
+fn no_degenerate_edges(network: &NodeNetwork) -> bool {
+    network.connections.iter().all(|edge| {
+        let (a, b) = edge.endpoints();
+        a != b
+    })
+}
+
+#[test]
+fn split_edge_replaces_edge_in_place() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(10.0, 0.0));
+    let c = network.add_node(vec2(10.0, 10.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+    network.connections[0].set_state(EdgeState::Transparent);
+    let thickness = network.connections[0].thickness();
+
+    let middle = network.split_edge(0, vec2(5.0, 0.0));
+
+    assert_eq!(endpoints(&network), vec![(a, middle), (middle, b), (b, c)]);
+    assert_eq!(network.node_position(middle), Some(vec2(5.0, 0.0)));
+    for edge in &network.connections[..2] {
+        assert_eq!(edge.state(), EdgeState::Transparent);
+        assert_eq!(edge.thickness(), thickness);
+    }
+    assert!(no_degenerate_edges(&network));
+}
+
+#[test]
+fn merge_rewires_edges_onto_the_kept_node() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(1.0, 0.0));
+    let c = network.add_node(vec2(0.0, 10.0));
+    network.add_connection(b, c);
+
+    network.merge_nodes(a, b);
+
+    assert_eq!(endpoints(&network), vec![(a, c)]);
+    assert_eq!(network.node_position(b), None);
+}
+
+#[test]
+fn merge_drops_self_loops_and_duplicates() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(1.0, 0.0));
+    let c = network.add_node(vec2(0.0, 10.0));
+    network.add_connection(a, c);
+    network.add_connection(a, b);
+    network.add_connection(c, b);
+
+    network.merge_nodes(a, b);
+
+    // a-b collapses to a loop, c-b duplicates a-c
+    assert_eq!(endpoints(&network), vec![(a, c)]);
+    assert!(no_degenerate_edges(&network));
+}
+
+#[test]
+fn merge_with_itself_or_missing_node_is_a_no_op() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(1.0, 0.0));
+    network.add_connection(a, b);
+    let generation = network.generation();
+
+    network.merge_nodes(a, a);
+    network.merge_nodes(a, 99);
+
+    assert_eq!(endpoints(&network), vec![(a, b)]);
+    assert_eq!(network.generation(), generation);
+}
+
+#[test]
+fn set_all_states_leaves_the_border_alone() {
+    let mut network = NodeNetwork::headless();
+    network.add_bounds(Rect::new(0.0, 0.0, 100.0, 100.0), EdgeState::Absorptive);
+    let a = network.add_node(vec2(10.0, 10.0));
+    let b = network.add_node(vec2(20.0, 10.0));
+    network.add_connection(a, b);
+    let generation = network.generation();
+
+    network.set_all_states(EdgeState::Transparent);
+
+    for edge in &network.connections {
+        let expected = if edge.is_border() { EdgeState::Absorptive } else { EdgeState::Transparent };
+        assert_eq!(edge.state(), expected);
+    }
+    assert_eq!(network.generation(), generation + 1);
+}
+
+#[test]
+fn circle_is_one_closed_loop() {
+    let mut network = NodeNetwork::headless();
+    let nodes = network.add_circle(vec2(0.0, 0.0), 50.0, EdgeState::Transparent, 12, 5.0, None);
+
+    let components = network.components();
+
+    assert_eq!(components.len(), 1);
+    assert_eq!(components[0].len(), nodes.len());
+    assert!(network.is_closed_loop(&components[0]));
+    assert!(nodes.iter().all(|node| network.degree(*node) == 2));
+}
+
+#[test]
+fn open_polyline_and_figure_eight_are_not_loops() {
+    let mut network = NodeNetwork::headless();
+    let path: Vec<usize> = (0..4).map(|i| network.add_node(vec2(i as f32, 0.0))).collect();
+    for pair in path.windows(2) {
+        network.add_connection(pair[0], pair[1]);
+    }
+    assert!(!network.is_closed_loop(&network.components()[0]));
+
+    // two triangles sharing a node: degree four in the middle
+    let mut network = NodeNetwork::headless();
+    let ids: Vec<usize> = (0..5).map(|i| network.add_node(vec2(i as f32, (i % 2) as f32))).collect();
+    for (a, b) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2)] {
+        network.add_connection(ids[a], ids[b]);
+    }
+    assert!(!network.is_closed_loop(&network.components()[0]));
+    // and two separate triangles given as one component are not one loop either
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 5.0, EdgeState::Reflective, 3, 5.0, None);
+    network.add_circle(vec2(50.0, 0.0), 5.0, EdgeState::Reflective, 3, 5.0, None);
+    let all: Vec<usize> = network.components().concat();
+    assert!(!network.is_closed_loop(&all));
+}
+
+#[test]
+fn closed_loops_list_their_nodes_in_order() {
+    let mut network = NodeNetwork::headless();
+    let square = network.add_circle(vec2(0.0, 0.0), 10.0, EdgeState::Reflective, 4, 5.0, None);
+    let path: Vec<usize> = (0..3).map(|i| network.add_node(vec2(50.0 + i as f32, 0.0))).collect();
+    network.add_connection(path[0], path[1]);
+    network.add_connection(path[1], path[2]);
+
+    let loops = network.closed_loops();
+
+    assert_eq!(loops.len(), 1);
+    let mut cycle = loops[0].clone();
+    // either way round the square, starting from its first node
+    if cycle[1] != square[1] { cycle[1..].reverse(); }
+    assert_eq!(cycle, square);
+}
+
+#[test]
+fn only_closed_transparent_loops_are_filled() {
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 10.0, EdgeState::Transparent, 4, 5.0, None);
+    network.add_circle(vec2(50.0, 0.0), 10.0, EdgeState::Reflective, 4, 5.0, None);
+    // a bow tie can't be filled
+    let corners = [vec2(100.0, 0.0), vec2(110.0, 10.0), vec2(110.0, 0.0), vec2(100.0, 10.0)];
+    let tie: Vec<usize> = corners.iter().map(|&corner| network.add_node(corner)).collect();
+    for i in 0..4 {
+        network.add_connection(tie[i], tie[(i + 1) % 4]);
+    }
+    for edge in &mut network.connections[8..] { edge.set_state(EdgeState::Transparent); }
+
+    network.refresh_fills();
+
+    assert_eq!(network.fills().len(), 2);
+    let area: f32 = network.fills().iter().map(|triangle| polygon::signed_area(triangle).abs()).sum();
+    assert!((area - 200.0).abs() < 1e-3);
+    assert!(network.fills().iter().flatten().all(|corner| corner.x.abs() <= 10.0 + 1e-3));
+
+    let square = network.closed_loops()[0].clone();
+    network.move_node(square[0], vec2(20.0, 0.0));
+    network.refresh_fills();
+    let area: f32 = network.fills().iter().map(|triangle| polygon::signed_area(triangle).abs()).sum();
+    assert!((area - 300.0).abs() < 1e-3);
+}
+
+#[test]
+fn refreshing_with_animated_nodes_keeps_every_loop_filled() {
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 10.0, EdgeState::Transparent, 4, 5.0, None);
+    network.add_circle(vec2(50.0, 0.0), 10.0, EdgeState::Transparent, 4, 5.0, None);
+    let swinging = network.closed_loops()[1][0];
+    network.set_animation(swinging, Some(Animation::Oscillate { axis: Vec2::Y, amplitude: 1.0, speed: 1.0 }));
+    network.refresh_fills();
+    let fills = network.fills().to_vec();
+
+    network.refresh_fills();
+    network.refresh_fills();
+
+    assert_eq!(fills.len(), 4);
+    assert_eq!(network.fills(), fills);
+}
+
+#[test]
+fn labyrinth_walls_are_one_component() {
+    let mut labyrinth = Labyrinth::new(10.0, (6, 5));
+    labyrinth.generate(Algorithm::DepthFirst, 3);
+    let segments: Vec<Segment> = labyrinth.get_as_lines().iter()
+        .map(|&((x1, y1), (x2, y2))| Segment(vec2(x1, y1), vec2(x2, y2), EdgeState::Reflective))
+        .collect();
+    let mut network = NodeNetwork::headless();
+
+    network.add_segments(&segments);
+
+    assert_eq!(network.components().len(), 1);
+}
+
+#[test]
+fn removing_a_bridge_node_splits_components() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(10.0, 0.0));
+    let c = network.add_node(vec2(20.0, 0.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+    assert_eq!(network.components(), vec![vec![a, b, c]]);
+    assert_eq!(network.edges_of(b), vec![0, 1]);
+    assert_eq!(network.degree(b), 2);
+
+    network.remove_node(b);
+
+    assert_eq!(network.components(), vec![vec![a], vec![c]]);
+    assert_eq!(network.degree(a), 0);
+}
+
+#[test]
+fn bounding_box_spans_all_nodes() {
+    let mut network = NodeNetwork::headless();
+    assert_eq!(network.bounding_box(), None);
+    network.add_node(vec2(-5.0, 10.0));
+    network.add_node(vec2(20.0, -3.0));
+
+    assert_eq!(network.bounding_box(), Some((vec2(-5.0, -3.0), vec2(20.0, 10.0))));
+}
+
+#[test]
+fn only_closed_transparent_loops_are_media() {
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 50.0, EdgeState::Transparent, 6, 5.0, None);
+    network.add_circle(vec2(200.0, 0.0), 50.0, EdgeState::Reflective, 4, 5.0, None);
+    network.add_circle(vec2(400.0, 0.0), 50.0, EdgeState::Transparent, 5, 5.0, None);
+    let a = network.add_node(vec2(0.0, 300.0));
+    let b = network.add_node(vec2(10.0, 300.0));
+    network.add_connection(a, b);
+    network.connections.last_mut().unwrap().set_state(EdgeState::Transparent);
+
+    let media = network.segment_media();
+
+    assert_eq!(media.len(), network.connections.len());
+    assert_eq!(media[..6], [Some(0); 6]);
+    assert_eq!(media[6..10], [None; 4]);
+    assert_eq!(media[10..15], [Some(1); 5]);
+    assert_eq!(media[15], None);
+}
+
+#[test]
+fn edge_roughness_is_clamped_and_kept_by_splits() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    let c = network.add_node(vec2(100.0, 100.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+    let generation = network.generation();
+
+    network.set_edge_roughness(0, 1.5);
+    assert_eq!(network.connections[0].roughness(), 1.0);
+    assert!(network.generation() > generation);
+    network.set_edge_roughness(1, 0.25);
+    network.split_edge(0, vec2(50.0, 0.0));
+
+    assert_eq!(network.segment_roughness(), vec![1.0, 1.0, 0.25]);
+}
+
+#[test]
+fn edge_thickness_is_clamped_and_widens_the_hover_band() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    network.add_connection(a, b);
+    assert_eq!(network.connections[0].thickness(), DEFAULT_THICKNESS);
+    assert_eq!(network.pick(vec2(50.0, 9.0)), None);
+    let generation = network.generation();
+
+    network.set_edge_thickness(0, 100.0);
+    assert_eq!(network.connections[0].thickness(), Edge::MAX_THICKNESS);
+    assert!(network.generation() > generation);
+    assert_eq!(network.pick(vec2(50.0, 9.0)), Some(PickResult::Edge(0)));
+    network.set_edge_thickness(0, 0.0);
+    assert_eq!(network.connections[0].thickness(), Edge::MIN_THICKNESS);
+    network.split_edge(0, vec2(50.0, 0.0));
+    assert_eq!(network.surfaces().thickness, vec![Edge::MIN_THICKNESS; 2]);
+}
+
+#[test]
+fn built_shapes_take_the_thickness_they_are_given() {
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 20.0, EdgeState::Reflective, 5, 12.0, None);
+    network.add_lines(&[((0.0, 0.0), (10.0, 0.0))], vec2(100.0, 0.0), EdgeState::Absorptive, 2.0, None);
+
+    let thickness: Vec<f32> = network.connections.iter().map(|edge| edge.thickness()).collect();
+    assert_eq!(thickness, vec![12.0, 12.0, 12.0, 12.0, 12.0, 2.0]);
+    assert_eq!(network.surfaces().thickness, network.segment_thickness());
+}
+
+#[test]
+fn self_loops_are_rejected() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let generation = network.generation();
+
+    assert!(!network.add_connection(a, a));
+    assert!(network.connections.is_empty());
+    assert_eq!(network.generation(), generation);
+}
+
+#[test]
+fn zero_length_edges_are_not_traced() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(5.0, 5.0));
+    let b = network.add_node(vec2(5.0, 5.0));
+    let c = network.add_node(vec2(15.0, 5.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+
+    assert_eq!(network.connections.len(), 2);
+    assert_eq!(network.get_all_connections(), vec![Segment(vec2(5.0, 5.0), vec2(15.0, 5.0), EdgeState::Reflective)]);
+    assert_eq!(network.segment_media(), vec![None]);
+}
+
+#[test]
+fn duplicate_edges_are_traced_once() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(10.0, 0.0));
+    let c = network.add_node(vec2(10.0, 0.0));
+    let d = network.add_node(vec2(0.0, 0.0));
+    network.add_connection(a, b);
+    // same wall again, drawn the other way round between different nodes
+    network.add_connection(c, d);
+
+    assert_eq!(network.get_all_connections().len(), 1);
+
+    network.connections[1].set_state(EdgeState::Absorptive);
+    assert_eq!(network.get_all_connections().len(), 2);
+}
+
+#[test]
+fn dangling_edges_are_skipped_and_removed() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(10.0, 0.0));
+    network.add_connection(a, b);
+    network.add_connection(a, 99);
+
+    assert_eq!(network.get_all_connections(), vec![Segment(vec2(0.0, 0.0), vec2(10.0, 0.0), EdgeState::Reflective)]);
+
+    let generation = network.generation();
+    assert_eq!(network.remove_dangling_edges(), 1);
+    assert_eq!(endpoints(&network), vec![(a, b)]);
+    assert!(network.generation() > generation);
+    assert_eq!(network.remove_dangling_edges(), 0);
+}
+
+#[test]
+fn imported_degenerate_line_adds_no_edge() {
+    let mut network = NodeNetwork::headless();
+
+    network.add_segments(&[Segment(vec2(3.0, 3.0), vec2(3.0, 3.0), EdgeState::Absorptive),
+        Segment(vec2(0.0, 0.0), vec2(10.0, 0.0), EdgeState::Transparent)]);
+
+    assert!(no_degenerate_edges(&network));
+    assert_eq!(network.get_all_connections(), vec![Segment(vec2(0.0, 0.0), vec2(10.0, 0.0), EdgeState::Transparent)]);
+}
+
+#[test]
+fn pick_prefers_nodes_over_edges() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    network.add_connection(a, b);
+
+    assert_eq!(network.pick(vec2(2.0, 1.0)), Some(PickResult::Node(a)));
+    assert_eq!(network.pick(vec2(50.0, 1.0)), Some(PickResult::Edge(0)));
+    assert_eq!(network.pick(vec2(50.0, 20.0)), None);
+}
+
+#[test]
+fn pick_takes_the_closest_of_overlapping_edges() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    let c = network.add_node(vec2(0.0, 2.0));
+    let d = network.add_node(vec2(100.0, 2.0));
+    network.add_connection(a, b);
+    network.add_connection(c, d);
+
+    assert_eq!(network.pick(vec2(50.0, 0.5)), Some(PickResult::Edge(0)));
+    assert_eq!(network.pick(vec2(50.0, 1.5)), Some(PickResult::Edge(1)));
+}
+
+#[test]
+fn pick_takes_the_closest_of_overlapping_nodes() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(6.0, 0.0));
+
+    assert_eq!(network.pick(vec2(2.0, 0.0)), Some(PickResult::Node(a)));
+    assert_eq!(network.pick(vec2(4.0, 0.0)), Some(PickResult::Node(b)));
+}
+
+#[test]
+fn placement_locks_to_the_closer_axis() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(100.0, 50.0));
+
+    assert_eq!(network.placement(vec2(130.0, 60.0), true), vec2(130.0, 60.0));
+
+    network.select(Some(a));
+    assert_eq!(network.placement(vec2(130.0, 60.0), false), vec2(130.0, 60.0));
+    assert_eq!(network.placement(vec2(130.0, 60.0), true), vec2(130.0, 50.0));
+    assert_eq!(network.placement(vec2(95.0, -20.0), true), vec2(100.0, -20.0));
+}
+
+#[test]
+fn node_hit_area_has_a_minimum_screen_size() {
+    let node = Node::new(vec2(0.0, 0.0), 8.0);
+
+    // zoomed far out, 6 px on screen is 60 world units
+    assert!(node.contains(vec2(50.0, 0.0), 0.1, 6.0));
+    assert!(!node.contains(vec2(70.0, 0.0), 0.1, 6.0));
+    // at 1x the node's own radius is already larger
+    assert!(node.contains(vec2(7.0, 0.0), 1.0, 6.0));
+    assert!(!node.contains(vec2(9.0, 0.0), 1.0, 6.0));
+    // zoomed in the node is big on screen and keeps its own size
+    assert!(node.contains(vec2(7.0, 0.0), 10.0, 6.0));
+    assert!(!node.contains(vec2(9.0, 0.0), 10.0, 6.0));
+}
+
+#[test]
+fn pick_keeps_edges_clickable_when_zoomed_out() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(1000.0, 0.0));
+    network.add_connection(a, b);
+    assert_eq!(network.pick(vec2(500.0, 10.0)), None);
+
+    unsafe { network.update_camera(vec2(500.0, 0.0), 0.1); }
+
+    assert_eq!(network.pick(vec2(500.0, 10.0)), Some(PickResult::Edge(0)));
+}
+
+#[test]
+fn add_lines_offsets_and_shares_nodes() {
+    let mut network = NodeNetwork::headless();
+
+    let nodes = network.add_lines(&[((0.0, 0.0), (10.0, 0.0)), ((10.0, 0.0), (10.0, 10.0))],
+                                  vec2(5.0, 5.0), EdgeState::Absorptive, 5.0, None);
+
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(network.get_all_connections(), vec![
+        Segment(vec2(5.0, 5.0), vec2(15.0, 5.0), EdgeState::Absorptive),
+        Segment(vec2(15.0, 5.0), vec2(15.0, 15.0), EdgeState::Absorptive),
+    ]);
+}
+
+#[test]
+fn edit_lines_cuts_walls_out_of_longer_edges_and_adds_them_back() {
+    let build = || {
+        let mut network = NodeNetwork::headless();
+        network.add_lines(&Labyrinth::new(10.0, (3, 1)).get_as_lines(), vec2(5.0, 5.0), EdgeState::Absorptive, 5.0,
+                          Some("maze"));
+        network
+    };
+    let length = |network: &NodeNetwork| -> f32 {
+        network.get_all_connections().iter().map(|segment| segment.0.distance(segment.1)).sum()
+    };
+    let mut network = build();
+    assert_eq!(length(&network), 100.0);
+    network.set_recording(true);
+
+    // the middle cell's top is part of one edge along the whole top
+    network.edit_lines(&[((10.0, 0.0), (10.0, 10.0)), ((10.0, 0.0), (20.0, 0.0))], &[], vec2(5.0, 5.0),
+                       EdgeState::Absorptive, 5.0, "maze");
+    assert_eq!(length(&network), 80.0);
+    network.edit_lines(&[], &[((10.0, 0.0), (20.0, 0.0))], vec2(5.0, 5.0), EdgeState::Absorptive, 5.0, "maze");
+
+    assert_eq!(length(&network), 90.0);
+    let members = network.group_members("maze");
+    assert_eq!(members.len(), network.nodes.len());
+    assert!(members.iter().all(|&node| network.is_pinned(node) && network.degree(node) > 0));
+    let events = network.take_events();
+    assert_eq!(events.len(), 2);
+    let mut replayed = build();
+    for event in &events { apply(event, &mut replayed, &mut Laser::headless(Vec2::ZERO, Vec2::X)); }
+    assert_eq!(replayed.get_all_connections(), network.get_all_connections());
+}
+
+#[test]
+fn pinned_nodes_are_not_dragged() {
+    let mut network = NodeNetwork::headless();
+    let walls = network.add_lines(&[((0.0, 0.0), (10.0, 0.0))], vec2(5.0, 5.0), EdgeState::Absorptive, 5.0, None);
+    let circle = network.add_circle(vec2(100.0, 100.0), 20.0, EdgeState::Reflective, 5, 5.0, None);
+    let free = network.add_node(vec2(50.0, 50.0));
+    assert!(walls.iter().chain(&circle).all(|&node| network.is_pinned(node)));
+    assert!(!network.is_pinned(free));
+
+    network.set_pinned(free, true);
+    network.drag_node(free, vec2(60.0, 60.0));
+    assert_eq!(network.node_position(free), Some(vec2(50.0, 50.0)));
+
+    // still connected to and deleted like any other node
+    assert!(network.add_connection(free, walls[0]));
+    network.remove_node(free);
+    assert_eq!(network.node_position(free), None);
+
+    network.pin_all(false);
+    network.drag_node(circle[0], vec2(0.0, 0.0));
+    assert_eq!(network.node_position(circle[0]), Some(vec2(0.0, 0.0)));
+}
+
+/// A chain a-b-c along the x axis, 100 apart, with both edges locked as `kind`.
+fn locked_chain(network: &mut NodeNetwork, kind: LockKind) -> [usize; 3] {
+    let nodes = [0.0, 100.0, 200.0].map(|x| network.add_node(vec2(x, 0.0)));
+    for (i, pair) in nodes.windows(2).enumerate() {
+        network.add_connection(pair[0], pair[1]);
+        assert!(network.lock_edge(i, Some(kind)));
+    }
+    nodes
+}
+
+#[test]
+fn locked_length_pulls_the_other_end_along() {
+    let mut network = NodeNetwork::headless();
+    let [a, b, c] = locked_chain(&mut network, LockKind::Length);
+
+    network.drag_node(c, vec2(200.0, 300.0));
+
+    let [a, b, c] = [a, b, c].map(|node| network.node_position(node).unwrap());
+    assert!((b.distance(c) - 100.0).abs() < 1e-3);
+    assert!((a.distance(b) - 100.0).abs() < 1e-3);
+    assert!(b.x > 100.0 && b.y > 0.0, "{}", b);
+}
+
+#[test]
+fn locked_angle_slides_along_the_edge_line() {
+    let mut network = NodeNetwork::headless();
+    let [a, b, _] = locked_chain(&mut network, LockKind::Angle);
+    network.lock_edge(1, None);
+
+    network.drag_node(b, vec2(140.0, 30.0));
+
+    // a keeps the angle, at its distance along the line; c is no longer locked
+    assert_eq!(network.node_position(a), Some(vec2(0.0, 30.0)));
+    assert_eq!(network.connections[1].lock(), None);
+}
+
+#[test]
+fn rigid_locks_carry_a_chain_along() {
+    let mut network = NodeNetwork::headless();
+    let [a, b, c] = locked_chain(&mut network, LockKind::Rigid);
+
+    network.drag_node(b, vec2(150.0, -20.0));
+
+    assert_eq!(network.node_position(a), Some(vec2(50.0, -20.0)));
+    assert_eq!(network.node_position(c), Some(vec2(250.0, -20.0)));
+}
+
+#[test]
+fn locks_closing_a_loop_are_refused() {
+    let mut network = NodeNetwork::headless();
+    let [a, _, c] = locked_chain(&mut network, LockKind::Length);
+    network.add_connection(c, a);
+
+    assert!(!network.lock_edge(2, Some(LockKind::Rigid)));
+    assert_eq!(network.connections[2].lock(), None);
+    // changing an existing lock is fine, and once the chain is open the loop can close
+    assert!(network.lock_edge(0, Some(LockKind::Angle)));
+    assert!(network.lock_edge(1, None));
+    assert!(network.lock_edge(2, Some(LockKind::Rigid)));
+    assert_eq!(LockKind::cycle(Some(LockKind::Rigid)), None);
+}
+
+#[test]
+fn back_faces_are_traced_and_flip() {
+    let mut network = NodeNetwork::headless();
+    network.add_segments(&[Segment(vec2(0.0, 0.0), vec2(100.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(0.0, 50.0), vec2(100.0, 50.0), EdgeState::Reflective)]);
+    let generation = network.generation();
+
+    network.set_edge_back_state(0, Some(EdgeState::Absorptive));
+    assert!(network.generation() > generation);
+    assert_eq!(network.connections[0].faces(), (EdgeState::Reflective, EdgeState::Absorptive));
+    assert_eq!(network.surfaces().back_states, vec![Some(EdgeState::Absorptive), None]);
+
+    network.flip_edge_faces(0);
+    assert_eq!(network.connections[0].faces(), (EdgeState::Absorptive, EdgeState::Reflective));
+    // one-sided edges have nothing to flip
+    network.flip_edge_faces(1);
+    assert_eq!(network.connections[1].faces(), (EdgeState::Reflective, EdgeState::Reflective));
+
+    network.set_edge_back_state(0, None);
+    assert_eq!(network.connections[0].back_state(), None);
+}
+
+#[test]
+fn emitters_are_traced_and_cleared() {
+    let mut network = NodeNetwork::headless();
+    network.add_segments(&[Segment(vec2(0.0, 0.0), vec2(100.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(0.0, 50.0), vec2(100.0, 50.0), EdgeState::Absorptive)]);
+    let generation = network.generation();
+    let emitter = EmitterConfig { rays: 3, spread: 0.2, color: [1.0, 0.0, 0.0], intensity: 0.5 };
+
+    network.set_edge_emitter(1, Some(emitter));
+    assert!(network.generation() > generation);
+    assert_eq!(network.connections[1].emitter(), Some(emitter));
+    assert_eq!(network.surfaces().emitters, vec![None, Some(emitter)]);
+
+    network.set_edge_emitter(1, None);
+    assert_eq!(network.surfaces().emitters, vec![None, None]);
+}
+
+#[test]
+fn groups_that_dont_collide_are_left_out_of_the_trace() {
+    let mut network = NodeNetwork::headless();
+    let maze = network.add_lines(&[((0.0, 0.0), (10.0, 0.0))], Vec2::ZERO, EdgeState::Absorptive, 5.0, Some("maze"));
+    network.add_circle(vec2(100.0, 100.0), 20.0, EdgeState::Reflective, 3, 5.0, Some("lens"));
+    let free = network.add_segments(&[Segment(vec2(0.0, 50.0), vec2(10.0, 50.0), EdgeState::Transparent)]);
+    assert_eq!(network.groups().keys().collect::<Vec<_>>(), ["lens", "maze"]);
+    assert_eq!(network.node_group(maze[0]), Some("maze"));
+    assert_eq!(network.node_group(free[0]), None);
+    assert_eq!(network.get_all_connections().len(), 5);
+
+    let generation = network.generation();
+    network.set_group_collides("lens", false);
+    assert!(network.generation() > generation);
+    assert_eq!(network.get_all_connections(), vec![
+        Segment(vec2(0.0, 0.0), vec2(10.0, 0.0), EdgeState::Absorptive),
+        Segment(vec2(0.0, 50.0), vec2(10.0, 50.0), EdgeState::Transparent),
+    ]);
+    assert_eq!(network.surfaces().media.len(), 2);
+
+    // hidden groups are still traced
+    network.set_group_visible("maze", false);
+    assert_eq!(network.get_all_connections().len(), 2);
+    network.set_group_collides("lens", true);
+    assert_eq!(network.get_all_connections().len(), 5);
+}
+
+#[test]
+fn assigning_a_group_takes_the_edges_among_the_nodes() {
+    let mut network = NodeNetwork::headless();
+    let [a, b, c] = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0)].map(|position| network.add_node(position));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+
+    network.assign_group(&[a, b], Some("frame"));
+    assert_eq!(network.connections[0].group(), Some("frame"));
+    assert_eq!(network.connections[1].group(), None);
+    network.set_group_collides("frame", false);
+    assert_eq!(network.get_all_connections(), vec![Segment(vec2(10.0, 0.0), vec2(10.0, 10.0), EdgeState::Reflective)]);
+
+    network.assign_group(&[a, b], None);
+    assert_eq!(network.get_all_connections().len(), 2);
+}
+
+#[test]
+fn deleting_a_group_removes_its_nodes_and_edges() {
+    let mut network = NodeNetwork::headless();
+    let name = network.unused_group_name("maze");
+    network.add_lines(&[((0.0, 0.0), (10.0, 0.0)), ((10.0, 0.0), (10.0, 10.0))], Vec2::ZERO, EdgeState::Reflective, 5.0,
+                      Some(&name));
+    assert_eq!(network.unused_group_name("maze"), "maze 2");
+    let lens = network.add_circle(vec2(100.0, 100.0), 20.0, EdgeState::Transparent, 4, 5.0, Some("lens"));
+
+    network.select_group(Some("maze"));
+    assert_eq!(network.selected_structure().len(), 3);
+    network.delete_group("maze");
+    assert!(network.groups().get("maze").is_none());
+    assert_eq!(network.selected_group(), None);
+    assert_eq!(network.nodes.len(), 4);
+    assert_eq!(network.group_members("lens"), lens);
+    assert_eq!(network.connections.len(), 4);
+}
+
+#[test]
+fn quick_connect_joins_the_node_it_is_released_over() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(200.0, 0.0));
+    let nodes = network.nodes.len();
+
+    assert!(!network.begin_quick_connect(vec2(100.0, 100.0)));
+    assert_eq!(network.quick_connect_origin(), None);
+    assert!(!network.end_quick_connect(vec2(200.0, 0.0)));
+
+    assert!(network.begin_quick_connect(vec2(1.0, 1.0)));
+    assert_eq!(network.quick_connect_origin(), Some(a));
+    assert!(network.end_quick_connect(vec2(199.0, 1.0)));
+    assert_eq!(network.quick_connect_origin(), None);
+    assert_eq!(endpoints(&network), vec![(a, b)]);
+    assert_eq!(network.connections[0].state(), EdgeState::default());
+    assert_eq!(network.nodes.len(), nodes);
+}
+
+#[test]
+fn quick_connect_released_on_its_start_or_nowhere_does_nothing() {
+    let mut network = NodeNetwork::headless();
+    network.add_node(vec2(0.0, 0.0));
+    network.add_node(vec2(200.0, 0.0));
+    let generation = network.generation();
+
+    assert!(network.begin_quick_connect(vec2(0.0, 0.0)));
+    assert!(!network.end_quick_connect(vec2(2.0, 0.0)));
+    assert!(network.begin_quick_connect(vec2(0.0, 0.0)));
+    assert!(!network.end_quick_connect(vec2(100.0, 300.0)));
+
+    assert!(network.connections.is_empty());
+    assert_eq!(network.nodes.len(), 2);
+    assert_eq!(network.generation(), generation);
+    assert_eq!(network.quick_connect_origin(), None);
+}
+
+#[test]
+fn dragging_a_selected_node_moves_the_whole_selection_as_one_edit() {
+    let mut network = NodeNetwork::headless();
+    let nodes = [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(50.0, 80.0)].map(|position| network.add_node(position));
+    let outsider = network.add_node(vec2(300.0, 300.0));
+    for node in nodes { network.toggle_selected(node); }
+    assert_eq!(network.selected_nodes(), nodes.to_vec());
+    network.set_recording(true);
+    let generation = network.generation();
+
+    assert!(!network.begin_group_drag(vec2(300.0, 300.0)));
+    assert!(network.begin_group_drag(vec2(100.0, 0.0)));
+    network.drag_group_to(vec2(110.0, 20.0), false);
+    network.drag_group_to(vec2(130.0, -40.0), false);
+    assert!(network.end_group_drag());
+    assert!(!network.is_group_dragging());
+
+    let offset = vec2(30.0, -40.0);
+    let moved = nodes.map(|node| network.node_position(node).unwrap());
+    assert_eq!(moved, [vec2(30.0, -40.0), vec2(130.0, -40.0), vec2(80.0, 40.0)]);
+    assert_eq!(network.node_position(outsider), Some(vec2(300.0, 300.0)));
+    assert_eq!(network.generation(), generation + 1);
+    let events = network.take_events();
+    assert_eq!(events, vec![Event::MoveNodes { nodes: nodes.to_vec(), offset }]);
+
+    let mut replayed = NodeNetwork::headless();
+    for position in [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(50.0, 80.0)] { replayed.add_node(position); }
+    apply(&events[0], &mut replayed, &mut Laser::headless(Vec2::ZERO, Vec2::X));
+    assert_eq!(nodes.map(|node| replayed.node_position(node).unwrap()), moved);
+}
+
+#[test]
+fn axis_lock_applies_to_the_group_offset() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(40.0, 60.0));
+    network.toggle_selected(a);
+    network.toggle_selected(b);
+
+    assert!(network.begin_group_drag(vec2(40.0, 60.0)));
+    network.drag_group_to(vec2(90.0, 75.0), true);
+    assert_eq!(network.node_position(a), Some(vec2(50.0, 0.0)));
+    assert_eq!(network.node_position(b), Some(vec2(90.0, 60.0)));
+    network.drag_group_to(vec2(45.0, 0.0), true);
+    assert_eq!(network.node_position(a), Some(vec2(0.0, -60.0)));
+    assert_eq!(network.node_position(b), Some(vec2(40.0, 0.0)));
+    assert!(network.end_group_drag());
+}
+
+#[test]
+fn toggling_a_selected_node_takes_it_out() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    network.toggle_selected(a);
+    network.toggle_selected(b);
+    network.toggle_selected(a);
+    assert_eq!(network.selected_nodes(), vec![b]);
+    assert!(!network.begin_group_drag(vec2(0.0, 0.0)));
+
+    network.remove_node(b);
+    assert!(network.selected_nodes().is_empty());
+}
+
+/// Two nodes either side of the edge between `from` and `to`, in `state`.
+fn wall(network: &mut NodeNetwork, from: Vec2, to: Vec2, state: EdgeState) -> (usize, usize) {
+    let (a, b) = (network.add_node(from), network.add_node(to));
+    network.connect_as(a, b, state);
+    (a, b)
+}
+
+#[test]
+fn a_crossing_connection_splits_both_edges() {
+    let mut network = NodeNetwork::headless();
+    network.split_crossings = true;
+    let (a, b) = wall(&mut network, vec2(50.0, 0.0), vec2(50.0, 100.0), EdgeState::Absorptive);
+    let c = network.add_node(vec2(0.0, 50.0));
+    let d = network.add_node(vec2(100.0, 50.0));
+    assert!(network.add_connection(c, d));
+
+    assert_eq!(network.nodes.len(), 5);
+    let middle = network.connections[0].endpoints().1;
+    assert_eq!(network.node_position(middle), Some(vec2(50.0, 50.0)));
+    assert_eq!(endpoints(&network), vec![(a, middle), (middle, b), (c, middle), (middle, d)]);
+    let states: Vec<EdgeState> = network.connections.iter().map(Edge::state).collect();
+    assert_eq!(states, [EdgeState::Absorptive, EdgeState::Absorptive, EdgeState::Reflective, EdgeState::Reflective]);
+}
+
+#[test]
+fn crossings_are_split_in_order_along_the_new_edge() {
+    let mut network = NodeNetwork::headless();
+    network.split_crossings = true;
+    wall(&mut network, vec2(70.0, 0.0), vec2(70.0, 100.0), EdgeState::Reflective);
+    wall(&mut network, vec2(30.0, 0.0), vec2(30.0, 100.0), EdgeState::Reflective);
+    let c = network.add_node(vec2(0.0, 50.0));
+    let d = network.add_node(vec2(100.0, 50.0));
+    assert!(network.add_connection(c, d));
+
+    let new: Vec<(Vec2, Vec2)> = network.connections[4..].iter()
+        .map(|edge| edge.endpoints())
+        .map(|(a, b)| (network.node_position(a).unwrap().round(), network.node_position(b).unwrap().round()))
+        .collect();
+    assert_eq!(new, vec![
+        (vec2(0.0, 50.0), vec2(30.0, 50.0)),
+        (vec2(30.0, 50.0), vec2(70.0, 50.0)),
+        (vec2(70.0, 50.0), vec2(100.0, 50.0)),
+    ]);
+    assert_eq!(network.nodes.len(), 8);
+}
+
+#[test]
+fn an_overlapping_connection_leaves_the_edge_alone() {
+    let mut network = NodeNetwork::headless();
+    network.split_crossings = true;
+    let (a, b) = wall(&mut network, vec2(0.0, 0.0), vec2(100.0, 0.0), EdgeState::Reflective);
+    let c = network.add_node(vec2(50.0, 0.0));
+    let d = network.add_node(vec2(150.0, 0.0));
+    assert!(network.add_connection(c, d));
+
+    assert_eq!(network.nodes.len(), 4);
+    assert_eq!(endpoints(&network), vec![(a, b), (c, d)]);
+}
+
+#[test]
+fn traced_edges_map_segments_back_to_edges_after_edits() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    let c = network.add_node(vec2(100.0, 100.0));
+    let d = network.add_node(vec2(0.0, 100.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+    network.add_connection(c, d);
+    let mapped = |network: &NodeNetwork| -> Vec<(usize, usize)> {
+        let edges = network.traced_edges();
+        assert_eq!(edges.len(), network.get_all_connections().len());
+        edges.into_iter().map(|i| network.connections[i].endpoints()).collect()
+    };
+    assert_eq!(mapped(&network), vec![(a, b), (b, c), (c, d)]);
+
+    network.remove_edge(0);
+    network.add_connection(d, a);
+    assert_eq!(mapped(&network), vec![(b, c), (c, d), (d, a)]);
+    for (segment, edge) in network.get_all_connections().iter().zip(network.traced_edges()) {
+        let (from, to) = network.connections[edge].endpoints();
+        let ends = (network.node_position(from).unwrap(), network.node_position(to).unwrap());
+        assert_eq!((segment.0, segment.1), ends);
+    }
+
+    // a node moved onto another leaves the edge between them out of the trace, and the mapping skips it
+    network.move_node(c, vec2(100.0, 0.0));
+    assert_eq!(mapped(&network), vec![(c, d), (d, a)]);
+}
+
+#[test]
+fn surfaces_into_a_kept_buffer_match_fresh_surfaces() {
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 50.0, EdgeState::Transparent, 8, 3.0, None);
+    network.add_bounds(Rect::new(-200.0, -200.0, 400.0, 400.0), EdgeState::Reflective);
+    network.set_edge_roughness(9, 0.4);
+    let mut kept = Surfaces::default();
+    network.surfaces_into(&mut kept);
+    assert_eq!(kept, network.surfaces());
+    assert_eq!(kept.media[..8], [Some(0); 8]);
+
+    let capacity = kept.layers.capacity();
+    for _ in 0..8 { network.remove_edge(0); }
+    network.surfaces_into(&mut kept);
+    assert_eq!(kept, network.surfaces());
+    assert_eq!(kept.layers.len(), 4);
+    assert_eq!(kept.layers.capacity(), capacity);
+}
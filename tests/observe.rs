@@ -0,0 +1,111 @@
+use macroquad::math::vec2;
+use ray_cast::core::EdgeState;
+use ray_cast::observe::NetworkEvent::{self, *};
+use ray_cast::NodeNetwork;
+
+fn observed() -> NodeNetwork {
+    let mut network = NodeNetwork::headless();
+    network.set_observed(true);
+    network
+}
+
+#[test]
+fn nothing_is_queued_unless_observed() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    network.add_connection(a, b);
+    assert!(network.take_network_events().is_empty());
+}
+
+#[test]
+fn a_scripted_session_comes_out_change_by_change() {
+    let mut network = observed();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    let c = network.add_node(vec2(100.0, 100.0));
+    network.add_connection(a, b);
+    network.connect_as(b, c, EdgeState::Transparent);
+    network.set_edge_state(0, EdgeState::Absorptive);
+    network.set_edge_state(0, EdgeState::Absorptive);
+    network.move_node(c, vec2(150.0, 100.0));
+    network.remove_node(b);
+
+    assert_eq!(network.take_network_events(), vec![
+        NodeAdded { id: a, pos: vec2(0.0, 0.0) },
+        NodeAdded { id: b, pos: vec2(100.0, 0.0) },
+        NodeAdded { id: c, pos: vec2(100.0, 100.0) },
+        EdgeAdded { index: 0, a, b },
+        EdgeAdded { index: 1, a: b, b: c },
+        EdgeStateChanged { index: 1, old: EdgeState::Reflective, new: EdgeState::Transparent },
+        EdgeStateChanged { index: 0, old: EdgeState::Reflective, new: EdgeState::Absorptive },
+        NodeMoved { id: c, from: vec2(100.0, 100.0), to: vec2(150.0, 100.0) },
+        EdgeRemoved { index: 1 },
+        EdgeRemoved { index: 0 },
+        NodeRemoved { id: b },
+    ]);
+    assert!(network.take_network_events().is_empty());
+}
+
+#[test]
+fn a_split_replaces_the_edge_by_its_halves() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    network.connect_as(a, b, EdgeState::Absorptive);
+    network.set_observed(true);
+
+    let middle = network.split_edge(0, vec2(50.0, 0.0));
+    let restated = |index| EdgeStateChanged { index, old: EdgeState::Reflective, new: EdgeState::Absorptive };
+    assert_eq!(network.take_network_events(), vec![
+        NodeAdded { id: middle, pos: vec2(50.0, 0.0) },
+        EdgeRemoved { index: 0 },
+        EdgeAdded { index: 0, a, b: middle },
+        restated(0),
+        EdgeAdded { index: 1, a: middle, b },
+        restated(1),
+    ]);
+}
+
+#[test]
+fn a_group_drag_is_one_move_per_node_on_release() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    network.toggle_selected(a);
+    network.toggle_selected(b);
+    network.set_observed(true);
+
+    assert!(network.begin_group_drag(vec2(0.0, 0.0)));
+    for step in 1..=5 {
+        network.drag_group_to(vec2(step as f32 * 10.0, 0.0), false);
+    }
+    assert!(network.take_network_events().is_empty());
+    assert!(network.end_group_drag());
+
+    let moved: Vec<NetworkEvent> = network.take_network_events();
+    assert_eq!(moved, vec![
+        NodeMoved { id: a, from: vec2(0.0, 0.0), to: vec2(50.0, 0.0) },
+        NodeMoved { id: b, from: vec2(100.0, 0.0), to: vec2(150.0, 0.0) },
+    ]);
+}
+
+#[test]
+fn merging_rewires_and_drops_duplicate_edges() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(5.0, 0.0));
+    let c = network.add_node(vec2(100.0, 0.0));
+    network.add_connection(a, c);
+    network.add_connection(b, c);
+    network.set_observed(true);
+
+    network.merge_nodes(a, b);
+    assert_eq!(network.take_network_events(), vec![
+        EdgeRemoved { index: 1 },
+        EdgeAdded { index: 1, a, b: c },
+        EdgeRemoved { index: 1 },
+        NodeRemoved { id: b },
+    ]);
+    assert_eq!(network.connections.len(), 1);
+}
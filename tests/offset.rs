@@ -0,0 +1,49 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::core::EdgeState;
+use ray_cast::offset::Offset;
+use ray_cast::NodeNetwork;
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(actual.distance(expected) < 1e-4, "{} != {}", actual, expected);
+}
+
+#[test]
+fn polar_angles_turn_like_the_laser() {
+    let at = |angle| Offset::Polar { length: 10.0, angle }.vector();
+
+    assert_near(at(0.0), vec2(10.0, 0.0));
+    assert_near(at(90.0), vec2(0.0, 10.0));
+    assert_near(at(180.0), vec2(-10.0, 0.0));
+    assert_near(at(-90.0), vec2(0.0, -10.0));
+    assert_near(at(45.0), Vec2::splat(10.0 / 2f32.sqrt()));
+    assert_near(Offset::Polar { length: 0.0, angle: 30.0 }.vector(), Vec2::ZERO);
+}
+
+#[test]
+fn parsing_needs_two_finite_numbers() {
+    assert_eq!(Offset::parse(0, " 3 ", "-4.5"), Some(Offset::Cartesian { dx: 3.0, dy: -4.5 }));
+    assert_eq!(Offset::parse(1, "20", "90"), Some(Offset::Polar { length: 20.0, angle: 90.0 }));
+
+    assert_eq!(Offset::parse(0, "", "1"), None);
+    assert_eq!(Offset::parse(0, "1", "NaN"), None);
+    assert_eq!(Offset::parse(1, "inf", "0"), None);
+    assert_eq!(Offset::parse(1, "ten", "0"), None);
+}
+
+#[test]
+fn offsets_place_connected_nodes() {
+    let mut network = NodeNetwork::headless();
+    let start = network.add_node(vec2(100.0, 100.0));
+
+    let right = network.add_at_offset(start, Offset::Cartesian { dx: 50.0, dy: 0.0 }, EdgeState::Absorptive).unwrap();
+    let below = network.add_at_offset(right, Offset::Polar { length: 30.0, angle: 90.0 }, EdgeState::Transparent)
+        .unwrap();
+
+    assert_eq!(network.node_position(right), Some(vec2(150.0, 100.0)));
+    assert_near(network.node_position(below).unwrap(), vec2(150.0, 130.0));
+    assert_eq!(network.connections[0].endpoints(), (start, right));
+    assert_eq!(network.connections[0].state(), EdgeState::Absorptive);
+    assert_eq!(network.connections[1].endpoints(), (right, below));
+    assert_eq!(network.connections[1].state(), EdgeState::Transparent);
+    assert_eq!(network.add_at_offset(42, Offset::Cartesian { dx: 1.0, dy: 1.0 }, EdgeState::Reflective), None);
+}
@@ -0,0 +1,62 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::pattern::{dashes, hatches};
+
+fn length((start, end): &(Vec2, Vec2)) -> f32 {
+    start.distance(*end)
+}
+
+#[test]
+fn dashes_start_and_end_on_the_edge_at_any_length() {
+    let (dash, gap) = (10.0, 5.0);
+    for edge_length in [10.5, 24.0, 25.0, 26.0, 99.0, 100.0, 1234.5] {
+        let (start, end) = (vec2(3.0, -7.0), vec2(3.0, -7.0) + Vec2::from_angle(0.7) * edge_length);
+        let pieces = dashes(start, end, dash, gap);
+
+        assert!(pieces[0].0.abs_diff_eq(start, 1e-3), "{}", edge_length);
+        assert!(pieces[pieces.len() - 1].1.abs_diff_eq(end, 1e-3), "{}", edge_length);
+        let drawn: f32 = pieces.iter().map(length).sum();
+        let periods = pieces.len() as f32;
+        // every dash is the same length and every gap too, within what fitting them stretched
+        for piece in &pieces {
+            assert!((length(piece) - drawn / periods).abs() < 1e-3, "{}", edge_length);
+            assert!(length(piece) > dash * 0.5 && length(piece) < dash * 2.0, "{}: {:?}", edge_length, piece);
+        }
+        for pair in pieces.windows(2) {
+            let gap_length = pair[0].1.distance(pair[1].0);
+            assert!(gap_length > gap * 0.5 && gap_length < gap * 2.0, "{}", edge_length);
+        }
+    }
+}
+
+#[test]
+fn edges_no_longer_than_a_dash_are_one_dash() {
+    let (start, end) = (vec2(0.0, 0.0), vec2(8.0, 0.0));
+    assert_eq!(dashes(start, end, 10.0, 5.0), vec![(start, end)]);
+    assert_eq!(dashes(start, end, 0.0, 5.0), vec![(start, end)]);
+    assert_eq!(dashes(start, start, 10.0, 5.0), vec![(start, start)]);
+}
+
+#[test]
+fn dash_counts_follow_the_length() {
+    let count = |length: f32| dashes(Vec2::ZERO, vec2(length, 0.0), 10.0, 5.0).len();
+    assert_eq!(count(25.0), 2);
+    assert_eq!(count(100.0), 7);
+    // tiny dashes on a long edge are capped rather than drawn by the million
+    assert_eq!(dashes(Vec2::ZERO, vec2(1e6, 0.0), 0.01, 0.01).len(), 1000);
+}
+
+#[test]
+fn hatches_are_spread_evenly_across_the_edge() {
+    let (start, end) = (vec2(0.0, 0.0), vec2(100.0, 0.0));
+    let strokes = hatches(start, end, 20.0, 8.0);
+
+    assert_eq!(strokes.len(), 5);
+    for (i, stroke) in strokes.iter().enumerate() {
+        let center = (stroke.0 + stroke.1) / 2.0;
+        assert!(center.abs_diff_eq(vec2(10.0 + 20.0 * i as f32, 0.0), 1e-4), "{:?}", center);
+        assert!((length(stroke) - 8.0).abs() < 1e-4);
+        assert!((stroke.1 - stroke.0).y.abs() > 1.0, "strokes cross the edge");
+    }
+    assert_eq!(hatches(start, vec2(5.0, 0.0), 20.0, 8.0).len(), 1);
+    assert!(hatches(start, start, 20.0, 8.0).is_empty());
+}
@@ -0,0 +1,119 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::polygon::{is_simple, point_in_polygon, signed_area, triangulate};
+
+fn square() -> Vec<Vec2> {
+    vec![vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0), vec2(0.0, 10.0)]
+}
+
+/// An L: the square with its top right quarter cut away.
+fn l_shape() -> Vec<Vec2> {
+    vec![vec2(0.0, 0.0), vec2(5.0, 0.0), vec2(5.0, 5.0), vec2(10.0, 5.0), vec2(10.0, 10.0), vec2(0.0, 10.0)]
+}
+
+/// Total area of `triangles` over `polygon`, which also checks none of them is flipped.
+fn triangulated_area(polygon: &[Vec2], triangles: &[[usize; 3]]) -> f32 {
+    triangles.iter()
+        .map(|&[a, b, c]| {
+            let area = signed_area(&[polygon[a], polygon[b], polygon[c]]);
+            assert!(area * signed_area(polygon) > 0.0, "triangle {:?} is flipped", [a, b, c]);
+            area.abs()
+        })
+        .sum()
+}
+
+#[test]
+fn points_inside_a_convex_polygon() {
+    let square = square();
+
+    assert!(point_in_polygon(vec2(5.0, 5.0), &square));
+    assert!(point_in_polygon(vec2(0.1, 9.9), &square));
+    assert!(!point_in_polygon(vec2(-1.0, 5.0), &square));
+    assert!(!point_in_polygon(vec2(5.0, 11.0), &square));
+    assert!(!point_in_polygon(vec2(5.0, 5.0), &[]));
+}
+
+#[test]
+fn points_inside_a_concave_polygon() {
+    let l = l_shape();
+
+    assert!(point_in_polygon(vec2(2.0, 2.0), &l));
+    assert!(point_in_polygon(vec2(8.0, 8.0), &l));
+    // the cut away corner, which the bounding box still covers
+    assert!(!point_in_polygon(vec2(8.0, 2.0), &l));
+    // level with the reflex corner
+    assert!(point_in_polygon(vec2(2.0, 5.0), &l));
+    assert!(!point_in_polygon(vec2(11.0, 5.0), &l));
+}
+
+#[test]
+fn points_inside_a_sliver() {
+    let sliver = [vec2(0.0, 0.0), vec2(100.0, 0.01), vec2(0.0, 0.02)];
+
+    assert!(point_in_polygon(vec2(10.0, 0.01), &sliver));
+    assert!(!point_in_polygon(vec2(10.0, 0.05), &sliver));
+    assert!(!point_in_polygon(vec2(101.0, 0.01), &sliver));
+}
+
+#[test]
+fn area_is_signed_by_winding() {
+    let mut square = square();
+    assert_eq!(signed_area(&square), 100.0);
+    square.reverse();
+    assert_eq!(signed_area(&square), -100.0);
+    assert_eq!(signed_area(&l_shape()), 75.0);
+}
+
+#[test]
+fn convex_polygons_triangulate_into_a_fan() {
+    let hexagon: Vec<Vec2> = (0..6)
+        .map(|i| Vec2::from_angle(i as f32 * std::f32::consts::TAU / 6.0) * 10.0)
+        .collect();
+    let triangles = triangulate(&hexagon).unwrap();
+
+    assert_eq!(triangles.len(), 4);
+    assert!((triangulated_area(&hexagon, &triangles) - signed_area(&hexagon).abs()).abs() < 1e-3);
+}
+
+#[test]
+fn concave_polygons_triangulate_either_way_round() {
+    let mut l = l_shape();
+    for _ in 0..2 {
+        let triangles = triangulate(&l).unwrap();
+
+        assert_eq!(triangles.len(), l.len() - 2);
+        assert_eq!(triangulated_area(&l, &triangles), 75.0);
+        l.reverse();
+    }
+}
+
+#[test]
+fn corners_on_a_straight_line_add_no_flat_triangles() {
+    let square = [vec2(0.0, 0.0), vec2(5.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0), vec2(0.0, 10.0)];
+    let triangles = triangulate(&square).unwrap();
+
+    assert!(triangles.len() <= 3);
+    assert_eq!(triangulated_area(&square, &triangles), 100.0);
+}
+
+#[test]
+fn nearly_degenerate_polygons_keep_their_area() {
+    let sliver = [vec2(0.0, 0.0), vec2(100.0, 0.01), vec2(200.0, 0.0), vec2(100.0, 0.05)];
+    let triangles = triangulate(&sliver).unwrap();
+
+    assert!((triangulated_area(&sliver, &triangles) - signed_area(&sliver).abs()).abs() < 1e-3);
+}
+
+#[test]
+fn polygons_without_area_or_crossing_themselves_are_not_triangulated() {
+    // every corner on one line
+    assert_eq!(triangulate(&[vec2(0.0, 0.0), vec2(5.0, 0.0), vec2(10.0, 0.0)]), None);
+    assert_eq!(triangulate(&[vec2(0.0, 0.0), vec2(5.0, 5.0)]), None);
+    // a bow tie
+    let bow_tie = [vec2(0.0, 0.0), vec2(10.0, 10.0), vec2(10.0, 0.0), vec2(0.0, 10.0)];
+    assert!(!is_simple(&bow_tie));
+    assert_eq!(triangulate(&bow_tie), None);
+    // a corner resting on another edge
+    let pinched = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(5.0, 10.0), vec2(5.0, 0.0), vec2(2.0, 10.0)];
+    assert!(!is_simple(&pinched));
+    assert!(is_simple(&l_shape()));
+}
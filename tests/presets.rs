@@ -0,0 +1,63 @@
+use macroquad::math::Vec2;
+use ray_cast::core::Interaction;
+use ray_cast::{presets, Laser, NodeNetwork};
+
+/// Builds the named preset into a fresh network, returning it with its laser.
+fn build(name: &str) -> (NodeNetwork, Laser) {
+    let (_, build) = presets::all().iter().find(|(preset, _)| *preset == name).unwrap();
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    build(&mut network, &mut laser);
+    (network, laser)
+}
+
+/// Node and edge counts of the preset, after checking its beam hits the scene on the first bounce.
+fn check(name: &str) -> (usize, usize) {
+    let (network, laser) = build(name);
+    let result = laser.solve_collisions(&network.get_all_connections());
+    let first = result.hits.first().expect("no hits");
+    assert_ne!(first.interaction, Interaction::Escaped, "{} beam misses", name);
+    (network.nodes.len(), network.connections.len())
+}
+
+#[test]
+fn mirror_box() {
+    assert_eq!(check("Mirror box"), (4, 4));
+}
+
+#[test]
+fn corner_reflector() {
+    assert_eq!(check("Corner reflector"), (3, 2));
+}
+
+#[test]
+fn lens_circle() {
+    assert_eq!(check("Lens circle"), (32, 32));
+}
+
+#[test]
+fn parabolic_mirror() {
+    assert_eq!(check("Parabolic mirror"), (17, 16));
+}
+
+#[test]
+fn small_maze() {
+    // collinear walls are merged, so this is fewer than the maze's wall count
+    assert_eq!(check("Small maze"), (37, 36));
+}
+
+#[test]
+fn names_are_unique() {
+    let mut names: Vec<&str> = presets::all().iter().map(|(name, _)| *name).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    assert_eq!(names.len(), presets::all().len());
+}
+
+#[test]
+fn spinning_mirror() {
+    assert_eq!(check("Spinning mirror"), (2, 1));
+    let (network, _) = build("Spinning mirror");
+    assert!(network.is_animated());
+}
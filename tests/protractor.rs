@@ -0,0 +1,60 @@
+use macroquad::color::Color;
+use macroquad::math::{vec2, Vec2};
+use ray_cast::core::{trace, trace_with_media, EdgeState, Ray, Segment, SimConfig};
+use ray_cast::protractor::{BounceAngles, Protractor};
+
+const RED: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+
+/// A ray from the origin meeting a horizontal wall at y = 100 at `incidence` degrees.
+fn ray_onto_floor(incidence: f32) -> Ray {
+    Ray::new(vec2(0.0, 0.0), Vec2::from_angle((90.0 - incidence).to_radians()), RED)
+}
+
+#[test]
+fn a_mirror_reflects_at_the_angle_of_incidence() {
+    let segments = [Segment(vec2(-500.0, 100.0), vec2(500.0, 100.0), EdgeState::Reflective)];
+    let trace = trace(ray_onto_floor(30.0), &segments, &SimConfig::default());
+
+    let angles = BounceAngles::at(&trace, 0).unwrap();
+    assert!(angles.normal.abs_diff_eq(vec2(0.0, -1.0), 1e-6), "{:?}", angles.normal);
+    assert!((angles.incidence.to_degrees() - 30.0).abs() < 1e-3, "{}", angles.incidence.to_degrees());
+    let (direction, reflected) = angles.reflected.unwrap();
+    assert!((reflected - angles.incidence).abs() < 1e-4);
+    assert!(direction.y < 0.0);
+    assert_eq!(angles.transmitted, None);
+    // the escaped line ends on nothing, there is no bounce to inspect
+    assert_eq!(BounceAngles::at(&trace, 1), None);
+}
+
+#[test]
+fn light_entering_glass_bends_towards_the_normal() {
+    let segments = [Segment(vec2(-500.0, 100.0), vec2(500.0, 100.0), EdgeState::Transparent),
+        Segment(vec2(500.0, 300.0), vec2(-500.0, 300.0), EdgeState::Transparent)];
+    let config = SimConfig { refractive_index: 1.5, min_intensity: 0.01, ..Default::default() };
+    let trace = trace_with_media(ray_onto_floor(40.0), &segments, &[Some(0), Some(0)], &config);
+
+    let angles = BounceAngles::at(&trace, 0).unwrap();
+    let (_, transmitted) = angles.transmitted.unwrap();
+    let expected = (angles.incidence.sin() / 1.5).asin();
+    assert!((transmitted - expected).abs() < 1e-4, "{} vs {}", transmitted.to_degrees(), expected.to_degrees());
+    assert!(angles.reflected.is_some());
+}
+
+#[test]
+fn the_pick_follows_its_bounce_through_retraces() {
+    let mirror = |y: f32| [Segment(vec2(-500.0, y), vec2(500.0, y), EdgeState::Reflective)];
+    let config = SimConfig::default();
+    let before = trace(ray_onto_floor(30.0), &mirror(100.0), &config);
+    let mut protractor = Protractor::default();
+
+    protractor.pick(&before, before.hits[0].position + vec2(3.0, 4.0), 10.0);
+    assert_eq!(protractor.selected(&before).unwrap().position, before.hits[0].position);
+
+    let after = trace(ray_onto_floor(30.0), &mirror(102.0), &config);
+    let moved = protractor.selected(&after).unwrap();
+    assert_eq!(moved.position, after.hits[0].position);
+    assert_ne!(moved.position, before.hits[0].position);
+
+    protractor.pick(&after, vec2(1000.0, 1000.0), 10.0);
+    assert_eq!(protractor.selected(&after), None);
+}
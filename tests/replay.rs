@@ -0,0 +1,220 @@
+use macroquad::math::{vec2, Rect, Vec2};
+use ray_cast::labyrinth::{Algorithm, LabyrinthSpec, Side};
+use ray_cast::core::DEFAULT_THICKNESS;
+use ray_cast::replay::{Event, Recorder, Replayer, Session};
+use ray_cast::{EdgeState, Laser, NodeNetwork};
+
+fn spec() -> LabyrinthSpec {
+    LabyrinthSpec {
+        cell_size: 40.0,
+        size: (4, 3),
+        algorithm: Algorithm::Prim,
+        entrance: Some(Side::Left),
+        exit: Some(Side::Right),
+        loopiness: 0.5,
+        seed: 11,
+    }
+}
+
+/// A short editing session touching every kind of edit, recorded at a few points in time.
+fn record_session() -> (Session, NodeNetwork, Laser) {
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(vec2(20.0, 20.0), Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+
+    network.add_bounds(Rect::new(0.0, 0.0, 800.0, 600.0), EdgeState::Absorptive);
+    laser.place(vec2(30.0, 300.0), vec2(400.0, 250.0));
+    recorder.collect(0.0, &mut network, &laser);
+
+    let a = network.add_node(vec2(600.0, 100.0));
+    let b = network.add_node(vec2(650.0, 500.0));
+    let c = network.add_node(vec2(700.0, 300.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+    network.set_edge_state(network.connections.len() - 1, EdgeState::Transparent);
+    network.add_circle(vec2(300.0, 400.0), 60.0, EdgeState::Reflective, 12, 5.0, None);
+    recorder.collect(0.5, &mut network, &laser);
+
+    network.add_labyrinth(&spec(), vec2(100.0, 50.0), EdgeState::Reflective, 5.0, None);
+    let split = network.split_edge(4, vec2(625.0, 300.0));
+    network.move_node(split, vec2(560.0, 310.0));
+    let duplicate = network.add_node(vec2(705.0, 302.0));
+    network.merge_nodes(c, duplicate);
+    network.remove_edge(5);
+    network.remove_node(a);
+    laser.look_at(vec2(700.0, 320.0));
+    recorder.collect(1.0, &mut network, &laser);
+
+    (recorder.into_session(), network, laser)
+}
+
+fn scene(network: &NodeNetwork) -> Vec<(usize, Vec2)> {
+    let mut nodes: Vec<(usize, Vec2)> = network.nodes.keys()
+        .map(|&i| (i, network.node_position(i).unwrap()))
+        .collect();
+    nodes.sort_by_key(|(i, _)| *i);
+    nodes
+}
+
+#[test]
+fn replayed_session_rebuilds_the_same_scene_and_trace() {
+    let (session, network, laser) = record_session();
+    let session = Session::from_json(&session.to_json()).unwrap();
+
+    let mut replayed = NodeNetwork::headless();
+    let mut replayed_laser = Laser::headless(Vec2::ZERO, Vec2::Y);
+    let mut replayer = Replayer::new(session);
+    replayer.run_to_end(&mut replayed, &mut replayed_laser);
+
+    assert!(replayer.is_finished());
+    assert_eq!(scene(&replayed), scene(&network));
+    assert_eq!(replayed.get_all_connections(), network.get_all_connections());
+    assert_eq!(replayed_laser.aim(), laser.aim());
+    let segments = network.get_all_connections();
+    assert_eq!(replayed_laser.solve_collisions(&segments).lines(), laser.solve_collisions(&segments).lines());
+}
+
+#[test]
+fn replayer_follows_the_recorded_times() {
+    let (session, ..) = record_session();
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::Y);
+    let mut replayer = Replayer::new(session);
+
+    replayer.advance(0.2, &mut network, &mut laser);
+    assert_eq!(network.nodes.len(), 4); // just the border
+    assert_eq!(laser.position(), vec2(30.0, 300.0));
+
+    replayer.advance(0.7, &mut network, &mut laser);
+    assert_eq!(network.nodes.len(), 4 + 3 + 12);
+    assert!(!replayer.is_finished());
+
+    replayer.advance(1.0, &mut network, &mut laser);
+    assert!(replayer.is_finished());
+}
+
+#[test]
+fn compound_edits_are_recorded_once() {
+    let mut network = NodeNetwork::headless();
+    network.set_recording(true);
+
+    network.add_bounds(Rect::new(0.0, 0.0, 100.0, 100.0), EdgeState::Absorptive);
+    network.add_bounds(Rect::new(0.0, 0.0, 100.0, 100.0), EdgeState::Absorptive);
+    network.add_labyrinth(&spec(), Vec2::ZERO, EdgeState::Reflective, 5.0, None);
+    network.add_circle(Vec2::ZERO, 10.0, EdgeState::Absorptive, 5, 5.0, None);
+
+    let events = network.take_events();
+    assert_eq!(events.len(), 3, "{:?}", events);
+    assert!(matches!(events[0], Event::Bounds { state: EdgeState::Absorptive, .. }));
+    assert!(matches!(&events[1], Event::Labyrinth { spec: recorded, .. } if *recorded == spec()));
+    assert!(matches!(events[2], Event::Circle { sides: 5, .. }));
+    assert!(network.take_events().is_empty());
+}
+
+#[test]
+fn nothing_is_logged_unless_recording() {
+    let mut network = NodeNetwork::headless();
+
+    network.add_node(Vec2::ZERO);
+
+    assert!(!network.is_recording());
+    assert!(network.take_events().is_empty());
+}
+
+#[test]
+fn every_chain_link_is_one_edit() {
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    network.set_chain_mode(true);
+    assert_eq!(network.chain_end(), None);
+
+    let start = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_chain_link(start, vec2(100.0, 0.0), EdgeState::Absorptive);
+    let c = network.add_chain_link(b, vec2(100.0, 100.0), EdgeState::Absorptive);
+    assert!(network.connect_as(c, start, EdgeState::Transparent));
+
+    let events = network.take_events();
+    assert_eq!(events.len(), 4, "{:?}", events);
+    assert!(matches!(events[1], Event::ChainLink { from, state: EdgeState::Absorptive, .. } if from == start));
+    assert!(matches!(events[3], Event::ConnectAs { a, b, state: EdgeState::Transparent } if (a, b) == (c, start)));
+    assert_eq!(network.connections.iter().map(|edge| edge.state()).collect::<Vec<_>>(),
+               vec![EdgeState::Absorptive, EdgeState::Absorptive, EdgeState::Transparent]);
+
+    for (i, event) in events.into_iter().enumerate() { recorder.record(i as f64, event); }
+    let mut replayed = NodeNetwork::headless();
+    Replayer::new(recorder.into_session()).run_to_end(&mut replayed, &mut laser);
+    assert_eq!(replayed.get_all_connections(), network.get_all_connections());
+    assert!(!network.connect_as(c, start, EdgeState::Transparent));
+}
+
+#[test]
+fn groups_replay_with_the_scene() {
+    let mut network = NodeNetwork::headless();
+    let laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    network.add_labyrinth(&spec(), Vec2::ZERO, EdgeState::Reflective, 5.0, Some("maze"));
+    let lens = network.add_circle(vec2(500.0, 0.0), 20.0, EdgeState::Transparent, 6, 5.0, Some("lens"));
+    network.assign_group(&lens[..3], Some("frame"));
+    network.set_group_visible("lens", false);
+    network.set_group_collides("frame", false);
+    network.delete_group("maze");
+    recorder.collect(0.0, &mut network, &laser);
+
+    let session = Session::from_json(&recorder.into_session().to_json()).unwrap();
+    let mut replayed = NodeNetwork::headless();
+    let mut replayed_laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    Replayer::new(session).run_to_end(&mut replayed, &mut replayed_laser);
+
+    assert_eq!(replayed.groups(), network.groups());
+    assert_eq!(replayed.group_members("frame"), network.group_members("frame"));
+    assert_eq!(replayed.get_all_connections(), network.get_all_connections());
+}
+
+#[test]
+fn edge_thickness_replays_and_older_sessions_default_it() {
+    let mut network = NodeNetwork::headless();
+    let laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    network.add_circle(vec2(0.0, 0.0), 20.0, EdgeState::Reflective, 4, 12.0, None);
+    network.set_edge_thickness(0, 3.0);
+    recorder.collect(0.0, &mut network, &laser);
+    let json = recorder.into_session().to_json();
+    let thickness = |network: &NodeNetwork| network.connections.iter().map(|edge| edge.thickness()).collect::<Vec<_>>();
+
+    let mut replayed = NodeNetwork::headless();
+    let mut replayed_laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    Replayer::new(Session::from_json(&json).unwrap()).run_to_end(&mut replayed, &mut replayed_laser);
+    assert_eq!(thickness(&replayed), vec![3.0, 12.0, 12.0, 12.0]);
+
+    let older = json.replace(r#","thickness":12.0"#, "").replace(r#"{"SetEdgeThickness":{"edge":0,"thickness":3.0}}"#,
+                                                                  r#"{"SetAllStates":{"state":"Reflective"}}"#);
+    assert!(!older.contains("12.0") && !older.contains("SetEdgeThickness"), "{}", older);
+    let mut old = NodeNetwork::headless();
+    Replayer::new(Session::from_json(&older).unwrap()).run_to_end(&mut old, &mut replayed_laser);
+    assert_eq!(thickness(&old), vec![DEFAULT_THICKNESS; 4]);
+}
+
+#[test]
+fn a_replayed_session_keeps_the_laser_on_its_node() {
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    let host = network.add_node(vec2(100.0, 100.0));
+    let neighbour = network.add_node(vec2(200.0, 100.0));
+    network.add_connection(host, neighbour);
+    laser.attach_to_node(host);
+    laser.align_along(Some(neighbour));
+    laser.follow_mount(&network);
+    recorder.collect(0.0, &mut network, &laser);
+    assert!(recorder.session().events.iter().any(|(_, event)|
+        *event == Event::LaserMount { node: Some(host), align: Some(neighbour) }));
+
+    let (mut replayed, mut replayed_laser) = (NodeNetwork::headless(), Laser::headless(Vec2::ZERO, Vec2::Y));
+    Replayer::new(recorder.into_session()).run_to_end(&mut replayed, &mut replayed_laser);
+    assert_eq!((replayed_laser.mount(), replayed_laser.mount_align()), (Some(host), Some(neighbour)));
+    replayed.move_node(host, vec2(100.0, 300.0));
+    replayed_laser.follow_mount(&replayed);
+    assert_eq!(replayed_laser.position(), vec2(100.0, 300.0));
+    assert_eq!(replayed_laser.aim().1, (vec2(200.0, 100.0) - vec2(100.0, 300.0)).normalize());
+}
@@ -0,0 +1,21 @@
+use ray_cast::scale_bar::{nice_scale_length, SCALE_BAR_PIXELS};
+
+#[test]
+fn the_bar_is_the_longest_round_length_that_fits() {
+    for (zoom, length) in [(1.0, 100.0), (1.5, 50.0), (2.0, 50.0), (4.0, 10.0), (10.0, 10.0), (15.0, 5.0),
+        (100.0, 1.0), (0.5, 100.0), (0.2, 500.0), (0.1, 1000.0), (400.0, 0.1)] {
+        assert_eq!(nice_scale_length(zoom), length, "zoom {}", zoom);
+    }
+    for step in 0..400 {
+        let zoom = 0.01 * 1.05f32.powi(step);
+        let pixels = nice_scale_length(zoom) * zoom;
+        assert!(pixels <= SCALE_BAR_PIXELS * 1.0001 && pixels > SCALE_BAR_PIXELS / 5.0, "zoom {}", zoom);
+    }
+}
+
+#[test]
+fn a_degenerate_zoom_still_gets_a_bar() {
+    for zoom in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+        assert_eq!(nice_scale_length(zoom), 1.0);
+    }
+}
@@ -0,0 +1,121 @@
+use ray_cast::schedule::{BudgetController, SimScheduler};
+
+#[test]
+fn recomputes_at_the_configured_rate() {
+    let mut scheduler = SimScheduler::new(10.0);
+
+    let recomputes = (0..100).filter(|_| scheduler.tick(0.01, false)).count();
+
+    // one second of 10 ms frames, about every tenth frame has waited a tenth of a second
+    assert!((9..=10).contains(&recomputes), "{}", recomputes);
+}
+
+#[test]
+fn urgent_changes_recompute_immediately() {
+    let mut scheduler = SimScheduler::new(1.0);
+
+    assert!(scheduler.tick(0.01, true));
+    assert!(!scheduler.tick(0.01, false));
+    assert!(scheduler.tick(0.01, true));
+}
+
+#[test]
+fn paused_never_recomputes_and_goes_stale() {
+    let mut scheduler = SimScheduler::new(1000.0);
+    scheduler.toggle_pause();
+
+    assert!((0..50).all(|_| !scheduler.tick(1.0, false)));
+    assert!(!scheduler.is_stale());
+    assert!(!scheduler.tick(0.01, true));
+    assert!(scheduler.is_stale());
+}
+
+#[test]
+fn disabled_collisions_never_recompute() {
+    let mut scheduler = SimScheduler::new(1000.0);
+    scheduler.set_collisions_enabled(false);
+    scheduler.step();
+
+    assert!((0..50).all(|_| !scheduler.tick(1.0, true)));
+    assert!(!scheduler.collisions_enabled());
+}
+
+#[test]
+fn enabling_collisions_recomputes_right_away() {
+    let mut scheduler = SimScheduler::new(1.0);
+    scheduler.set_collisions_enabled(false);
+    assert!(!scheduler.tick(0.01, false));
+
+    scheduler.set_collisions_enabled(true);
+    assert!(scheduler.tick(0.001, false));
+    assert!(!scheduler.tick(0.001, false));
+    // enabling what is already enabled changes nothing
+    scheduler.set_collisions_enabled(true);
+    assert!(!scheduler.tick(0.001, false));
+}
+
+#[test]
+fn enabling_collisions_while_paused_only_goes_stale() {
+    let mut scheduler = SimScheduler::new(60.0);
+    scheduler.toggle_pause();
+    scheduler.set_collisions_enabled(false);
+    scheduler.set_collisions_enabled(true);
+
+    assert!(!scheduler.tick(0.01, false));
+    assert!(scheduler.is_stale());
+}
+
+#[test]
+fn step_recomputes_once_while_paused() {
+    let mut scheduler = SimScheduler::new(60.0);
+    scheduler.toggle_pause();
+    scheduler.mark_changed();
+    assert!(scheduler.is_stale());
+
+    scheduler.step();
+
+    assert!(scheduler.tick(0.01, false));
+    assert!(!scheduler.is_stale());
+    assert!(!scheduler.tick(0.01, false));
+}
+
+#[test]
+fn changes_only_stale_the_beam_while_paused() {
+    let mut scheduler = SimScheduler::new(60.0);
+    scheduler.mark_changed();
+    assert!(!scheduler.is_stale());
+
+    scheduler.toggle_pause();
+    scheduler.mark_changed();
+    scheduler.toggle_pause();
+    // resuming catches up on the next periodic recompute
+    assert!(scheduler.is_stale());
+    assert!((0..10).any(|_| scheduler.tick(0.01, false)));
+    assert!(!scheduler.is_stale());
+}
+
+#[test]
+fn budget_settles_where_traces_take_the_target_time() {
+    let mut controller = BudgetController::new(1000, 4000.0);
+    // a scene where every ray costs 2 µs, so 2000 rays hit 4 ms
+    for _ in 0..30 {
+        let budget = controller.budget();
+        controller.update(budget as f32 * 2.0);
+    }
+    assert!((1990..=2010).contains(&controller.budget()), "{}", controller.budget());
+}
+
+#[test]
+fn budget_moves_by_bounded_steps_within_its_bounds() {
+    let mut controller = BudgetController::new(1000, 4000.0);
+    controller.min = 200;
+    controller.max = 3000;
+
+    assert_eq!(controller.update(f32::INFINITY), 500);
+    assert_eq!(controller.update(1_000_000.0), 251);
+    assert_eq!(controller.update(1_000_000.0), 200);
+    // zero-length spans, as on the web, read as plenty of headroom
+    assert_eq!(controller.update(0.0), 400);
+    for _ in 0..10 { controller.update(1.0); }
+    assert_eq!(controller.budget(), 3000);
+}
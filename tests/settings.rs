@@ -0,0 +1,80 @@
+use macroquad::input::KeyCode;
+use ray_cast::input::{Action, Binding};
+use ray_cast::settings::{AutoSave, Settings};
+use ray_cast::{LightArea, Modulation, RayColorMode, Sweep};
+
+fn customized() -> Settings {
+    let mut settings = Settings::default();
+    settings.simulation.max_rays = 250.0;
+    settings.simulation.thick_edges = true;
+    settings.laser.color = [0.2, 0.4, 0.6, 1.0];
+    settings.laser.color_mode = RayColorMode::ByBounceDepth;
+    settings.laser.modulation = Modulation::Square { hz: 2.0, duty: 0.25 };
+    settings.laser.light_area = LightArea::UnderBeam;
+    settings.laser.sweep = Sweep { enabled: true, start: -20.0, end: 60.0, steps: 9, accumulate: true };
+    settings.camera.zoom = 3.0;
+    settings.show_ui = true;
+    settings.input.bind(Action::ToggleCollisions, Binding::Key(KeyCode::C));
+    settings
+}
+
+fn temp_file(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("ray_cast_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn json_round_trip() {
+    let settings = customized();
+
+    assert_eq!(Settings::from_json(&settings.to_json()), Ok(settings));
+}
+
+#[test]
+fn file_round_trip() {
+    let path = temp_file("round_trip.json");
+    customized().save(&path).unwrap();
+
+    let loaded = Settings::load_or_default(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, customized());
+}
+
+#[test]
+fn corrupt_or_missing_files_fall_back_to_defaults() {
+    let path = temp_file("corrupt.json");
+    std::fs::write(&path, "{\"show_ui\": tru").unwrap();
+
+    let loaded = Settings::load_or_default(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, Settings::default());
+    assert_eq!(Settings::load_or_default(&temp_file("missing.json")), Settings::default());
+    assert!(Settings::from_json("{\"input\": {\"bindings\": {\"Pause\": \"Hyper\"}}}").is_err());
+}
+
+#[test]
+fn missing_fields_take_defaults() {
+    let settings = Settings::from_json("{\"show_ui\": true, \"camera\": {\"zoom\": 2.0}}").unwrap();
+
+    assert!(settings.show_ui);
+    assert_eq!(settings.camera.zoom, 2.0);
+    assert_eq!(settings.camera.zoom_step, Settings::default().camera.zoom_step);
+    assert_eq!(settings.simulation, Settings::default().simulation);
+}
+
+#[test]
+fn autosave_waits_for_changes_to_settle() {
+    let mut autosave = AutoSave::new(Settings::default(), 1.0);
+    assert_eq!(autosave.update(&Settings::default(), 0.0), None);
+
+    let mut settings = Settings { recompute_rate: 30.0, ..Default::default() };
+    assert_eq!(autosave.update(&settings, 1.0), None);
+    // still changing, the wait starts over
+    settings.recompute_rate = 20.0;
+    assert_eq!(autosave.update(&settings, 1.8), None);
+    assert_eq!(autosave.update(&settings, 2.5), None);
+    assert_eq!(autosave.update(&settings, 2.9), Some(&settings));
+    // saved, nothing more to write
+    assert_eq!(autosave.update(&settings, 5.0), None);
+}
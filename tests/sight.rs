@@ -0,0 +1,49 @@
+use macroquad::math::vec2;
+use ray_cast::core::first_obstruction_where;
+use ray_cast::{first_obstruction, line_of_sight, EdgeState, Segment};
+
+fn wall(x: f32, state: EdgeState) -> Segment {
+    Segment(vec2(x, -10.0), vec2(x, 10.0), state)
+}
+
+#[test]
+fn open_space_is_visible() {
+    assert!(line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &[]));
+    assert!(line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &[Segment(vec2(0.0, 20.0), vec2(100.0, 20.0), EdgeState::Reflective)]));
+}
+
+#[test]
+fn wall_between_blocks_and_reports_the_closest() {
+    let segments = [wall(70.0, EdgeState::Absorptive), wall(30.0, EdgeState::Reflective)];
+
+    let (collision, index) = first_obstruction(vec2(0.0, 0.0), vec2(100.0, 0.0), &segments).unwrap();
+
+    assert_eq!(index, 1);
+    assert!((collision.position - vec2(30.0, 0.0)).length() < 1e-4);
+    assert!(!line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &segments));
+}
+
+#[test]
+fn wall_beyond_the_target_does_not_block() {
+    let segments = [wall(150.0, EdgeState::Reflective)];
+
+    assert!(line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &segments));
+    // nor one behind the start
+    assert!(line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &[wall(-5.0, EdgeState::Reflective)]));
+}
+
+#[test]
+fn transparent_walls_pass_unless_configured() {
+    let segments = [wall(50.0, EdgeState::Transparent)];
+
+    assert!(line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &segments));
+    let opaque_glass = first_obstruction_where(vec2(0.0, 0.0), vec2(100.0, 0.0), &segments, |_| true);
+    assert_eq!(opaque_glass.map(|(_, index)| index), Some(0));
+}
+
+#[test]
+fn point_on_a_wall_sees_away_from_it() {
+    let segments = [wall(0.0, EdgeState::Reflective)];
+
+    assert!(line_of_sight(vec2(0.0, 0.0), vec2(100.0, 0.0), &segments));
+}
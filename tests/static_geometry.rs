@@ -0,0 +1,75 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::labyrinth::{Algorithm, LabyrinthSpec, Side};
+use ray_cast::replay::{Recorder, Replayer};
+use ray_cast::{EdgeState, Laser, NodeNetwork, Segment};
+
+fn spec() -> LabyrinthSpec {
+    LabyrinthSpec {
+        cell_size: 40.0,
+        size: (6, 5),
+        algorithm: Algorithm::DepthFirst,
+        entrance: Some(Side::Left),
+        exit: Some(Side::Right),
+        loopiness: 0.0,
+        seed: 3,
+    }
+}
+
+#[test]
+fn static_labyrinth_is_traced_but_not_editable() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(-100.0, 0.0));
+    let b = network.add_node(vec2(-100.0, 100.0));
+    network.add_connection(a, b);
+    let generation = network.generation();
+
+    network.add_static_labyrinth(&spec(), vec2(0.0, 0.0), EdgeState::Absorptive);
+
+    assert!(network.generation() > generation);
+    assert_eq!(network.nodes.len(), 2);
+    let walls = network.static_geometry().segments.len();
+    assert!(walls > 0);
+    let traced = network.traced_segments();
+    assert_eq!(traced.len(), 1 + walls);
+    assert_eq!(traced[0], network.get_all_connections()[0]);
+    // the top wall is solid, nothing is there to pick
+    assert_eq!(network.pick(vec2(100.0, 0.0)), None);
+    assert_eq!(network.stats().static_segments, walls);
+}
+
+#[test]
+fn static_geometry_converts_to_nodes_and_back() {
+    let mut network = NodeNetwork::headless();
+    let walls = [Segment(vec2(0.0, 0.0), vec2(100.0, 0.0), EdgeState::Reflective),
+        Segment(vec2(100.0, 0.0), vec2(100.0, 100.0), EdgeState::Absorptive)];
+    network.add_static_segments(&walls);
+
+    let nodes = network.static_to_nodes();
+    assert_eq!(nodes.len(), 3);
+    assert!(network.static_geometry().is_empty());
+    assert_eq!(network.get_all_connections().len(), 2);
+
+    network.nodes_to_static(&nodes);
+    assert!(network.nodes.is_empty() && network.connections.is_empty());
+    let mut segments = network.static_geometry().segments.clone();
+    segments.sort_by(|a, b| a.0.x.total_cmp(&b.0.x).then(a.1.y.total_cmp(&b.1.y)));
+    assert_eq!(segments, walls);
+
+    network.clean(false);
+    assert!(network.static_geometry().is_empty());
+}
+
+#[test]
+fn sessions_replay_the_static_layer() {
+    let mut network = NodeNetwork::headless();
+    let mut laser = Laser::headless(Vec2::ZERO, Vec2::X);
+    let mut recorder = Recorder::start(&mut network);
+    network.add_static_labyrinth(&spec(), vec2(50.0, 50.0), EdgeState::Reflective);
+    network.add_static_segments(&[Segment(vec2(0.0, 0.0), vec2(0.0, 40.0), EdgeState::Absorptive)]);
+    recorder.collect(0.0, &mut network, &laser);
+
+    let mut replayed = NodeNetwork::headless();
+    Replayer::new(recorder.into_session()).run_to_end(&mut replayed, &mut laser);
+
+    assert_eq!(replayed.static_geometry(), network.static_geometry());
+}
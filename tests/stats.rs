@@ -0,0 +1,69 @@
+use macroquad::color::Color;
+use macroquad::math::vec2;
+use ray_cast::core::{trace, Ray, SimConfig, TraceStats};
+use ray_cast::{Edge, EdgeState, NodeNetwork};
+
+#[test]
+fn counts_edges_by_state_and_what_the_trace_leaves_out() {
+    let mut network = NodeNetwork::headless();
+    let a = network.add_node(vec2(0.0, 0.0));
+    let b = network.add_node(vec2(100.0, 0.0));
+    let c = network.add_node(vec2(200.0, 0.0));
+    let d = network.add_node(vec2(200.0, 0.0));
+    network.add_node(vec2(500.0, 500.0));
+    network.add_connection(a, b);
+    network.add_connection(b, c);
+    network.add_connection(c, d);
+    network.set_edge_state(2, EdgeState::Absorptive);
+    // a second edge over a-b, and one to a node that doesn't exist
+    network.connections.push(Edge::new(b, a));
+    network.connections.push(Edge::new_with_state(c, 99, EdgeState::Transparent));
+
+    let stats = network.stats();
+
+    assert_eq!((stats.nodes, stats.edges), (5, 5));
+    assert_eq!((stats.reflective, stats.absorptive, stats.transparent), (3, 1, 1));
+    assert_eq!(stats.traced_segments, 2);
+    // a-b and b-c are collinear, reflective and meet at b
+    assert_eq!(stats.merged_segments, 1);
+    assert_eq!(stats.components, 2);
+    assert_eq!(stats.zero_length_edges, 1);
+    assert_eq!(stats.duplicate_edges, 1);
+    assert_eq!(stats.dangling_edges, vec![(4, 99)]);
+    assert_eq!(stats.warnings(None), vec!["1 zero-length edges removed", "1 duplicate edges removed",
+                                          "edge 4 points at missing node 99"]);
+}
+
+#[test]
+fn a_clean_network_has_no_warnings() {
+    let mut network = NodeNetwork::headless();
+    assert_eq!(network.stats().components, 0);
+    network.add_circle(vec2(0.0, 0.0), 50.0, EdgeState::Reflective, 6, 5.0, None);
+
+    let stats = network.stats();
+
+    assert_eq!((stats.nodes, stats.edges, stats.traced_segments, stats.components), (6, 6, 6, 1));
+    assert!(stats.warnings(None).is_empty());
+}
+
+#[test]
+fn an_exhausted_ray_budget_is_reported() {
+    let mut network = NodeNetwork::headless();
+    network.add_circle(vec2(0.0, 0.0), 100.0, EdgeState::Reflective, 4, 5.0, None);
+    let segments = network.get_all_connections();
+    let ray = Ray::new(vec2(0.0, 0.0), vec2(1.0, 0.3).normalize(), Color::new(1.0, 0.0, 0.0, 1.0));
+
+    let exhausted = trace(ray, &segments, &SimConfig { max_rays: 5, ..Default::default() }).stats;
+    assert!(exhausted.budget_exhausted);
+    assert!(exhausted.buffer_bytes > 0);
+    assert_eq!(network.stats().warnings(Some(&exhausted)), vec!["MAX_RAYS budget exhausted last trace"]);
+
+    let open = trace(ray, &segments[..1], &SimConfig::default()).stats;
+    assert!(!open.budget_exhausted);
+}
+
+#[test]
+fn an_aborted_trace_is_reported() {
+    let stats = TraceStats { aborted: true, ..Default::default() };
+    assert_eq!(NodeNetwork::headless().stats().warnings(Some(&stats)), vec!["last trace aborted, scene too complex"]);
+}
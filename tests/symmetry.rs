@@ -0,0 +1,175 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::symmetry::SymmetryAxis;
+use ray_cast::{EdgeState, NodeNetwork};
+
+const AXIS_X: f32 = 400.0;
+
+fn symmetric_network() -> NodeNetwork {
+    let mut network = NodeNetwork::headless();
+    network.set_symmetry(Some(SymmetryAxis::vertical(AXIS_X)));
+    network
+}
+
+/// Builds the right half of a parabola opening upwards, vertex on the axis, and returns its
+/// nodes from the vertex out.
+fn half_parabola(network: &mut NodeNetwork) -> Vec<usize> {
+    let mut nodes = vec![network.place_node(vec2(AXIS_X + 3.0, 400.0))];
+    for i in 1..=8 {
+        let x = i as f32 * 25.0;
+        nodes.push(network.place_node(vec2(AXIS_X + x, 400.0 - x * x / 400.0)));
+        network.add_connection(nodes[i - 1], nodes[i]);
+    }
+    nodes
+}
+
+fn has_edge(network: &NodeNetwork, a: usize, b: usize) -> bool {
+    network.connections.iter().any(|edge| edge.endpoints() == (a, b) || edge.endpoints() == (b, a))
+}
+
+#[test]
+fn axes_mirror_points_exactly() {
+    let vertical = SymmetryAxis::vertical(100.0);
+    let horizontal = SymmetryAxis::horizontal(-20.0);
+    let diagonal = SymmetryAxis::through(Vec2::ZERO, vec2(5.0, 5.0)).unwrap();
+
+    assert_eq!(vertical.mirror(vec2(62.5, 7.0)), vec2(137.5, 7.0));
+    assert_eq!(horizontal.mirror(vec2(3.0, 10.0)), vec2(3.0, -50.0));
+    assert!(diagonal.mirror(vec2(3.0, 1.0)).abs_diff_eq(vec2(1.0, 3.0), 1e-5));
+    assert!(diagonal.mirror(diagonal.mirror(vec2(-7.0, 2.0))).abs_diff_eq(vec2(-7.0, 2.0), 1e-5));
+    assert!((diagonal.distance(vec2(1.0, -1.0)) - 2f32.sqrt()).abs() < 1e-5);
+    assert_eq!(vertical.project(vec2(90.0, 4.0)), vec2(100.0, 4.0));
+    assert!(SymmetryAxis::through(Vec2::ONE, Vec2::ONE).is_none());
+}
+
+#[test]
+fn half_a_parabola_builds_the_other_half() {
+    let mut network = symmetric_network();
+
+    let nodes = half_parabola(&mut network);
+
+    // the vertex snapped onto the axis and is its own mirror image
+    assert_eq!(network.node_position(nodes[0]), Some(vec2(AXIS_X, 400.0)));
+    assert_eq!(network.twin(nodes[0]), None);
+    assert_eq!(network.nodes.len(), 1 + 2 * 8);
+    assert_eq!(network.connections.len(), 2 * 8);
+    for pair in nodes.windows(2) {
+        let [a, b] = [pair[0], pair[1]].map(|node| network.twin(node).unwrap_or(node));
+        assert!(has_edge(&network, a, b), "missing mirror of {:?}", pair);
+    }
+    for &node in &nodes[1..] {
+        let (position, twin) = (network.node_position(node).unwrap(), network.twin(node).unwrap());
+        assert_eq!(network.twin(twin), Some(node));
+        assert_eq!(network.node_position(twin), Some(vec2(2.0 * AXIS_X - position.x, position.y)));
+    }
+}
+
+#[test]
+fn edits_apply_to_both_twins() {
+    let mut network = symmetric_network();
+    let nodes = half_parabola(&mut network);
+    let twin = network.twin(nodes[3]).unwrap();
+
+    network.move_node(nodes[3], vec2(AXIS_X + 90.0, 300.0));
+    assert_eq!(network.node_position(twin), Some(vec2(AXIS_X - 90.0, 300.0)));
+
+    let edge = network.connections.iter().position(|edge| edge.endpoints() == (nodes[4], nodes[5])).unwrap();
+    network.set_edge_state(edge, EdgeState::Absorptive);
+    assert_eq!(network.connections.iter().filter(|edge| edge.state() == EdgeState::Absorptive).count(), 2);
+
+    let split = network.split_edge(edge, vec2(AXIS_X + 110.0, 360.0));
+    assert_eq!(network.node_position(network.twin(split).unwrap()), Some(vec2(AXIS_X - 110.0, 360.0)));
+    assert_eq!(network.connections.len(), 2 * 8 + 2);
+
+    network.remove_node(nodes[8]);
+    assert_eq!(network.nodes.len(), 1 + 2 * 8 + 2 - 2);
+    assert_eq!(network.connections.len(), 2 * 7 + 2);
+
+    let last = network.connections.iter().position(|edge| edge.endpoints() == (nodes[6], nodes[7])).unwrap();
+    network.remove_edge(last);
+    assert_eq!(network.connections.len(), 2 * 6 + 2);
+    assert!(!has_edge(&network, network.twin(nodes[6]).unwrap(), network.twin(nodes[7]).unwrap()));
+}
+
+#[test]
+fn detached_twins_are_edited_separately() {
+    let mut network = symmetric_network();
+    let node = network.place_node(vec2(AXIS_X + 50.0, 100.0));
+    let twin = network.twin(node).unwrap();
+
+    assert_eq!(network.detach_twin(twin), Some(node));
+    network.move_node(node, vec2(AXIS_X + 80.0, 100.0));
+    network.remove_node(node);
+
+    assert_eq!(network.twin(twin), None);
+    assert_eq!(network.node_position(twin), Some(vec2(AXIS_X - 50.0, 100.0)));
+}
+
+#[test]
+fn changing_the_axis_forgets_twins() {
+    let mut network = symmetric_network();
+    let node = network.place_node(vec2(AXIS_X + 50.0, 100.0));
+
+    network.set_symmetry(Some(SymmetryAxis::vertical(AXIS_X)));
+    assert!(network.twin(node).is_some());
+
+    network.set_symmetry(Some(SymmetryAxis::horizontal(0.0)));
+    assert_eq!(network.twin(node), None);
+    network.set_symmetry(None);
+    let plain = network.place_node(vec2(AXIS_X + 50.0, 200.0));
+    assert_eq!(network.twin(plain), None);
+    assert_eq!(network.nodes.len(), 3);
+}
+
+#[test]
+fn merging_a_twin_merges_its_twin_too() {
+    let mut network = symmetric_network();
+    let a = network.place_node(vec2(AXIS_X + 50.0, 100.0));
+    let b = network.place_node(vec2(AXIS_X + 52.0, 100.0));
+    let c = network.place_node(vec2(AXIS_X + 90.0, 200.0));
+    network.add_connection(b, c);
+    let (twin_a, twin_c) = (network.twin(a).unwrap(), network.twin(c).unwrap());
+
+    network.merge_nodes(a, b);
+
+    assert_eq!(network.nodes.len(), 4);
+    assert_eq!(network.twin(a), Some(twin_a));
+    assert!(has_edge(&network, a, c));
+    assert!(has_edge(&network, twin_a, twin_c));
+}
+
+#[test]
+fn twins_mirror_their_faces() {
+    let mut network = symmetric_network();
+    let a = network.place_node(vec2(AXIS_X + 50.0, 100.0));
+    let b = network.place_node(vec2(AXIS_X + 50.0, 200.0));
+    network.add_connection(a, b);
+    let twin = network.connections.iter().position(|edge| edge.endpoints().0 != a).unwrap();
+
+    network.set_edge_back_state(0, Some(EdgeState::Absorptive));
+
+    // the original faces the axis with its front, so the twin must too
+    let towards_axis = |network: &NodeNetwork, edge: usize| {
+        let (start, end) = network.connections[edge].endpoints();
+        let (start, end) = (network.node_position(start).unwrap(), network.node_position(end).unwrap());
+        let (front, back) = network.connections[edge].faces();
+        if (vec2(AXIS_X, 150.0) - start).dot((end - start).perp()) > 0.0 { front } else { back }
+    };
+    assert_eq!(towards_axis(&network, 0), EdgeState::Reflective);
+    assert_eq!(towards_axis(&network, twin), EdgeState::Reflective);
+    assert!(network.connections[twin].back_state().is_some());
+}
+
+#[test]
+fn snapping_onto_the_axis_reaches_as_far_on_screen_at_any_zoom() {
+    let mut network = symmetric_network();
+    unsafe { network.update_camera(Vec2::ZERO, 0.5); }
+
+    let node = network.place_node(vec2(AXIS_X + 15.0, 100.0));
+
+    assert_eq!(network.nodes.len(), 1);
+    assert_eq!(network.node_position(node), Some(vec2(AXIS_X, 100.0)));
+
+    unsafe { network.update_camera(Vec2::ZERO, 2.0); }
+    network.place_node(vec2(AXIS_X + 8.0, 200.0));
+    assert_eq!(network.nodes.len(), 3);
+}
@@ -0,0 +1,79 @@
+use macroquad::color::Color;
+use ray_cast::core::EdgeState;
+use ray_cast::settings::Settings;
+use ray_cast::theme::Theme;
+use ray_cast::smooth_toward;
+
+#[test]
+fn presets_round_trip_through_json() {
+    for (name, theme) in Theme::PRESETS {
+        let json = serde_json::to_string(&theme).unwrap();
+        assert_eq!(serde_json::from_str::<Theme>(&json).unwrap(), theme, "{}", name);
+    }
+}
+
+#[test]
+fn edge_colors_come_from_the_theme() {
+    let theme = Theme::LIGHT;
+
+    assert_eq!(theme.state_color(EdgeState::Reflective), theme.reflective);
+    assert_eq!(theme.state_color(EdgeState::Absorptive), theme.absorptive);
+    assert_eq!(theme.state_color(EdgeState::Transparent), theme.transparent);
+}
+
+#[test]
+fn only_the_light_theme_outlines_absorptive_edges() {
+    assert_eq!(Theme::DARK.absorptive_outline.a, 0.0);
+    assert_eq!(Theme::LIGHT.absorptive_outline.a, 1.0);
+    assert_ne!(Theme::LIGHT.absorptive, Theme::LIGHT.background);
+    assert_ne!(Theme::LIGHT.additive_light, Theme::DARK.additive_light);
+}
+
+#[test]
+fn presets_are_recognized_until_customized() {
+    assert_eq!(Theme::DARK.preset(), Some(0));
+    assert_eq!(Theme::LIGHT.preset(), Some(1));
+
+    let custom = Theme { background: Color::new(0.1, 0.3, 0.1, 1.0), ..Theme::DARK };
+    assert_eq!(custom.preset(), None);
+}
+
+#[test]
+fn a_customized_theme_is_saved_with_the_settings() {
+    let theme = Theme { hover: Color::new(1.0, 0.0, 1.0, 1.0), ..Theme::LIGHT };
+    let settings = Settings { theme, ..Settings::default() };
+
+    assert_eq!(Settings::from_json(&settings.to_json()).unwrap(), settings);
+    assert_eq!(Settings::from_json("{}").unwrap().theme, Theme::DARK);
+    assert_eq!(Settings::from_json(r#"{"theme": {"additive_light": false}}"#).unwrap().theme,
+               Theme { additive_light: false, ..Theme::DARK });
+}
+
+#[test]
+fn smoothing_approaches_the_target_without_overshooting() {
+    let half_life = Theme::DARK.hover_half_life;
+    for dt in [0.001, 0.016, 0.1, 0.25, 0.5] {
+        let (mut value, mut previous) = (8.0, 8.0);
+        for _ in 0..100 {
+            value = smooth_toward(value, 16.0, half_life, dt);
+            assert!(value >= previous && value <= 16.0, "dt {}: {} after {}", dt, value, previous);
+            previous = value;
+        }
+        let shrunk = smooth_toward(16.0, 8.0, half_life, dt);
+        assert!((8.0..16.0).contains(&shrunk), "dt {}: {}", dt, shrunk);
+    }
+}
+
+#[test]
+fn smoothing_covers_the_same_ground_at_any_frame_rate() {
+    let half_life = 0.1;
+    let one_step = smooth_toward(0.0, 1.0, half_life, half_life);
+    assert!((one_step - 0.5).abs() < 1e-6);
+
+    let mut many_steps = 0.0;
+    for _ in 0..100 {
+        many_steps = smooth_toward(many_steps, 1.0, half_life, half_life / 100.0);
+    }
+    assert!((many_steps - one_step).abs() < 1e-4);
+    assert_eq!(smooth_toward(0.0, 1.0, 0.0, 0.016), 1.0);
+}
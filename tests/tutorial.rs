@@ -0,0 +1,83 @@
+use macroquad::math::{vec2, Vec2};
+use ray_cast::core::EdgeState;
+use ray_cast::tutorial::{self, changed_edge_state, connected_nodes, placed_node, toggled_ui, Snapshot, Tutorial,
+                         UiState};
+use ray_cast::{Laser, NodeNetwork};
+
+fn ui_state(network: &NodeNetwork, show_ui: bool) -> UiState {
+    UiState { show_ui, step_start: Snapshot::of(network, show_ui) }
+}
+
+#[test]
+fn placing_a_node_is_seen() {
+    let (mut network, laser) = (NodeNetwork::headless(), Laser::headless(Vec2::ZERO, Vec2::X));
+    network.add_node(vec2(0.0, 0.0));
+    let ui = ui_state(&network, false);
+    assert!(!placed_node(&network, &laser, &ui));
+
+    network.add_node(vec2(10.0, 0.0));
+    assert!(placed_node(&network, &laser, &ui));
+}
+
+#[test]
+fn connecting_nodes_is_seen() {
+    let (mut network, laser) = (NodeNetwork::headless(), Laser::headless(Vec2::ZERO, Vec2::X));
+    let (a, b) = (network.add_node(vec2(0.0, 0.0)), network.add_node(vec2(10.0, 0.0)));
+    let ui = ui_state(&network, false);
+    assert!(!connected_nodes(&network, &laser, &ui));
+
+    network.add_connection(a, b);
+    assert!(connected_nodes(&network, &laser, &ui));
+}
+
+#[test]
+fn only_a_changed_state_counts_as_an_edge_change() {
+    let (mut network, laser) = (NodeNetwork::headless(), Laser::headless(Vec2::ZERO, Vec2::X));
+    let (a, b) = (network.add_node(vec2(0.0, 0.0)), network.add_node(vec2(10.0, 0.0)));
+    network.add_connection(a, b);
+    let ui = ui_state(&network, false);
+
+    // a new edge isn't a changed one
+    let c = network.add_node(vec2(10.0, 10.0));
+    network.add_connection(b, c);
+    network.connections[1].set_state(EdgeState::Absorptive);
+    assert!(!changed_edge_state(&network, &laser, &ui));
+
+    network.connections[0].cycle_state();
+    assert!(changed_edge_state(&network, &laser, &ui));
+}
+
+#[test]
+fn toggling_the_options_either_way_is_seen() {
+    let (network, laser) = (NodeNetwork::headless(), Laser::headless(Vec2::ZERO, Vec2::X));
+    for shown in [false, true] {
+        let mut ui = ui_state(&network, shown);
+        assert!(!toggled_ui(&network, &laser, &ui));
+        ui.show_ui = !shown;
+        assert!(toggled_ui(&network, &laser, &ui));
+    }
+}
+
+#[test]
+fn the_tutorial_only_advances_on_the_current_step() {
+    let (mut network, laser) = (NodeNetwork::headless(), Laser::headless(Vec2::ZERO, Vec2::X));
+    let mut tutorial = Tutorial::new(&tutorial::STEPS, &network, false);
+
+    // the options toggled early don't count for the last step
+    assert!(!tutorial.update(&network, &laser, true));
+    let a = network.add_node(vec2(0.0, 0.0));
+    assert!(tutorial.update(&network, &laser, true));
+    assert!(!tutorial.update(&network, &laser, true));
+
+    let b = network.add_node(vec2(10.0, 0.0));
+    network.add_connection(a, b);
+    assert!(tutorial.update(&network, &laser, true));
+    network.connections[0].cycle_state();
+    assert!(tutorial.update(&network, &laser, true));
+    assert!(!tutorial.is_finished());
+
+    assert!(tutorial.update(&network, &laser, false));
+    assert!(tutorial.is_finished());
+    assert!(tutorial.step().is_none());
+    assert!(!tutorial.update(&network, &laser, true));
+}
@@ -0,0 +1,111 @@
+use macroquad::math::{vec2, Rect, Vec2};
+use ray_cast::view::{apply_wheel, bounding_rect, fit_aspect, fit_camera, screen_to_world, view_rect, WheelMode,
+                     world_to_screen, zoom_around};
+
+#[test]
+fn bounding_rect_covers_points_plus_margin() {
+    let points = [vec2(10.0, 20.0), vec2(-5.0, 40.0), vec2(30.0, 25.0)];
+
+    assert_eq!(bounding_rect(points, 0.0), Some(Rect::new(-5.0, 20.0, 35.0, 20.0)));
+    assert_eq!(bounding_rect(points, 10.0), Some(Rect::new(-15.0, 10.0, 55.0, 40.0)));
+    assert_eq!(bounding_rect([], 10.0), None);
+}
+
+#[test]
+fn single_point_gets_margin_sized_rect() {
+    assert_eq!(bounding_rect([vec2(3.0, 4.0)], 2.0), Some(Rect::new(1.0, 2.0, 4.0, 4.0)));
+}
+
+#[test]
+fn fit_aspect_grows_the_short_side_around_the_center() {
+    let tall = fit_aspect(Rect::new(0.0, 0.0, 100.0, 200.0), 2.0);
+    assert_eq!(tall, Rect::new(-150.0, 0.0, 400.0, 200.0));
+
+    let wide = fit_aspect(Rect::new(0.0, 0.0, 400.0, 100.0), 2.0);
+    assert_eq!(wide, Rect::new(0.0, -50.0, 400.0, 200.0));
+
+    let exact = Rect::new(5.0, 5.0, 200.0, 100.0);
+    assert_eq!(fit_aspect(exact, 2.0), exact);
+}
+
+#[test]
+fn fit_camera_frames_rect_with_margin() {
+    let screen = vec2(1000.0, 500.0);
+
+    let (target, zoom) = fit_camera(Rect::new(0.0, 0.0, 100.0, 100.0), screen, 0.1);
+    assert_eq!(target, vec2(50.0, 50.0));
+    assert!((zoom - 500.0 / 120.0).abs() < 1e-4, "height limits: {}", zoom);
+
+    let (_, zoom) = fit_camera(Rect::new(-400.0, 0.0, 800.0, 10.0), screen, 0.0);
+    assert!((zoom - 1.25).abs() < 1e-4, "width limits: {}", zoom);
+}
+
+#[test]
+fn fit_camera_on_degenerate_rects() {
+    let screen = vec2(1000.0, 500.0);
+
+    assert_eq!(fit_camera(Rect::new(7.0, 8.0, 0.0, 0.0), screen, 0.1), (vec2(7.0, 8.0), 1.0));
+    // a horizontal line only constrains the width
+    let (_, zoom) = fit_camera(Rect::new(0.0, 0.0, 500.0, 0.0), screen, 0.0);
+    assert!((zoom - 2.0).abs() < 1e-4, "{}", zoom);
+}
+
+#[test]
+fn screen_and_world_round_trip() {
+    let screen = vec2(960.0, 540.0);
+    for (target, zoom) in [(vec2(480.0, 270.0), 1.0), (vec2(-1234.5, 77.25), 0.03), (vec2(1e4, -3e3), 40.0)] {
+        for point in [Vec2::ZERO, vec2(13.0, 500.0), screen] {
+            let world = screen_to_world(point, screen, target, zoom);
+            let back = world_to_screen(world, screen, target, zoom);
+            assert!(back.distance(point) < 1e-2, "{} -> {} -> {}", point, world, back);
+        }
+    }
+    assert_eq!(screen_to_world(screen / 2.0, screen, vec2(5.0, 6.0), 3.0), vec2(5.0, 6.0));
+}
+
+#[test]
+fn zooming_keeps_the_point_under_the_cursor() {
+    let screen = vec2(960.0, 540.0);
+    let mut target = vec2(480.0, 270.0);
+    let mut zoom = 1.0;
+    // zoom in and out around different cursors, as after panning around
+    for (i, cursor) in [vec2(10.0, 20.0), vec2(900.0, 500.0), vec2(333.3, 111.1), vec2(480.0, 0.0)].iter().enumerate() {
+        for factor in [1.1, 3.0, 0.5, 0.01, 250.0] {
+            let under_cursor = screen_to_world(*cursor, screen, target, zoom);
+            let new_zoom = zoom * factor;
+            target = zoom_around(*cursor, screen, target, zoom, new_zoom);
+            zoom = new_zoom;
+            let back = world_to_screen(under_cursor, screen, target, zoom);
+            assert!(back.distance(*cursor) < 1e-2, "step {} factor {}: {} vs {}", i, factor, back, cursor);
+        }
+    }
+}
+
+#[test]
+fn view_rect_covers_the_screen_in_world_units() {
+    let screen = vec2(800.0, 600.0);
+
+    assert_eq!(view_rect(vec2(400.0, 300.0), 1.0, screen), Rect::new(0.0, 0.0, 800.0, 600.0));
+    assert_eq!(view_rect(vec2(0.0, 0.0), 2.0, screen), Rect::new(-200.0, -150.0, 400.0, 300.0));
+}
+
+#[test]
+fn wheel_zoom_keeps_the_cursor_point_fixed() {
+    let (screen, cursor, target) = (vec2(800.0, 600.0), vec2(100.0, 50.0), vec2(30.0, 40.0));
+    let before = screen_to_world(cursor, screen, target, 1.0);
+
+    let (new_target, new_zoom) = apply_wheel(WheelMode::Zoom, 120.0, cursor, screen, target, 1.0, 0.001);
+
+    assert!((new_zoom - 0.12f32.exp()).abs() < 1e-6);
+    assert!(screen_to_world(cursor, screen, new_target, new_zoom).distance(before) < 1e-3);
+}
+
+#[test]
+fn wheel_scroll_moves_less_when_zoomed_in() {
+    let screen = vec2(800.0, 600.0);
+
+    assert_eq!(apply_wheel(WheelMode::ScrollVertical, 40.0, Vec2::ZERO, screen, Vec2::ZERO, 2.0, 0.001),
+               (vec2(0.0, -5.0), 2.0));
+    assert_eq!(apply_wheel(WheelMode::ScrollHorizontal, 40.0, Vec2::ZERO, screen, Vec2::ZERO, 1.0, 0.001),
+               (vec2(-10.0, 0.0), 1.0));
+}